@@ -0,0 +1,112 @@
+//! 大描述的吞吐量基准：解析（JSON、Markdown、约 50 MB 的 JSON）与生成（dry-run 规划、实际写盘）
+//!
+//! `cargo bench --bench large`；节点数由 `TREEGEN_BENCH_NODES` 调整（默认 20000）
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use treegen::{generate, parse_json, parse_markdown, GenerateOptions, Node};
+
+/// 每个目录 10 个文件、每 10 个目录一层，直到凑够 `nodes` 个节点
+fn synthetic(nodes: usize) -> Node {
+    fn fill(dir: &mut Node, budget: &mut usize, depth: usize) {
+        for i in 0..10 {
+            if *budget == 0 {
+                return;
+            }
+            *budget -= 1;
+            dir.children.push(Node::new_file(
+                format!("file{}.txt", i),
+                Some(format!("content {}\n", i)),
+            ));
+        }
+        for i in 0..10 {
+            if *budget == 0 || depth == 6 {
+                return;
+            }
+            *budget -= 1;
+            let mut child = Node::new_dir(format!("dir{}", i));
+            fill(&mut child, budget, depth + 1);
+            dir.children.push(child);
+        }
+    }
+    let mut root = Node::new_dir(String::new());
+    let mut budget = nodes;
+    while budget > 0 {
+        let mut top = Node::new_dir(format!("top{}", root.children.len()));
+        budget -= 1;
+        fill(&mut top, &mut budget, 0);
+        root.children.push(top);
+    }
+    root
+}
+
+fn nodes() -> usize {
+    std::env::var("TREEGEN_BENCH_NODES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(20_000)
+}
+
+fn parse(c: &mut Criterion) {
+    let root = synthetic(nodes());
+    let json = root.to_json(false).unwrap();
+    let markdown = root.to_markdown().unwrap();
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(nodes() as u64));
+    group.bench_function("json", |b| b.iter(|| parse_json(&json).unwrap()));
+    group.bench_function("markdown", |b| {
+        b.iter(|| parse_markdown(&markdown).unwrap())
+    });
+    group.finish();
+}
+
+/// 约 50 MB 的 JSON，大部分是文件内容：解析时内容按值移入节点而不是复制
+fn parse_large_json(c: &mut Criterion) {
+    fn fill_content(node: &mut Node, content: &str) {
+        for child in &mut node.children {
+            if child.content.is_some() {
+                child.content = Some(content.to_string());
+            }
+            fill_content(child, content);
+        }
+    }
+    const FILES: usize = 5_000;
+    let mut root = synthetic(FILES * 11 / 10);
+    fill_content(&mut root, &"x".repeat(50 * 1024 * 1024 / FILES));
+    let json = root.to_json(false).unwrap();
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(json.len() as u64));
+    group.bench_function("json_50mb", |b| b.iter(|| parse_json(&json).unwrap()));
+    group.finish();
+}
+
+fn generation(c: &mut Criterion) {
+    let root = synthetic(nodes());
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(nodes() as u64));
+    group.bench_function("plan", |b| {
+        let out = tempfile::tempdir().unwrap();
+        let opts = GenerateOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        b.iter(|| generate(&root, out.path(), &opts).unwrap())
+    });
+    group.bench_function("execute", |b| {
+        b.iter_batched(
+            || tempfile::tempdir().unwrap(),
+            // 返回临时目录，删除它的时间不计入
+            |out| {
+                generate(&root, out.path(), &GenerateOptions::default()).unwrap();
+                out
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse, parse_large_json, generation);
+criterion_main!(benches);
@@ -0,0 +1,264 @@
+//! zip 模板包：`bundle.zip`（或用 `bundle.zip!spec.yaml` 指定成员）中的描述文件，
+//! 其中相对路径的复制节点从同一个压缩包的其他成员复制，生成时直接从压缩包流式写出。
+//!
+//! ```
+//! use std::path::{Path, PathBuf};
+//! use treegen::archive;
+//!
+//! let split = |s: &str| archive::split(Path::new(s));
+//! assert_eq!(split("bundle.zip"), Some((PathBuf::from("bundle.zip"), None)));
+//! assert_eq!(
+//!     split("bundle.ZIP!specs/app.yaml"),
+//!     Some((PathBuf::from("bundle.ZIP"), Some(PathBuf::from("specs/app.yaml"))))
+//! );
+//! assert_eq!(split("spec.yaml"), None);
+//! ```
+
+use crate::{Format, SPEC_EXTENSIONS};
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+use zip::ZipArchive;
+
+/// 压缩包路径与成员之间的分隔符
+pub const MEMBER_SEP: char = '!';
+
+/// 把输入拆成（压缩包, 成员）：以 `.zip` 结尾，或写成 `ARCHIVE.zip!MEMBER`
+pub fn split(path: &Path) -> Option<(PathBuf, Option<PathBuf>)> {
+    let text = path.to_str()?;
+    let is_zip = |archive: &str| archive.to_lowercase().ends_with(".zip");
+    if is_zip(text) {
+        return Some((path.to_path_buf(), None));
+    }
+    let (archive, member) = text.split_once(MEMBER_SEP)?;
+    is_zip(archive).then(|| (PathBuf::from(archive), Some(PathBuf::from(member))))
+}
+
+/// 用于日志与错误信息的 `ARCHIVE!MEMBER`
+pub fn display(archive: &Path, member: &Path) -> String {
+    format!("{}{}{}", archive.display(), MEMBER_SEP, slash(member))
+}
+
+/// 成员名总是用 `/` 分隔
+fn slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 压缩包内相对 `base` 的路径，按字面消去 `.` 与 `..`
+pub(crate) fn resolve(base: &Path, path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in base.components().chain(path.components()) {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::Normal(name) => out.push(name),
+            _ => {}
+        }
+    }
+    out
+}
+
+/// 压缩包中的一个成员
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Member {
+    /// 文件及其（解压后的）大小
+    File(u64),
+    /// 目录：其下的所有子目录与文件（相对该目录，按路径排序）
+    Dir {
+        dirs: Vec<PathBuf>,
+        files: Vec<(PathBuf, u64)>,
+    },
+}
+
+/// 打开的 zip 压缩包
+pub(crate) struct Archive {
+    path: PathBuf,
+    zip: ZipArchive<fs::File>,
+    /// 所有条目的（名称, 解压后的大小），目录名以 `/` 结尾
+    entries: Vec<(String, u64)>,
+}
+
+impl Archive {
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let read_error = || format!("Failed to read zip archive '{}'", path.display());
+        let file = fs::File::open(path)
+            .with_context(|| format!("Failed to open archive '{}'", path.display()))?;
+        let mut zip = ZipArchive::new(file).with_context(read_error)?;
+        let entries: Vec<(String, u64)> = (0..zip.len())
+            .map(|index| {
+                let entry = zip.by_index_raw(index)?;
+                let unsafe_name = entry.enclosed_name().is_none()
+                    || entry.name().split(['/', '\\']).any(|part| part == "..");
+                Ok((entry.name().to_string(), entry.size(), unsafe_name))
+            })
+            .collect::<zip::result::ZipResult<Vec<_>>>()
+            .with_context(read_error)?
+            .into_iter()
+            .map(|(name, size, unsafe_name)| {
+                // 含 `..` 或绝对路径的条目复制时会写到输出目录之外（zip slip）
+                if unsafe_name {
+                    bail!(
+                        "Archive '{}' contains an unsafe entry '{}' (absolute or with '..')",
+                        path.display(),
+                        name
+                    );
+                }
+                Ok((name, size))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Archive {
+            path: path.to_path_buf(),
+            zip,
+            entries,
+        })
+    }
+
+    /// 用于日志与错误信息的 `ARCHIVE!MEMBER`
+    pub(crate) fn display(&self, member: &Path) -> String {
+        display(&self.path, member)
+    }
+
+    /// 按 `member`（或唯一的描述文件）读出描述的内容，返回成员路径与内容
+    pub(crate) fn read_spec(&mut self, member: Option<&Path>) -> Result<(PathBuf, String)> {
+        let member = match member {
+            Some(member) => resolve(Path::new(""), member),
+            None => self.find_spec()?,
+        };
+        match self.member(&member) {
+            Some(Member::File(_)) => {}
+            Some(Member::Dir { .. }) => bail!(
+                "'{}' is a directory, not a spec file",
+                display(&self.path, &member)
+            ),
+            None => bail!(
+                "'{}' not found in archive '{}'",
+                slash(&member),
+                self.path.display()
+            ),
+        }
+        let data = self.read(&member)?;
+        let content = String::from_utf8(data)
+            .with_context(|| format!("'{}' is not UTF-8", display(&self.path, &member)))?;
+        Ok((member, content))
+    }
+
+    /// 没有指定成员时的描述文件：扩展名受支持的文件中，优先取顶层的，必须唯一
+    fn find_spec(&self) -> Result<PathBuf> {
+        let specs: Vec<&str> = self
+            .entries
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| !name.ends_with('/') && Format::from_path(Path::new(name)).is_some())
+            .collect();
+        let top: Vec<&str> = specs
+            .iter()
+            .copied()
+            .filter(|name| !name.contains('/'))
+            .collect();
+        let mut candidates = if top.is_empty() { specs } else { top };
+        candidates.sort();
+        match candidates.as_slice() {
+            [only] => Ok(PathBuf::from(only)),
+            [] => bail!(
+                "No spec file in archive '{}' (expected a {} file)",
+                self.path.display(),
+                SPEC_EXTENSIONS.join("/")
+            ),
+            many => bail!(
+                "Archive '{}' contains several spec files ({}); pick one with '{}{}{}'",
+                self.path.display(),
+                many.join(", "),
+                self.path.display(),
+                MEMBER_SEP,
+                many[0]
+            ),
+        }
+    }
+
+    /// 查找成员：文件，或（有显式的目录条目或其下有文件的）目录
+    pub(crate) fn member(&self, path: &Path) -> Option<Member> {
+        let name = slash(path);
+        if name.is_empty() {
+            return None;
+        }
+        let prefix = format!("{}/", name);
+        let mut found = false;
+        let mut dirs = BTreeSet::new();
+        let mut files = Vec::new();
+        for (entry, size) in &self.entries {
+            if *entry == name {
+                return Some(Member::File(*size));
+            }
+            let Some(rel) = entry.strip_prefix(&prefix) else {
+                continue;
+            };
+            found = true;
+            let rel = Path::new(rel.trim_end_matches('/'));
+            if rel.as_os_str().is_empty() {
+                continue;
+            }
+            // 没有显式目录条目的上级目录同样要创建
+            dirs.extend(
+                rel.ancestors()
+                    .skip(1)
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .map(Path::to_path_buf),
+            );
+            if entry.ends_with('/') {
+                dirs.insert(rel.to_path_buf());
+            } else {
+                files.push((rel.to_path_buf(), *size));
+            }
+        }
+        files.sort();
+        found.then(|| Member::Dir {
+            dirs: dirs.into_iter().collect(),
+            files,
+        })
+    }
+
+    /// 读出文件成员的全部内容
+    pub(crate) fn read(&mut self, member: &Path) -> Result<Vec<u8>> {
+        let source = display(&self.path, member);
+        let mut data = Vec::new();
+        self.open_member(member)?
+            .read_to_end(&mut data)
+            .with_context(|| format!("Failed to read '{}'", source))?;
+        Ok(data)
+    }
+
+    /// 把文件成员流式写入 `dest`，返回写入的字节数；有 Unix 权限时一并设置
+    pub(crate) fn extract(&mut self, member: &Path, dest: &Path) -> Result<u64> {
+        let source = display(&self.path, member);
+        let mut entry = self.open_member(member)?;
+        let mode = entry.unix_mode();
+        let mut file = fs::File::create(dest)
+            .with_context(|| format!("Failed to create file '{}'", dest.display()))?;
+        let written = io::copy(&mut entry, &mut file)
+            .with_context(|| format!("Failed to copy '{}' to '{}'", source, dest.display()))?;
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(dest, fs::Permissions::from_mode(mode & 0o7777))
+                .with_context(|| format!("Failed to set permissions for '{}'", dest.display()))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        Ok(written)
+    }
+
+    fn open_member(&mut self, member: &Path) -> Result<zip::read::ZipFile<'_, fs::File>> {
+        let source = display(&self.path, member);
+        self.zip
+            .by_name(&slash(member))
+            .with_context(|| format!("Failed to read '{}'", source))
+    }
+}
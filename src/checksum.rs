@@ -0,0 +1,312 @@
+//! SHA-256 校验和清单（`--checksums` 与 `treegen verify`）：格式与 coreutils
+//! `sha256sum` 相同，每行为 `<哈希>  <相对输出目录的路径>`，因此也可以在输出目录中用
+//! `sha256sum -c` 检查。哈希的是磁盘上实际写入的字节，大文件流式读取。
+//!
+//! ```
+//! use std::path::Path;
+//! use treegen::checksum::parse_manifest;
+//!
+//! let entries = parse_manifest(
+//!     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855  empty.txt\n",
+//! )?;
+//! assert_eq!(entries[0].path, Path::new("empty.txt"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::diff::{Change, Difference};
+use crate::{
+    archive::{Archive, Member},
+    exclude_set, Node, NodeType,
+};
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+/// 清单中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// 相对输出目录的路径
+    pub path: PathBuf,
+    /// 小写十六进制的 SHA-256
+    pub hash: String,
+}
+
+/// 生成 `root` 后输出目录中的所有文件（相对 `out`，按路径排序）：
+/// 文件、硬链接，以及复制节点复制出的每个文件（跳过 `exclude` 匹配的路径）
+pub fn generated_files(root: &Node, out: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for (rel, node) in root.iter() {
+        let rel: PathBuf = rel.components().collect();
+        match node.node_type {
+            NodeType::File | NodeType::Hardlink => files.push(rel),
+            NodeType::Copy => {
+                let source = node.copy.as_ref().expect("copy node has a source");
+                if let (Some(zip), true) = (&source.archive, out.join(&rel).is_dir()) {
+                    let skip = exclude_set(source)?;
+                    if let Some(Member::Dir { files: members, .. }) =
+                        Archive::open(zip)?.member(&source.from)
+                    {
+                        files.extend(
+                            members
+                                .into_iter()
+                                .map(|(member, _)| member)
+                                .filter(|member| {
+                                    !member
+                                        .ancestors()
+                                        .any(|p| !p.as_os_str().is_empty() && skip.is_match(p))
+                                })
+                                .map(|member| rel.join(member)),
+                        );
+                    }
+                } else if out.join(&rel).is_dir() {
+                    let skip = exclude_set(source)?;
+                    copied_files(&source.from, Path::new(""), &skip, &rel, &mut files)?;
+                } else {
+                    files.push(rel);
+                }
+            }
+            NodeType::Dir => {}
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// 递归复制时会复制的文件，规则与生成时相同：`skip` 匹配的文件或目录整个跳过
+fn copied_files(
+    src: &Path,
+    rel: &Path,
+    skip: &globset::GlobSet,
+    dest: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let dir = src.join(rel);
+    let entries = fs::read_dir(&dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read copy source '{}'", dir.display()))?;
+    for entry in entries {
+        let child = rel.join(entry.file_name());
+        if skip.is_match(&child) {
+            continue;
+        }
+        // 不跟随符号链接，与复制时一致（复制时它们会被拒绝）
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            copied_files(src, &child, skip, dest, files)?;
+        } else {
+            files.push(dest.join(&child));
+        }
+    }
+    Ok(())
+}
+
+/// 文件内容的 SHA-256（流式读取）
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        fs::File::open(path).with_context(|| format!("Failed to open '{}'", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read '{}'", path.display()))?;
+    Ok(hex(hasher))
+}
+
+/// 内存中数据的 SHA-256
+///
+/// ```
+/// assert_eq!(
+///     treegen::checksum::sha256_bytes(b""),
+///     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+/// );
+/// ```
+pub fn sha256_bytes(data: &[u8]) -> String {
+    hex(Sha256::new_with_prefix(data))
+}
+
+/// 小写十六进制的摘要
+fn hex(hasher: Sha256) -> String {
+    let mut hex = String::with_capacity(64);
+    for byte in hasher.finalize() {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+/// 为 `out` 下的 `files` 写出 `sha256sum` 格式的清单
+pub fn manifest(out: &Path, files: &[PathBuf]) -> Result<String> {
+    manifest_with(out, files, &BTreeMap::new())
+}
+
+/// 同 [`manifest`]，`known` 中已有哈希的文件（按 `out.join(rel)` 查找，
+/// 如 `--verify-writes` 回读时算出的 [`Stats::verified`](crate::Stats::verified)）不再重新读取
+pub fn manifest_with(
+    out: &Path,
+    files: &[PathBuf],
+    known: &BTreeMap<PathBuf, String>,
+) -> Result<String> {
+    let mut text = String::new();
+    for rel in files {
+        let path = out.join(rel);
+        let hash = match known.get(&path) {
+            Some(hash) => hash.clone(),
+            None => sha256_file(&path)?,
+        };
+        let name = slash_path(rel);
+        // 与 sha256sum 相同：名称含反斜杠或换行时整行以 \ 开头，名称中转义
+        if name.contains(['\\', '\n']) {
+            let escaped = name.replace('\\', "\\\\").replace('\n', "\\n");
+            writeln!(text, "\\{}  {}", hash, escaped).unwrap();
+        } else {
+            writeln!(text, "{}  {}", hash, name).unwrap();
+        }
+    }
+    Ok(text)
+}
+
+/// 以 `/` 分隔的路径（Windows 上生成的清单也能在类 Unix 平台上使用）
+fn slash_path(path: &Path) -> String {
+    let parts: Vec<_> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    parts.join("/")
+}
+
+/// 解析 `sha256sum` 格式的清单（文本模式 `  ` 与二进制模式 ` *` 均可，跳过空行）
+pub fn parse_manifest(text: &str) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (escaped, line) = match line.strip_prefix('\\') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let invalid = || format!("Invalid checksum line {}: '{}'", i + 1, line);
+        let (hash, rest) = line.split_at_checked(64).with_context(invalid)?;
+        let name = rest
+            .strip_prefix("  ")
+            .or_else(|| rest.strip_prefix(" *"))
+            .with_context(invalid)?;
+        if !hash.bytes().all(|b| b.is_ascii_hexdigit()) || name.is_empty() {
+            bail!(invalid());
+        }
+        let name = if escaped {
+            unescape(name)
+        } else {
+            name.to_string()
+        };
+        let path = PathBuf::from(&name);
+        if !path
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            bail!(
+                "Checksum line {} names a path outside the output directory: '{}'",
+                i + 1,
+                name
+            );
+        }
+        entries.push(Entry {
+            path,
+            hash: hash.to_ascii_lowercase(),
+        });
+    }
+    Ok(entries)
+}
+
+/// 还原 sha256sum 对名称的转义（`\\` 与 `\n`）
+fn unescape(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 按清单检查输出目录：[`Change::Changed`] 为哈希不符，[`Change::Removed`] 为缺失的文件，
+/// [`Change::Added`] 为清单之外多出的文件；`manifest_path` 指向的清单文件本身不算多出
+pub fn verify(
+    entries: &[Entry],
+    out: &Path,
+    manifest_path: Option<&Path>,
+) -> Result<Vec<Difference>> {
+    let expected: BTreeMap<PathBuf, &str> = entries
+        .iter()
+        .map(|e| (e.path.components().collect(), e.hash.as_str()))
+        .collect();
+    let mut problems = Vec::new();
+    for (rel, hash) in &expected {
+        let path = out.join(rel);
+        let (change, message) = if !path.is_file() {
+            (Change::Removed, "file is missing".to_string())
+        } else {
+            let actual = sha256_file(&path)?;
+            if actual == *hash {
+                continue;
+            }
+            (
+                Change::Changed,
+                format!("expected {}, found {}", hash, actual),
+            )
+        };
+        problems.push(Difference {
+            change,
+            path: rel.clone(),
+            message,
+            contents: None,
+        });
+    }
+
+    let skip = manifest_path.and_then(|p| fs::canonicalize(p).ok());
+    let mut found = Vec::new();
+    if out.is_dir() {
+        scan_files(out, Path::new(""), &mut found)?;
+    }
+    for rel in found {
+        let is_manifest = skip.is_some() && fs::canonicalize(out.join(&rel)).ok() == skip;
+        if !expected.contains_key(&rel) && !is_manifest {
+            problems.push(Difference {
+                change: Change::Added,
+                path: rel,
+                message: "file is not in the manifest".to_string(),
+                contents: None,
+            });
+        }
+    }
+    problems.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(problems)
+}
+
+/// `dir/rel` 下的所有文件（不含目录），路径相对 `dir`
+fn scan_files(dir: &Path, rel: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let path = dir.join(rel);
+    let entries = fs::read_dir(&path)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read directory '{}'", path.display()))?;
+    for entry in entries {
+        let child = rel.join(entry.file_name());
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            scan_files(dir, &child, files)?;
+        } else {
+            files.push(child);
+        }
+    }
+    Ok(())
+}
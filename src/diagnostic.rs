@@ -0,0 +1,301 @@
+//! 描述文件解析错误的诊断信息：从 serde_yaml/serde_json/toml/json5 的错误中取出位置，
+//! 摘出出错的那一行并在对应的列下画出 `^`，附上一行提示。节点既不是文件内容也不是目录时
+//! （`SerdeNode` 的 untagged 反序列化失败，底层错误没有位置），把文本重新解析成通用的值，
+//! 找到第一个出错的节点，报告它的路径并在文本中定位。
+//!
+//! ```
+//! use treegen::{diagnostic::Diagnostic, parse_str, Format, ParseOptions};
+//!
+//! let spec = "app:\n  README.md: hello\n  port: 8080\n";
+//! let opts = ParseOptions::default();
+//! let err = parse_str("spec.yaml", spec, Some(Format::Yaml), &opts).unwrap_err();
+//! let diagnostic = err.downcast_ref::<Diagnostic>().unwrap();
+//! assert_eq!(diagnostic.key_path.as_deref(), Some("app/port"));
+//! assert_eq!(diagnostic.position, Some((3, 9)));
+//! assert!(diagnostic.to_string().contains("3 |   port: 8080\n  |         ^"));
+//! ```
+
+use crate::{Format, MetaNode, SerdeNode};
+use serde_json::Value;
+use std::fmt;
+
+/// 描述文档中的设置项，不属于树
+const SETTINGS: [&str; 5] = ["$hooks", "$vars", "$rules", "$blocks", "$out"];
+
+/// 一个解析错误及其在描述文件中的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// 错误说明（不含位置）
+    pub message: String,
+    /// 描述文件的名称或路径
+    pub file: String,
+    /// 从 1 开始的行号与列号
+    pub position: Option<(usize, usize)>,
+    /// 出错的那一行
+    pub excerpt: Option<String>,
+    /// 出错节点在树中的路径（如 `app/src/port`），只在节点类型不对时给出
+    pub key_path: Option<String>,
+    /// 一行修改建议
+    pub hint: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let gutter = " ".repeat(self.position.map_or(1, |(line, _)| line.to_string().len()));
+        write!(f, "{}\n{}--> {}", self.message, gutter, self.file)?;
+        if let Some((line, column)) = self.position {
+            write!(f, ":{}:{}", line, column)?;
+            if let Some(excerpt) = &self.excerpt {
+                // 制表符照抄，非 ASCII 字符按两格宽，`^` 才能对准终端中的列
+                let pad: String = excerpt
+                    .chars()
+                    .take(column.saturating_sub(1))
+                    .map(|c| match c {
+                        '\t' => "\t",
+                        c if c.is_ascii() => " ",
+                        _ => "  ",
+                    })
+                    .collect();
+                write!(
+                    f,
+                    "\n{0} |\n{1} | {2}\n{0} | {3}^",
+                    gutter, line, excerpt, pad
+                )?;
+            }
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\n{} = hint: {}", gutter, hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// 把 `format` 格式的描述 `content` 的解析错误换成 [`Diagnostic`]；
+/// 不是直接来自底层解析器的错误（如节点元数据的检查、Markdown 的 front matter）原样返回
+pub(crate) fn diagnose(
+    err: anyhow::Error,
+    format: Format,
+    content: &str,
+    file: &str,
+) -> anyhow::Error {
+    // 带上下文的错误中的位置不是相对 `content` 的
+    if matches!(format, Format::Markdown | Format::Paths) || err.chain().count() > 1 {
+        return err;
+    }
+    let (message, mut position) = if let Some(e) = err.downcast_ref::<serde_yaml::Error>() {
+        (
+            e.to_string(),
+            e.location().map(|at| (at.line(), at.column())),
+        )
+    } else if let Some(e) = err.downcast_ref::<serde_json::Error>() {
+        (
+            e.to_string(),
+            (e.line() > 0).then(|| (e.line(), e.column().max(1))),
+        )
+    } else if let Some(e) = err.downcast_ref::<toml::de::Error>() {
+        (
+            e.to_string(),
+            e.line_col().map(|(line, col)| (line + 1, col + 1)),
+        )
+    } else if let Some(json5::Error::Message { msg, location }) = err.downcast_ref() {
+        (
+            pest_message(msg),
+            location.as_ref().map(|at| (at.line, at.column)),
+        )
+    } else {
+        return err;
+    };
+    let mut message = strip_position(&message);
+    let mut key_path = None;
+    let mut hint = None;
+    if message.contains("untagged enum SerdeNode") {
+        if let Some(bad) = find_bad_node(format, content) {
+            // 底层给出的位置是外层映射的开头或结尾，定位到节点本身
+            position = locate(content, &bad.path).or(position);
+            let path = bad.path.join("/");
+            message = format!("'{}' {}", path, bad.problem);
+            key_path = Some(path);
+            hint = Some(bad.hint);
+        }
+    }
+    let excerpt = position.and_then(|(line, _)| content.lines().nth(line - 1).map(str::to_string));
+    Diagnostic {
+        hint: hint
+            .or_else(|| syntax_hint(format, &message, excerpt.as_deref()).map(str::to_string)),
+        message,
+        file: file.to_string(),
+        position,
+        excerpt,
+        key_path,
+    }
+    .into()
+}
+
+/// 去掉错误说明中的 ` at line N column M`（位置单独显示）
+fn strip_position(message: &str) -> String {
+    let Some(start) = message.find(" at line ") else {
+        return message.to_string();
+    };
+    let rest = &message[start + " at line ".len()..];
+    let digits =
+        |text: &str| text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let line = digits(rest);
+    let Some(after) = rest[line..].strip_prefix(" column ") else {
+        return message.to_string();
+    };
+    let end = message.len() - after.len() + digits(after);
+    format!("{}{}", &message[..start], &message[end..])
+}
+
+/// pest 的错误是多行的图示，取最后的 `= expected ...` 一行
+fn pest_message(msg: &str) -> String {
+    msg.lines()
+        .rev()
+        .find_map(|line| line.trim_start().strip_prefix("= "))
+        .unwrap_or(msg)
+        .to_string()
+}
+
+/// 第一个出错的节点：路径、说明（跟在路径之后）与提示
+struct BadNode {
+    path: Vec<String>,
+    problem: String,
+    hint: String,
+}
+
+/// 按通用的值重新解析，按文档顺序找出第一个既不是文件内容也不是目录的节点
+fn find_bad_node(format: Format, content: &str) -> Option<BadNode> {
+    let value: Value = match format {
+        Format::Yaml => serde_yaml::from_str(content).ok()?,
+        Format::Json => serde_json::from_str(content).ok()?,
+        Format::Toml => serde_json::to_value(toml::from_str::<toml::Value>(content).ok()?).ok()?,
+        Format::Json5 => json5::from_str(content).ok()?,
+        Format::Markdown | Format::Paths => return None,
+    };
+    let mut path = Vec::new();
+    value
+        .as_object()?
+        .iter()
+        .filter(|(key, _)| !SETTINGS.contains(&key.as_str()))
+        .find_map(|(key, value)| {
+            path.push(key.clone());
+            let found = bad_node(value, &mut path);
+            path.pop();
+            found
+        })
+}
+
+fn bad_node(value: &Value, path: &mut Vec<String>) -> Option<BadNode> {
+    let map = match value {
+        Value::String(_) => return None,
+        Value::Object(map) => map,
+        _ => {
+            let kind = kind(value);
+            let mut hint = format!("values must be strings or nested maps — found {}", kind);
+            if value.is_null() {
+                hint.push_str("; write \"\" for an empty file or {} for an empty directory");
+            }
+            return Some(BadNode {
+                path: path.clone(),
+                problem: format!("is neither file content nor a directory ({})", kind),
+                hint,
+            });
+        }
+    };
+    if serde_json::from_value::<SerdeNode>(value.clone()).is_ok() {
+        return None;
+    }
+    let children = match map.get("type") {
+        // 带类型的节点只有 children 中是子节点，其余字段是元数据
+        Some(Value::String(_)) => map.get("children").and_then(Value::as_object),
+        _ => Some(map),
+    };
+    let found = children.into_iter().flatten().find_map(|(key, child)| {
+        path.push(key.clone());
+        let found = bad_node(child, path);
+        path.pop();
+        found
+    });
+    found.or_else(|| {
+        let Some(Value::String(kind)) = map.get("type") else {
+            return None;
+        };
+        let err = serde_json::from_value::<MetaNode>(value.clone()).err()?;
+        Some(BadNode {
+            path: path.clone(),
+            problem: format!("is not a valid '{}' node: {}", kind, err),
+            hint: "see `treegen schema` for the fields of each node type".to_string(),
+        })
+    })
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "list",
+        Value::Object(_) => "map",
+    }
+}
+
+/// 依次在文本中查找路径上的每个键，返回最后一个键的值所在的位置（值在下一行时为键的位置）
+fn locate(content: &str, path: &[String]) -> Option<(usize, usize)> {
+    let mut from = 0;
+    let mut key_at = 0;
+    for key in path {
+        key_at = from + find_key(&content[from..], key)?;
+        from = key_at + key.len();
+    }
+    let rest = &content[from..];
+    let value = rest
+        .trim_start_matches(['"', '\''])
+        .trim_start_matches([' ', '\t'])
+        .strip_prefix([':', '='])
+        .map(|rest| rest.trim_start_matches([' ', '\t']))
+        .filter(|rest| !rest.is_empty() && !rest.starts_with(['\n', '\r', '#']));
+    let at = value.map_or(key_at, |value| content.len() - value.len());
+    let line_start = content[..at].rfind('\n').map_or(0, |i| i + 1);
+    Some((
+        content[..at].matches('\n').count() + 1,
+        content[line_start..at].chars().count() + 1,
+    ))
+}
+
+/// 作为完整的键出现的 `key`：前后不是键名中的字符
+fn find_key(text: &str, key: &str) -> Option<usize> {
+    let is_boundary =
+        |c: Option<char>| c.is_none_or(|c| c.is_whitespace() || "\"'{}[],.:=".contains(c));
+    text.match_indices(key).map(|(at, _)| at).find(|&at| {
+        is_boundary(text[..at].chars().next_back())
+            && is_boundary(text[at + key.len()..].chars().next())
+    })
+}
+
+/// 常见语法错误的提示
+fn syntax_hint(format: Format, message: &str, excerpt: Option<&str>) -> Option<&'static str> {
+    let hint = match format {
+        Format::Yaml
+            if excerpt.is_some_and(|line| {
+                line[..line.len() - line.trim_start().len()].contains('\t')
+            }) =>
+        {
+            "YAML indentation must use spaces, not tabs"
+        }
+        Format::Yaml if message.contains("mapping values are not allowed") => {
+            "quote values that contain ': ', e.g. key: \"a: b\""
+        }
+        Format::Json if message.contains("trailing comma") => {
+            "JSON does not allow trailing commas; JSON5 (.json5) does"
+        }
+        Format::Json if message.contains("key must be a string") => {
+            "JSON keys must be in double quotes; JSON5 (.json5) allows bare keys"
+        }
+        _ => return None,
+    };
+    Some(hint)
+}
@@ -0,0 +1,490 @@
+//! 两棵 [`Node`] 树之间的结构差异（`treegen diff`）：按路径对齐后逐项比较，
+//! 因此两侧的描述文件可以是不同格式。
+//!
+//! ```
+//! use treegen::diff::{diff, Change};
+//!
+//! let old = treegen::parse_yaml("src:\n  main.rs: old\n  lib.rs: ''\nLICENSE: MIT\ndocs: {}\n")?;
+//! let new = treegen::parse_markdown("src/\n├── main.rs\n└── util.rs\nLICENSE\ndocs\n")?;
+//! let found: Vec<_> = diff(&old.root, &new.root)
+//!     .into_iter()
+//!     .map(|d| (d.change, d.path.display().to_string()))
+//!     .collect();
+//! assert_eq!(
+//!     found,
+//!     [
+//!         (Change::Changed, "LICENSE".to_string()),
+//!         (Change::Changed, "docs".to_string()),
+//!         (Change::Removed, "src/lib.rs".to_string()),
+//!         (Change::Changed, "src/main.rs".to_string()),
+//!         (Change::Added, "src/util.rs".to_string()),
+//!     ]
+//! );
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::{
+    archive::{Archive, Member},
+    content_generator, normalize, plan_hardlinks, refuse_symlink, render_vars, Attrs, CopyRules,
+    CopySource, GenerateOptions, Node, NodeType,
+};
+use anyhow::{bail, Context, Result};
+use globset::GlobSet;
+use similar::TextDiff;
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// 差异类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Change {
+    /// 只在新树中存在
+    Added,
+    /// 只在旧树中存在
+    Removed,
+    /// 两侧都有，但类型、内容或元数据不同
+    Changed,
+}
+
+impl Change {
+    /// 输出时的前缀符号
+    pub fn symbol(self) -> char {
+        match self {
+            Change::Added => '+',
+            Change::Removed => '-',
+            Change::Changed => '~',
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Change::Added => "added",
+            Change::Removed => "removed",
+            Change::Changed => "changed",
+        })
+    }
+}
+
+/// 一处差异；文件内容不同时带上两侧的内容，用于 [`unified`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub change: Change,
+    pub path: PathBuf,
+    /// 简短说明，如 "file -> directory"、"content differs"
+    pub message: String,
+    /// 旧/新文件内容（仅两侧都是文件且内容不同时）
+    pub contents: Option<(String, String)>,
+}
+
+/// 比较两棵树，结果按路径排序；新增或删除的目录会连同其下每个路径一起列出
+pub fn diff(old: &Node, new: &Node) -> Vec<Difference> {
+    let old = entries(old);
+    let new = entries(new);
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        let order = match (old.get(i), new.get(j)) {
+            (Some((a, _)), Some((b, _))) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+        match order {
+            Ordering::Less => {
+                let (path, node) = &old[i];
+                out.push(Difference {
+                    change: Change::Removed,
+                    path: path.clone(),
+                    message: format!("{} removed", kind(node)),
+                    contents: None,
+                });
+                i += 1;
+            }
+            Ordering::Greater => {
+                let (path, node) = &new[j];
+                out.push(Difference {
+                    change: Change::Added,
+                    path: path.clone(),
+                    message: format!("{} added", kind(node)),
+                    contents: None,
+                });
+                j += 1;
+            }
+            Ordering::Equal => {
+                let (path, a) = &old[i];
+                let (_, b) = &new[j];
+                if let Some(difference) = compare(path, a, b) {
+                    out.push(difference);
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out
+}
+
+/// 比较描述与磁盘上的目录 `out`（`--check`）：[`Change::Removed`] 为目录中缺失的路径，
+/// [`Change::Added`] 为目录中多出的路径。匹配 `exclude` 的路径（及其下所有路径）不参与比较。
+/// 复制节点按来源展开，硬链接按目标的内容比较；属主、扩展属性等元数据不检查
+pub fn diff_dir(root: &Node, out: &Path, exclude: &GlobSet) -> Result<Vec<Difference>> {
+    let expected = expected_tree(root, out)?;
+    let mut actual = Node::new_dir(String::new());
+    if out.is_dir() {
+        actual.children = scan(out, Path::new(""), false, &|rel, _| excluded(exclude, rel))?;
+    }
+    Ok(diff(&expected, &actual)
+        .into_iter()
+        .filter(|d| !excluded(exclude, &d.path))
+        .collect())
+}
+
+/// 计划路径与输出目录中已有内容的关系（dry-run 的标注）
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Status {
+    /// 目录中还没有
+    New,
+    /// 已存在且与描述一致（目录：其下所有计划路径都一致）
+    Identical,
+    /// 已存在但内容不同（目录：其下有新建、不同或冲突的路径）
+    Differs,
+    /// 已存在但类型不符，如计划的文件处已是目录；说明形如 "file -> directory"
+    Conflict(String),
+}
+
+impl Status {
+    /// 树中标注的符号
+    pub fn symbol(&self) -> char {
+        match self {
+            Status::New => '+',
+            Status::Identical => '=',
+            Status::Differs => '~',
+            Status::Conflict(_) => '!',
+        }
+    }
+}
+
+/// 按 [`diff_dir`] 的方式比较描述与输出目录 `out`，给描述中的每个路径（键与
+/// [`Node::render_tree`] 传给标注函数的路径相同）一个 [`Status`]
+///
+/// ```
+/// use treegen::diff::{status_dir, Status};
+///
+/// let out = tempfile::tempdir()?;
+/// std::fs::write(out.path().join("README.md"), "old")?;
+/// std::fs::create_dir(out.path().join("main.rs"))?;
+/// let spec = treegen::parse_yaml("README.md: new\nmain.rs: ''\nsrc: { lib.rs: '' }\n")?;
+/// let status = status_dir(&spec.root, out.path())?;
+/// assert_eq!(status[std::path::Path::new("README.md")], Status::Differs);
+/// assert_eq!(status[std::path::Path::new("src/lib.rs")], Status::New);
+/// assert_eq!(
+///     status[std::path::Path::new("main.rs")],
+///     Status::Conflict("file -> directory".to_string())
+/// );
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn status_dir(root: &Node, out: &Path) -> Result<BTreeMap<PathBuf, Status>> {
+    // diff_dir 中的 Removed 是目录中缺失的路径，Added（目录中多出的路径）与计划无关
+    let differences: BTreeMap<PathBuf, Difference> = diff_dir(root, out, &GlobSet::empty())?
+        .into_iter()
+        .filter(|d| d.change != Change::Added)
+        .map(|d| (d.path.clone(), d))
+        .collect();
+    let mut status = BTreeMap::new();
+    for (path, _) in entries(root) {
+        let found = match differences.get(&path) {
+            Some(d) if d.change == Change::Removed => Status::New,
+            Some(d) if d.contents.is_none() && d.message.contains(" -> ") => {
+                Status::Conflict(d.message.clone())
+            }
+            Some(_) => Status::Differs,
+            // 目录与复制节点看其下的路径
+            None if differences.keys().any(|p| p.starts_with(&path)) => Status::Differs,
+            None => Status::Identical,
+        };
+        status.insert(path, found);
+    }
+    Ok(status)
+}
+
+/// 路径本身或其任一上级匹配 `exclude`
+fn excluded(exclude: &GlobSet, path: &Path) -> bool {
+    path.ancestors()
+        .filter(|p| !p.as_os_str().is_empty())
+        .any(|p| exclude.is_match(p))
+}
+
+/// 生成后磁盘上应有的树：复制节点按与生成相同的规则（忽略、`exclude`/`include`、变量替换）
+/// 替换为来源的内容，硬链接替换为目标内容的文件，去掉元数据；展开失败的可选节点去掉
+fn expected_tree(root: &Node, out: &Path) -> Result<Node> {
+    fn expand(node: &mut Node, out: &Path, path: &Path, opts: &GenerateOptions) -> Result<()> {
+        let path = path.join(node.name.trim_end_matches('/'));
+        // 占位文件与生成时一样只比较大小：大小相符时取目录中的内容，否则是一行说明
+        if let Some(placeholder) = node.attrs.placeholder {
+            let actual = out.join(&path);
+            node.content = Some(match fs::metadata(&actual) {
+                Ok(meta) if meta.is_file() && meta.len() == placeholder.size => {
+                    read_lossy(&actual)?
+                }
+                _ => format!(
+                    "<{} bytes, fill: {}>\n",
+                    placeholder.size,
+                    placeholder.fill.name()
+                ),
+            });
+        }
+        // 内容生成器（@uuid 等）的值每次生成都可能不同，已有的文件即视为一致
+        if let Some(Ok(generator)) = content_generator(node) {
+            let actual = out.join(&path);
+            node.content = Some(if actual.is_file() {
+                read_lossy(&actual)?
+            } else {
+                format!("<{}>\n", generator.name())
+            });
+        }
+        node.attrs = Attrs::default();
+        if let Some(source) = node.copy.take() {
+            let name = node.name.clone();
+            *node = match &source.archive {
+                Some(zip) => archived(zip, &source, name, &path, opts)?,
+                None if source.from.is_dir() => {
+                    let rules = CopyRules::for_dir(&source, &path, opts)?;
+                    let mut dir = Node::new_dir(name);
+                    dir.children = scan(&source.from, Path::new(""), true, &|rel, is_dir| {
+                        rules.skips(rel, is_dir)
+                    })?;
+                    substitute(&mut dir, &rules, &path)?;
+                    dir
+                }
+                None => {
+                    let mut file = Node::new_file(name, Some(read_lossy(&source.from)?));
+                    substitute(&mut file, &CopyRules::new(&source, None, opts)?, &path)?;
+                    file
+                }
+            };
+            return Ok(());
+        }
+        // 展开失败的可选节点（如复制来源不存在）生成时只会警告，不会出现在目录中
+        let mut result = Ok(());
+        node.children.retain_mut(|child| {
+            let optional = child.attrs.optional;
+            match result.is_ok().then(|| expand(child, out, &path, opts)) {
+                Some(Err(_)) if optional => false,
+                Some(Err(e)) => {
+                    result = Err(e);
+                    true
+                }
+                _ => true,
+            }
+        });
+        result
+    }
+
+    let mut expected = root.clone();
+    expand(
+        &mut expected,
+        out,
+        Path::new(""),
+        &GenerateOptions::default(),
+    )?;
+    // 按依赖顺序解析硬链接，指向其他硬链接的也能拿到内容
+    for (rel, target) in plan_hardlinks(root, out)? {
+        let content = match find(&expected, target) {
+            Some(node) => node.content.clone().unwrap_or_default(),
+            None => read_lossy(&out.join(target))?,
+        };
+        if let Some(node) = find_mut(&mut expected, &rel) {
+            *node = Node::new_file(node.name.clone(), Some(content));
+        }
+    }
+    Ok(expected)
+}
+
+/// 按路径查找节点（路径相对于未命名的根节点）
+fn find<'a>(root: &'a Node, path: &Path) -> Option<&'a Node> {
+    normalize(path).iter().try_fold(root, |node, name| {
+        node.children
+            .iter()
+            .find(|c| c.name.trim_end_matches('/') == name)
+    })
+}
+
+fn find_mut<'a>(root: &'a mut Node, path: &Path) -> Option<&'a mut Node> {
+    normalize(path).iter().try_fold(root, |node, name| {
+        node.children
+            .iter_mut()
+            .find(|c| c.name.trim_end_matches('/') == name)
+    })
+}
+
+/// 复制出的文本文件替换变量（`substitute` 时）；二进制文件读入时已有替换字符，保持原样
+fn substitute(node: &mut Node, rules: &CopyRules, path: &Path) -> Result<()> {
+    let Some(vars) = rules.vars else {
+        return Ok(());
+    };
+    let text = node.content.as_deref().unwrap_or("");
+    if node.node_type == NodeType::File && !text.contains(['\0', '\u{FFFD}']) {
+        node.content = Some(render_vars(text, vars, path)?);
+    }
+    for child in &mut node.children {
+        substitute(child, rules, &path.join(&child.name))?;
+    }
+    Ok(())
+}
+
+/// 压缩包中的复制来源会复制出的树（跳过忽略、`exclude` 匹配或 `include` 未选中的路径）
+fn archived(
+    zip: &Path,
+    source: &CopySource,
+    name: String,
+    path: &Path,
+    opts: &GenerateOptions,
+) -> Result<Node> {
+    let mut archive = Archive::open(zip)?;
+    let from = &source.from;
+    let (dirs, files) = match archive.member(from) {
+        None => bail!("Copy source '{}' does not exist", source.display()),
+        Some(Member::File(_)) => {
+            let content = String::from_utf8_lossy(&archive.read(from)?).into_owned();
+            let mut file = Node::new_file(name, Some(content));
+            substitute(&mut file, &CopyRules::new(source, None, opts)?, path)?;
+            return Ok(file);
+        }
+        Some(Member::Dir { dirs, files }) => (dirs, files),
+    };
+    let rules = CopyRules::for_archive(&mut archive, source, &files, path, opts)?;
+    let excluded = |rel: &Path| rules.drops_member(rel) || !rules.keeps(rel);
+    let mut root = Node::new_dir(name);
+    // 上级目录排在前面，插入时父节点总是已经存在
+    for dir in dirs.iter().filter(|dir| !excluded(dir)) {
+        let parent = dir.parent().unwrap_or(Path::new(""));
+        if let Some(parent) = find_mut(&mut root, parent) {
+            let name = dir.file_name().unwrap_or_default().to_string_lossy();
+            parent.children.push(Node::new_dir(name.into_owned()));
+        }
+    }
+    for (file, _) in files.iter().filter(|(file, _)| !excluded(file)) {
+        let content = String::from_utf8_lossy(&archive.read(&from.join(file))?).into_owned();
+        let parent = file.parent().unwrap_or(Path::new(""));
+        if let Some(parent) = find_mut(&mut root, parent) {
+            let name = file.file_name().unwrap_or_default().to_string_lossy();
+            parent
+                .children
+                .push(Node::new_file(name.into_owned(), Some(content)));
+        }
+    }
+    substitute(&mut root, &rules, path)?;
+    Ok(root)
+}
+
+/// 读入 `dir/rel` 下的条目（按名称排序），`skip` 对其返回 true 的路径（相对 `dir`，及是否为目录）不读入；
+/// `source` 为复制来源时与复制一样拒绝其中的符号链接
+fn scan(
+    dir: &Path,
+    rel: &Path,
+    source: bool,
+    skip: &dyn Fn(&Path, bool) -> bool,
+) -> Result<Vec<Node>> {
+    let path = dir.join(rel);
+    let mut entries = fs::read_dir(&path)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read directory '{}'", path.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    let mut nodes = Vec::new();
+    for entry in entries {
+        let child = rel.join(entry.file_name());
+        let is_dir = match source {
+            true => {
+                let meta = fs::symlink_metadata(entry.path())
+                    .with_context(|| format!("Failed to read '{}'", entry.path().display()))?;
+                if !skip(&child, meta.is_dir()) {
+                    refuse_symlink(&entry.path(), &meta)?;
+                }
+                meta.is_dir()
+            }
+            false => entry.path().is_dir(),
+        };
+        if skip(&child, is_dir) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if is_dir {
+            let mut node = Node::new_dir(name);
+            node.children = scan(dir, &child, source, skip)?;
+            nodes.push(node);
+        } else {
+            nodes.push(Node::new_file(name, Some(read_lossy(&entry.path())?)));
+        }
+    }
+    Ok(nodes)
+}
+
+/// 文件内容；不是 UTF-8 的字节按替换字符读入（描述中的内容总是 UTF-8，必然不相等）
+fn read_lossy(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// 文件内容的统一格式 diff（带 `a/`、`b/` 前缀的文件头）
+pub fn unified(path: &Path, old: &str, new: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(
+            &format!("a/{}", path.display()),
+            &format!("b/{}", path.display()),
+        )
+        .to_string()
+}
+
+/// 按路径排序的所有节点（不含未命名的根节点），路径去掉目录名末尾的 `/`
+fn entries(root: &Node) -> Vec<(PathBuf, &Node)> {
+    let mut entries: Vec<(PathBuf, &Node)> = root
+        .iter()
+        .filter(|(path, _)| !path.as_os_str().is_empty())
+        .map(|(path, node)| (path.components().collect(), node))
+        .collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries
+}
+
+/// 同一路径上两个节点的差异
+fn compare(path: &Path, old: &Node, new: &Node) -> Option<Difference> {
+    let changed = |message: String, contents| {
+        Some(Difference {
+            change: Change::Changed,
+            path: path.to_path_buf(),
+            message,
+            contents,
+        })
+    };
+    if old.node_type != new.node_type {
+        return changed(format!("{} -> {}", kind(old), kind(new)), None);
+    }
+    // 没有内容（如 Markdown 树中的文件）等同于空文件
+    let (a, b) = (
+        old.content.as_deref().unwrap_or(""),
+        new.content.as_deref().unwrap_or(""),
+    );
+    if old.node_type == NodeType::File && a != b {
+        let contents = (a.to_string(), b.to_string());
+        return changed("content differs".to_string(), Some(contents));
+    }
+    if old.copy != new.copy || old.target != new.target || old.attrs != new.attrs {
+        return changed("metadata differs".to_string(), None);
+    }
+    None
+}
+
+/// 节点类型的名称
+fn kind(node: &Node) -> &'static str {
+    match node.node_type {
+        NodeType::Dir => "directory",
+        NodeType::File => "file",
+        NodeType::Copy => "copy",
+        NodeType::Hardlink => "hardlink",
+    }
+}
@@ -0,0 +1,129 @@
+//! 生成失败的路径（`--keep-going` 时收集全部，否则只有第一个）：按 [`std::io::ErrorKind`]
+//! 归类，显示时分组列出路径并给出处理建议，同样的结构也写进 `--summary-json`。
+//!
+//! ```
+//! use std::io;
+//! use treegen::failure::{Failure, FailureKind, FailureReport};
+//!
+//! let err = anyhow::Error::new(io::Error::from(io::ErrorKind::PermissionDenied))
+//!     .context("Failed to write file 'out/a.txt'");
+//! let failure = Failure::from_error("a.txt".into(), &err);
+//! assert_eq!(failure.kind, FailureKind::PermissionDenied);
+//! let report = FailureReport(vec![failure]).to_string();
+//! assert!(report.starts_with("1 path(s) could not be generated"));
+//! assert!(report.contains("Permission denied (1):\n  - a.txt: Failed to write file"));
+//! ```
+
+use serde::Serialize;
+use std::{fmt, io, path::PathBuf};
+
+/// 失败的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    /// 没有权限写入
+    PermissionDenied,
+    /// 只读文件系统
+    ReadOnlyFilesystem,
+    /// 名称或路径过长
+    NameTooLong,
+    /// 磁盘或配额已满
+    DiskFull,
+    /// 其他错误（包括不是 IO 的错误）
+    Other,
+}
+
+impl FailureKind {
+    /// 按 IO 错误的类型归类
+    pub fn classify(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => FailureKind::PermissionDenied,
+            io::ErrorKind::ReadOnlyFilesystem => FailureKind::ReadOnlyFilesystem,
+            io::ErrorKind::InvalidFilename => FailureKind::NameTooLong,
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => FailureKind::DiskFull,
+            _ => FailureKind::Other,
+        }
+    }
+
+    /// 分组标题
+    pub fn title(self) -> &'static str {
+        match self {
+            FailureKind::PermissionDenied => "Permission denied",
+            FailureKind::ReadOnlyFilesystem => "Read-only file system",
+            FailureKind::NameTooLong => "Name too long",
+            FailureKind::DiskFull => "Disk full",
+            FailureKind::Other => "Other errors",
+        }
+    }
+
+    /// 处理建议
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            FailureKind::PermissionDenied => Some(
+                "check the ownership and permissions of the parent directories, \
+                 or run as a user that can write there (see --chown)",
+            ),
+            FailureKind::ReadOnlyFilesystem => {
+                Some("the target is mounted read-only; remount it read-write or choose another --out")
+            }
+            FailureKind::NameTooLong => {
+                Some("shorten the names; most file systems allow 255 bytes per name")
+            }
+            FailureKind::DiskFull => Some(
+                "free up space or quota on the target file system; --dry-run estimates the size needed",
+            ),
+            FailureKind::Other => None,
+        }
+    }
+}
+
+/// 一个没能生成的路径
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Failure {
+    /// 相对输出目录的路径
+    pub path: PathBuf,
+    pub kind: FailureKind,
+    /// 完整的错误说明（含各层上下文）
+    pub error: String,
+}
+
+impl Failure {
+    /// 从错误链中找出 IO 错误归类；找不到时为 [`FailureKind::Other`]
+    pub fn from_error(path: PathBuf, error: &anyhow::Error) -> Self {
+        let kind = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<io::Error>())
+            .map_or(FailureKind::Other, FailureKind::classify);
+        Failure {
+            path,
+            kind,
+            error: format!("{:#}", error),
+        }
+    }
+}
+
+/// 按类型分组显示的失败列表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureReport(pub Vec<Failure>);
+
+impl fmt::Display for FailureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} path(s) could not be generated", self.0.len())?;
+        let mut kinds: Vec<FailureKind> = self.0.iter().map(|failure| failure.kind).collect();
+        kinds.sort();
+        kinds.dedup();
+        for kind in kinds {
+            let group: Vec<&Failure> = self.0.iter().filter(|f| f.kind == kind).collect();
+            write!(f, "\n{} ({}):", kind.title(), group.len())?;
+            for failure in group {
+                write!(f, "\n  - {}: {}", failure.path.display(), failure.error)?;
+            }
+            if let Some(hint) = kind.hint() {
+                write!(f, "\n  hint: {}", hint)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FailureReport {}
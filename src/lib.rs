@@ -0,0 +1,7873 @@
+//! treegen 核心库：解析 Markdown/YAML/JSON/TOML/JSON5 描述文件为 [`Node`] 树，
+//! 并在磁盘上生成对应的目录和文件。命令行工具 `treegen` 只是这个库的一个使用者。
+//!
+//! ```no_run
+//! use treegen::{generate, parse_yaml, GenerateOptions};
+//!
+//! let spec = parse_yaml("app:\n  src:\n    main.rs: 'fn main() {}'\n  README.md: ''\n")?;
+//! assert!(spec.root.find("app/src/main.rs").is_some());
+//! let stats = generate(&spec.root, "out".as_ref(), &GenerateOptions::default())?;
+//! println!("{} files", stats.files);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+pub mod archive;
+pub mod checksum;
+pub mod diagnostic;
+pub mod diff;
+pub mod failure;
+pub mod lint;
+pub mod lock;
+pub mod preflight;
+pub mod remote;
+pub mod render;
+
+/// 支持的描述文件扩展名
+pub const SPEC_EXTENSIONS: &[&str] = &[
+    "md", "yaml", "yml", "json", "toml", "json5", "paths", "list",
+];
+
+/// 节点类型：目录或文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeType {
+    Dir,
+    File,
+    /// 从磁盘上已有的文件或目录复制（见 [`CopySource`]）
+    Copy,
+    /// 指向另一个文件的硬链接（见 [`Node::target`]）
+    Hardlink,
+}
+
+/// 树节点结构
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub node_type: NodeType,
+    pub children: Vec<Node>,
+    pub content: Option<String>, // 用于 YAML/JSON/TOML/JSON5 中指定文件内容
+    /// 复制来源，仅 [`NodeType::Copy`] 节点有
+    pub copy: Option<CopySource>,
+    /// 链接目标（相对输出根目录），仅 [`NodeType::Hardlink`] 节点有
+    pub target: Option<PathBuf>,
+    /// 附加属性（属主等），来自带类型节点的元数据
+    pub attrs: Attrs,
+}
+
+/// 节点的附加属性
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attrs {
+    /// 属主：用户名或数字 uid（仅类 Unix 平台生效）
+    pub owner: Option<String>,
+    /// 属组：组名或数字 gid（仅类 Unix 平台生效）
+    pub group: Option<String>,
+    /// 扩展属性：名称 -> 值，值带 [`XATTR_BASE64_PREFIX`] 前缀时按 base64 解码
+    pub xattrs: BTreeMap<String, String>,
+    /// 所属的 profile；为空的节点总会生成，见 [`filter_profiles`]
+    pub tags: BTreeSet<String>,
+    /// 文件内容不做模板变量替换（见 [`substitute_vars`]）
+    pub raw: bool,
+    /// 去掉文件内容的行尾空白并压缩连续的空行（见 [`trim_trailing_whitespace`]）
+    pub trim: bool,
+    /// 文件权限，覆盖 [`GenerateOptions::mode`]（仅类 Unix 平台生效）
+    pub mode: Option<u32>,
+    /// 文件已存在时的处理，覆盖命令行的策略（[`GenerateOptions::skip_existing`]）
+    pub if_exists: Option<IfExists>,
+    /// 结构化的文件内容；`content` 是它序列化后的文本，变量替换后重新序列化
+    pub structured: Option<Structured>,
+    /// 不是 UTF-8 的文件内容（如 `--stdin-content` 读入的二进制），按字节原样写入；
+    /// 此时 `content` 只是它的有损文本形式。不会写回描述文件
+    pub bytes: Option<Vec<u8>>,
+    /// 可选节点：生成时出错（如复制来源不存在）只记入 [`Stats::warnings`] 并继续，
+    /// 其下的路径一并跳过；不受 [`GenerateOptions::keep_going`] 影响
+    pub optional: bool,
+    /// 只声明大小的占位文件（此时 `content` 为空）
+    pub placeholder: Option<Placeholder>,
+    /// 内容替换了要隐去的变量（见 [`mark_redacted`]），报告中不给出它的哈希。
+    /// 不会写回描述文件
+    pub redacted: bool,
+}
+
+/// 结构化文件内容的序列化格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    /// 缩进两格的 JSON
+    Json,
+    Yaml,
+    Toml,
+}
+
+/// 按 `format` 序列化写入文件的结构化内容（`{ type: file, format: json, content: {...} }`），
+/// 字符串值中的模板变量在序列化之前替换
+///
+/// ```
+/// use treegen::{ContentFormat, Structured};
+///
+/// let value = serde_json::json!({ "name": "demo", "private": true });
+/// let json = Structured { format: ContentFormat::Json, value: value.clone() };
+/// assert_eq!(json.render()?, "{\n  \"name\": \"demo\",\n  \"private\": true\n}\n");
+/// let toml = Structured { format: ContentFormat::Toml, value };
+/// assert_eq!(toml.render()?, "name = \"demo\"\nprivate = true\n");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Structured {
+    pub format: ContentFormat,
+    pub value: serde_json::Value,
+}
+
+impl Structured {
+    /// 序列化为文件内容（以换行结尾）
+    pub fn render(&self) -> Result<String> {
+        let mut text = match self.format {
+            ContentFormat::Json => serde_json::to_string_pretty(&self.value)?,
+            ContentFormat::Yaml => serde_yaml::to_string(&self.value)?,
+            ContentFormat::Toml => {
+                if !self.value.is_object() {
+                    bail!("TOML content must be a table at the top level");
+                }
+                // 先转成 toml::Value，由它把表排在标量之后
+                toml::to_string(&toml::Value::try_from(&self.value)?)?
+            }
+        };
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// 替换所有字符串值中的模板变量（键不替换）
+    fn substitute(&mut self, vars: &BTreeMap<String, String>, path: &Path) -> Result<()> {
+        fn walk(
+            value: &mut serde_json::Value,
+            vars: &BTreeMap<String, String>,
+            path: &Path,
+        ) -> Result<()> {
+            match value {
+                serde_json::Value::String(text) => *text = render_vars(text, vars, path)?,
+                serde_json::Value::Array(items) => {
+                    for item in items {
+                        walk(item, vars, path)?;
+                    }
+                }
+                serde_json::Value::Object(map) => {
+                    for item in map.values_mut() {
+                        walk(item, vars, path)?;
+                    }
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+        walk(&mut self.value, vars, path)
+    }
+}
+
+/// 要生成的文件已存在（且内容不同）时的处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IfExists {
+    /// 重写为新内容；内容相同时不重写
+    #[default]
+    Overwrite,
+    /// 原样保留，不改内容与权限
+    Skip,
+    /// 报错
+    Error,
+    /// 原文件改名为 `<名称>.bak`（覆盖之前的备份）后写入新内容
+    Backup,
+    /// 把新内容追加到原文件末尾
+    Append,
+}
+
+impl IfExists {
+    /// 所有取值，与 [`IfExists::name`] 一致
+    pub const NAMES: &'static [&'static str] = &["overwrite", "skip", "error", "backup", "append"];
+
+    /// 元数据中的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            IfExists::Overwrite => "overwrite",
+            IfExists::Skip => "skip",
+            IfExists::Error => "error",
+            IfExists::Backup => "backup",
+            IfExists::Append => "append",
+        }
+    }
+
+    /// 按名称解析
+    ///
+    /// ```
+    /// use treegen::IfExists;
+    /// assert_eq!(IfExists::parse("backup"), Some(IfExists::Backup));
+    /// assert_eq!(IfExists::parse("replace"), None);
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            IfExists::Overwrite,
+            IfExists::Skip,
+            IfExists::Error,
+            IfExists::Backup,
+            IfExists::Append,
+        ]
+        .into_iter()
+        .find(|policy| policy.name() == name)
+    }
+}
+
+/// 占位文件（`{ type: file, size: 50MiB, fill: ... }`）的填充方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fill {
+    /// 写入零字节
+    #[default]
+    Zero,
+    /// 只设置文件长度（`set_len`），支持稀疏文件的文件系统上不分配数据块；读出来同样是零字节
+    Sparse,
+    /// 伪随机字节，由 [`GenerateOptions::seed`] 与文件路径决定
+    Random,
+}
+
+impl Fill {
+    /// 所有取值，与 [`Fill::name`] 一致
+    pub const NAMES: &'static [&'static str] = &["zero", "sparse", "random"];
+
+    /// 元数据中的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Fill::Zero => "zero",
+            Fill::Sparse => "sparse",
+            Fill::Random => "random",
+        }
+    }
+
+    /// 按名称解析
+    pub fn parse(name: &str) -> Option<Self> {
+        [Fill::Zero, Fill::Sparse, Fill::Random]
+            .into_iter()
+            .find(|fill| fill.name() == name)
+    }
+}
+
+/// 只声明大小的占位文件，内容在生成时按 `fill` 写出，不必写进描述文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placeholder {
+    /// 文件大小（字节）
+    pub size: u64,
+    pub fill: Fill,
+}
+
+impl Placeholder {
+    /// 文件内容；`seed` 只影响 [`Fill::Random`]
+    fn reader(&self, seed: u64) -> FillReader {
+        FillReader {
+            left: self.size,
+            state: (self.fill == Fill::Random).then_some(seed),
+            word: [0; 8],
+            used: 8,
+        }
+    }
+
+    /// 写出文件：sparse 只设置长度，其余按块流式写入，不在内存中放下整个文件
+    fn write(&self, path: &Path, seed: u64) -> Result<()> {
+        use std::io::Write as _;
+        let file = fs::File::create(path)
+            .with_context(|| format!("Failed to create file '{}'", path.display()))?;
+        let written = match self.fill {
+            Fill::Sparse => file.set_len(self.size),
+            Fill::Zero | Fill::Random => {
+                let mut writer = std::io::BufWriter::with_capacity(64 * 1024, file);
+                std::io::copy(&mut self.reader(seed), &mut writer).and_then(|_| writer.flush())
+            }
+        };
+        if written.is_err() {
+            // 不留下写了一半的文件（如磁盘已满），它可能占着大部分空间
+            let _ = fs::remove_file(path);
+        }
+        written.with_context(|| format!("Failed to write file '{}'", path.display()))
+    }
+}
+
+/// 占位文件的内容：零字节，或 SplitMix64 生成的伪随机字节（同一种子在任何平台上都相同）
+struct FillReader {
+    left: u64,
+    /// 随机填充时的生成器状态；`None` 时填零
+    state: Option<u64>,
+    /// 当前 8 字节的随机数及已用掉的字节数，保证输出与每次读取的长度无关
+    word: [u8; 8],
+    used: usize,
+}
+
+impl std::io::Read for FillReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf
+            .len()
+            .min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        match &mut self.state {
+            None => buf[..n].fill(0),
+            Some(state) => {
+                for byte in &mut buf[..n] {
+                    if self.used == 8 {
+                        self.word = splitmix64(state).to_le_bytes();
+                        self.used = 0;
+                    }
+                    *byte = self.word[self.used];
+                    self.used += 1;
+                }
+            }
+        }
+        self.left -= n as u64;
+        Ok(n)
+    }
+}
+
+/// SplitMix64 的下一个值
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// 由本次生成的种子与 `key`（如相对输出目录的路径）派生的种子：同一种子下不同的 key 互不相同，
+/// 种子相同时跨平台、跨版本可复现（FNV-1a，不依赖标准库哈希的实现）
+fn derive_seed(seed: u64, key: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in key {
+        hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+    let mut state = seed ^ hash;
+    splitmix64(&mut state)
+}
+
+/// 以 `/` 分隔的相对路径，作为 [`derive_seed`] 的 key（与平台的分隔符无关）
+fn seed_key(rel: &Path) -> Vec<u8> {
+    let mut key = Vec::new();
+    for component in rel.components() {
+        key.extend_from_slice(component.as_os_str().as_encoded_bytes());
+        key.push(b'/');
+    }
+    key
+}
+
+/// 生成时才算出值的文件内容：内容恰好是 `@uuid`、`@random:N`（N 个随机字节，N 可带单位，
+/// 见 [`parse_size`]）、`@timestamp`（RFC 3339）或 `@timestamp:unix` 时，每个文件各得一个值；
+/// 后面加上 `:shared:NAME` 的引用在同一次生成中共用一个值。`raw: true` 的文件不解析
+///
+/// ```
+/// use treegen::{ContentGenerator, GeneratorKind};
+///
+/// let uuid = ContentGenerator::parse("@uuid:shared:tenant").unwrap()?;
+/// assert_eq!(uuid.kind, GeneratorKind::Uuid);
+/// assert_eq!(uuid.shared.as_deref(), Some("tenant"));
+/// assert_eq!(ContentGenerator::parse("@random:1KiB").unwrap()?.kind, GeneratorKind::Random(1024));
+/// assert!(ContentGenerator::parse("@random").unwrap().is_err());
+/// assert!(ContentGenerator::parse("@types/node").is_none());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentGenerator {
+    pub kind: GeneratorKind,
+    /// `:shared:NAME` 的名称
+    pub shared: Option<String>,
+}
+
+/// [`ContentGenerator`] 生成的值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorKind {
+    /// 第 4 版 UUID（随机），小写，以换行结尾
+    Uuid,
+    /// 给定字节数的随机内容
+    Random(u64),
+    /// 开始生成的时间，RFC 3339 格式（UTC，精确到秒），以换行结尾
+    Timestamp,
+    /// 开始生成的时间，Unix 时间戳（秒），以换行结尾
+    UnixTimestamp,
+}
+
+impl ContentGenerator {
+    /// 解析文件内容；不是生成器引用时为 `None`（按普通内容写入），
+    /// 生成器名称正确但参数不对（如 `@random` 缺少字节数）时报错
+    pub fn parse(content: &str) -> Option<Result<Self>> {
+        let body = content.strip_prefix('@')?;
+        let (body, shared) = match body.split_once(":shared:") {
+            Some((body, name)) => (body, Some(name)),
+            None => (body, None),
+        };
+        let (name, arg) = match body.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (body, None),
+        };
+        if !["uuid", "random", "timestamp"].contains(&name) {
+            return None;
+        }
+        let kind = match (name, arg) {
+            ("uuid", None) => Ok(GeneratorKind::Uuid),
+            ("random", Some(size)) => parse_size(size)
+                .map(GeneratorKind::Random)
+                .with_context(|| format!("Invalid content generator '{}'", content)),
+            ("timestamp", None | Some("rfc3339")) => Ok(GeneratorKind::Timestamp),
+            ("timestamp", Some("unix")) => Ok(GeneratorKind::UnixTimestamp),
+            _ => Err(anyhow!(
+                "Invalid content generator '{}'; use @uuid, @random:N, @timestamp or @timestamp:unix, optionally followed by :shared:NAME",
+                content
+            )),
+        };
+        Some(match (kind, shared) {
+            (_, Some("")) => Err(anyhow!(
+                "Content generator '{}' needs a name after ':shared:'",
+                content
+            )),
+            (kind, shared) => kind.map(|kind| ContentGenerator {
+                kind,
+                shared: shared.map(str::to_string),
+            }),
+        })
+    }
+
+    /// 生成器的名称，如 `@random:256`（dry-run 中代替值显示）
+    pub fn name(&self) -> String {
+        let kind = match self.kind {
+            GeneratorKind::Uuid => "@uuid".to_string(),
+            GeneratorKind::Random(size) => format!("@random:{}", size),
+            GeneratorKind::Timestamp => "@timestamp".to_string(),
+            GeneratorKind::UnixTimestamp => "@timestamp:unix".to_string(),
+        };
+        match &self.shared {
+            Some(name) => format!("{}:shared:{}", kind, name),
+            None => kind,
+        }
+    }
+
+    /// 生成的字节数，用于估计写入量
+    pub(crate) fn len_hint(&self) -> u64 {
+        match self.kind {
+            GeneratorKind::Uuid => 37,
+            GeneratorKind::Random(size) => size,
+            GeneratorKind::Timestamp => 21,
+            GeneratorKind::UnixTimestamp => 11,
+        }
+    }
+
+    /// 生成 `rel` 处文件的内容：随机值由 [`GenerateOptions::seed`] 与路径（共用时为名称）决定
+    fn generate(&self, rel: &Path, opts: &GenerateOptions) -> Result<Vec<u8>> {
+        let key = match &self.shared {
+            Some(name) => format!("{}:shared:{}", self.kind_name(), name).into_bytes(),
+            None => seed_key(rel),
+        };
+        let seed = derive_seed(opts.seed.unwrap_or_default(), &key);
+        let now = || opts.timestamp.unwrap_or_else(std::time::SystemTime::now);
+        Ok(match self.kind {
+            GeneratorKind::Uuid => {
+                let mut state = seed;
+                let mut bytes = [0u8; 16];
+                bytes[..8].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+                bytes[8..].copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+                // 版本 4、RFC 4122 变体
+                bytes[6] = (bytes[6] & 0x0f) | 0x40;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+                let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!(
+                    "{}-{}-{}-{}-{}\n",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+                .into_bytes()
+            }
+            GeneratorKind::Random(size) => {
+                use std::io::Read as _;
+                let placeholder = Placeholder {
+                    size,
+                    fill: Fill::Random,
+                };
+                let mut bytes = Vec::with_capacity(usize::try_from(size).unwrap_or(0));
+                placeholder.reader(seed).read_to_end(&mut bytes)?;
+                bytes
+            }
+            GeneratorKind::Timestamp => {
+                let time = chrono::DateTime::<chrono::Utc>::from(now());
+                format!(
+                    "{}\n",
+                    time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                )
+                .into_bytes()
+            }
+            GeneratorKind::UnixTimestamp => {
+                let secs = now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs());
+                format!("{}\n", secs).into_bytes()
+            }
+        })
+    }
+
+    /// 共用值的 key 中的类型名（不含参数：同名的 `@random:16` 与 `@random:32` 前缀相同）
+    fn kind_name(&self) -> &'static str {
+        match self.kind {
+            GeneratorKind::Uuid => "uuid",
+            GeneratorKind::Random(_) => "random",
+            GeneratorKind::Timestamp | GeneratorKind::UnixTimestamp => "timestamp",
+        }
+    }
+}
+
+/// 文件节点内容中的生成器引用（见 [`ContentGenerator`]）；`raw` 或字节内容的节点没有
+pub(crate) fn content_generator(node: &Node) -> Option<Result<ContentGenerator>> {
+    if node.node_type != NodeType::File || node.attrs.raw || node.attrs.bytes.is_some() {
+        return None;
+    }
+    ContentGenerator::parse(node.content.as_deref()?)
+}
+
+/// 解析文件大小：字节数，或带 `B`、`KiB`、`MiB`、`GiB`、`TiB` 单位（不区分大小写，可有空格）
+///
+/// ```
+/// assert_eq!(treegen::parse_size("1024")?, 1024);
+/// assert_eq!(treegen::parse_size("50MiB")?, 50 * 1024 * 1024);
+/// assert_eq!(treegen::parse_size("2 kib")?, 2048);
+/// assert!(treegen::parse_size("1.5GiB").is_err());
+/// assert!(treegen::parse_size("10MB").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_size(text: &str) -> Result<u64> {
+    const UNITS: &[(&str, u64)] = &[
+        ("b", 1),
+        ("kib", 1 << 10),
+        ("mib", 1 << 20),
+        ("gib", 1 << 30),
+        ("tib", 1 << 40),
+    ];
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let unit = unit.trim_start().to_ascii_lowercase();
+    let multiplier = match unit.as_str() {
+        "" => Some(1),
+        unit => UNITS.iter().find(|(name, _)| *name == unit).map(|u| u.1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .zip(multiplier)
+        .and_then(|(number, multiplier)| number.checked_mul(multiplier))
+        .with_context(|| {
+            format!(
+                "Invalid size '{}'; use a byte count or a whole number with B, KiB, MiB, GiB or TiB",
+                text
+            )
+        })
+}
+
+/// 扩展属性值的前缀，其后为二进制值的 base64 编码
+pub const XATTR_BASE64_PREFIX: &str = "@base64:";
+
+/// 扩展属性值的实际字节
+///
+/// ```
+/// assert_eq!(treegen::xattr_value("text/plain").unwrap(), b"text/plain");
+/// assert_eq!(treegen::xattr_value("@base64:AAH/").unwrap(), [0, 1, 255]);
+/// assert!(treegen::xattr_value("@base64:%%").is_err());
+/// ```
+pub fn xattr_value(raw: &str) -> Result<Vec<u8>> {
+    match raw.strip_prefix(XATTR_BASE64_PREFIX) {
+        Some(encoded) => BASE64
+            .decode(encoded)
+            .with_context(|| format!("Invalid base64 value '{}'", encoded)),
+        None => Ok(raw.as_bytes().to_vec()),
+    }
+}
+
+impl Attrs {
+    fn is_empty(&self) -> bool {
+        let attrs = Attrs {
+            redacted: false,
+            ..self.clone()
+        };
+        attrs == Attrs::default()
+    }
+}
+
+/// `{ "type": "copy", "from": ... }` 节点的复制来源
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopySource {
+    /// 源文件或目录；解析描述文件时相对路径按描述文件所在目录解析
+    pub from: PathBuf,
+    /// 允许复制目录（递归复制其全部内容）
+    pub recursive: bool,
+    /// 递归复制时跳过的 glob（相对源目录匹配，如 `*.tmp`、`cache/**`）
+    pub exclude: Vec<String>,
+    /// 只复制匹配这些 glob 的文件（相对源目录，如 `**/*.rs`、`Cargo.toml`），保留相对结构，
+    /// 只创建包含选中文件的目录；为空时复制全部。一个文件都没有选中时报错
+    pub include: Vec<String>,
+    /// 复制的文本文件做模板变量替换；含 NUL 字节或不是 UTF-8 的文件原样复制
+    pub substitute: bool,
+    /// `substitute` 时使用的变量，由 [`substitute_vars`] 填入
+    pub vars: BTreeMap<String, String>,
+    /// 来源所在的 zip 压缩包（见 [`archive`]）：描述本身来自压缩包时，相对的 `from`
+    /// 是同一压缩包内的成员路径，生成时直接从压缩包写出
+    pub archive: Option<PathBuf>,
+}
+
+impl CopySource {
+    /// 用于日志与错误信息的来源（压缩包内的成员写成 `ARCHIVE!MEMBER`）
+    pub fn display(&self) -> String {
+        match &self.archive {
+            Some(path) => archive::display(path, &self.from),
+            None => self.from.display().to_string(),
+        }
+    }
+
+    /// 压缩包中的来源；不在压缩包中时为 `None`，打不开或没有该成员时为 `Some(None)`
+    fn member(&self) -> Option<Option<archive::Member>> {
+        let path = self.archive.as_ref()?;
+        Some(
+            archive::Archive::open(path)
+                .ok()
+                .and_then(|zip| zip.member(&self.from)),
+        )
+    }
+
+    /// 来源是目录
+    pub fn is_dir(&self) -> bool {
+        match self.member() {
+            Some(member) => matches!(member, Some(archive::Member::Dir { .. })),
+            None => self.from.is_dir(),
+        }
+    }
+
+    /// 递归复制的来源目录中有文件 `inside`
+    fn has_file(&self, inside: &Path) -> bool {
+        match self.member() {
+            Some(Some(archive::Member::Dir { files, .. })) => {
+                files.iter().any(|(rel, _)| rel == inside)
+            }
+            Some(_) => false,
+            None => self.from.join(inside).is_file(),
+        }
+    }
+}
+
+impl Node {
+    /// 构造一个文件节点（可携带内容）
+    pub fn new_file(name: String, content: Option<String>) -> Self {
+        Node {
+            name,
+            node_type: NodeType::File,
+            children: Vec::new(),
+            content,
+            copy: None,
+            target: None,
+            attrs: Attrs::default(),
+        }
+    }
+    /// 构造一个空目录节点
+    pub fn new_dir(name: String) -> Self {
+        Node {
+            name,
+            node_type: NodeType::Dir,
+            children: Vec::new(),
+            content: None,
+            copy: None,
+            target: None,
+            attrs: Attrs::default(),
+        }
+    }
+
+    /// 构造一个复制节点：生成时把 `source` 指向的文件或目录复制到此处
+    pub fn new_copy(name: String, source: CopySource) -> Self {
+        Node {
+            name,
+            node_type: NodeType::Copy,
+            children: Vec::new(),
+            content: None,
+            copy: Some(source),
+            target: None,
+            attrs: Attrs::default(),
+        }
+    }
+
+    /// 构造一个硬链接节点：`target` 相对输出根目录，可以是本次生成的文件，
+    /// 也可以是磁盘上已有的文件
+    pub fn new_hardlink(name: String, target: PathBuf) -> Self {
+        Node {
+            name,
+            node_type: NodeType::Hardlink,
+            children: Vec::new(),
+            content: None,
+            copy: None,
+            target: Some(target),
+            attrs: Attrs::default(),
+        }
+    }
+
+    /// 开始构建未命名的根目录（对应输出目录本身）
+    pub fn root() -> NodeBuilder {
+        NodeBuilder::new(Node::new_dir(String::new()))
+    }
+
+    /// 开始构建一个目录；名称不能为空
+    ///
+    /// ```
+    /// use treegen::Node;
+    ///
+    /// let root = Node::root()
+    ///     .child(
+    ///         Node::dir("demo")
+    ///             .file("Cargo.toml", "[package]\nname = \"demo\"\n")
+    ///             .child(Node::dir("src").file("main.rs", "fn main() {}\n").file_empty("lib.rs")),
+    ///     )
+    ///     .build()?;
+    /// assert!(root.find("demo/src/lib.rs").is_some());
+    /// # Ok::<(), treegen::NodeError>(())
+    /// ```
+    pub fn dir(name: impl Into<String>) -> NodeBuilder {
+        let name = name.into();
+        let mut builder = NodeBuilder::new(Node::new_dir(name.clone()));
+        if name.is_empty() {
+            builder.error = Some(NodeError::EmptyName);
+        }
+        builder
+    }
+
+    /// 比较用的名称：Markdown 中的目录名带有结尾的 /
+    fn key(&self) -> &str {
+        self.name.trim_end_matches('/')
+    }
+
+    /// 添加子节点；只有目录能有子节点，子节点名称不能为空
+    pub fn add_child(&mut self, child: Node) -> Result<(), NodeError> {
+        if self.node_type != NodeType::Dir {
+            return Err(NodeError::ChildOfFile {
+                file: self.name.clone(),
+            });
+        }
+        if child.name.is_empty() {
+            return Err(NodeError::EmptyName);
+        }
+        self.children.push(child);
+        Ok(())
+    }
+
+    /// 按相对路径查找后代节点（目录名结尾的 / 可省略）
+    ///
+    /// ```
+    /// let spec = treegen::parse_markdown("app/\n└── src/\n    └── main.rs\n")?;
+    /// assert!(spec.root.find("app/src/main.rs").is_some());
+    /// assert!(spec.root.find("app/missing").is_none());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn find(&self, path: impl AsRef<Path>) -> Option<&Node> {
+        path.as_ref()
+            .components()
+            .try_fold(self, |node, component| match component {
+                Component::CurDir => Some(node),
+                Component::Normal(name) => {
+                    let name = name.to_str()?;
+                    node.children.iter().find(|c| c.key() == name)
+                }
+                _ => None,
+            })
+    }
+
+    /// 把另一棵树合并进来：同名目录递归合并，同名文件（及复制节点）以 `other` 为准，
+    /// 不同类型的同名节点互相冲突
+    ///
+    /// ```
+    /// use treegen::Node;
+    ///
+    /// let mut base = Node::root().child(Node::dir("src").file("main.rs", "v1")).build()?;
+    /// let extra = Node::root()
+    ///     .child(Node::dir("src").file("main.rs", "v2").file_empty("lib.rs"))
+    ///     .build()?;
+    /// base.merge(extra)?;
+    /// assert_eq!(base.children.len(), 1);
+    /// assert_eq!(base.find("src/main.rs").unwrap().content.as_deref(), Some("v2"));
+    /// assert!(base.find("src/lib.rs").is_some());
+    /// # Ok::<(), treegen::NodeError>(())
+    /// ```
+    pub fn merge(&mut self, other: Node) -> Result<(), NodeError> {
+        fn walk(node: &mut Node, other: Node, path: &Path) -> Result<(), NodeError> {
+            for child in other.children {
+                let child_path = path.join(child.key());
+                match node.children.iter_mut().find(|c| c.key() == child.key()) {
+                    Some(existing) if existing.node_type != child.node_type => {
+                        return Err(NodeError::TypeConflict {
+                            path: child_path.display().to_string(),
+                        });
+                    }
+                    Some(existing) if existing.node_type != NodeType::Dir => *existing = child,
+                    Some(existing) => walk(existing, child, &child_path)?,
+                    None => node.add_child(child)?,
+                }
+            }
+            Ok(())
+        }
+
+        if self.node_type != other.node_type {
+            return Err(NodeError::TypeConflict {
+                path: self.name.clone(),
+            });
+        }
+        if self.node_type != NodeType::Dir {
+            *self = other;
+            return Ok(());
+        }
+        let path = PathBuf::from(self.key());
+        walk(self, other, &path)
+    }
+}
+
+// === 遍历 ===
+
+/// [`Node::walk`] 回调的返回值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkControl {
+    /// 继续遍历（包括当前节点的子节点）
+    Continue,
+    /// 跳过当前节点的子节点
+    SkipChildren,
+    /// 立即结束遍历
+    Stop,
+}
+
+/// 深度优先（先序）遍历，产出 (相对路径, 节点)；由 [`Node::iter`] 创建
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    stack: Vec<(PathBuf, &'a Node)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (PathBuf, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+        self.push_children(&path, node);
+        Some((path, node))
+    }
+}
+
+impl<'a> Iter<'a> {
+    /// 逆序压栈，保证按声明顺序出栈
+    fn push_children(&mut self, path: &Path, node: &'a Node) {
+        self.stack.extend(
+            node.children
+                .iter()
+                .rev()
+                .map(|child| (path.join(&child.name), child)),
+        );
+    }
+}
+
+impl Node {
+    /// 深度优先（先序）遍历自身及所有后代，顺序与生成时创建的顺序一致。
+    /// 路径相对于自身所在目录：未命名的根节点为空路径，其余节点以自身名称开头
+    ///
+    /// ```
+    /// let spec = treegen::parse_markdown("app/\n├── src/\n│   └── main.rs\n└── README.md\n")?;
+    /// let paths: Vec<_> = spec.root.iter().map(|(path, _)| path).collect();
+    /// assert_eq!(paths, ["", "app/", "app/src/", "app/src/main.rs", "app/README.md"].map(std::path::PathBuf::from));
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            stack: vec![(PathBuf::from(&self.name), self)],
+        }
+    }
+
+    /// 只遍历文件
+    pub fn iter_files(&self) -> impl Iterator<Item = (PathBuf, &Node)> {
+        self.iter()
+            .filter(|(_, node)| node.node_type == NodeType::File)
+    }
+
+    /// 只遍历目录（包括自身）
+    pub fn iter_dirs(&self) -> impl Iterator<Item = (PathBuf, &Node)> {
+        self.iter()
+            .filter(|(_, node)| node.node_type == NodeType::Dir)
+    }
+
+    /// 按 [`Node::iter`] 的顺序访问每个节点，回调可以跳过子树或提前结束
+    ///
+    /// ```
+    /// use treegen::{Node, WalkControl};
+    ///
+    /// let root = Node::root()
+    ///     .child(Node::dir("target").file_empty("big.bin"))
+    ///     .child(Node::dir("src").file_empty("main.rs"))
+    ///     .build()?;
+    /// let mut seen = Vec::new();
+    /// root.walk(&mut |path, _| {
+    ///     seen.push(path.to_path_buf());
+    ///     if path.ends_with("target") { WalkControl::SkipChildren } else { WalkControl::Continue }
+    /// });
+    /// assert_eq!(seen, ["", "target", "src", "src/main.rs"].map(std::path::PathBuf::from));
+    /// # Ok::<(), treegen::NodeError>(())
+    /// ```
+    pub fn walk(&self, visit: &mut impl FnMut(&Path, &Node) -> WalkControl) {
+        let mut iter = self.iter();
+        while let Some((path, node)) = iter.stack.pop() {
+            match visit(&path, node) {
+                WalkControl::Continue => iter.push_children(&path, node),
+                WalkControl::SkipChildren => {}
+                WalkControl::Stop => return,
+            }
+        }
+    }
+}
+
+/// 构建 Node 树时违反的约束
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeError {
+    /// 只有根目录可以没有名称
+    EmptyName,
+    /// 文件不能有子节点
+    ChildOfFile { file: String },
+    /// 同一路径在一棵树中是文件、在另一棵树中是目录
+    TypeConflict { path: String },
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeError::EmptyName => {
+                write!(f, "Node name must not be empty (only the root is unnamed)")
+            }
+            NodeError::ChildOfFile { file } => write!(f, "File '{}' cannot have children", file),
+            NodeError::TypeConflict { path } => write!(
+                f,
+                "'{}' is a file in one tree and a directory in the other",
+                path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NodeError {}
+
+/// 链式构建目录节点；第一个违反约束的操作会被记录下来，由 [`NodeBuilder::build`] 返回
+#[derive(Debug, Clone)]
+#[must_use = "call .build() to get the Node"]
+pub struct NodeBuilder {
+    node: Node,
+    error: Option<NodeError>,
+}
+
+impl NodeBuilder {
+    fn new(node: Node) -> Self {
+        NodeBuilder { node, error: None }
+    }
+
+    fn push(mut self, child: Result<Node, NodeError>) -> Self {
+        if self.error.is_none() {
+            if let Err(e) = child.and_then(|child| self.node.add_child(child)) {
+                self.error = Some(e);
+            }
+        }
+        self
+    }
+
+    /// 添加带内容的文件
+    pub fn file(self, name: impl Into<String>, content: impl Into<String>) -> Self {
+        let file = Node::new_file(name.into(), Some(content.into()));
+        self.push(Ok(file))
+    }
+
+    /// 添加空文件
+    pub fn file_empty(self, name: impl Into<String>) -> Self {
+        self.file(name, String::new())
+    }
+
+    /// 添加子目录（由另一个构建器构建）
+    pub fn child(self, dir: NodeBuilder) -> Self {
+        self.push(dir.build())
+    }
+
+    /// 完成构建，返回第一个违反约束的错误
+    pub fn build(self) -> Result<Node, NodeError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.node),
+        }
+    }
+}
+
+/// 解析后的描述文件：文件树及其附带的设置
+#[derive(Debug, Clone)]
+pub struct Spec {
+    /// 未命名的根目录（对应输出目录本身）
+    pub root: Node,
+    /// 描述文件中 `$hooks.post` 声明的钩子命令
+    pub hooks: Vec<String>,
+    /// 描述文件中 `$vars` 声明的模板变量
+    pub vars: BTreeMap<String, VarDecl>,
+    /// 描述文件中 `$rules` 声明的规则，按顺序应用（见 [`apply_rules`]）
+    pub rules: Vec<Rule>,
+    /// Markdown 描述的 front matter 中的生成设置，命令行参数优先
+    pub config: SpecConfig,
+}
+
+/// 描述文件自带的生成设置（Markdown 描述开头的 YAML front matter），
+/// 只在命令行与环境变量都没有提供对应参数时使用
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpecConfig {
+    /// 输出目录，同 `--out`
+    pub out: Option<PathBuf>,
+    /// 新建文件的权限，同 `--mode`，如 `"0o644"`
+    pub mode: Option<String>,
+}
+
+impl SpecConfig {
+    /// 用 `other` 中设置了的值覆盖
+    pub fn merge(&mut self, other: SpecConfig) {
+        if other.out.is_some() {
+            self.out = other.out;
+        }
+        if other.mode.is_some() {
+            self.mode = other.mode;
+        }
+    }
+}
+
+/// `$rules` 中的一条规则：修改路径匹配 `pattern` 的每个文件节点。
+/// 没有设置的字段不影响节点；设置了的字段覆盖节点原有的值
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rule {
+    /// 相对输出目录的路径 glob，如 `**/*.rs`
+    pub pattern: String,
+    /// 加在文件内容之前的文本
+    pub prepend: Option<String>,
+    /// 加在文件内容之后的文本
+    pub append: Option<String>,
+    pub mode: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    /// 逐项设置的扩展属性
+    pub xattrs: BTreeMap<String, String>,
+    /// 非空时替换节点的标签
+    pub tags: BTreeSet<String>,
+    pub raw: Option<bool>,
+}
+
+/// `$vars` 中声明的一个模板变量
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VarDecl {
+    /// 值的类型，`--var` 与默认值都按它检查
+    pub kind: VarType,
+    /// 没有提供时使用的值
+    pub default: Option<String>,
+    /// 说明，显示在 `treegen vars` 与输入提示中
+    pub description: Option<String>,
+}
+
+/// 模板变量的类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VarType {
+    /// 任意文本
+    #[default]
+    String,
+    /// 整数
+    Int,
+    /// `true` 或 `false`
+    Bool,
+}
+
+impl VarType {
+    /// 类型名
+    pub fn name(self) -> &'static str {
+        match self {
+            VarType::String => "string",
+            VarType::Int => "int",
+            VarType::Bool => "bool",
+        }
+    }
+}
+
+impl VarDecl {
+    /// 检查 `value` 是否符合声明的类型
+    ///
+    /// ```
+    /// use treegen::{VarDecl, VarType};
+    ///
+    /// let port = VarDecl { kind: VarType::Int, ..Default::default() };
+    /// assert!(port.check("port", "8080").is_ok());
+    /// let err = port.check("port", "http").unwrap_err();
+    /// assert_eq!(err.to_string(), "Invalid value 'http' for variable 'port': expected int");
+    /// ```
+    pub fn check(&self, name: &str, value: &str) -> Result<()> {
+        let valid = match self.kind {
+            VarType::String => true,
+            VarType::Int => value.parse::<i64>().is_ok(),
+            VarType::Bool => matches!(value, "true" | "false"),
+        };
+        if !valid {
+            bail!(
+                "Invalid value '{}' for variable '{}': expected {}",
+                value,
+                name,
+                self.kind.name()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Spec {
+    /// 只有文件树、没有附加设置的描述（如 Markdown）
+    pub fn from_tree(root: Node) -> Self {
+        Spec {
+            root,
+            hooks: Vec::new(),
+            vars: BTreeMap::new(),
+            rules: Vec::new(),
+            config: SpecConfig::default(),
+        }
+    }
+}
+
+/// Markdown 树中的一行拆成的各部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MdLine<'a> {
+    /// 缩进的级数
+    indent: usize,
+    /// 缩进之后是否有连接符（`├──`、`└──`）
+    connector: bool,
+    /// 其余部分，去掉了首尾空白
+    name: &'a str,
+    /// 名称在行中的列（按字符计，从 1 开始）
+    column: usize,
+}
+
+impl MdLine<'_> {
+    /// 节点的层级：没有连接符的行为缩进级数 + 1，有连接符的为 + 2
+    fn level(&self) -> usize {
+        self.indent + if self.connector { 2 } else { 1 }
+    }
+}
+
+/// 逐个字符扫描一行：先是缩进，再是可选的连接符，其余是名称。
+/// - 缩进：`│` 连同其后至多 3 个空格（或一个制表符）算一级，连续的空格每 4 个算一级，制表符一个算一级；
+///   不足一级的零散空格不计。不间断空格（GNU tree 的输出中常见）按空格处理
+/// - 连接符：`├` 或 `└` 后跟一个以上的 `─`，之后的空白可有可无；连接符之后的内容都是名称，
+///   其中的连接线不再特殊处理
+fn scan_md_line(line: &str) -> MdLine<'_> {
+    let is_space = |c: char| c == ' ' || c == '\u{a0}';
+    let mut rest = line;
+    let mut indent = 0;
+    let mut spaces = 0;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '│' => {
+                rest = &rest[c.len_utf8()..];
+                rest = match rest.strip_prefix('\t') {
+                    Some(after) => after,
+                    None => {
+                        let skip: usize = rest
+                            .chars()
+                            .take(3)
+                            .take_while(|&c| is_space(c))
+                            .map(char::len_utf8)
+                            .sum();
+                        &rest[skip..]
+                    }
+                };
+                indent += spaces / 4 + 1;
+                spaces = 0;
+            }
+            '\t' => {
+                rest = &rest[1..];
+                indent += spaces / 4 + 1;
+                spaces = 0;
+            }
+            c if is_space(c) => {
+                rest = &rest[c.len_utf8()..];
+                spaces += 1;
+            }
+            _ => break,
+        }
+    }
+    indent += spaces / 4;
+    let mut chars = rest.chars();
+    let connector = matches!(chars.next(), Some('├' | '└')) && chars.as_str().starts_with('─');
+    if connector {
+        rest = chars.as_str().trim_start_matches('─');
+    }
+    let name = rest.trim();
+    let start = line.len() - rest.trim_start().len();
+    MdLine {
+        indent,
+        connector,
+        name,
+        column: line[..start].chars().count() + 1,
+    }
+}
+
+/// 名称后的 `#[full, docs]` 后缀（前面至少一个空白）是节点的 profile：返回名称与标签文本。
+/// 标签中不能有 `]`；有多个 `#[` 时从第一个可能的位置开始
+fn split_md_tags(name: &str) -> Option<(&str, &str)> {
+    let body = name.strip_suffix(']')?;
+    let from = body.rfind(']').map_or(0, |close| close + 1);
+    body[from..]
+        .match_indices("#[")
+        .map(|(open, _)| from + open)
+        .find(|&open| body[..open].ends_with(char::is_whitespace))
+        .map(|open| (body[..open].trim_end(), &body[open + 2..]))
+}
+
+/// 标签之前的 `[f]`/`[d]` 后缀（前面至少一个空白）显式指定节点类型，优先于结尾的 `/`
+fn split_md_kind(name: &str) -> Option<(&str, NodeType)> {
+    let (head, kind) = match name.strip_suffix("[f]") {
+        Some(head) => (head, NodeType::File),
+        None => (name.strip_suffix("[d]")?, NodeType::Dir),
+    };
+    head.ends_with(char::is_whitespace)
+        .then(|| (head.trim_end(), kind))
+}
+
+/// === Markdown 树状目录解析 ===
+/// 示例：
+/// project/
+/// ├── src/
+/// │   ├── main.rs
+/// │   └── lib.rs
+/// ├── Cargo.toml
+/// └── README.md
+///
+/// 只有省略号的行（`...`、`…`，可以跟一个括号说明，如 `… (20 more files)`）是文档中的省略，
+/// 除非 `keep_ellipsis` 否则跳过；返回的提示列出每个跳过的行。
+/// 只有连接线（`│`）与空白的行，以及没有连接符、以注释前缀开头的整行注释也跳过，不影响后续行的层级
+fn parse_md_tree(lines: &[String], opts: &ParseOptions) -> Result<(Node, Vec<String>)> {
+    // 根节点（"" 表示从指定输出目录开始，不创建额外文件夹）
+    let mut root = Node::new_dir("".to_string());
+
+    // 栈：维护 (level, *mut Node) 以便附加子节点
+    let mut stack: Vec<(usize, *mut Node)> = Vec::new();
+    let root_ptr: *mut Node = &mut root as *mut Node;
+    stack.push((0, root_ptr));
+
+    let mut notes = Vec::new();
+    // 上一个节点：(层级, 行号, 节点, 是否用 [f] 显式标为文件)
+    let mut last: Option<(usize, usize, *mut Node, bool)> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        // 从终端复制时常见的只有 `│` 的续行
+        if line.chars().all(|c| c.is_whitespace() || is_box_drawing(c)) {
+            continue;
+        }
+        let scanned = scan_md_line(line);
+        let level = scanned.level();
+        let mut name = scanned.name;
+        // 带连接符的行总是节点，`├── # notes.md` 是名为 `# notes.md` 的文件
+        if !scanned.connector && is_md_comment(name, &opts.comment_prefixes) {
+            continue;
+        }
+        if !opts.keep_ellipsis && is_ellipsis(name) {
+            notes.push(format!("Skip placeholder line {}: '{}'", index + 1, name));
+            continue;
+        }
+        let mut attrs = Attrs::default();
+        if let Some((bare, tags)) = split_md_tags(name) {
+            attrs.tags = tags.split(',').map(|tag| tag.trim().to_string()).collect();
+            name = bare;
+            check_tags(&attrs.tags, name)?;
+        }
+        let mut explicit_file = false;
+        let node_type = match split_md_kind(name) {
+            Some((bare, node_type)) => {
+                if node_type == NodeType::File && bare.ends_with('/') {
+                    bail!(
+                        "Line {}, column {}: '{}' ends with '/' but is marked as a file with [f]",
+                        index + 1,
+                        scanned.column,
+                        bare
+                    );
+                }
+                name = bare;
+                explicit_file = node_type == NodeType::File;
+                node_type
+            }
+            None if name.ends_with('/') => NodeType::Dir,
+            None => NodeType::File,
+        };
+        if let Some(issue) = name_path_issue(name) {
+            bail!(
+                "Line {}, column {}: '{}' {}; spec paths must be relative to the output directory",
+                index + 1,
+                scanned.column,
+                name,
+                issue
+            );
+        }
+        // 修复文件名语法问题；盘符要在替换之前检查
+        let name = name.replace(':', "_");
+
+        let child = Node {
+            name: name.clone(),
+            node_type,
+            children: Vec::new(),
+            content: None, // 移除内容填充功能
+            copy: None,
+            target: None,
+            attrs,
+        };
+
+        // 嵌套在文件下的行：文件其实是漏写了结尾 `/` 的目录，按 infer_dirs 改为目录或报错
+        if let Some((last_level, last_line, last_ptr, last_explicit)) = last {
+            let last_node = unsafe { &mut *last_ptr };
+            if level > last_level && last_node.node_type == NodeType::File {
+                if last_explicit {
+                    bail!(
+                        "'{}' at line {} is marked as a file with [f] but line {} is nested beneath it",
+                        last_node.name,
+                        last_line,
+                        index + 1
+                    );
+                }
+                if !opts.infer_dirs {
+                    bail!(
+                        "'{}' at line {} is a file but line {} is nested beneath it — add a trailing '/' or pass --infer-dirs",
+                        last_node.name,
+                        last_line,
+                        index + 1
+                    );
+                }
+                notes.push(format!(
+                    "Line {}: '{}' has nested lines; read as a directory",
+                    last_line, last_node.name
+                ));
+                last_node.node_type = NodeType::Dir;
+                last_node.name.push('/');
+                stack.push((last_level, last_ptr));
+            }
+        }
+
+        // 弹出直到栈顶的 level < 当前 level
+        while stack.last().unwrap().0 >= level {
+            stack.pop();
+        }
+        // 此时栈顶即为父节点
+        let parent_ptr = stack.last().unwrap().1;
+        unsafe {
+            let parent_ref: &mut Node = &mut *parent_ptr;
+            parent_ref.children.push(child);
+            let last_idx = parent_ref.children.len() - 1;
+            let child_ptr: *mut Node = &mut parent_ref.children[last_idx] as *mut Node;
+            if let NodeType::Dir = parent_ref.children[last_idx].node_type {
+                // 如果新节点是目录，把它压入栈
+                stack.push((level, child_ptr));
+            }
+            last = Some((level, index + 1, child_ptr, explicit_file));
+        }
+    }
+
+    Ok((root, notes))
+}
+
+/// 制表符区段（U+2500..U+257F）中的字符，如 `│`、`├`、`─`
+fn is_box_drawing(c: char) -> bool {
+    ('\u{2500}'..='\u{257F}').contains(&c)
+}
+
+/// 以任一注释前缀开头（空前缀不算）
+fn is_md_comment(text: &str, prefixes: &[impl AsRef<str>]) -> bool {
+    prefixes.iter().any(|prefix| {
+        let prefix = prefix.as_ref();
+        !prefix.is_empty() && text.starts_with(prefix)
+    })
+}
+
+/// Markdown 树中表示省略的名称：`...`、`…`，可以跟一个括号说明
+fn is_ellipsis(name: &str) -> bool {
+    let rest = name
+        .strip_prefix("...")
+        .or_else(|| name.strip_prefix('…'))
+        .map(|rest| rest.trim_start_matches(['.', '…']));
+    match rest.map(str::trim_start) {
+        Some("") => true,
+        Some(note) => note.starts_with('(') && note.ends_with(')'),
+        None => false,
+    }
+}
+
+/// 从 Markdown 树文本中解析描述（只有结构，文件没有内容）
+///
+/// ```
+/// let spec = treegen::parse_markdown("project/\n├── src/\n│   └── main.rs\n└── README.md\n")?;
+/// let project = &spec.root.children[0];
+/// assert_eq!(project.name, "project/");
+/// assert_eq!(project.children.len(), 2);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_markdown(content: &str) -> Result<Spec> {
+    parse_markdown_with(content, &ParseOptions::default()).map(|(spec, _)| spec)
+}
+
+/// 同 [`parse_markdown`]，按 `opts` 处理省略行，并返回解析时的提示
+fn parse_markdown_with(content: &str, opts: &ParseOptions) -> Result<(Spec, Vec<String>)> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let front_matter = take_front_matter(&mut lines)?;
+    let sectioned = match opts.md_style {
+        MdStyle::Auto => looks_sectioned(&lines),
+        MdStyle::Tree => false,
+        MdStyle::Sections => true,
+    };
+    let (mut root, notes) = if sectioned {
+        parse_md_sections(&lines, opts)?
+    } else {
+        if !opts.sections.is_empty() {
+            bail!("Sections were selected, but the Markdown spec has no '## ' headings with tree blocks");
+        }
+        let (lines, ls_tree) = normalize_ls_tree(lines);
+        let (root, mut notes) = parse_md_tree(&lines, opts)?;
+        if ls_tree {
+            notes.insert(0, "Read as exa/lsd tree output".to_string());
+        }
+        (root, notes)
+    };
+    let Some(front) = front_matter else {
+        return Ok((Spec::from_tree(root), notes));
+    };
+    // `root` 把整棵树放进一个目录，名称中的模板变量与其他名称一样替换
+    if let Some(name) = front.root {
+        let mut dir = Node::new_dir(name);
+        dir.children = std::mem::take(&mut root.children);
+        root.children.push(dir);
+    }
+    let mut spec = Spec::from_tree(root);
+    spec.vars = front
+        .vars
+        .into_iter()
+        .map(|(name, var)| Ok((name.clone(), var.into_decl(&name)?)))
+        .collect::<Result<_>>()?;
+    spec.config = SpecConfig {
+        out: front.out,
+        mode: front.mode,
+    };
+    Ok((spec, notes))
+}
+
+/// 围栏代码块的开始/结束行：三个以上的 `` ` `` 或 `~`，返回围栏字符串与信息字符串
+fn md_fence(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    let ch = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = line.len() - line.trim_start_matches(ch).len();
+    (len >= 3).then(|| (&line[..len], line[len..].trim()))
+}
+
+/// 二级标题（`## Backend`）的文字
+fn md_section_heading(line: &str) -> Option<&str> {
+    let title = line.strip_prefix("##")?;
+    if !(title.is_empty() || title.starts_with([' ', '\t'])) {
+        return None;
+    }
+    // 结尾可选的 `##`
+    Some(title.trim().trim_end_matches('#').trim_end())
+}
+
+/// 有二级标题，也有围栏代码块：按分节文档解析
+fn looks_sectioned(lines: &[String]) -> bool {
+    lines.iter().any(|line| md_section_heading(line).is_some())
+        && lines.iter().any(|line| md_fence(line).is_some())
+}
+
+/// 标题转为目录名：小写，字母数字以外的字符连续出现时换成一个 `-`
+///
+/// ```
+/// assert_eq!(treegen::heading_slug("Backend API (v2)"), "backend-api-v2");
+/// assert_eq!(treegen::heading_slug("前端 / Web"), "前端-web");
+/// ```
+pub fn heading_slug(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// === Markdown 分节文档解析 ===
+/// 每个二级标题开始一节，节中（包括其下的三级以下标题中）信息字符串为空或 `text`/`txt`/`tree`/`plain`
+/// 的围栏代码块按 Markdown 树解析，生成在以标题 slug 命名的子目录中；
+/// 其他语言的代码块、没有树的标题、第一个二级标题之前的内容都忽略。同名的节合并
+fn parse_md_sections(lines: &[String], opts: &ParseOptions) -> Result<(Node, Vec<String>)> {
+    let wanted: Vec<String> = opts.sections.iter().map(|s| heading_slug(s)).collect();
+    let mut root = Node::new_dir("".to_string());
+    let mut notes = Vec::new();
+    let mut found = BTreeSet::new();
+    let mut section: Option<String> = None;
+    let mut index = 0;
+    while index < lines.len() {
+        let line = &lines[index];
+        if let Some(title) = md_section_heading(line) {
+            let slug = heading_slug(title);
+            if slug.is_empty() {
+                bail!(
+                    "Line {}: heading '{}' has no characters usable as a directory name",
+                    index + 1,
+                    line.trim()
+                );
+            }
+            section = Some(slug);
+            index += 1;
+            continue;
+        }
+        let Some((fence, info)) = md_fence(line) else {
+            index += 1;
+            continue;
+        };
+        let start = index + 1;
+        let end = (start..lines.len())
+            .find(|&i| {
+                let line = lines[i].trim();
+                line.starts_with(fence) && line.trim_start_matches(&fence[..1]).is_empty()
+            })
+            .unwrap_or(lines.len());
+        index = end + 1;
+        let is_tree = matches!(
+            info.split_whitespace().next().unwrap_or(""),
+            "" | "text" | "txt" | "tree" | "plain"
+        );
+        let Some(slug) = section.as_ref().filter(|_| is_tree) else {
+            continue;
+        };
+        found.insert(slug.clone());
+        if !wanted.is_empty() && !wanted.contains(slug) {
+            continue;
+        }
+        // 块外的行清空，树中的行号与文件一致
+        let mut block = vec![String::new(); lines.len()];
+        block[start..end].clone_from_slice(&lines[start..end]);
+        let (block, ls_tree) = normalize_ls_tree(block);
+        let (tree, block_notes) = parse_md_tree(&block, opts)?;
+        if ls_tree {
+            notes.push(format!(
+                "Read the tree at line {} as exa/lsd tree output",
+                start
+            ));
+        }
+        notes.extend(block_notes);
+        let mut dir = Node::new_dir(slug.clone());
+        dir.children = tree.children;
+        let mut wrapper = Node::new_dir("".to_string());
+        wrapper.children.push(dir);
+        root.merge(wrapper).with_context(|| {
+            format!(
+                "Failed to merge the tree at line {} into section '{}'",
+                start, slug
+            )
+        })?;
+    }
+    if let Some(missing) = wanted.iter().find(|slug| !found.contains(*slug)) {
+        bail!(
+            "Section '{}' not found; the spec has {}",
+            missing,
+            if found.is_empty() {
+                "no sections with tree blocks".to_string()
+            } else {
+                found.into_iter().collect::<Vec<_>>().join(", ")
+            }
+        );
+    }
+    Ok((root, notes))
+}
+
+/// Markdown 描述开头 `---` 与 `---` 之间的 YAML front matter
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrontMatter {
+    #[serde(default)]
+    out: Option<PathBuf>,
+    #[serde(default)]
+    mode: Option<String>,
+    /// 同 `$vars`
+    #[serde(default)]
+    vars: BTreeMap<String, SerdeVar>,
+    /// 放置整棵树的目录名
+    #[serde(default)]
+    root: Option<String>,
+}
+
+/// 取出第一行为 `---` 的 front matter，原来的行换成空行，树中的行号不变
+fn take_front_matter(lines: &mut [String]) -> Result<Option<FrontMatter>> {
+    if lines.first().map(|line| line.trim_end()) != Some("---") {
+        return Ok(None);
+    }
+    let Some(end) = lines
+        .iter()
+        .skip(1)
+        .position(|line| matches!(line.trim_end(), "---" | "..."))
+    else {
+        bail!("Front matter starting at line 1 is not closed with '---'");
+    };
+    let yaml = lines[1..=end].join("\n");
+    let front: FrontMatter = if yaml.trim().is_empty() {
+        serde_yaml::from_str("{}")?
+    } else {
+        serde_yaml::from_str(&yaml).context("Invalid front matter")?
+    };
+    if let Some(mode) = &front.mode {
+        parse_mode(mode).context("Invalid 'mode' in front matter")?;
+    }
+    for line in &mut lines[..end + 2] {
+        line.clear();
+    }
+    Ok(Some(front))
+}
+
+/// `exa -T`/`lsd --tree` 的输出转为标准的 Markdown 树，不是这种输出时原样返回（第二项为 false）。
+///
+/// 这些工具每级缩进 3 个字符（`│  `），目录名不带 `/`，可能在名称前加图标，
+/// 配合 `-l` 时树的左边还有权限、大小等列：
+/// - 连接符左边的列按最靠左的连接线位置整体去掉，权限以 `d` 开头的是目录
+/// - 缩进换算为每级 4 个空格
+/// - 有更深子项的名称是目录；代表当前目录的根行 `.` 去掉，子项直接放在输出目录下
+fn normalize_ls_tree(lines: Vec<String>) -> (Vec<String>, bool) {
+    let has_connector = |line: &[char]| connector_at(line).is_some();
+    let mut rows: Vec<Vec<char>> = lines.iter().map(|line| line.chars().collect()).collect();
+
+    // 树左边的列：所有带连接符的行中，最靠左的连接线所在的位置
+    let column = rows
+        .iter()
+        .filter(|row| has_connector(row))
+        .filter_map(|row| row.iter().position(|c| matches!(c, '│' | '├' | '└')))
+        .min()
+        .unwrap_or(0);
+    let mut dirs = vec![false; rows.len()];
+    if column > 0 {
+        let permissions = Regex::new(r"^[dl.\-][rwxsStT\-]{9}[@+.]?$").unwrap();
+        for (row, dir) in rows.iter_mut().zip(dirs.iter_mut()) {
+            if row.len() <= column {
+                continue;
+            }
+            let columns: String = row.drain(..column).collect();
+            let first = columns.split_whitespace().next().unwrap_or("");
+            *dir = permissions.is_match(first) && first.starts_with('d');
+        }
+    }
+
+    // 每级缩进的宽度：最小的非零连接符位置
+    let unit = rows
+        .iter()
+        .filter_map(|row| connector_at(row))
+        .filter(|&at| at > 0)
+        .min();
+    if column == 0 && unit != Some(3) {
+        return (lines, false);
+    }
+    let unit = unit.unwrap_or(4);
+
+    // (层级, 名称) 与标准 Markdown 树一致：无连接符的行为 1，连接符在第 k 级缩进后为 k + 2
+    let mut entries: Vec<(usize, String, bool)> = Vec::new();
+    for (row, dir) in rows.iter().zip(dirs) {
+        // 空行与只有连接线的续行
+        if row.iter().all(|&c| c.is_whitespace() || is_box_drawing(c)) {
+            continue;
+        }
+        let (level, name) = match connector_at(row) {
+            Some(at) => (
+                at / unit + 2,
+                row[(at + 4).min(row.len())..].iter().collect(),
+            ),
+            None => (1, row.iter().collect::<String>()),
+        };
+        entries.push((level, strip_icon(name.trim()).to_string(), dir));
+    }
+    // 根行 `.` 即输出目录本身
+    if entries
+        .first()
+        .is_some_and(|(level, name, _)| *level == 1 && name == ".")
+    {
+        entries.remove(0);
+    }
+    let normalized = (0..entries.len())
+        .map(|i| {
+            let (level, name, dir) = &entries[i];
+            let parent = entries.get(i + 1).is_some_and(|next| next.0 > *level);
+            let slash = if (*dir || parent) && !name.ends_with('/') {
+                "/"
+            } else {
+                ""
+            };
+            let indent = match level {
+                1 => String::new(),
+                _ => format!("{}├── ", " ".repeat((level - 2) * 4)),
+            };
+            format!("{}{}{}", indent, name, slash)
+        })
+        .collect();
+    (normalized, true)
+}
+
+/// 行中连接符 `├──`/`└──` 的位置（按字符计）
+fn connector_at(row: &[char]) -> Option<usize> {
+    row.windows(3)
+        .position(|w| matches!(w, ['├' | '└', '─', '─']))
+}
+
+/// 去掉名称前的图标（Nerd Font 等私用区字符）及其后的空白
+fn strip_icon(name: &str) -> &str {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if ('\u{E000}'..='\u{F8FF}').contains(&c) || c >= '\u{F0000}' => {
+            chars.as_str().trim_start()
+        }
+        _ => name,
+    }
+}
+
+/// === YAML/JSON/TOML 解析 ===
+/// SerdeNode 用于反序列化：
+/// - Str(String)：代表文件内容
+/// - Map(BTreeMap<_, _>)：代表目录及其子结构
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(
+    rename = "Node",
+    description = "A string is the content of a file, a map is a directory"
+)]
+enum SerdeNode {
+    #[schemars(description = "File content (\"\" creates an empty file)")]
+    Str(String),
+    Meta(Box<MetaNode>),
+    #[schemars(description = "Directory: entry name -> node ({} creates an empty directory)")]
+    Map(BTreeMap<String, SerdeNode>),
+}
+
+/// 带类型的节点：`type` 为已知节点类型的映射，其余映射仍是目录
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(description = "A node with metadata, selected by its type")]
+struct MetaNode {
+    #[serde(rename = "type")]
+    kind: MetaKind,
+    /// copy：源文件或目录，相对描述文件所在目录
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "copy: source file or directory, relative to the spec file")]
+    from: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(description = "copy: allow copying a directory with all its contents")]
+    recursive: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(description = "copy: globs (relative to the source directory) to skip")]
+    exclude: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(
+        description = "copy: only copy files matching these globs (relative to the source directory); implies recursive"
+    )]
+    include: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(
+        description = "copy: substitute template variables in copied text files; binary files are copied as-is"
+    )]
+    substitute: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "hardlink: file to link to, relative to the output root (generated in this run or already on disk)"
+    )]
+    target: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "file: file content; an object or array (with \"format\") is serialized into the file"
+    )]
+    content: Option<MetaContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "file: format used to serialize structured content")]
+    format: Option<ContentFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "file/dir/copy: owner, a user name or numeric uid (Unix only)")]
+    owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "file/dir/copy: group, a group name or numeric gid (Unix only)")]
+    group: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(
+        description = "file/dir: extended attributes, name -> value (\"@base64:\" prefix for binary values)"
+    )]
+    xattrs: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    #[schemars(
+        description = "any type: profiles the node belongs to; untagged nodes are always generated"
+    )]
+    tags: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(
+        description = "file: write the content as-is, without template variable substitution"
+    )]
+    raw: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(
+        description = "file: strip trailing spaces/tabs from each line of the content and collapse more than two consecutive blank lines"
+    )]
+    trim: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "file: permissions in octal, e.g. \"0o755\" (Unix only)")]
+    mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "file: what to do when the file already exists: overwrite, skip, error, backup or append; overrides --skip-existing"
+    )]
+    if_exists: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[schemars(
+        description = "any type: failures while generating this node (and everything below it) are warnings instead of errors"
+    )]
+    optional: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "file: declared size of a placeholder file instead of content, in bytes or with a unit, e.g. \"50MiB\" (B, KiB, MiB, GiB, TiB)"
+    )]
+    size: Option<MetaSize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(
+        description = "file: how a placeholder file with \"size\" is filled: zero (default), sparse or random"
+    )]
+    fill: Option<String>,
+    // 放在最后：TOML 要求标量写在表之前
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "dir: entries of the directory")]
+    children: Option<BTreeMap<String, SerdeNode>>,
+    /// 未知字段：解析后报错，而不是悄悄忽略拼写错误
+    #[serde(flatten, skip_serializing)]
+    #[schemars(skip)]
+    unknown: BTreeMap<String, serde::de::IgnoredAny>,
+}
+
+/// 带类型节点的 `content`：文本，或按 `format` 序列化的结构
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+enum MetaContent {
+    Text(String),
+    Structured(serde_json::Value),
+}
+
+/// 带类型节点的 `size`：字节数，或带单位的文本（见 [`parse_size`]）
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+enum MetaSize {
+    Bytes(u64),
+    Text(String),
+}
+
+/// 带类型节点的类型名
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum MetaKind {
+    File,
+    Dir,
+    Copy,
+    Hardlink,
+}
+
+impl MetaKind {
+    fn name(self) -> &'static str {
+        match self {
+            MetaKind::File => "file",
+            MetaKind::Dir => "dir",
+            MetaKind::Copy => "copy",
+            MetaKind::Hardlink => "hardlink",
+        }
+    }
+
+    /// 该类型接受的字段
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            MetaKind::File => &[
+                "content",
+                "owner",
+                "group",
+                "xattrs",
+                "tags",
+                "raw",
+                "trim",
+                "mode",
+                "if_exists",
+                "format",
+                "optional",
+                "size",
+                "fill",
+            ],
+            MetaKind::Dir => &["children", "owner", "group", "xattrs", "tags", "optional"],
+            MetaKind::Copy => &[
+                "from",
+                "recursive",
+                "exclude",
+                "include",
+                "substitute",
+                "owner",
+                "group",
+                "tags",
+                "optional",
+            ],
+            MetaKind::Hardlink => &["target", "tags", "optional"],
+        }
+    }
+}
+
+impl MetaNode {
+    /// 只有类型与附加属性的节点，其余字段由调用方填写
+    fn new(kind: MetaKind, attrs: &Attrs) -> Self {
+        MetaNode {
+            kind,
+            from: None,
+            recursive: false,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            substitute: false,
+            target: None,
+            content: None,
+            format: attrs.structured.as_ref().map(|s| s.format),
+            children: None,
+            owner: attrs.owner.clone(),
+            group: attrs.group.clone(),
+            xattrs: attrs.xattrs.clone(),
+            tags: attrs.tags.clone(),
+            raw: attrs.raw,
+            trim: attrs.trim,
+            mode: attrs.mode.map(|mode| format!("0o{:o}", mode)),
+            if_exists: attrs.if_exists.map(|policy| policy.name().to_string()),
+            optional: attrs.optional,
+            size: attrs.placeholder.map(|p| MetaSize::Bytes(p.size)),
+            fill: attrs
+                .placeholder
+                .filter(|p| p.fill != Fill::Zero)
+                .map(|p| p.fill.name().to_string()),
+            unknown: BTreeMap::new(),
+        }
+    }
+}
+
+/// 将 SerdeNode 转为我们自己的 Node 结构；按值消费，键与内容直接移动而不复制
+fn serde_to_node(name: String, snode: SerdeNode) -> Result<Node> {
+    if let Some(issue) = name_path_issue(&name) {
+        bail!(
+            "Key '{}' {}; spec paths must be relative to the output directory",
+            name,
+            issue
+        );
+    }
+    match snode {
+        SerdeNode::Str(content) => Ok(Node::new_file(name, Some(content))),
+        SerdeNode::Meta(meta) => meta_to_node(name, *meta),
+        SerdeNode::Map(map) => {
+            let mut dir = Node::new_dir(name);
+            // collect 到 Result 时拿不到长度，直接按映射大小预分配
+            dir.children.reserve_exact(map.len());
+            for (k, v) in map {
+                dir.children.push(serde_to_node(k, v)?);
+            }
+            Ok(dir)
+        }
+    }
+}
+
+/// 检查带类型节点的字段并转为 Node
+fn meta_to_node(name: String, meta: MetaNode) -> Result<Node> {
+    if let Some(field) = meta.unknown.keys().next() {
+        bail!("Unknown field '{}' in node '{}'", field, name);
+    }
+    // 每种类型只接受自己的字段
+    let set = [
+        ("from", meta.from.is_some()),
+        ("recursive", meta.recursive),
+        ("exclude", !meta.exclude.is_empty()),
+        ("include", !meta.include.is_empty()),
+        ("substitute", meta.substitute),
+        ("target", meta.target.is_some()),
+        ("content", meta.content.is_some()),
+        ("children", meta.children.is_some()),
+        ("owner", meta.owner.is_some()),
+        ("group", meta.group.is_some()),
+        ("xattrs", !meta.xattrs.is_empty()),
+        ("tags", !meta.tags.is_empty()),
+        ("raw", meta.raw),
+        ("trim", meta.trim),
+        ("mode", meta.mode.is_some()),
+        ("if_exists", meta.if_exists.is_some()),
+        ("format", meta.format.is_some()),
+        ("optional", meta.optional),
+        ("size", meta.size.is_some()),
+        ("fill", meta.fill.is_some()),
+    ];
+    if let Some((field, _)) = set
+        .iter()
+        .find(|(field, set)| *set && !meta.kind.fields().contains(field))
+    {
+        bail!(
+            "Field '{}' does not apply to {} node '{}'",
+            field,
+            meta.kind.name(),
+            name
+        );
+    }
+    for (attr, value) in &meta.xattrs {
+        if attr.is_empty() {
+            bail!("Empty xattr name in node '{}'", name);
+        }
+        xattr_value(value)
+            .with_context(|| format!("Invalid value of xattr '{}' in node '{}'", attr, name))?;
+    }
+    check_tags(&meta.tags, &name)?;
+    // 显式的类型优先于名称，但不能与结尾的 `/` 矛盾
+    if matches!(meta.kind, MetaKind::File | MetaKind::Hardlink) && name.ends_with('/') {
+        bail!(
+            "Node '{}' ends with '/' but has type {}",
+            name,
+            meta.kind.name()
+        );
+    }
+    let mode = meta
+        .mode
+        .as_deref()
+        .map(parse_mode)
+        .transpose()
+        .with_context(|| format!("Invalid 'mode' in node '{}'", name))?;
+    let if_exists = match meta.if_exists.as_deref() {
+        None => None,
+        Some(value) => Some(IfExists::parse(value).with_context(|| {
+            format!(
+                "Invalid 'if_exists' value '{}' in node '{}' (expected one of: {})",
+                value,
+                name,
+                IfExists::NAMES.join(", ")
+            )
+        })?),
+    };
+    let fill = match meta.fill.as_deref() {
+        None => None,
+        Some(value) => Some(Fill::parse(value).with_context(|| {
+            format!(
+                "Invalid 'fill' value '{}' in node '{}' (expected one of: {})",
+                value,
+                name,
+                Fill::NAMES.join(", ")
+            )
+        })?),
+    };
+    let placeholder = match meta.size {
+        None if fill.is_some() => bail!("'fill' of node '{}' needs 'size'", name),
+        None => None,
+        Some(_) if meta.content.is_some() || meta.format.is_some() => {
+            bail!("Node '{}' has both 'size' and 'content'", name)
+        }
+        Some(_) if if_exists == Some(IfExists::Append) => bail!(
+            "'if_exists: append' does not apply to node '{}' with 'size'",
+            name
+        ),
+        Some(MetaSize::Bytes(size)) => Some(size),
+        Some(MetaSize::Text(text)) => {
+            Some(parse_size(&text).with_context(|| format!("Invalid 'size' in node '{}'", name))?)
+        }
+    }
+    .map(|size| Placeholder {
+        size,
+        fill: fill.unwrap_or_default(),
+    });
+    let (content, structured) = match (meta.content, meta.format) {
+        (Some(MetaContent::Structured(value)), Some(format)) => {
+            let structured = Structured { format, value };
+            let text = structured
+                .render()
+                .with_context(|| format!("Failed to serialize 'content' of node '{}'", name))?;
+            (Some(text), Some(structured))
+        }
+        (Some(MetaContent::Structured(_)), None) => bail!(
+            "Structured 'content' of node '{}' needs 'format' (json, yaml or toml)",
+            name
+        ),
+        (_, Some(_)) => bail!(
+            "'format' of node '{}' needs structured 'content' (an object or array)",
+            name
+        ),
+        (Some(MetaContent::Text(text)), None) => (Some(text), None),
+        (None, None) => (None, None),
+    };
+    let attrs = Attrs {
+        owner: meta.owner,
+        group: meta.group,
+        xattrs: meta.xattrs,
+        tags: meta.tags,
+        raw: meta.raw,
+        trim: meta.trim,
+        mode,
+        if_exists,
+        structured,
+        bytes: None,
+        optional: meta.optional,
+        placeholder,
+        redacted: false,
+    };
+    let mut node = match meta.kind {
+        MetaKind::File => Node::new_file(name, Some(content.unwrap_or_default())),
+        MetaKind::Dir => serde_to_node(name, SerdeNode::Map(meta.children.unwrap_or_default()))?,
+        MetaKind::Hardlink => {
+            let Some(target) = meta.target else {
+                bail!("Hardlink node '{}' is missing 'target'", name);
+            };
+            let escapes = target
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+            if escapes || target.as_os_str().is_empty() {
+                bail!(
+                    "Hardlink target '{}' of node '{}' must be a path inside the output root",
+                    target.display(),
+                    name
+                );
+            }
+            Node::new_hardlink(name, target)
+        }
+        MetaKind::Copy => {
+            let Some(from) = meta.from else {
+                bail!("Copy node '{}' is missing 'from'", name);
+            };
+            // include 只对目录有意义，等同于允许递归复制
+            let source = CopySource {
+                from,
+                recursive: meta.recursive || !meta.include.is_empty(),
+                exclude: meta.exclude,
+                include: meta.include,
+                substitute: meta.substitute,
+                ..CopySource::default()
+            };
+            // 提前检查 glob，错误指向描述文件而不是生成阶段
+            exclude_set(&source)
+                .with_context(|| format!("Invalid 'exclude' in node '{}'", name))?;
+            glob_set(&source.include)
+                .with_context(|| format!("Invalid 'include' in node '{}'", name))?;
+            Node::new_copy(name, source)
+        }
+    };
+    node.attrs = attrs;
+    Ok(node)
+}
+
+/// 解析八进制权限，可带 `0o` 前缀，如 `0o644`
+///
+/// ```
+/// assert_eq!(treegen::parse_mode("0o755")?, 0o755);
+/// assert_eq!(treegen::parse_mode("644")?, 0o644);
+/// assert!(treegen::parse_mode("0o9").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_mode(text: &str) -> Result<u32> {
+    u32::from_str_radix(text.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .with_context(|| format!("Invalid mode '{}'; use octal like 0o644", text))
+}
+
+/// profile 名不能为空，也不能含空白、逗号或方括号（Markdown 的 `#[a, b]` 后缀无法表示）
+fn check_tags(tags: &BTreeSet<String>, name: &str) -> Result<()> {
+    if let Some(tag) = tags.iter().find(|tag| {
+        tag.is_empty() || tag.contains(|c: char| c.is_whitespace() || ",[]".contains(c))
+    }) {
+        bail!("Invalid tag '{}' in node '{}'", tag, name);
+    }
+    Ok(())
+}
+
+/// 描述文件的顶层：以 $ 开头的保留键是设置，其余键组成文件树
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(
+    title = "treegen spec",
+    description = "Top-level keys are files and directories; keys starting with $ are settings"
+)]
+struct SpecDocument {
+    /// 生成完成后执行的钩子：`"$hooks": { "post": ["cargo fmt"] }`
+    #[serde(rename = "$hooks", default)]
+    #[schemars(description = "Commands run in the output directory after generation")]
+    hooks: Hooks,
+
+    /// 模板变量声明：`"$vars": { "license": "MIT", "port": { "type": "int", "default": 8080 } }`
+    #[serde(rename = "$vars", default)]
+    #[schemars(
+        description = "Template variables: a default value, or a declaration with type/default/description"
+    )]
+    vars: BTreeMap<String, SerdeVar>,
+
+    /// 规则：`"$rules": [{ "match": "**/*.sh", "mode": "0o755" }]`
+    #[serde(rename = "$rules", default)]
+    #[schemars(
+        description = "Rules applied in order to every file whose path matches; later rules override earlier ones"
+    )]
+    rules: Vec<SerdeRule>,
+
+    /// 命名内容块：`"$blocks": { "pyinit": "..." }`，文件内容写 `"@block:pyinit"` 引用
+    #[serde(rename = "$blocks", default)]
+    #[schemars(
+        description = "Named content blocks; a file whose content is \"@block:NAME\" gets the block's text. Template variables are substituted, and a block may itself be \"@snippet:NAME\""
+    )]
+    blocks: BTreeMap<String, String>,
+
+    /// 默认输出目录：`"$out": "./services/{{name}}"`，相对描述文件所在目录
+    #[serde(rename = "$out", default)]
+    #[schemars(
+        description = "Default output directory, relative to the spec file; used when --out is not given. Template variables are substituted"
+    )]
+    out: Option<String>,
+
+    #[serde(flatten)]
+    tree: BTreeMap<String, SerdeNode>,
+}
+
+/// `$rules` 的一项
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SerdeRule {
+    #[serde(rename = "match")]
+    #[schemars(
+        description = "Glob matched against file paths relative to the output root, e.g. \"**/*.rs\""
+    )]
+    pattern: String,
+    #[serde(default)]
+    #[schemars(description = "Text added before the file content")]
+    prepend: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Text added after the file content")]
+    append: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Permissions in octal, e.g. \"0o755\" (Unix only)")]
+    mode: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Owner, a user name or numeric uid (Unix only)")]
+    owner: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Group, a group name or numeric gid (Unix only)")]
+    group: Option<String>,
+    #[serde(default)]
+    #[schemars(description = "Extended attributes to set, name -> value")]
+    xattrs: BTreeMap<String, String>,
+    #[serde(default)]
+    #[schemars(description = "Profiles, replacing the node's tags")]
+    tags: BTreeSet<String>,
+    #[serde(default)]
+    #[schemars(description = "Write the content without template variable substitution")]
+    raw: Option<bool>,
+}
+
+impl SerdeRule {
+    fn into_rule(self) -> Result<Rule> {
+        Glob::new(&self.pattern).with_context(|| format!("Invalid glob '{}'", self.pattern))?;
+        let mode = self.mode.as_deref().map(parse_mode).transpose()?;
+        for (attr, value) in &self.xattrs {
+            if attr.is_empty() {
+                bail!("Empty xattr name");
+            }
+            xattr_value(value).with_context(|| format!("Invalid value of xattr '{}'", attr))?;
+        }
+        check_tags(&self.tags, &self.pattern)?;
+        Ok(Rule {
+            pattern: self.pattern,
+            prepend: self.prepend,
+            append: self.append,
+            mode,
+            owner: self.owner,
+            group: self.group,
+            xattrs: self.xattrs,
+            tags: self.tags,
+            raw: self.raw,
+        })
+    }
+}
+
+/// `$vars` 的一项：只写默认值，或完整的声明
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum SerdeVar {
+    #[schemars(description = "Default value")]
+    Default(Scalar),
+    Decl(VarSpec),
+}
+
+/// 变量声明
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct VarSpec {
+    #[serde(rename = "type", default)]
+    #[schemars(description = "Value type checked against --var and the default (default: string)")]
+    kind: VarType,
+    #[serde(default)]
+    #[schemars(description = "Value used when the variable is not given")]
+    default: Option<Scalar>,
+    #[serde(default)]
+    #[schemars(description = "Shown by `treegen vars` and in prompts")]
+    description: Option<String>,
+}
+
+/// 标量值：各格式中的数字、布尔值都按文本使用
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum Scalar {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scalar::Bool(b) => write!(f, "{}", b),
+            Scalar::Int(i) => write!(f, "{}", i),
+            Scalar::Float(x) => write!(f, "{}", x),
+            Scalar::Str(s) => f.write_str(s),
+        }
+    }
+}
+
+impl SerdeVar {
+    fn into_decl(self, name: &str) -> Result<VarDecl> {
+        let decl = match self {
+            SerdeVar::Default(value) => VarDecl {
+                default: Some(value.to_string()),
+                ..Default::default()
+            },
+            SerdeVar::Decl(spec) => VarDecl {
+                kind: spec.kind,
+                default: spec.default.map(|value| value.to_string()),
+                description: spec.description,
+            },
+        };
+        if let Some(default) = &decl.default {
+            decl.check(name, default)
+                .context("Invalid default in '$vars'")?;
+        }
+        Ok(decl)
+    }
+}
+
+/// 钩子命令
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[schemars(description = "Hook commands")]
+struct Hooks {
+    #[serde(default)]
+    #[schemars(description = "Shell commands run in order after a successful generation")]
+    post: Vec<String>,
+}
+
+impl SpecDocument {
+    fn into_spec(self) -> Result<Spec> {
+        let mut root = serde_to_node(String::new(), SerdeNode::Map(self.tree))?;
+        resolve_blocks(&mut root, &self.blocks)?;
+        Ok(Spec {
+            root,
+            hooks: self.hooks.post,
+            vars: self
+                .vars
+                .into_iter()
+                .map(|(name, var)| Ok((name.clone(), var.into_decl(&name)?)))
+                .collect::<Result<_>>()?,
+            rules: self
+                .rules
+                .into_iter()
+                .enumerate()
+                .map(|(i, rule)| {
+                    rule.into_rule()
+                        .with_context(|| format!("Invalid rule {} in '$rules'", i + 1))
+                })
+                .collect::<Result<_>>()?,
+            config: SpecConfig {
+                out: self.out.map(PathBuf::from),
+                mode: None,
+            },
+        })
+    }
+}
+
+/// 引用 `$blocks` 中内容块的文件内容前缀
+pub const BLOCK_PREFIX: &str = "@block:";
+
+/// 把内容为 `@block:NAME` 的文件换成同一描述文件 `$blocks` 中的文本（`raw: true` 的文件不解析）。
+/// 块的文本原样放入，之后与其他内容一样做变量替换；块本身可以是 `@snippet:NAME`，
+/// 由命令行在生成前展开，但不能再引用其他块
+///
+/// ```
+/// let spec = treegen::parse_yaml(
+///     "$blocks: { pyinit: '# {{name}}' }\npkg:\n  __init__.py: '@block:pyinit'\n",
+/// )?;
+/// let init = spec.root.find("pkg/__init__.py").unwrap();
+/// assert_eq!(init.content.as_deref(), Some("# {{name}}"));
+///
+/// let err = treegen::parse_yaml("a.py: '@block:missing'\n").unwrap_err();
+/// assert!(err.to_string().contains("Undefined block 'missing' referenced by 'a.py'"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+fn resolve_blocks(root: &mut Node, blocks: &BTreeMap<String, String>) -> Result<()> {
+    fn walk(node: &mut Node, parent: &Path, blocks: &BTreeMap<String, String>) -> Result<()> {
+        let path = parent.join(&node.name);
+        let name = node
+            .content
+            .as_deref()
+            .and_then(|content| content.strip_prefix(BLOCK_PREFIX))
+            .filter(|_| {
+                node.node_type == NodeType::File
+                    && !node.attrs.raw
+                    && node.attrs.structured.is_none()
+            });
+        if let Some(name) = name {
+            let Some(block) = blocks.get(name) else {
+                let defined: Vec<&str> = blocks.keys().map(String::as_str).collect();
+                bail!(
+                    "Undefined block '{}' referenced by '{}' (defined in '$blocks': {})",
+                    name,
+                    path.display(),
+                    if defined.is_empty() {
+                        "none".to_string()
+                    } else {
+                        defined.join(", ")
+                    }
+                );
+            };
+            if block.starts_with(BLOCK_PREFIX) {
+                bail!(
+                    "Block '{}' (used by '{}') refers to another block; blocks can only reference snippets",
+                    name,
+                    path.display()
+                );
+            }
+            node.content = Some(block.clone());
+        }
+        for child in node.children.iter_mut() {
+            walk(child, &path, blocks)?;
+        }
+        Ok(())
+    }
+
+    walk(root, Path::new(""), blocks)
+}
+
+/// 从 YAML 文本中解析出描述：映射表示目录，字符串表示文件内容
+///
+/// ```
+/// let spec = treegen::parse_yaml("src:\n  main.rs: 'fn main() {}'\n")?;
+/// let main_rs = &spec.root.children[0].children[0];
+/// assert_eq!(main_rs.content.as_deref(), Some("fn main() {}"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_yaml(content: &str) -> Result<Spec> {
+    let doc: SpecDocument = serde_yaml::from_str(content)?;
+    doc.into_spec()
+}
+
+/// 从 JSON 文本中解析出描述；也接受 GNU tree 的 JSON 输出（`tree -J`），
+/// 其中的符号链接只保留指向树内文件的，转为硬链接
+///
+/// ```
+/// let spec = treegen::parse_json(r#"{ "src": { "lib.rs": "" }, "assets": {} }"#)?;
+/// assert_eq!(spec.root.children.len(), 2);
+///
+/// let spec = treegen::parse_json(
+///     r#"[{"type":"directory","name":".","contents":[{"type":"file","name":"a.txt"}]},
+///        {"type":"report","directories":0,"files":1}]"#,
+/// )?;
+/// assert!(spec.root.find("a.txt").is_some());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_json(content: &str) -> Result<Spec> {
+    parse_json_with(content).map(|(spec, _)| spec)
+}
+
+/// 解析 JSON 描述，同时返回解析过程的说明（`tree -J` 输出中被跳过的项等）
+fn parse_json_with(content: &str) -> Result<(Spec, Vec<String>)> {
+    if is_tree_json(content) {
+        let value: serde_json::Value = serde_json::from_str(content)?;
+        if let Some(entries) = tree_json_entries(&value) {
+            return Ok(TreeJson::convert(entries));
+        }
+    }
+    let doc: SpecDocument = serde_json::from_str(content)?;
+    Ok((doc.into_spec()?, Vec::new()))
+}
+
+/// 描述文档总是对象，顶层是数组的只可能是 `tree -J` 的输出
+fn is_tree_json(content: &str) -> bool {
+    content.trim_start().starts_with('[')
+}
+
+/// `tree -J` 输出的顶层数组：每个元素都是带 `type` 的对象
+fn tree_json_entries(value: &serde_json::Value) -> Option<&[serde_json::Value]> {
+    let entries = value.as_array()?;
+    entries
+        .iter()
+        .all(|entry| entry.get("type").is_some_and(|kind| kind.is_string()))
+        .then_some(entries.as_slice())
+}
+
+/// `tree -J` 输出的转换：先收集所有路径的类型，以便判断链接的目标
+#[derive(Default)]
+struct TreeJson<'a> {
+    /// 路径（相对根）-> (type, 链接目标)
+    kinds: BTreeMap<PathBuf, (&'a str, Option<&'a str>)>,
+    notes: Vec<String>,
+}
+
+impl<'a> TreeJson<'a> {
+    /// 名为 `.` 的顶层目录（在当前目录运行 `tree -J`）的内容直接放在根下，
+    /// 其他顶层项只保留最后一段名称；`report` 对象被忽略
+    fn convert(entries: &'a [serde_json::Value]) -> (Spec, Vec<String>) {
+        let mut tree = TreeJson::default();
+        tree.notes.push("Read as tree -J output".to_string());
+        let mut tops = Vec::new();
+        for entry in entries {
+            let kind = entry["type"].as_str().unwrap_or_default();
+            if kind == "report" {
+                continue;
+            }
+            let name = entry["name"].as_str().unwrap_or_default();
+            let path = match Path::new(name).file_name() {
+                Some(last) if kind != "directory" || name != "." => PathBuf::from(last),
+                _ => PathBuf::new(),
+            };
+            tree.collect(entry, &path);
+            tops.push((entry, path));
+        }
+        let mut root = Node::new_dir(String::new());
+        for (entry, path) in tops {
+            if path.as_os_str().is_empty() {
+                root.children.extend(tree.children(entry, &path));
+            } else {
+                root.children.extend(tree.node(entry, path));
+            }
+        }
+        (Spec::from_tree(root), tree.notes)
+    }
+
+    fn collect(&mut self, entry: &'a serde_json::Value, path: &Path) {
+        let kind = entry["type"].as_str().unwrap_or_default();
+        if !path.as_os_str().is_empty() {
+            self.kinds
+                .insert(path.to_path_buf(), (kind, entry["target"].as_str()));
+        }
+        for child in entry["contents"].as_array().into_iter().flatten() {
+            if let Some(name) = child["name"].as_str() {
+                self.collect(child, &path.join(name));
+            }
+        }
+    }
+
+    fn children(&mut self, entry: &serde_json::Value, path: &Path) -> Vec<Node> {
+        let mut children = Vec::new();
+        for child in entry["contents"].as_array().into_iter().flatten() {
+            match (child["name"].as_str(), child["error"].as_str()) {
+                (Some(name), _) => children.extend(self.node(child, path.join(name))),
+                (None, Some(error)) => {
+                    self.notes
+                        .push(format!("Skip error in '{}': {}", path.display(), error))
+                }
+                (None, None) => {}
+            }
+        }
+        children
+    }
+
+    fn node(&mut self, entry: &serde_json::Value, path: PathBuf) -> Option<Node> {
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        match entry["type"].as_str().unwrap_or_default() {
+            "file" => Some(Node::new_file(name, None)),
+            "directory" => {
+                let mut dir = Node::new_dir(name);
+                dir.children = self.children(entry, &path);
+                Some(dir)
+            }
+            "link" => {
+                let target = entry["target"].as_str().unwrap_or_default();
+                match self.link_target(&path, 0) {
+                    Ok(resolved) => Some(Node::new_hardlink(name, resolved)),
+                    Err(reason) => {
+                        self.notes.push(format!(
+                            "Skip link '{}' -> '{}': {}",
+                            path.display(),
+                            target,
+                            reason
+                        ));
+                        None
+                    }
+                }
+            }
+            kind => {
+                self.notes
+                    .push(format!("Skip {} '{}'", kind, path.display()));
+                None
+            }
+        }
+    }
+
+    /// 链接目标（相对链接所在目录）解析为相对根的路径；只接受树内的文件，
+    /// 或最终指向树内文件的链接
+    fn link_target(&self, link: &Path, depth: usize) -> Result<PathBuf, &'static str> {
+        let Some((_, Some(target))) = self.kinds.get(link) else {
+            return Err("link has no target");
+        };
+        let mut resolved = link.parent().map(Path::to_path_buf).unwrap_or_default();
+        for component in Path::new(target).components() {
+            match component {
+                Component::CurDir => {}
+                Component::Normal(name) => resolved.push(name),
+                Component::ParentDir if resolved.pop() => {}
+                _ => return Err("target is outside the tree"),
+            }
+        }
+        match self.kinds.get(&resolved) {
+            Some(("file", _)) => Ok(resolved),
+            Some(("link", _)) if depth < 40 => {
+                self.link_target(&resolved, depth + 1).map(|_| resolved)
+            }
+            Some(("link", _)) => Err("too many levels of links"),
+            Some(("directory", _)) => Err("target is a directory"),
+            Some(_) => Err("target is not a regular file"),
+            None => Err("target is not in the tree"),
+        }
+    }
+}
+
+/// 从 TOML 文本中解析出描述
+///
+/// ```
+/// let spec = treegen::parse_toml("[src]\n\"main.rs\" = \"fn main() {}\"\n")?;
+/// assert_eq!(spec.root.children[0].name, "src");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_toml(content: &str) -> Result<Spec> {
+    check_nesting(content, Syntax::Toml)?;
+    let doc: SpecDocument = toml::from_str(content)?;
+    doc.into_spec()
+}
+
+/// dedent(): 去除多行字符串的首尾空行 + 公共缩进，保持内容整体对齐
+fn dedent(s: &str) -> String {
+    // 1. 按行拆分，去掉首尾纯空行
+    let mut lines: Vec<&str> = s.lines().collect();
+    // 去掉前导空行
+    while !lines.is_empty() && lines.first().unwrap().trim().is_empty() {
+        lines.remove(0);
+    }
+    // 去掉末尾空行
+    while !lines.is_empty() && lines.last().unwrap().trim().is_empty() {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    // 2. 找到所有非空行的最小缩进数（以空格计）
+    let mut min_indent = usize::MAX;
+    for &line in &lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let count = line.chars().take_while(|c| *c == ' ').count();
+        if count < min_indent {
+            min_indent = count;
+        }
+    }
+    if min_indent == usize::MAX {
+        min_indent = 0;
+    }
+    // 3. 对每行去除前 min_indent 个空格
+    let dedented: Vec<String> = lines
+        .into_iter()
+        .map(|line| {
+            if line.len() >= min_indent {
+                line[min_indent..].to_string()
+            } else {
+                line.trim_start().to_string()
+            }
+        })
+        .collect();
+    dedented.join("\n")
+}
+
+/// === JSON5 格式解析 ===
+/// 支持：
+///  - 反引号（`…`）包裹多行字符串
+///  - 单/双引号字符串、无引号键、注释、末尾逗号等 JSON5 特性
+///  - 写法示例 (structure.json5)：
+///    ```json5
+///    // 顶层就是一个对象
+///    {
+///      my_project: {
+///        src: {
+///          "main.rs": `
+///            fn main() {
+///                println!("Hello from JSON5!");
+///            }
+///          `,
+///          "lib.rs": ""
+///        },
+///        "Cargo.toml": `
+///    [package]
+///    name = "my_project"
+///    version = "0.1.0"
+///    `,
+///        "README.md": `
+///    # My Project
+///
+///    这是示例项目，通过 JSON5 定义生成。
+///    `
+///      }
+///    }
+///    ```
+///  直接用 `json5::from_str` 解析时，内部会保留原样的多行文本，我们再对其 dedent 后输出。
+///
+/// ```
+/// let spec = treegen::parse_json5("{ src: { 'lib.rs': '', }, // comment\n}")?;
+/// assert_eq!(spec.root.children[0].children[0].name, "lib.rs");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_json5(raw: &str) -> Result<Spec> {
+    // 1. 我们需要先把所有反引号包裹的多行内容 dedent 后再交给 json5 解析。
+    //    简单思路：扫描整个 raw，将 `…` 之间的内容先提取、dedent、再放回 raw 中。
+    let mut output = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '`' {
+            // 收集反引号内的内容
+            let mut content = String::new();
+            while let Some(&next_ch) = chars.peek() {
+                chars.next();
+                if next_ch == '`' {
+                    break;
+                } else {
+                    content.push(next_ch);
+                }
+            }
+            // dedent 之后再放到 output：用三引号包裹以便 JSON5 理解多行？
+            // 但 json5 本身也支持反引号，此处只要保证「缩进对齐」，让 JSON5 解析时拿到干净的多行文本即可。
+            let dedented = dedent(&content); // 修复反引号包裹内容的缩进问题
+            output.push('`');
+            output.push_str(&dedented);
+            output.push('`');
+        } else {
+            output.push(ch);
+        }
+    }
+
+    // 2. 用 json5 解析成 SpecDocument，再转为 Node 树
+    check_nesting(&output, Syntax::Json5)?;
+    let doc: SpecDocument = json5::from_str(&output)?;
+    doc.into_spec()
+}
+
+/// 从路径列表（如 `git ls-files`、`find` 的输出）解析描述：每行一个相对路径，开头的 `./` 去掉，
+/// 以 `/` 结尾或是其他路径上级的是目录，中间目录自动补齐，重复的行忽略；文件没有内容。
+/// 绝对路径与含 `..` 的路径报错
+///
+/// ```
+/// use treegen::NodeType;
+///
+/// let spec = treegen::parse_paths("./src/main.rs\nsrc/lib.rs\nsrc/main.rs\ndocs/\nREADME.md\n")?;
+/// assert_eq!(spec.root.children.len(), 3);
+/// assert_eq!(spec.root.find("src").unwrap().children.len(), 2);
+/// assert_eq!(spec.root.find("docs").unwrap().node_type, NodeType::Dir);
+/// assert!(treegen::parse_paths("src/../x\n").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_paths(content: &str) -> Result<Spec> {
+    let mut root = Node::new_dir(String::new());
+    for (index, line) in content.lines().enumerate() {
+        let mut path = line.trim_end_matches('\r');
+        while let Some(rest) = path.strip_prefix("./") {
+            path = rest;
+        }
+        if path.trim().is_empty() || path == "." {
+            continue;
+        }
+        if !matches!(name_path_issue(path), None | Some(PathIssue::ParentDir)) {
+            bail!("Line {}: '{}' must be a relative path", index + 1, path);
+        }
+        let names: Vec<&str> = path
+            .split('/')
+            .filter(|name| !name.is_empty() && *name != ".")
+            .collect();
+        if names.contains(&"..") {
+            bail!("Line {}: '..' is not allowed in '{}'", index + 1, path);
+        }
+        let is_dir = path.ends_with('/');
+        let mut dir = &mut root;
+        for (i, &name) in names.iter().enumerate() {
+            let file = i + 1 == names.len() && !is_dir;
+            let index = match dir.children.iter().position(|c| c.name == name) {
+                Some(index) => {
+                    // 先作为文件出现、之后又有子项的是目录
+                    let child = &mut dir.children[index];
+                    if !file && child.node_type == NodeType::File {
+                        child.node_type = NodeType::Dir;
+                    }
+                    index
+                }
+                None => {
+                    dir.children.push(if file {
+                        Node::new_file(name.to_string(), None)
+                    } else {
+                        Node::new_dir(name.to_string())
+                    });
+                    dir.children.len() - 1
+                }
+            };
+            dir = &mut dir.children[index];
+        }
+    }
+    Ok(Spec::from_tree(root))
+}
+
+/// toml 与 json5 解析器没有递归深度限制（serde_json/serde_yaml 有），
+/// 过深的输入会直接栈溢出，因此先用词法扫描估算嵌套深度
+const PARSE_MAX_NESTING: usize = 512;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Syntax {
+    Toml,
+    Json5,
+}
+
+/// 估算文本的嵌套深度（括号层数；TOML 另计表头与键中的点号），超出 PARSE_MAX_NESTING 时报错。
+/// 只需保守估计：字符串与注释中的字符不计入
+fn check_nesting(text: &str, syntax: Syntax) -> Result<()> {
+    let toml = syntax == Syntax::Toml;
+    let mut chars = text.chars().peekable();
+    let (mut depth, mut header, mut line_dots) = (0usize, 0usize, 0usize);
+    let mut header_line = false;
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        let at_line_start = std::mem::replace(&mut line_start, false);
+        match c {
+            '"' | '\'' | '`' if c != '`' || !toml => {
+                while let Some(s) = chars.next() {
+                    if s == '\\' && c != '`' {
+                        chars.next();
+                    } else if s == c {
+                        break;
+                    }
+                }
+            }
+            '#' if toml => while chars.next_if(|&s| s != '\n').is_some() {},
+            '/' if !toml && chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|&s| s != '\n').is_some() {}
+            }
+            '/' if !toml && chars.next_if_eq(&'*').is_some() => {
+                while let Some(s) = chars.next() {
+                    if s == '*' && chars.next_if_eq(&'/').is_some() {
+                        break;
+                    }
+                }
+            }
+            '{' | '[' => {
+                header_line |= toml && at_line_start && c == '[';
+                depth += 1;
+            }
+            '}' | ']' => depth = depth.saturating_sub(1),
+            '.' if toml => line_dots += 1,
+            '\n' => {
+                if header_line {
+                    header = line_dots + 1;
+                }
+                (line_dots, header_line, line_start) = (0, false, true);
+            }
+            c if c.is_whitespace() => line_start = at_line_start,
+            _ => {}
+        }
+        if depth + header + line_dots > PARSE_MAX_NESTING {
+            bail!(
+                "Spec is nested deeper than {} levels, which the parser cannot handle",
+                PARSE_MAX_NESTING
+            );
+        }
+    }
+    Ok(())
+}
+
+// === 描述文件格式 ===
+
+/// 描述文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Yaml,
+    Json,
+    Toml,
+    Json5,
+    /// 每行一个路径的列表，见 [`parse_paths`]
+    Paths,
+}
+
+/// 描述文件的压缩格式，按最后一个扩展名识别（`spec.json.gz`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// `.gz`
+    Gzip,
+    /// `.zst`，需要启用 `zstd` feature
+    Zstd,
+}
+
+impl Compression {
+    /// 根据最后一个扩展名确定压缩格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// 用于错误信息的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// 解压为 UTF-8 文本
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use treegen::Compression;
+    ///
+    /// let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    /// encoder.write_all(b"a.txt: x\n")?;
+    /// assert_eq!(Compression::Gzip.decompress(&encoder.finish()?)?, "a.txt: x\n");
+    /// assert!(Compression::Gzip.decompress(b"a.txt: x\n").is_err());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn decompress(self, data: &[u8]) -> Result<String> {
+        let mut content = String::new();
+        match self {
+            Compression::Gzip => {
+                use std::io::Read as _;
+                flate2::read::MultiGzDecoder::new(data).read_to_string(&mut content)?;
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                use std::io::Read as _;
+                zstd::Decoder::new(data)?.read_to_string(&mut content)?;
+            }
+            #[cfg(not(feature = "zstd"))]
+            Compression::Zstd => {
+                bail!("zstd support is not enabled (rebuild treegen with the 'zstd' feature)")
+            }
+        }
+        Ok(content)
+    }
+}
+
+impl Format {
+    /// 根据扩展名确定格式；压缩文件（`.gz`/`.zst`）按内层的扩展名，如 `spec.json.gz`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let path = match Compression::from_path(path) {
+            Some(_) => Path::new(path.file_stem()?),
+            None => path,
+        };
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "md" => Some(Format::Markdown),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "json5" => Some(Format::Json5),
+            "paths" | "list" => Some(Format::Paths),
+            _ => None,
+        }
+    }
+
+    /// 用于日志与错误信息的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Markdown => "Markdown",
+            Format::Yaml => "YAML",
+            Format::Json => "JSON",
+            Format::Toml => "TOML",
+            Format::Json5 => "JSON5",
+            Format::Paths => "path list",
+        }
+    }
+
+    /// 该格式的默认扩展名
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Markdown => "md",
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+            Format::Toml => "toml",
+            Format::Json5 => "json5",
+            Format::Paths => "paths",
+        }
+    }
+
+    /// 按 [`schema`] 校验该格式的文本，报告每处不符合的 JSON Pointer；
+    /// Markdown 树与路径列表没有对应的 JSON 结构，总是通过
+    ///
+    /// ```
+    /// use treegen::Format;
+    ///
+    /// assert!(Format::Yaml.validate("src:\n  main.rs: ''\n").is_ok());
+    /// let err = Format::Json.validate(r#"{ "src": { "main.rs": 42 } }"#).unwrap_err();
+    /// assert!(format!("{:#}", err).contains("/src/main.rs"));
+    /// ```
+    pub fn validate(self, content: &str) -> Result<()> {
+        let value: serde_json::Value = match self {
+            Format::Markdown | Format::Paths => return Ok(()),
+            Format::Yaml => serde_yaml::from_str(content)?,
+            Format::Json => {
+                let value = serde_json::from_str(content)?;
+                // tree -J 的输出不是描述文档，没有可校验的结构
+                if tree_json_entries(&value).is_some() {
+                    return Ok(());
+                }
+                value
+            }
+            Format::Toml => {
+                check_nesting(content, Syntax::Toml)?;
+                serde_json::to_value(toml::from_str::<toml::Value>(content)?)?
+            }
+            Format::Json5 => {
+                check_nesting(content, Syntax::Json5)?;
+                json5::from_str(content)?
+            }
+        };
+        validate_value(&value)
+    }
+
+    /// 按该格式解析文本
+    pub fn parse(self, content: &str) -> Result<Spec> {
+        match self {
+            Format::Markdown => parse_markdown(content),
+            Format::Yaml => parse_yaml(content),
+            Format::Json => parse_json(content),
+            Format::Toml => parse_toml(content),
+            Format::Json5 => parse_json5(content),
+            Format::Paths => parse_paths(content),
+        }
+    }
+}
+
+/// 格式是如何确定的
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    /// 调用方显式指定
+    Override,
+    /// 根据扩展名
+    Extension,
+    /// 扩展名缺失或无法识别，根据内容推断
+    Sniffed,
+}
+
+/// 解析后的单个描述文件，以及它的格式与确定方式
+#[derive(Debug, Clone)]
+pub struct Parsed {
+    pub spec: Spec,
+    pub format: Format,
+    pub detection: Detection,
+    /// 解析时的提示（如跳过的 Markdown 省略行），供 `--verbose` 显示
+    pub notes: Vec<String>,
+    /// 读取与解析的耗时、解析出的节点数与描述的字节数
+    pub timing: Timing,
+}
+
+/// 根据内容推断格式，返回推断出的格式及解析结果。
+///
+/// 依次检查（只尝试外观相符的格式，且必须能成功解析）：
+/// 1. 首个有效行（跳过空行与注释）以 `{` 开头：JSON，其次 JSON5；
+///    为 `[` 或以 `[{` 开头：`tree -J` 输出的 JSON
+/// 2. 含 `├──`/`└──` 连接符，或首行以 `/` 结尾：Markdown 树
+/// 3. 首个有效行形如 `key = ...` 或 `[table]`：TOML
+/// 4. 首个有效行形如 `key:`：YAML
+///
+/// 多类格式都能解析时视为有歧义而报错，需要显式指定格式。
+///
+/// ```
+/// use treegen::{sniff, Format};
+///
+/// assert_eq!(sniff("src:\n  main.rs: ''\n")?.0, Format::Yaml);
+/// assert_eq!(sniff("[src]\n\"main.rs\" = \"\"\n")?.0, Format::Toml);
+/// assert_eq!(sniff("{ src: { 'main.rs': '' } }")?.0, Format::Json5);
+/// assert_eq!(sniff("app/\n└── main.rs\n")?.0, Format::Markdown);
+/// assert!(sniff("just some words").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn sniff(content: &str) -> Result<(Format, Spec)> {
+    let first_line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("//"))
+        .unwrap_or("");
+    let toml_line = Regex::new(r#"^(\[[^\]]+\]|[A-Za-z0-9_.\-"']+\s*=)"#).unwrap();
+    let yaml_line = Regex::new(r"^(---|[^\s:#\-{\[][^:]*:(\s|$))").unwrap();
+
+    // 每一类是一组按顺序尝试的格式
+    let mut families: Vec<&[Format]> = Vec::new();
+    if first_line.starts_with('{') {
+        families.push(&[Format::Json, Format::Json5]);
+    }
+    // tree -J 的输出
+    if first_line == "[" || first_line.starts_with("[{") {
+        families.push(&[Format::Json]);
+    }
+    if content.contains("├── ") || content.contains("└── ") || first_line.ends_with('/')
+    {
+        families.push(&[Format::Markdown]);
+    }
+    if toml_line.is_match(first_line) {
+        families.push(&[Format::Toml]);
+    }
+    if yaml_line.is_match(first_line) {
+        families.push(&[Format::Yaml]);
+    }
+    if families.is_empty() {
+        bail!("Content does not look like any supported format (JSON, JSON5, Markdown tree, TOML, YAML); specify the format explicitly");
+    }
+
+    let mut matched = Vec::new();
+    let mut failures = Vec::new();
+    for family in families {
+        for &format in family {
+            match format.parse(content) {
+                Ok(spec) => {
+                    matched.push((format, spec));
+                    break;
+                }
+                Err(e) => failures.push(format!("{}: {}", format.name(), e)),
+            }
+        }
+    }
+    match matched.len() {
+        0 => bail!("Could not detect the format; tried {}", failures.join("; ")),
+        1 => Ok(matched.remove(0)),
+        _ => bail!(
+            "Ambiguous content: it parses as {}; specify the format explicitly",
+            matched
+                .iter()
+                .map(|(format, _)| format.name())
+                .collect::<Vec<_>>()
+                .join(" and ")
+        ),
+    }
+}
+
+/// 描述文件结构的 JSON Schema，由解析用的类型生成，因此不会与解析器脱节。
+/// 适用于 YAML/JSON/TOML/JSON5 描述文件
+pub fn schema() -> serde_json::Value {
+    let mut gen = schemars::gen::SchemaSettings::draft07().into_generator();
+    let mut root = gen.root_schema_for::<SpecDocument>();
+    // schemars 不会把 flatten 的 map 转成 additionalProperties，这里补上文件树部分
+    root.schema.object().additional_properties = Some(Box::new(gen.subschema_for::<SerdeNode>()));
+    root.definitions.extend(gen.take_definitions());
+    serde_json::to_value(root).expect("schema is valid JSON")
+}
+
+/// 按 [`schema`] 校验已加载的文档，汇总所有违反之处
+fn validate_value(value: &serde_json::Value) -> Result<()> {
+    let validator = jsonschema::validator_for(&schema()).expect("generated schema is valid");
+    let mut violations = Vec::new();
+    for error in validator.iter_errors(value) {
+        collect_violations(&error, &mut violations);
+    }
+    if violations.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "Spec does not match the schema ({} violation(s)):\n{}",
+        violations.len(),
+        violations.join("\n")
+    )
+}
+
+/// 把一个校验错误展开为 "JSON Pointer: 说明" 行。
+/// 节点是 anyOf（字符串或映射）：类型匹配映射而子节点出错时，报告子节点的错误，
+/// 这样指针指向真正出错的位置而不是它所在的目录
+fn collect_violations(error: &jsonschema::ValidationError, out: &mut Vec<String>) {
+    let pointer = error.instance_path().to_string();
+    if let jsonschema::error::ValidationErrorKind::AnyOf { context } = error.kind() {
+        let deeper: Vec<_> = context
+            .iter()
+            .filter(|branch| {
+                !branch.is_empty()
+                    && branch
+                        .iter()
+                        .all(|e| e.instance_path().to_string() != pointer)
+            })
+            .collect();
+        if let [branch] = deeper[..] {
+            for e in branch {
+                collect_violations(e, out);
+            }
+            return;
+        }
+        out.push(format!(
+            "  - {}: expected file content (a string) or a directory (a map), got {}",
+            display_pointer(&pointer),
+            error.instance()
+        ));
+        return;
+    }
+    out.push(format!("  - {}: {}", display_pointer(&pointer), error));
+}
+
+/// 根位置的 JSON Pointer 是空串，显示为 (root)
+fn display_pointer(pointer: &str) -> &str {
+    if pointer.is_empty() {
+        "(root)"
+    } else {
+        pointer
+    }
+}
+
+/// Markdown 树中整行注释的默认前缀
+pub const DEFAULT_COMMENT_PREFIXES: &[&str] = &["#", "//"];
+
+/// 解析选项
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// 转换为 Node 树之前先按 [`schema`] 校验（Markdown 树除外）
+    pub schema_validate: bool,
+    /// 不展开名称中的花括号（见 [`expand_braces`]），用于真实文件名含花括号的描述
+    pub no_brace_expansion: bool,
+    /// Markdown 树中只有省略号的行（`...`、`… (20 more files)`）也当作文件，而不是跳过
+    pub keep_ellipsis: bool,
+    /// Markdown 树中没有连接符、以这些前缀开头的行是注释（默认 [`DEFAULT_COMMENT_PREFIXES`]，
+    /// 空列表表示没有注释）
+    pub comment_prefixes: Vec<String>,
+    /// 名称的 Unicode 规范化形式，在展开花括号之后、合并与生成之前应用
+    pub normalize: Normalization,
+    /// Markdown 树中下一行缩进更深的文件（漏写了结尾的 `/`）改为目录，而不是报错
+    pub infer_dirs: bool,
+    /// Markdown 描述是一棵树，还是按二级标题分节的文档（见 [`MdStyle`]）
+    pub md_style: MdStyle,
+    /// 分节文档中只生成这些节（按标题的 slug 匹配）；为空时生成所有节
+    pub sections: Vec<String>,
+}
+
+/// Markdown 描述的写法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MdStyle {
+    /// 含 `## ` 标题与围栏代码块时按分节文档解析，否则按一棵树解析
+    #[default]
+    Auto,
+    /// 整个文件是一棵树
+    Tree,
+    /// 每个二级标题是一个目标子目录（名称为标题的 slug），其下围栏代码块中的树生成在该目录中
+    Sections,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            schema_validate: false,
+            no_brace_expansion: false,
+            keep_ellipsis: false,
+            comment_prefixes: DEFAULT_COMMENT_PREFIXES
+                .iter()
+                .map(|prefix| prefix.to_string())
+                .collect(),
+            normalize: Normalization::default(),
+            infer_dirs: false,
+            md_style: MdStyle::default(),
+            sections: Vec::new(),
+        }
+    }
+}
+
+/// 一个输入文件，可选地显式指定格式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Input {
+    pub path: PathBuf,
+    pub format: Option<Format>,
+}
+
+impl From<PathBuf> for Input {
+    fn from(path: PathBuf) -> Self {
+        Input { path, format: None }
+    }
+}
+
+/// 读取并解析单个描述文件。格式的优先级：`format_override` > 扩展名 > 根据内容推断
+///
+/// ```no_run
+/// use std::path::Path;
+/// use treegen::{parse_spec, Detection, Format};
+///
+/// let parsed = parse_spec(Path::new("spec.tmpl"), Some(Format::Yaml))?;
+/// assert_eq!((parsed.format, parsed.detection), (Format::Yaml, Detection::Override));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_spec(path: &Path, format_override: Option<Format>) -> Result<Parsed> {
+    parse_spec_with(path, format_override, &ParseOptions::default())
+}
+
+/// 按 `opts` 读取并解析单个描述文件，见 [`parse_spec`]
+pub fn parse_spec_with(
+    path: &Path,
+    format_override: Option<Format>,
+    opts: &ParseOptions,
+) -> Result<Parsed> {
+    let _span = tracing::info_span!("parse", path = %path.display()).entered();
+    // 耗时包括读取（与解压）文件
+    let started = Instant::now();
+    if let Some((zip, member)) = archive::split(path) {
+        let mut parsed = parse_archive_spec(&zip, member.as_deref(), format_override, opts)?;
+        parsed.timing.elapsed = started.elapsed();
+        return Ok(parsed);
+    }
+    if !path.exists() {
+        bail!("Input file '{}' does not exist", path.display());
+    }
+    let content = match Compression::from_path(path) {
+        Some(compression) => {
+            let data = fs::read(path)
+                .with_context(|| format!("Failed to read spec file '{}'", path.display()))?;
+            compression.decompress(&data).with_context(|| {
+                format!(
+                    "Failed to decompress {} spec file '{}'",
+                    compression.name(),
+                    path.display()
+                )
+            })?
+        }
+        None => fs::read_to_string(path)
+            .with_context(|| format!("Failed to read spec file '{}'", path.display()))?,
+    };
+    let known = match format_override {
+        Some(format) => Some((format, Detection::Override)),
+        None => Format::from_path(path).map(|format| (format, Detection::Extension)),
+    };
+    let undetected = format!(
+        "the extension is not one of {} and content detection failed",
+        SPEC_EXTENSIONS.join("/")
+    );
+    let name = path.display().to_string();
+    let mut parsed = parse_content(&content, &name, known, &undetected, opts)?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    resolve_copy_sources(&mut parsed.spec.root, base);
+    resolve_out(&mut parsed.spec.config, base);
+    parsed.timing.elapsed = started.elapsed();
+    tracing::debug!(
+        format = parsed.format.name(),
+        detection = ?parsed.detection,
+        nodes = parsed.timing.nodes,
+        "parsed"
+    );
+    Ok(parsed)
+}
+
+/// 解析 zip 压缩包中的描述（见 [`archive`]）：相对的复制来源指向同一压缩包内的成员，
+/// 相对的输出目录相对压缩包所在目录
+fn parse_archive_spec(
+    zip: &Path,
+    member: Option<&Path>,
+    format_override: Option<Format>,
+    opts: &ParseOptions,
+) -> Result<Parsed> {
+    if !zip.is_file() {
+        bail!("Archive '{}' does not exist", zip.display());
+    }
+    let (member, content) = archive::Archive::open(zip)?.read_spec(member)?;
+    let known = match format_override {
+        Some(format) => Some((format, Detection::Override)),
+        None => Format::from_path(&member).map(|format| (format, Detection::Extension)),
+    };
+    let undetected = format!(
+        "the extension is not one of {} and content detection failed",
+        SPEC_EXTENSIONS.join("/")
+    );
+    let name = archive::display(zip, &member);
+    let mut parsed = parse_content(&content, &name, known, &undetected, opts)?;
+    let base = member.parent().unwrap_or(Path::new(""));
+    fn resolve_members(node: &mut Node, zip: &Path, base: &Path) {
+        if let Some(source) = node.copy.as_mut().filter(|c| c.from.is_relative()) {
+            source.from = archive::resolve(base, &source.from);
+            source.archive = Some(zip.to_path_buf());
+        }
+        for child in node.children.iter_mut() {
+            resolve_members(child, zip, base);
+        }
+    }
+    resolve_members(&mut parsed.spec.root, zip, base);
+    resolve_out(
+        &mut parsed.spec.config,
+        zip.parent().unwrap_or(Path::new("")),
+    );
+    Ok(parsed)
+}
+
+/// 描述自带的相对输出目录相对 `base`（描述文件所在目录）
+fn resolve_out(config: &mut SpecConfig, base: &Path) {
+    if let Some(out) = config.out.as_mut().filter(|out| out.is_relative()) {
+        *out = base.join(out.strip_prefix(".").unwrap_or(out));
+    }
+}
+
+/// 解析内存中的描述（如剪贴板内容），`name` 只用于错误信息；
+/// 没有指定格式时根据内容推断。复制节点的相对来源相对当前目录
+///
+/// ```
+/// use treegen::{parse_str, Detection, Format, ParseOptions};
+///
+/// let parsed = parse_str("clipboard", "app/\n└── main.rs\n", None, &ParseOptions::default())?;
+/// assert_eq!((parsed.format, parsed.detection), (Format::Markdown, Detection::Sniffed));
+/// let err = parse_str("clipboard", "{", Some(Format::Json), &ParseOptions::default()).unwrap_err();
+/// assert_eq!(err.to_string(), "Failed to parse JSON in 'clipboard'");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn parse_str(
+    name: &str,
+    content: &str,
+    format: Option<Format>,
+    opts: &ParseOptions,
+) -> Result<Parsed> {
+    let known = format.map(|format| (format, Detection::Override));
+    parse_content(content, name, known, "content detection failed", opts)
+}
+
+/// 按已知格式解析，或根据内容推断；推断失败时的说明为 `undetected`
+fn parse_content(
+    content: &str,
+    name: &str,
+    known: Option<(Format, Detection)>,
+    undetected: &str,
+    opts: &ParseOptions,
+) -> Result<Parsed> {
+    let started = Instant::now();
+    let mut parsed = match known {
+        Some((format, detection)) => {
+            if opts.schema_validate {
+                format
+                    .validate(content)
+                    .with_context(|| format!("Schema validation failed for '{}'", name))?;
+            }
+            let (spec, notes) = match format {
+                Format::Markdown => parse_markdown_with(content, opts),
+                Format::Json => parse_json_with(content),
+                _ => format.parse(content).map(|spec| (spec, Vec::new())),
+            }
+            .map_err(|e| diagnostic::diagnose(e, format, content, name))
+            .with_context(|| format!("Failed to parse {} in '{}'", format.name(), name))?;
+            Parsed {
+                spec,
+                format,
+                detection,
+                notes,
+                timing: Timing::default(),
+            }
+        }
+        None => {
+            let (format, spec) = sniff(content).with_context(|| {
+                format!("Cannot determine the format of '{}': {}", name, undetected)
+            })?;
+            // 推断时已解析过一次，校验仍在返回树之前完成
+            if opts.schema_validate {
+                format
+                    .validate(content)
+                    .with_context(|| format!("Schema validation failed for '{}'", name))?;
+            }
+            // Markdown 按选项重新解析一次，推断时用的是默认选项
+            let (spec, notes) = match format {
+                Format::Markdown => parse_markdown_with(content, opts)?,
+                Format::Json => parse_json_with(content)?,
+                _ => (spec, Vec::new()),
+            };
+            Parsed {
+                spec,
+                format,
+                detection: Detection::Sniffed,
+                notes,
+                timing: Timing::default(),
+            }
+        }
+    };
+    if !opts.no_brace_expansion {
+        expand_braces(&mut parsed.spec.root).with_context(|| format!("In '{}'", name))?;
+    }
+    normalize_names(&mut parsed.spec.root, opts.normalize);
+    parsed.timing = Timing {
+        elapsed: started.elapsed(),
+        nodes: parsed.spec.root.iter().count(),
+        bytes: content.len() as u64,
+    };
+    Ok(parsed)
+}
+
+/// 把复制节点的相对来源解析为相对 `base`（描述文件所在目录）；
+/// 只能看到字符串的 `parse_*` 函数则保留原样，即相对当前目录
+fn resolve_copy_sources(node: &mut Node, base: &Path) {
+    if let Some(source) = &mut node.copy {
+        if source.from.is_relative() {
+            let from = source.from.strip_prefix(".").unwrap_or(&source.from);
+            source.from = base.join(from);
+        }
+    }
+    for child in node.children.iter_mut() {
+        resolve_copy_sources(child, base);
+    }
+}
+
+/// 读取并解析单个描述文件，见 [`parse_spec`]
+pub fn parse_file(path: &Path) -> Result<Spec> {
+    parse_spec(path, None).map(|parsed| parsed.spec)
+}
+
+/// 并行解析并合并多个描述文件，见 [`parse_specs`] 与 [`merge_specs`]
+pub fn parse_files(paths: &[PathBuf]) -> Result<Spec> {
+    let inputs: Vec<Input> = paths.iter().cloned().map(Input::from).collect();
+    let parsed = parse_specs(&inputs)?;
+    Ok(merge_specs(parsed.into_iter().map(|p| p.spec)))
+}
+
+/// 合并多个描述：顶层节点、钩子与规则按顺序拼接，变量默认值以后出现的为准
+pub fn merge_specs(specs: impl IntoIterator<Item = Spec>) -> Spec {
+    let mut merged = Spec::from_tree(Node::new_dir("".to_string()));
+    for spec in specs {
+        merged.root.children.extend(spec.root.children);
+        merged.hooks.extend(spec.hooks);
+        merged.vars.extend(spec.vars);
+        merged.rules.extend(spec.rules);
+        merged.config.merge(spec.config);
+    }
+    merged
+}
+
+/// 合并时同一路径被多次定义（且不都是目录）的处理方式；同名目录总是合并
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// 报错并指出定义它的两个输入
+    Error,
+    /// 保留先出现的定义
+    First,
+    /// 后出现的定义覆盖先前的
+    #[default]
+    Last,
+    /// 两者都保留，后出现的加上 `_1`、`_2` 等后缀（在最后一个 `.` 之前）
+    Rename,
+}
+
+/// [`OnDuplicate::Rename`] 的一次改名
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renamed {
+    /// 原路径
+    pub original: PathBuf,
+    /// 改名后的路径
+    pub renamed: PathBuf,
+    /// 提供这个定义的输入
+    pub input: PathBuf,
+}
+
+/// 按路径合并多个描述（每个带上来源，用于报告）：同名目录递归合并，
+/// 其余重复路径按 `on_duplicate` 处理；同一输入内的重复也会被发现。钩子按顺序拼接，变量默认值以后出现的为准
+///
+/// ```
+/// use std::path::PathBuf;
+/// use treegen::{merge_inputs, parse_yaml, OnDuplicate};
+///
+/// let specs = ["a", "b", "c"].map(|name| {
+///     let spec = parse_yaml(&format!("README.md: {}\ndocs: {{ {}.md: '' }}\n", name, name)).unwrap();
+///     (PathBuf::from(format!("{}.yaml", name)), spec)
+/// });
+/// let (merged, renames) = merge_inputs(specs.to_vec(), OnDuplicate::Rename)?;
+/// let names: Vec<_> = merged.root.children.iter().map(|c| c.name.as_str()).collect();
+/// assert_eq!(names, ["README.md", "docs", "README_1.md", "README_2.md"]);
+/// assert_eq!(merged.root.children[3].content.as_deref(), Some("c"));
+/// assert_eq!(merged.root.children[1].children.len(), 3);
+/// assert_eq!(renames[1].input, PathBuf::from("c.yaml"));
+///
+/// let err = merge_inputs(specs.to_vec(), OnDuplicate::Error).unwrap_err();
+/// assert_eq!(err.to_string(), "'README.md' is defined in both 'a.yaml' and 'b.yaml'");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn merge_inputs(
+    specs: Vec<(PathBuf, Spec)>,
+    on_duplicate: OnDuplicate,
+) -> Result<(Spec, Vec<Renamed>)> {
+    struct Merge {
+        on_duplicate: OnDuplicate,
+        /// 每个路径最先来自哪个输入
+        origins: BTreeMap<PathBuf, PathBuf>,
+        renames: Vec<Renamed>,
+    }
+
+    impl Merge {
+        /// 记录 `parent` 下的 `node` 及其后代来自 `input`
+        fn record(&mut self, node: &Node, parent: &Path, input: &Path) {
+            for (rel, _) in node.iter() {
+                let rel: PathBuf = parent.join(rel).components().collect();
+                self.origins
+                    .entry(rel)
+                    .or_insert_with(|| input.to_path_buf());
+            }
+        }
+
+        fn merge(
+            &mut self,
+            dir: &mut Node,
+            children: Vec<Node>,
+            path: &Path,
+            input: &Path,
+        ) -> Result<()> {
+            for mut child in children {
+                let child_path = path.join(child.key());
+                let Some(existing) = dir.children.iter_mut().find(|c| c.key() == child.key())
+                else {
+                    self.record(&child, path, input);
+                    dir.children.push(child);
+                    continue;
+                };
+                if existing.node_type == NodeType::Dir && child.node_type == NodeType::Dir {
+                    let grandchildren = std::mem::take(&mut child.children);
+                    self.merge(existing, grandchildren, &child_path, input)?;
+                    continue;
+                }
+                match self.on_duplicate {
+                    OnDuplicate::Error => {
+                        let origin = &self.origins[&child_path];
+                        if origin == input {
+                            bail!(
+                                "'{}' is defined more than once in '{}'",
+                                child_path.display(),
+                                input.display()
+                            );
+                        }
+                        bail!(
+                            "'{}' is defined in both '{}' and '{}'",
+                            child_path.display(),
+                            origin.display(),
+                            input.display()
+                        );
+                    }
+                    OnDuplicate::First => {}
+                    OnDuplicate::Last => *existing = child,
+                    OnDuplicate::Rename => {
+                        let name = unique_name(dir, child.key());
+                        self.renames.push(Renamed {
+                            original: child_path,
+                            renamed: path.join(&name),
+                            input: input.to_path_buf(),
+                        });
+                        child.name = if child.name.ends_with('/') {
+                            format!("{}/", name)
+                        } else {
+                            name
+                        };
+                        self.record(&child, path, input);
+                        dir.children.push(child);
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let mut merged = Spec::from_tree(Node::new_dir(String::new()));
+    let mut merge = Merge {
+        on_duplicate,
+        origins: BTreeMap::new(),
+        renames: Vec::new(),
+    };
+    for (input, spec) in specs {
+        merge.merge(&mut merged.root, spec.root.children, Path::new(""), &input)?;
+        merged.hooks.extend(spec.hooks);
+        merged.vars.extend(spec.vars);
+        merged.rules.extend(spec.rules);
+        merged.config.merge(spec.config);
+    }
+    Ok((merged, merge.renames))
+}
+
+/// 目录中还没有被占用的名称：在最后一个 `.` 之前依次尝试 `_1`、`_2`……（`.gitignore` 这类名称加在末尾）
+fn unique_name(dir: &Node, name: &str) -> String {
+    let (stem, ext) = match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| format!("{}_{}{}", stem, n, ext))
+        .find(|candidate| dir.children.iter().all(|c| c.key() != candidate))
+        .expect("some suffix is free")
+}
+
+/// 并行解析多个描述文件，结果按输入顺序返回；
+/// 有输入解析失败时汇总报告所有失败的输入
+pub fn parse_specs(inputs: &[Input]) -> Result<Vec<Parsed>> {
+    parse_specs_with(inputs, &ParseOptions::default())
+}
+
+/// 按 `opts` 并行解析多个描述文件，见 [`parse_specs`]
+pub fn parse_specs_with(inputs: &[Input], opts: &ParseOptions) -> Result<Vec<Parsed>> {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for result in parse_parallel(inputs, opts) {
+        match result {
+            Ok(p) => parsed.push(p),
+            Err(e) => errors.push(e),
+        }
+    }
+    match errors.len() {
+        0 => Ok(parsed),
+        1 => Err(errors.remove(0)),
+        n => bail!(
+            "Failed to parse {} of {} input files:\n{}",
+            n,
+            inputs.len(),
+            errors
+                .iter()
+                // 多行的错误（如带源码摘录的诊断）后续行与第一行对齐
+                .map(|e| format!("  - {:#}", e).replace('\n', "\n    "))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    }
+}
+
+/// 用最多 CPU 核数个线程解析，结果按输入顺序返回
+fn parse_parallel(inputs: &[Input], opts: &ParseOptions) -> Vec<Result<Parsed>> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(inputs.len());
+    let parse = |input: &Input| parse_spec_with(&input.path, input.format, opts);
+    if workers <= 1 {
+        return inputs.iter().map(parse).collect();
+    }
+    let mut results: Vec<(usize, Result<Parsed>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                scope.spawn(move || {
+                    inputs
+                        .iter()
+                        .enumerate()
+                        .skip(worker)
+                        .step_by(workers)
+                        .map(|(i, input)| (i, parse(input)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// === 将 Node 树写回描述文件 ===
+
+/// 将 Node 转回 SerdeNode（无内容的文件写成空字符串）
+/// 带附加属性的文件/目录写成带类型的节点
+fn node_to_serde(node: &Node) -> SerdeNode {
+    let children = || {
+        node.children
+            .iter()
+            .map(|c| (c.name.trim_end_matches('/').to_string(), node_to_serde(c)))
+            .collect()
+    };
+    match node.node_type {
+        NodeType::File if node.attrs.is_empty() => {
+            SerdeNode::Str(node.content.clone().unwrap_or_default())
+        }
+        NodeType::File => SerdeNode::Meta(Box::new(MetaNode {
+            content: match &node.attrs.structured {
+                _ if node.attrs.placeholder.is_some() => None,
+                Some(structured) => Some(MetaContent::Structured(structured.value.clone())),
+                None => Some(MetaContent::Text(node.content.clone().unwrap_or_default())),
+            },
+            ..MetaNode::new(MetaKind::File, &node.attrs)
+        })),
+        NodeType::Dir if node.attrs.is_empty() => SerdeNode::Map(children()),
+        NodeType::Dir => SerdeNode::Meta(Box::new(MetaNode {
+            children: Some(children()),
+            ..MetaNode::new(MetaKind::Dir, &node.attrs)
+        })),
+        NodeType::Copy => {
+            let source = node.copy.clone().unwrap_or_default();
+            SerdeNode::Meta(Box::new(MetaNode {
+                from: Some(source.from),
+                recursive: source.recursive,
+                exclude: source.exclude,
+                include: source.include,
+                substitute: source.substitute,
+                ..MetaNode::new(MetaKind::Copy, &node.attrs)
+            }))
+        }
+        NodeType::Hardlink => SerdeNode::Meta(Box::new(MetaNode {
+            target: node.target.clone(),
+            ..MetaNode::new(MetaKind::Hardlink, &node.attrs)
+        })),
+    }
+}
+
+/// 节点名称写进 Markdown 树后不能原样读回的原因；`top_line` 表示节点是顶层行
+/// （顶层行没有连接符，会被当作缩进）
+fn markdown_name_issue(node: &Node, top_line: bool) -> Option<&'static str> {
+    let name = node.name.trim_end_matches('/');
+    // 与 [`Node::render_tree`] 写出的一致：目录与递归复制带结尾的 `/`
+    let written = match node.node_type {
+        NodeType::Dir => format!("{}/", name),
+        NodeType::Copy if node.copy.as_ref().is_some_and(|c| c.recursive) => format!("{}/", name),
+        _ => node.name.clone(),
+    };
+    if name.contains(['\n', '\r']) {
+        Some("contains a line break")
+    } else if name.trim() != name {
+        Some("starts or ends with whitespace")
+    } else if name.contains(':') {
+        Some("contains ':', which Markdown trees replace with '_'")
+    } else if split_md_tags(&written).is_some() {
+        Some("ends with a #[...] tag suffix")
+    } else if is_ellipsis(&written) {
+        Some("is an ellipsis placeholder, which Markdown trees skip")
+    } else if split_md_kind(&written).is_some() {
+        Some("ends with a [f]/[d] type marker")
+    } else if check_tags(&node.attrs.tags, name).is_err() {
+        Some("has a tag that cannot be written as a #[...] suffix")
+    } else if top_line && name.starts_with(is_box_drawing) {
+        Some("starts with a tree connector at the top level")
+    } else if top_line && (name.contains("├──") || name.contains("└──")) {
+        // 连接符左边有文字的行会被当作 exa/lsd -l 的输出，见 [`normalize_ls_tree`]
+        Some("contains a tree connector at the top level")
+    } else if top_line && is_md_comment(name, DEFAULT_COMMENT_PREFIXES) {
+        Some("starts with a comment prefix at the top level")
+    } else {
+        None
+    }
+}
+
+impl Node {
+    /// 文档的顶层节点：未命名的根节点写出其子节点，具名节点写出自身
+    fn top_level(&self) -> &[Node] {
+        if self.name.is_empty() {
+            &self.children
+        } else {
+            std::slice::from_ref(self)
+        }
+    }
+
+    /// 序列化格式的文档：顶层映射（文件写成字符串，空文件为 ""，空目录为 {}）
+    fn to_serde(&self) -> SerdeNode {
+        SerdeNode::Map(
+            self.top_level()
+                .iter()
+                .map(|c| (c.name.trim_end_matches('/').to_string(), node_to_serde(c)))
+                .collect(),
+        )
+    }
+
+    /// 写出 Markdown 树：与 [`parse_markdown`] 接受的连接符/缩进保持一致（仅结构与 profile 后缀，不含内容）。
+    /// Markdown 树没有转义写法，读回来会变样的名称（见 [`markdown_name_issue`]）报错而不是写出
+    ///
+    /// ```
+    /// let md = "app/\n├── src/\n│   └── main.rs\n└── docs/ #[docs, full]\n";
+    /// let spec = treegen::parse_markdown(md)?;
+    /// assert_eq!(spec.root.to_markdown()?, md);
+    ///
+    /// let spec = treegen::parse_yaml("notes:\n  \"todo #[x]\": ''\n")?;
+    /// assert!(spec.root.to_markdown().is_err());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn to_markdown(&self) -> Result<String> {
+        for top in self.top_level() {
+            for (_, node) in top.iter() {
+                let top_line = std::ptr::eq(node, top);
+                if let Some(reason) = markdown_name_issue(node, top_line) {
+                    bail!(
+                        "'{}' cannot be written to a Markdown tree: the name {}; use another format",
+                        node.name,
+                        reason
+                    );
+                }
+            }
+        }
+        Ok(self.render_tree(&|_, node| {
+            if node.attrs.tags.is_empty() {
+                return String::new();
+            }
+            let tags: Vec<&str> = node.attrs.tags.iter().map(String::as_str).collect();
+            format!(" #[{}]", tags.join(", "))
+        }))
+    }
+
+    /// 用 Unicode 连接符画出树（与 [`Node::to_markdown`] 相同），`annotate` 返回的文本接在
+    /// 每个节点名称之后；其参数为节点路径（同 [`Node::iter`]）与节点本身
+    ///
+    /// ```
+    /// let spec = treegen::parse_markdown("app/\n└── main.rs\n")?;
+    /// let tree = spec.root.render_tree(&|path, _| format!("  # {}", path.display()));
+    /// assert_eq!(tree, "app/  # app\n└── main.rs  # app/main.rs\n");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn render_tree(&self, annotate: &dyn Fn(&Path, &Node) -> String) -> String {
+        fn walk(
+            out: &mut String,
+            node: &Node,
+            path: &Path,
+            indent: &str,
+            annotate: &dyn Fn(&Path, &Node) -> String,
+        ) {
+            let count = node.children.len();
+            for (i, child) in node.children.iter().enumerate() {
+                let last = i + 1 == count;
+                let child_path = path.join(child.name.trim_end_matches('/'));
+                out.push_str(indent);
+                out.push_str(if last { "└── " } else { "├── " });
+                out.push_str(&display_name(child));
+                out.push_str(&annotate(&child_path, child));
+                out.push('\n');
+                let next = format!("{}{}", indent, if last { "    " } else { "│   " });
+                walk(out, child, &child_path, &next, annotate);
+            }
+        }
+        fn display_name(node: &Node) -> String {
+            let is_dir = match node.node_type {
+                NodeType::Dir => true,
+                NodeType::Copy => node.copy.as_ref().is_some_and(|c| c.recursive),
+                NodeType::File | NodeType::Hardlink => false,
+            };
+            if is_dir && !node.name.ends_with('/') {
+                format!("{}/", node.name)
+            } else {
+                node.name.clone()
+            }
+        }
+
+        let mut out = String::new();
+        // 顶层节点不带连接符（层级 1），其子节点从连接符开始
+        for top in self.top_level() {
+            let path = PathBuf::from(top.name.trim_end_matches('/'));
+            out.push_str(&display_name(top));
+            out.push_str(&annotate(&path, top));
+            out.push('\n');
+            walk(&mut out, top, &path, "", annotate);
+        }
+        out
+    }
+
+    /// 写出 YAML：映射表示目录，字符串表示文件内容
+    ///
+    /// 各序列化格式都完整保留结构与内容（同级节点按名称排序写出），解析后得到相同的树：
+    ///
+    /// ```
+    /// use treegen::{parse_file, parse_json, parse_json5, parse_toml, parse_yaml, CopySource, Node};
+    ///
+    /// let mut tricky = Node::root()
+    ///     .child(
+    ///         Node::dir("odd names")
+    ///             .child(Node::dir("empty"))
+    ///             .file_empty("empty.txt")
+    ///             .file("quote\"s.md", "`code` and 'quotes'\n\ttab\n")
+    ///             .file("true", "123"),
+    ///     )
+    ///     .file("z.txt", "多字节 ✓")
+    ///     .build()?;
+    /// let vendor = CopySource {
+    ///     from: "/srv/`vendor`".into(),
+    ///     recursive: true,
+    ///     exclude: vec!["*.o".to_string()],
+    ///     ..CopySource::default()
+    /// };
+    /// tricky.children.insert(1, Node::new_copy("vendor".to_string(), vendor));
+    /// tricky.children[0].attrs.owner = Some("www-data".to_string());
+    /// tricky.children[0].children[2].attrs.group = Some("1000".to_string());
+    /// let xattrs = &mut tricky.children[2].attrs.xattrs;
+    /// xattrs.insert("user.mime_type".to_string(), "text/plain".to_string());
+    /// xattrs.insert("user.bin".to_string(), "@base64:AAH/".to_string());
+    /// let fixtures = ["tree.yaml", "tree.yml", "tree.json", "tree.toml", "tree.json5"]
+    ///     .map(|name| std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("example").join(name));
+    /// let mut corpus = vec![tricky];
+    /// for path in &fixtures {
+    ///     corpus.push(parse_file(path)?.root);
+    /// }
+    /// for root in corpus {
+    ///     assert_eq!(parse_yaml(&root.to_yaml()?)?.root, root);
+    ///     assert_eq!(parse_json(&root.to_json(false)?)?.root, root);
+    ///     assert_eq!(parse_json(&root.to_json(true)?)?.root, root);
+    ///     assert_eq!(parse_toml(&root.to_toml()?)?.root, root);
+    ///     assert_eq!(parse_json5(&root.to_json5())?.root, root);
+    /// }
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(&self.to_serde()).context("Failed to serialize YAML")
+    }
+
+    /// 写出 JSON；`pretty` 时缩进两格
+    pub fn to_json(&self, pretty: bool) -> Result<String> {
+        let json = if pretty {
+            serde_json::to_string_pretty(&self.to_serde())
+        } else {
+            serde_json::to_string(&self.to_serde())
+        };
+        json.context("Failed to serialize JSON")
+    }
+
+    /// 写出 TOML：目录是表，文件是字符串键值
+    pub fn to_toml(&self) -> Result<String> {
+        // 经 toml::Value 中转：它会先写普通键值再写子表，直接序列化 BTreeMap 时
+        // 排在子表之后的文件会报 ValueAfterTable
+        let value = toml::Value::try_from(self.to_serde()).context("Failed to serialize TOML")?;
+        toml::to_string(&value).context("Failed to serialize TOML")
+    }
+
+    /// 写出路径列表（见 [`parse_paths`]）：每行一个路径，目录以 `/` 结尾；文件内容与元数据不写出。
+    /// 名称含换行的节点无法写成一行，报错
+    ///
+    /// ```
+    /// let spec = treegen::parse_yaml("src:\n  main.rs: 'fn main() {}'\ndocs: {}\n")?;
+    /// assert_eq!(spec.root.to_paths()?, "docs/\nsrc/\nsrc/main.rs\n");
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn to_paths(&self) -> Result<String> {
+        let mut out = String::new();
+        for (path, node) in self.iter() {
+            if path.as_os_str().is_empty() {
+                continue;
+            }
+            if node.name.contains(['\n', '\r']) {
+                bail!(
+                    "'{}' cannot be written to a path list: the name contains a line break",
+                    node.name
+                );
+            }
+            let names: Vec<_> = path
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            out.push_str(&names.join("/"));
+            let is_dir = match node.node_type {
+                NodeType::Dir => true,
+                NodeType::Copy => node.copy.as_ref().is_some_and(|c| c.recursive),
+                _ => false,
+            };
+            if is_dir {
+                out.push('/');
+            }
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// 写出 JSON5：键在合法标识符时不加引号，字符串沿用 JSON 转义，保留末尾逗号。
+    /// 反引号转义为 \u0060，因为 [`parse_json5`] 会把反引号之间的文本当作多行字符串处理
+    pub fn to_json5(&self) -> String {
+        fn string(text: &str) -> String {
+            serde_json::to_string(text).unwrap().replace('`', "\\u0060")
+        }
+        fn key(name: &str) -> String {
+            let mut chars = name.chars();
+            let ident = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+            if ident {
+                name.to_string()
+            } else {
+                string(name)
+            }
+        }
+        fn walk(out: &mut String, snode: &SerdeNode, depth: usize) {
+            match snode {
+                SerdeNode::Str(content) => out.push_str(&string(content)),
+                // 带类型的节点字段很少，写成单行 JSON（JSON 是 JSON5 的子集）
+                SerdeNode::Meta(meta) if meta.children.is_none() => {
+                    out.push_str(&serde_json::to_string(meta).unwrap().replace('`', "\\u0060"))
+                }
+                // 带子节点的目录：标量字段逐行写出，children 照常展开
+                SerdeNode::Meta(meta) => {
+                    let fields = serde_json::to_value(MetaNode {
+                        children: None,
+                        ..(**meta).clone()
+                    })
+                    .unwrap();
+                    let fields = fields.as_object().unwrap();
+                    out.push_str("{\n");
+                    let kind = fields.get("type").into_iter().map(|v| ("type", v));
+                    let rest = fields
+                        .iter()
+                        .filter(|(k, _)| *k != "type")
+                        .map(|(k, v)| (k.as_str(), v));
+                    for (k, v) in kind.chain(rest) {
+                        out.push_str(&"\t".repeat(depth + 1));
+                        out.push_str(k);
+                        out.push_str(": ");
+                        out.push_str(&v.to_string().replace('`', "\\u0060"));
+                        out.push_str(",\n");
+                    }
+                    out.push_str(&"\t".repeat(depth + 1));
+                    out.push_str("children: ");
+                    let children = meta.children.clone().unwrap_or_default();
+                    walk(out, &SerdeNode::Map(children), depth + 1);
+                    out.push_str(",\n");
+                    out.push_str(&"\t".repeat(depth));
+                    out.push('}');
+                }
+                SerdeNode::Map(map) if map.is_empty() => out.push_str("{}"),
+                SerdeNode::Map(map) => {
+                    out.push_str("{\n");
+                    for (k, v) in map {
+                        out.push_str(&"\t".repeat(depth + 1));
+                        out.push_str(&key(k));
+                        out.push_str(": ");
+                        walk(out, v, depth + 1);
+                        out.push_str(",\n");
+                    }
+                    out.push_str(&"\t".repeat(depth));
+                    out.push('}');
+                }
+            }
+        }
+
+        let mut out = String::new();
+        walk(&mut out, &self.to_serde(), 0);
+        out.push('\n');
+        out
+    }
+}
+
+impl Spec {
+    /// 按 `format` 写出描述：文件树与 `$hooks`/`$vars`/`$rules`/`$out` 设置。
+    /// Markdown 把 `out`、`mode` 与变量写进 front matter；格式中无法表示的设置报错而不是丢弃
+    ///
+    /// ```
+    /// use treegen::{parse_yaml, Format};
+    ///
+    /// let spec = parse_yaml("$vars: { name: demo }\nREADME.md: '# {{name}}'\n")?;
+    /// let json = spec.write(Format::Json)?;
+    /// assert_eq!(json, "{\n  \"$vars\": {\n    \"name\": \"demo\"\n  },\n  \"README.md\": \"# {{name}}\"\n}\n");
+    /// assert!(spec.write(Format::Paths).is_err());
+    /// # Ok::<(), anyhow::Error>(())
+    /// ```
+    pub fn write(&self, format: Format) -> Result<String> {
+        let unsupported = |setting: &str| {
+            anyhow!(
+                "{} cannot be written to {}; use another format",
+                setting,
+                format.name()
+            )
+        };
+        let vars: serde_json::Map<String, serde_json::Value> = self
+            .vars
+            .iter()
+            .map(|(name, decl)| (name.clone(), var_to_json(decl)))
+            .collect();
+        match format {
+            Format::Markdown | Format::Paths => {
+                if !self.hooks.is_empty() {
+                    return Err(unsupported("'$hooks'"));
+                }
+                if !self.rules.is_empty() {
+                    return Err(unsupported("'$rules'"));
+                }
+                if format == Format::Paths {
+                    if self.config.out.is_some() || self.config.mode.is_some() {
+                        return Err(unsupported("'$out'/'mode'"));
+                    }
+                    if !vars.is_empty() {
+                        return Err(unsupported("'$vars'"));
+                    }
+                    return self.root.to_paths();
+                }
+                let mut front = serde_json::Map::new();
+                if let Some(out) = &self.config.out {
+                    front.insert("out".into(), out.to_string_lossy().into());
+                }
+                if let Some(mode) = &self.config.mode {
+                    front.insert("mode".into(), mode.clone().into());
+                }
+                if !vars.is_empty() {
+                    front.insert("vars".into(), vars.into());
+                }
+                let tree = self.root.to_markdown()?;
+                if front.is_empty() {
+                    return Ok(tree);
+                }
+                let yaml = serde_yaml::to_string(&front).context("Failed to serialize YAML")?;
+                Ok(format!("---\n{}---\n{}", yaml, tree))
+            }
+            _ => {
+                if self.config.mode.is_some() {
+                    return Err(unsupported("'mode' from Markdown front matter"));
+                }
+                let mut settings = serde_json::Map::new();
+                if !self.hooks.is_empty() {
+                    settings.insert("$hooks".into(), serde_json::json!({ "post": self.hooks }));
+                }
+                if !vars.is_empty() {
+                    settings.insert("$vars".into(), vars.into());
+                }
+                if !self.rules.is_empty() {
+                    let rules = self.rules.iter().map(rule_to_json).collect();
+                    settings.insert("$rules".into(), serde_json::Value::Array(rules));
+                }
+                if let Some(out) = &self.config.out {
+                    settings.insert("$out".into(), out.to_string_lossy().into());
+                }
+                match format {
+                    Format::Json5 => {
+                        // JSON5 的树有自己的写法，设置作为 JSON 值插在最前面
+                        let tree = self.root.to_json5();
+                        if settings.is_empty() {
+                            return Ok(tree);
+                        }
+                        let mut out = String::from("{\n");
+                        for (key, value) in &settings {
+                            out.push_str(&format!(
+                                "\t{}: {},\n",
+                                serde_json::to_string(key)?,
+                                value.to_string().replace('`', "\\u0060")
+                            ));
+                        }
+                        out.push_str(tree.strip_prefix("{\n").unwrap_or("}\n"));
+                        Ok(out)
+                    }
+                    _ => {
+                        let tree = serde_json::to_value(self.root.to_serde())?;
+                        if let serde_json::Value::Object(tree) = tree {
+                            settings.extend(tree);
+                        }
+                        let value = serde_json::Value::Object(settings);
+                        match format {
+                            Format::Yaml => {
+                                serde_yaml::to_string(&value).context("Failed to serialize YAML")
+                            }
+                            Format::Toml => {
+                                let value = toml::Value::try_from(value)
+                                    .context("Failed to serialize TOML")?;
+                                toml::to_string(&value).context("Failed to serialize TOML")
+                            }
+                            _ => Ok(serde_json::to_string_pretty(&value)
+                                .context("Failed to serialize JSON")?
+                                + "\n"),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `$vars` 的一项：只有字符串默认值时只写默认值，否则写完整的声明
+fn var_to_json(decl: &VarDecl) -> serde_json::Value {
+    match decl {
+        VarDecl {
+            kind: VarType::String,
+            default: Some(default),
+            description: None,
+        } => default.clone().into(),
+        VarDecl {
+            kind,
+            default,
+            description,
+        } => {
+            let mut map = serde_json::Map::new();
+            if *kind != VarType::String {
+                map.insert("type".into(), kind.name().into());
+            }
+            if let Some(default) = default {
+                map.insert("default".into(), default.clone().into());
+            }
+            if let Some(description) = description {
+                map.insert("description".into(), description.clone().into());
+            }
+            map.into()
+        }
+    }
+}
+
+/// `$rules` 的一项，与 [`SerdeRule`] 的写法一致
+fn rule_to_json(rule: &Rule) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+    map.insert("match".into(), rule.pattern.clone().into());
+    let text = [
+        ("prepend", rule.prepend.clone()),
+        ("append", rule.append.clone()),
+        ("mode", rule.mode.map(|mode| format!("0o{:o}", mode))),
+        ("owner", rule.owner.clone()),
+        ("group", rule.group.clone()),
+    ];
+    for (key, value) in text {
+        if let Some(value) = value {
+            map.insert(key.into(), value.into());
+        }
+    }
+    if !rule.xattrs.is_empty() {
+        map.insert("xattrs".into(), serde_json::json!(rule.xattrs));
+    }
+    if !rule.tags.is_empty() {
+        map.insert("tags".into(), serde_json::json!(rule.tags));
+    }
+    if let Some(raw) = rule.raw {
+        map.insert("raw".into(), raw.into());
+    }
+    map.into()
+}
+
+// === 花括号展开 ===
+
+/// 花括号展开最多产生的节点数，防止失控的范围（如 `{1..1000000}`）
+pub const MAX_BRACE_EXPANSION: usize = 10_000;
+
+/// 按 shell 的规则展开节点名称中的花括号：`{a,b,c}` 为逗号候选，`{01..16}` 为数字范围
+/// （任一端带前导零时按较长一端补零，可以倒序），可以嵌套。每个展开结果是一个兄弟节点，
+/// 带有原节点内容与子节点的副本。没有逗号也不是范围的花括号（如 `{{name}}`）原样保留。
+/// 展开后的重复名称交给 [`merge_inputs`] 处理；总共多产生的节点超过
+/// [`MAX_BRACE_EXPANSION`] 时报错
+///
+/// ```
+/// let mut spec = treegen::parse_yaml(r#"
+/// "shard{01..03}/": { data.bin: "" }
+/// "mod_{core,io,net}.rs": ""
+/// "{src,test}/{a,b{1..2}}.rs": ""
+/// "{{name}}.md": ""
+/// "#)?;
+/// treegen::expand_braces(&mut spec.root)?;
+/// let paths: Vec<_> = spec.root.iter().map(|(p, _)| p.display().to_string()).collect();
+/// for path in ["shard01/data.bin", "shard03/data.bin", "mod_net.rs", "test/b2.rs", "{{name}}.md"] {
+///     assert!(paths.iter().any(|p| p == path), "{}", path);
+/// }
+/// assert!(!paths.iter().any(|p| p.contains("..")));
+///
+/// let mut spec = treegen::parse_yaml("'f{1..100000}': ''")?;
+/// assert!(treegen::expand_braces(&mut spec.root).is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn expand_braces(root: &mut Node) -> Result<()> {
+    fn walk(dir: &mut Node, parent: &Path, created: &mut usize) -> Result<()> {
+        for mut child in std::mem::take(&mut dir.children) {
+            let path = parent.join(child.key());
+            walk(&mut child, &path, created)?;
+            let mut names = brace_names(&child.name)
+                .with_context(|| format!("Invalid brace expansion in '{}'", path.display()))?;
+            if names.len() == 1 {
+                child.name = names.remove(0);
+                dir.children.push(child);
+                continue;
+            }
+            *created += (names.len() - 1) * child.iter().count();
+            if *created > MAX_BRACE_EXPANSION {
+                bail!(
+                    "Brace expansion of '{}' produces more than {} nodes (pass --no-brace-expansion if the braces are part of the name)",
+                    path.display(),
+                    MAX_BRACE_EXPANSION
+                );
+            }
+            for name in names {
+                let mut copy = child.clone();
+                copy.name = name;
+                dir.children.push(copy);
+            }
+        }
+        Ok(())
+    }
+    walk(root, Path::new(""), &mut 0)
+}
+
+/// 展开一个名称中的所有花括号
+fn brace_names(name: &str) -> Result<Vec<String>> {
+    for (open, _) in name.match_indices('{') {
+        let Some(close) = matching_brace(name, open) else {
+            continue;
+        };
+        let Some(alternatives) = brace_alternatives(&name[open + 1..close])? else {
+            continue;
+        };
+        let suffixes = brace_names(&name[close + 1..])?;
+        let mut names = Vec::new();
+        for alternative in alternatives {
+            for middle in brace_names(&alternative)? {
+                for suffix in &suffixes {
+                    names.push(format!("{}{}{}", &name[..open], middle, suffix));
+                }
+            }
+            if names.len() > MAX_BRACE_EXPANSION {
+                bail!(
+                    "'{}' expands to more than {} names",
+                    name,
+                    MAX_BRACE_EXPANSION
+                );
+            }
+        }
+        return Ok(names);
+    }
+    Ok(vec![name.to_string()])
+}
+
+/// `open` 处的 `{` 对应的 `}`
+fn matching_brace(name: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in name[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 花括号内的候选：顶层逗号分隔的各项，或数字范围；都不是时返回 `None`
+fn brace_alternatives(inner: &str) -> Result<Option<Vec<String>>> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(inner[start..i].to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !parts.is_empty() {
+        parts.push(inner[start..].to_string());
+        return Ok(Some(parts));
+    }
+
+    let Some((from, to)) = inner.split_once("..") else {
+        return Ok(None);
+    };
+    let (Ok(a), Ok(b)) = (from.parse::<i64>(), to.parse::<i64>()) else {
+        return Ok(None);
+    };
+    let count = a.abs_diff(b);
+    if count >= MAX_BRACE_EXPANSION as u64 {
+        bail!(
+            "The range {{{}}} has more than {} values",
+            inner,
+            MAX_BRACE_EXPANSION
+        );
+    }
+    let padded =
+        |s: &str| s.trim_start_matches('-').len() > 1 && s.trim_start_matches('-').starts_with('0');
+    let width = if padded(from) || padded(to) {
+        from.len().max(to.len())
+    } else {
+        0
+    };
+    let values: Vec<i64> = if a <= b {
+        (a..=b).collect()
+    } else {
+        (b..=a).rev().collect()
+    };
+    Ok(Some(
+        values
+            .into_iter()
+            .map(|n| format!("{:0width$}", n, width = width))
+            .collect(),
+    ))
+}
+
+// === 模板变量 ===
+
+/// 内置模板变量，以 `__` 开头：`__year` 为当前年份（UTC）
+pub fn builtin_vars() -> BTreeMap<String, String> {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    BTreeMap::from([("__year".to_string(), civil_year(secs / 86_400).to_string())])
+}
+
+/// 1970-01-01 之后第 `days` 天所在的公历年份
+fn civil_year(days: u64) -> u64 {
+    // 以 0000-03-01 起算的 400 年周期（146097 天），见 Howard Hinnant 的 civil_from_days
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let year = yoe + era * 400;
+    if mp >= 10 {
+        year + 1
+    } else {
+        year
+    }
+}
+
+/// 模板变量占位符 {{name}} / {{name|filter|...}}（前面可以有转义的反斜杠 `\\`），或转义 `\{{`（写出字面的 `{{`）。
+/// 过滤器的名称在替换时才检查，因此 `{{name|}}` 这样的空过滤器同样报未知的过滤器
+fn var_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?P<escape>\\\{\{)|(?P<backslash>\\\\)?\{\{\s*(?P<name>[A-Za-z_][A-Za-z0-9_]*)(?P<filters>(?:\s*\|\s*[A-Za-z0-9_]*)*)\s*\}\}",
+        )
+        .unwrap()
+    })
+}
+
+/// 可以用在占位符中的过滤器：`{{name|snake}}`
+pub const FILTERS: &[&str] = &[
+    "upper", "lower", "snake", "kebab", "pascal", "camel", "slug",
+];
+
+/// 对变量值应用一个过滤器；未知的过滤器返回 `None`
+///
+/// `snake`/`kebab`/`pascal`/`camel` 先把值拆成单词：非字母数字字符分隔单词，
+/// 小写字母或数字后接大写字母、以及连续大写字母中的最后一个（`HTTPServer` → `HTTP`、`Server`）
+/// 开始新单词；已经是目标格式的值保持不变。`slug` 只转小写并把连续的非字母数字字符换成 `-`。
+/// 大小写转换遵循 Unicode。
+///
+/// ```
+/// use treegen::apply_filter;
+///
+/// let f = |filter, value| apply_filter(filter, value).unwrap();
+/// assert_eq!(f("upper", "my-app"), "MY-APP");
+/// assert_eq!(f("upper", "straße"), "STRASSE");
+/// assert_eq!(f("lower", "My App"), "my app");
+/// assert_eq!(f("lower", "ÉCOLE"), "école");
+///
+/// assert_eq!(f("snake", "My App"), "my_app");
+/// assert_eq!(f("snake", "HTTPServer2Go"), "http_server2_go");
+/// assert_eq!(f("snake", "my_app"), "my_app");
+/// assert_eq!(f("snake", "ÜberCafé"), "über_café");
+///
+/// assert_eq!(f("kebab", "myApp"), "my-app");
+/// assert_eq!(f("kebab", "my-app"), "my-app");
+/// assert_eq!(f("kebab", "Ölçü Birimi"), "ölçü-birimi");
+///
+/// assert_eq!(f("pascal", "my_app"), "MyApp");
+/// assert_eq!(f("pascal", "MyApp"), "MyApp");
+/// assert_eq!(f("pascal", "élan vital"), "ÉlanVital");
+///
+/// assert_eq!(f("camel", "My App"), "myApp");
+/// assert_eq!(f("camel", "myApp"), "myApp");
+/// assert_eq!(f("camel", "über-größe"), "überGröße");
+///
+/// assert_eq!(f("slug", "  My App: v2.0! "), "my-app-v2-0");
+/// assert_eq!(f("slug", "my-app"), "my-app");
+/// assert_eq!(f("slug", "Crème Brûlée"), "crème-brûlée");
+/// assert_eq!(f("slug", "日本 語"), "日本-語");
+///
+/// assert_eq!(apply_filter("title", "x"), None);
+/// ```
+pub fn apply_filter(filter: &str, value: &str) -> Option<String> {
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|c| {
+                c.to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let words = || split_words(value);
+    Some(match filter {
+        "upper" => value.to_uppercase(),
+        "lower" => value.to_lowercase(),
+        "snake" => words()
+            .iter()
+            .map(|w| w.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("_"),
+        "kebab" => words()
+            .iter()
+            .map(|w| w.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("-"),
+        "pascal" => words().iter().map(|w| capitalize(w)).collect(),
+        "camel" => words()
+            .iter()
+            .enumerate()
+            .map(|(i, w)| {
+                if i == 0 {
+                    w.to_lowercase()
+                } else {
+                    capitalize(w)
+                }
+            })
+            .collect(),
+        "slug" => value
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-"),
+        _ => return None,
+    })
+}
+
+/// 按分隔符与大小写边界把标识符拆成单词（见 [`apply_filter`]）
+fn split_words(value: &str) -> Vec<String> {
+    let chars: Vec<char> = value.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if let (Some(&prev), true) = (i.checked_sub(1).map(|p| &chars[p]), c.is_uppercase()) {
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            let boundary =
+                prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower);
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// 替换文本中的 {{name}} 占位符，`\{{` 写出字面的 `{{`（其后的内容原样保留），
+/// `\\{{name}}` 写出一个反斜杠后接替换的值；其他位置的反斜杠不变。
+/// `{{name|snake|upper}}` 依次应用过滤器（见 [`apply_filter`]）。
+/// 遇到未定义的变量或未知的过滤器时报错并指出 `path`
+///
+/// ```
+/// use std::{collections::BTreeMap, path::Path};
+///
+/// let vars = BTreeMap::from([("app".to_string(), "web".to_string())]);
+/// let helm = r#"name: {{app}}
+/// image: \{{ .Values.image }}
+/// tag: \{{tag}}
+/// "#;
+/// let out = treegen::render_vars(helm, &vars, Path::new("deployment.yaml"))?;
+/// assert_eq!(out, "name: web\nimage: {{ .Values.image }}\ntag: {{tag}}\n");
+/// let out = treegen::render_vars(r"C:\\{{app}} \{{app}} C:\dir", &vars, Path::new("x"))?;
+/// assert_eq!(out, r"C:\web {{app}} C:\dir");
+///
+/// let vars = BTreeMap::from([("name".to_string(), "My App".to_string())]);
+/// let out = treegen::render_vars("{{ name | kebab }}/{{name|snake|upper}}", &vars, Path::new("x"))?;
+/// assert_eq!(out, "my-app/MY_APP");
+/// assert!(treegen::render_vars("{{name|title}}", &vars, Path::new("x")).is_err());
+/// assert!(treegen::render_vars("{{name|}}", &vars, Path::new("x")).is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn render_vars(text: &str, vars: &BTreeMap<String, String>, path: &Path) -> Result<String> {
+    let mut error = None;
+    let out = var_regex().replace_all(text, |caps: &regex::Captures| {
+        if caps.name("escape").is_some() {
+            return "{{".to_string();
+        }
+        let name = &caps["name"];
+        let Some(value) = vars.get(name) else {
+            error.get_or_insert_with(|| {
+                anyhow!(
+                    "Undefined template variable '{}' in '{}' (pass --var {}=...)",
+                    name,
+                    path.display(),
+                    name
+                )
+            });
+            return String::new();
+        };
+        let backslash = if caps.name("backslash").is_some() {
+            "\\"
+        } else {
+            ""
+        };
+        let mut filters = caps["filters"].split('|').map(str::trim).skip(1);
+        filters
+            .try_fold(value.clone(), |value, filter| {
+                apply_filter(filter, &value).ok_or(filter)
+            })
+            .map(|value| format!("{}{}", backslash, value))
+            .unwrap_or_else(|filter| {
+                error.get_or_insert_with(|| {
+                    anyhow!(
+                        "Unknown filter '{}' in '{}' (available: {})",
+                        filter,
+                        path.display(),
+                        FILTERS.join(", ")
+                    )
+                });
+                String::new()
+            })
+    });
+    match error {
+        Some(err) => Err(err),
+        None => Ok(out.into_owned()),
+    }
+}
+
+/// 在名称与文件内容中替换 {{name}} 占位符；只识别标识符形式的占位符，
+/// 因此 `${{ github.ref }}` 之类的内容保持原样，其余需要字面 `{{` 的地方写成 `\{{`。
+/// 带 `raw: true` 的文件只替换名称，内容原样写出。未定义的变量会报错并指出节点路径
+///
+/// ```
+/// let workflow = r#"
+/// .github:
+///   workflows:
+///     ci.yml: |
+///       name: {{name}} CI
+///       on: push
+///       jobs:
+///         build:
+///           if: ${{ github.ref == 'refs/heads/main' }}
+///           steps:
+///             - run: echo \{{matrix}}
+/// chart.tpl: { type: file, raw: true, content: "{{- define \"{{name}}.labels\" -}}" }
+/// "#;
+/// let mut spec = treegen::parse_yaml(workflow)?;
+/// let vars = [("name".to_string(), "app".to_string())].into();
+/// treegen::substitute_vars(&mut spec.root, &vars)?;
+/// let ci = spec.root.find(".github/workflows/ci.yml").unwrap().content.as_deref().unwrap();
+/// assert!(ci.starts_with("name: app CI\n"));
+/// assert!(ci.contains("if: ${{ github.ref == 'refs/heads/main' }}"));
+/// assert!(ci.contains("run: echo {{matrix}}"));
+/// let chart = spec.root.find("chart.tpl").unwrap();
+/// assert_eq!(chart.content.as_deref(), Some("{{- define \"{{name}}.labels\" -}}"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn substitute_vars(node: &mut Node, vars: &BTreeMap<String, String>) -> Result<()> {
+    fn walk(node: &mut Node, parent: &Path, vars: &BTreeMap<String, String>) -> Result<()> {
+        let path = parent.join(&node.name);
+        node.name = render_vars(&node.name, vars, &path)?;
+        if node.attrs.raw {
+            // 原样写入
+        } else if let Some(structured) = &mut node.attrs.structured {
+            structured.substitute(vars, &path)?;
+            node.content = Some(
+                structured
+                    .render()
+                    .with_context(|| format!("Failed to serialize '{}'", path.display()))?,
+            );
+        } else if let Some(content) = &node.content {
+            node.content = Some(render_vars(content, vars, &path)?);
+        }
+        // 复制的文件在生成时才读取，替换用的变量随来源带过去
+        if let Some(copy) = node.copy.as_mut().filter(|copy| copy.substitute) {
+            copy.vars = vars.clone();
+        }
+        let path = parent.join(&node.name);
+        for child in node.children.iter_mut() {
+            walk(child, &path, vars)?;
+        }
+        Ok(())
+    }
+
+    walk(node, Path::new(""), vars)
+}
+
+/// 把外部读入的内容（`--stdin-content`）作为 `path` 处文件的内容：描述中没有的文件
+/// 连同上级目录一起创建，已有的文件节点改用这份内容，返回它原来的（非空）内容。
+/// 内容不是 UTF-8 时按字节原样写入（见 [`Attrs::bytes`]）
+///
+/// ```
+/// use std::path::Path;
+///
+/// let mut spec = treegen::parse_yaml("src:\n  main.rs: \"fn main() {}\"\n")?;
+/// let old = treegen::attach_content(&mut spec.root, Path::new("src/main.rs"), b"// new".to_vec())?;
+/// assert_eq!(old.as_deref(), Some("fn main() {}"));
+/// treegen::attach_content(&mut spec.root, Path::new("assets/logo.bin"), vec![0, 255])?;
+/// let logo = spec.root.find("assets/logo.bin").unwrap();
+/// assert_eq!(logo.attrs.bytes.as_deref(), Some(&[0, 255][..]));
+/// assert!(treegen::attach_content(&mut spec.root, Path::new("src/main.rs/x"), vec![]).is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn attach_content(root: &mut Node, path: &Path, content: Vec<u8>) -> Result<Option<String>> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(name) => names.push(
+                name.to_str()
+                    .with_context(|| format!("'{}' is not valid UTF-8", path.display()))?,
+            ),
+            _ => bail!(
+                "'{}' must be a relative path inside the tree",
+                path.display()
+            ),
+        }
+    }
+    let Some((file, dirs)) = names.split_last() else {
+        bail!("'{}' does not name a file", path.display());
+    };
+
+    let mut node = root;
+    let mut at = PathBuf::new();
+    for name in dirs {
+        at.push(name);
+        let index = match node.children.iter().position(|c| c.key() == *name) {
+            Some(index) => index,
+            None => {
+                node.children.push(Node::new_dir(name.to_string()));
+                node.children.len() - 1
+            }
+        };
+        node = &mut node.children[index];
+        if node.node_type != NodeType::Dir {
+            bail!("'{}' is not a directory in the spec", at.display());
+        }
+    }
+
+    let (text, bytes) = match String::from_utf8(content) {
+        Ok(text) => (text, None),
+        Err(e) => (
+            String::from_utf8_lossy(e.as_bytes()).into_owned(),
+            Some(e.into_bytes()),
+        ),
+    };
+    match node.children.iter_mut().find(|c| c.key() == *file) {
+        Some(existing) if existing.node_type != NodeType::File => {
+            bail!("'{}' is not a file in the spec", path.display())
+        }
+        Some(existing) => {
+            existing.attrs.structured = None;
+            existing.attrs.bytes = bytes;
+            Ok(existing.content.replace(text).filter(|old| !old.is_empty()))
+        }
+        None => {
+            let mut attached = Node::new_file(file.to_string(), Some(text));
+            attached.attrs.bytes = bytes;
+            node.children.push(attached);
+            Ok(None)
+        }
+    }
+}
+
+/// 去掉文件内容每行末尾的空格与制表符，并把两个以上连续的空行压成两个：`all` 时（`--trim-trailing-whitespace`）
+/// 处理所有文件，否则只处理 `trim: true` 的文件。复制、字节内容、结构化内容、占位文件与内容生成器
+/// 不处理；`\r\n` 换行与结尾是否有换行都保持不变。返回内容有变化的文件数
+///
+/// ```
+/// let mut spec = treegen::parse_yaml(
+///     "a.md: \"# A  \\n\\n\\n\\n\\ntext\\t\\n\"\nb.md: { type: file, trim: true, content: \"b \" }\n",
+/// )?;
+/// assert_eq!(treegen::trim_trailing_whitespace(&mut spec.root, false), 1);
+/// assert_eq!(spec.root.find("b.md").unwrap().content.as_deref(), Some("b"));
+/// assert_eq!(treegen::trim_trailing_whitespace(&mut spec.root, true), 1);
+/// assert_eq!(spec.root.find("a.md").unwrap().content.as_deref(), Some("# A\n\n\ntext\n"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn trim_trailing_whitespace(root: &mut Node, all: bool) -> usize {
+    let mut changed = 0;
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let attrs = &node.attrs;
+        let wanted = node.node_type == NodeType::File
+            && (all || attrs.trim)
+            && attrs.bytes.is_none()
+            && attrs.structured.is_none()
+            && attrs.placeholder.is_none()
+            && content_generator(node).is_none();
+        if let Some(content) = node.content.as_mut().filter(|_| wanted) {
+            let trimmed = trim_text(content);
+            if trimmed != *content {
+                *content = trimmed;
+                changed += 1;
+            }
+        }
+        stack.extend(node.children.iter_mut());
+    }
+    changed
+}
+
+/// 见 [`trim_trailing_whitespace`]
+fn trim_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank = 0;
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let (body, cr) = match line.strip_suffix('\r') {
+            Some(body) => (body, "\r"),
+            None => (line, ""),
+        };
+        let body = body.trim_end_matches([' ', '\t']);
+        // 最后一段是最后一个换行之后的内容，不是一行空行
+        if lines.peek().is_none() {
+            out.push_str(body);
+            out.push_str(cr);
+            break;
+        }
+        blank = if body.is_empty() { blank + 1 } else { 0 };
+        if blank <= 2 {
+            out.push_str(body);
+            out.push_str(cr);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// 按顺序对路径匹配的每个文件节点应用 `rules`：同一字段以后出现的规则为准
+/// （`xattrs` 逐项合并），再一次性修改节点，`prepend`/`append` 加在原有内容前后。
+/// 返回每个被修改的文件及匹配它的规则下标，顺序同 [`Node::iter`]
+///
+/// ```
+/// let mut spec = treegen::parse_yaml(r##"
+/// "$rules":
+///   - { match: "**/*.rs", prepend: "// Copyright {{__year}}\n" }
+///   - { match: "**/*.sh", mode: "0o755" }
+///   - { match: "src/bin/*.rs", prepend: "// SPDX-License-Identifier: MIT\n", append: "// end\n" }
+/// src:
+///   lib.rs: "pub fn f() {}\n"
+///   bin:
+///     main.rs: "fn main() {}\n"
+/// run.sh: "#!/bin/sh\n"
+/// "##)?;
+/// let touched = treegen::apply_rules(&mut spec.root, &spec.rules)?;
+/// let lib = spec.root.find("src/lib.rs").unwrap();
+/// assert_eq!(lib.content.as_deref(), Some("// Copyright {{__year}}\npub fn f() {}\n"));
+/// let main = spec.root.find("src/bin/main.rs").unwrap();
+/// assert_eq!(main.content.as_deref(), Some("// SPDX-License-Identifier: MIT\nfn main() {}\n// end\n"));
+/// assert_eq!(spec.root.find("run.sh").unwrap().attrs.mode, Some(0o755));
+/// assert_eq!(touched.len(), 3);
+/// assert_eq!(touched[0], ("run.sh".into(), vec![1]));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn apply_rules(root: &mut Node, rules: &[Rule]) -> Result<Vec<(PathBuf, Vec<usize>)>> {
+    fn walk(
+        node: &mut Node,
+        path: &Path,
+        rules: &[Rule],
+        matchers: &[GlobMatcher],
+        touched: &mut Vec<(PathBuf, Vec<usize>)>,
+    ) {
+        if node.node_type == NodeType::File {
+            let matched: Vec<usize> = (0..rules.len())
+                .filter(|&i| matchers[i].is_match(path))
+                .collect();
+            if matched.is_empty() {
+                return;
+            }
+            let mut effective = Rule::default();
+            for &i in &matched {
+                let rule = &rules[i];
+                effective.prepend = rule.prepend.clone().or(effective.prepend);
+                effective.append = rule.append.clone().or(effective.append);
+                effective.mode = rule.mode.or(effective.mode);
+                effective.owner = rule.owner.clone().or(effective.owner);
+                effective.group = rule.group.clone().or(effective.group);
+                effective.xattrs.extend(rule.xattrs.clone());
+                if !rule.tags.is_empty() {
+                    effective.tags = rule.tags.clone();
+                }
+                effective.raw = rule.raw.or(effective.raw);
+            }
+            if effective.prepend.is_some() || effective.append.is_some() {
+                // 加上文本后不再是结构化内容，之后按普通文本替换变量
+                node.attrs.structured = None;
+                let content = node.content.get_or_insert_with(String::new);
+                if let Some(prepend) = effective.prepend {
+                    content.insert_str(0, &prepend);
+                }
+                if let Some(append) = effective.append {
+                    content.push_str(&append);
+                }
+            }
+            let attrs = &mut node.attrs;
+            attrs.mode = effective.mode.or(attrs.mode);
+            attrs.owner = effective.owner.or(attrs.owner.take());
+            attrs.group = effective.group.or(attrs.group.take());
+            attrs.xattrs.extend(effective.xattrs);
+            if !effective.tags.is_empty() {
+                attrs.tags = effective.tags;
+            }
+            attrs.raw = effective.raw.unwrap_or(attrs.raw);
+            touched.push((path.to_path_buf(), matched));
+            return;
+        }
+        for child in node.children.iter_mut() {
+            let child_path = path.join(child.name.trim_end_matches('/'));
+            walk(child, &child_path, rules, matchers, touched);
+        }
+    }
+
+    let matchers = rules
+        .iter()
+        .map(|rule| {
+            Glob::new(&rule.pattern)
+                .map(|glob| glob.compile_matcher())
+                .with_context(|| format!("Invalid glob '{}' in rule", rule.pattern))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut touched = Vec::new();
+    if !rules.is_empty() {
+        walk(root, Path::new(""), rules, &matchers, &mut touched);
+    }
+    Ok(touched)
+}
+
+/// 树中出现的所有 profile 及带有该标签的节点数
+///
+/// ```
+/// let spec = treegen::parse_markdown("app/\n├── docs/ #[docs, full]\n└── bench.rs #[full]\n")?;
+/// let profiles: Vec<_> = treegen::profiles(&spec.root).into_iter().collect();
+/// assert_eq!(profiles, [("docs".to_string(), 1), ("full".to_string(), 2)]);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn profiles(root: &Node) -> BTreeMap<String, usize> {
+    let mut profiles = BTreeMap::new();
+    for (_, node) in root.iter() {
+        for tag in &node.attrs.tags {
+            *profiles.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+    profiles
+}
+
+/// 只保留没有标签或带有 `selected` 中任一标签的节点；被剪掉的目录连同其下所有节点一起去掉。
+/// 返回被剪掉的节点路径（不含其后代），顺序同 [`Node::iter`]
+///
+/// ```
+/// let mut spec = treegen::parse_markdown("app/\n├── docs/ #[docs]\n│   └── guide.md\n├── bench.rs #[full]\n└── main.rs\n")?;
+/// let selected = ["full".to_string()].into();
+/// let excluded = treegen::filter_profiles(&mut spec.root, &selected);
+/// assert_eq!(excluded, [std::path::PathBuf::from("app/docs")]);
+/// assert!(spec.root.find("app/bench.rs").is_some());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn filter_profiles(root: &mut Node, selected: &BTreeSet<String>) -> Vec<PathBuf> {
+    fn walk(node: &mut Node, path: &Path, selected: &BTreeSet<String>, out: &mut Vec<PathBuf>) {
+        node.children.retain_mut(|child| {
+            let child_path = path.join(child.name.trim_end_matches('/'));
+            let keep = child.attrs.tags.is_empty() || !child.attrs.tags.is_disjoint(selected);
+            if keep {
+                walk(child, &child_path, selected, out);
+            } else {
+                out.push(child_path);
+            }
+            keep
+        });
+    }
+
+    let mut excluded = Vec::new();
+    let path = PathBuf::from(root.name.trim_end_matches('/'));
+    walk(root, &path, selected, &mut excluded);
+    excluded
+}
+
+/// 子节点的生成顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// 保持描述中的顺序：Markdown 为行序，YAML/JSON/TOML/JSON5 为键的字典序，多个输入按输入顺序拼接
+    #[default]
+    Spec,
+    /// 每个目录的子节点按名称（忽略目录名结尾的 `/`）逐字节排序
+    Name,
+}
+
+/// 按 `order` 重排整棵树的子节点；排序是稳定的，同名节点保持原有先后。
+/// 生成、`--print-tree` 等都按树中的顺序进行，因此排序后同一描述的输出顺序完全确定
+///
+/// ```
+/// use treegen::{sort_tree, SortOrder};
+///
+/// let mut spec = treegen::parse_markdown("b/\n├── z.rs\n└── a.rs\nA.md\n")?;
+/// sort_tree(&mut spec.root, SortOrder::Name);
+/// let paths: Vec<_> = spec.root.iter().skip(1).map(|(path, _)| path).collect();
+/// assert_eq!(paths, ["A.md", "b/", "b/a.rs", "b/z.rs"].map(std::path::PathBuf::from));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn sort_tree(root: &mut Node, order: SortOrder) {
+    if order == SortOrder::Spec {
+        return;
+    }
+    root.children.sort_by(|a, b| a.key().cmp(b.key()));
+    for child in root.children.iter_mut() {
+        sort_tree(child, order);
+    }
+}
+
+/// 名称的 Unicode 规范化形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// 组合形式（`é` 为一个码位），Linux 与 Windows 上的常见写法
+    #[default]
+    Nfc,
+    /// 分解形式（`e` 加组合重音符），macOS 的 HFS+ 保存的形式
+    Nfd,
+    /// 保持原样
+    None,
+}
+
+/// 把整棵树的节点名称与硬链接目标规范化为 `form`，使同一名称的不同写法在合并时被视为重复
+///
+/// ```
+/// use treegen::{normalize_names, Node, Normalization};
+///
+/// let mut root = Node::root().file("cafe\u{301}.txt", "").build()?;
+/// normalize_names(&mut root, Normalization::Nfc);
+/// assert_eq!(root.children[0].name, "caf\u{e9}.txt");
+/// normalize_names(&mut root, Normalization::Nfd);
+/// assert_eq!(root.children[0].name, "cafe\u{301}.txt");
+/// # Ok::<(), treegen::NodeError>(())
+/// ```
+pub fn normalize_names(root: &mut Node, form: Normalization) {
+    if form == Normalization::None {
+        return;
+    }
+    use unicode_normalization::UnicodeNormalization;
+    let nfc = form == Normalization::Nfc;
+    let normalize = |s: &str| -> String {
+        if nfc {
+            s.nfc().collect()
+        } else {
+            s.nfd().collect()
+        }
+    };
+    root.name = normalize(&root.name);
+    if let Some(target) = root.target.as_deref().and_then(Path::to_str) {
+        root.target = Some(PathBuf::from(normalize(target)));
+    }
+    for child in root.children.iter_mut() {
+        normalize_names(child, form);
+    }
+}
+
+/// `--flatten` 的默认分隔符
+pub const DEFAULT_FLATTEN_SEP: &str = "__";
+
+/// 把整棵树压平成一层：每个文件（及复制文件、硬链接）移到根目录下，名称为其路径各段用 `sep`
+/// 连接，如 `src/main.rs` -> `src__main.rs`；指向树内文件的硬链接目标随之改名。
+/// 目录本身不再生成，返回被丢弃的空目录（其下没有任何文件）。
+/// 两个路径压平后同名、或复制的来源是目录时报错
+///
+/// ```
+/// let mut spec = treegen::parse_yaml("src:\n  main.rs: ''\n  util: { a.rs: '' }\ndocs: {}\n")?;
+/// let empty = treegen::flatten(&mut spec.root, "__")?;
+/// let names: Vec<_> = spec.root.children.iter().map(|c| c.name.as_str()).collect();
+/// assert_eq!(names, ["src__main.rs", "src__util__a.rs"]);
+/// assert_eq!(empty, [std::path::PathBuf::from("docs")]);
+///
+/// let mut clash = treegen::parse_yaml("a__b: ''\na: { b: '' }\n")?;
+/// assert!(treegen::flatten(&mut clash.root, "__").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn flatten(root: &mut Node, sep: &str) -> Result<Vec<PathBuf>> {
+    if sep.is_empty() || sep.contains(['/', '\\']) {
+        bail!("Invalid flatten separator '{}'", sep);
+    }
+    let mut files = Vec::new();
+    let mut empty = Vec::new();
+    for (rel, node) in root.iter() {
+        let parts: Vec<String> = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        match node.node_type {
+            NodeType::Dir => {
+                let has_file = node.iter().any(|(_, n)| n.node_type != NodeType::Dir);
+                if !has_file && !parts.is_empty() {
+                    empty.push(parts.iter().collect());
+                }
+            }
+            NodeType::Copy if node.copy.as_ref().is_some_and(CopySource::is_dir) => {
+                bail!(
+                    "Cannot flatten '{}': it copies a directory",
+                    parts.join("/")
+                );
+            }
+            _ => files.push((parts, node.clone())),
+        }
+    }
+
+    // 先检查冲突，再改名
+    let mut names: BTreeMap<String, String> = BTreeMap::new();
+    for (parts, _) in &files {
+        let original = parts.join("/");
+        if let Some(other) = names.insert(parts.join(sep), original.clone()) {
+            bail!(
+                "'{}' and '{}' both flatten to '{}'",
+                other,
+                original,
+                parts.join(sep)
+            );
+        }
+    }
+    let renamed: BTreeMap<String, String> = names
+        .iter()
+        .map(|(flat, original)| (original.clone(), flat.clone()))
+        .collect();
+    root.children = files
+        .into_iter()
+        .map(|(parts, mut node)| {
+            node.name = parts.join(sep);
+            if let Some(target) = &node.target {
+                let target: Vec<_> = target
+                    .components()
+                    .filter(|c| matches!(c, Component::Normal(_)))
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect();
+                if let Some(flat) = renamed.get(&target.join("/")) {
+                    node.target = Some(PathBuf::from(flat));
+                }
+            }
+            node
+        })
+        .collect();
+    Ok(empty)
+}
+
+/// 树中（名称与文件内容里）引用到的所有模板变量名
+///
+/// ```
+/// let spec = treegen::parse_yaml("'{{name}}':\n  README.md: '# {{ title }}'\n")?;
+/// let vars: Vec<String> = treegen::referenced_vars(&spec.root).into_iter().collect();
+/// assert_eq!(vars, ["name", "title"]);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn referenced_vars(root: &Node) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for (_, node) in root.iter() {
+        let content = node.content.as_ref().filter(|_| !node.attrs.raw);
+        for text in std::iter::once(&node.name).chain(content) {
+            names.extend(vars_in(text));
+        }
+    }
+    names
+}
+
+/// 标记内容引用了 `names` 中变量的文件（[`Attrs::redacted`]），须在 [`substitute_vars`]
+/// 之前调用；做变量替换的复制节点读到的内容无从得知，一律标记。返回标记的节点数
+///
+/// ```
+/// let mut spec = treegen::parse_yaml(".env: 'TOKEN={{token}}'\nREADME.md: '# {{name}}'\n")?;
+/// assert_eq!(treegen::mark_redacted(&mut spec.root, &["token".to_string()]), 1);
+/// assert!(spec.root.find(".env").unwrap().attrs.redacted);
+/// assert!(!spec.root.find("README.md").unwrap().attrs.redacted);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn mark_redacted(root: &mut Node, names: &[String]) -> usize {
+    fn walk(node: &mut Node, names: &[String]) -> usize {
+        let content = node.content.as_ref().filter(|_| !node.attrs.raw);
+        let substitutes = node.copy.as_ref().is_some_and(|copy| copy.substitute);
+        if substitutes || content.is_some_and(|text| vars_in(text).any(|var| names.contains(&var)))
+        {
+            node.attrs.redacted = true;
+        }
+        let marked = usize::from(node.attrs.redacted);
+        marked
+            + node
+                .children
+                .iter_mut()
+                .map(|child| walk(child, names))
+                .sum::<usize>()
+    }
+
+    if names.is_empty() {
+        return 0;
+    }
+    walk(root, names)
+}
+
+/// 一段文本（如 `$out`）中引用的模板变量
+///
+/// ```
+/// let vars: Vec<String> = treegen::vars_in("services/{{name}}-{{ env | upper }}").collect();
+/// assert_eq!(vars, ["name", "env"]);
+/// ```
+pub fn vars_in(text: &str) -> impl Iterator<Item = String> + '_ {
+    var_regex()
+        .captures_iter(text)
+        .filter_map(|caps| caps.name("name").map(|name| name.as_str().to_string()))
+}
+
+// === 在磁盘上生成 ===
+
+/// 默认允许的最大嵌套深度（顶层节点深度为 1）
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// 检查树的嵌套深度，超出 `max_depth` 时报错并指出第一个超出的路径。
+/// 检查本身不递归，因此在递归处理（变量替换、生成）之前调用即可避免栈溢出
+///
+/// ```
+/// let mut deep = treegen::Node::new_dir("".to_string());
+/// let mut cursor = &mut deep;
+/// for _ in 0..10_000 {
+///     cursor.children.push(treegen::Node::new_dir("d".to_string()));
+///     cursor = &mut cursor.children[0];
+/// }
+/// let err = treegen::check_depth(&deep, 256).unwrap_err();
+/// assert!(err.to_string().contains("nested deeper than 256"));
+/// ```
+pub fn check_depth(root: &Node, max_depth: usize) -> Result<()> {
+    let mut too_deep = None;
+    root.walk(&mut |path, _| {
+        if path.components().count() > max_depth {
+            too_deep = Some(path.to_path_buf());
+            WalkControl::Stop
+        } else {
+            WalkControl::Continue
+        }
+    });
+    match too_deep {
+        Some(path) => bail!(
+            "Tree is nested deeper than {} levels at '{}' (raise --max-tree-depth if this is intended)",
+            max_depth,
+            path.display()
+        ),
+        None => Ok(()),
+    }
+}
+
+/// 估计的写入量（文件内容、占位文件声明的大小与复制来源）超过 `max_bytes` 时报错，
+/// 不写入任何东西（见 [`GenerateOptions::max_bytes`]）
+///
+/// ```
+/// let spec = treegen::parse_yaml("big.bin: { type: file, size: 2MiB }\n")?;
+/// assert!(treegen::check_max_bytes(&spec.root, Some(4 << 20)).is_ok());
+/// let err = treegen::check_max_bytes(&spec.root, Some(1 << 20)).unwrap_err();
+/// assert!(err.to_string().contains("more than --max-bytes 1.0 MiB"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn check_max_bytes(root: &Node, max_bytes: Option<u64>) -> Result<()> {
+    if max_bytes.is_none() {
+        return Ok(());
+    }
+    match preflight::max_bytes_problem(preflight::estimated_bytes(root), max_bytes) {
+        Some(problem) => bail!(problem),
+        None => Ok(()),
+    }
+}
+
+/// 描述中有占位文件（声明了 `size`）时，估计的写入量超过 `out` 所在文件系统的可用空间就拒绝生成，
+/// 不会写到磁盘满才失败；预检（[`preflight::preflight`]）总会做这项检查
+///
+/// ```
+/// let spec = treegen::parse_yaml("big.bin: { type: file, size: 99999TiB }\n")?;
+/// let err = treegen::check_disk_space(&spec.root, &std::env::temp_dir()).unwrap_err();
+/// assert!(err.to_string().starts_with("Not enough disk space on "));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn check_disk_space(root: &Node, out: &Path) -> Result<()> {
+    if !root
+        .iter()
+        .any(|(_, node)| node.attrs.placeholder.is_some())
+    {
+        return Ok(());
+    }
+    let Some(dir) = preflight::existing_ancestor(out) else {
+        return Ok(());
+    };
+    match preflight::space_problem(&dir, preflight::disk_bytes(root)) {
+        Some(problem) => bail!(problem),
+        None => Ok(()),
+    }
+}
+
+/// 名称中会让路径离开输出目录的写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PathIssue {
+    /// `\\server\share` 或 `//server/share`
+    Unc,
+    /// `C:`、`C:\x`、`C:/x`
+    Drive,
+    /// 以 `/` 或 `\` 开头
+    Absolute,
+    /// 含 `..` 段
+    ParentDir,
+}
+
+impl fmt::Display for PathIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PathIssue::Unc => "is a UNC path",
+            PathIssue::Drive => "starts with a drive letter",
+            PathIssue::Absolute => "is an absolute path",
+            PathIssue::ParentDir => "contains '..'",
+        })
+    }
+}
+
+/// 按 Windows 与 Unix 两种规则检查名称，与当前平台无关
+fn name_path_issue(name: &str) -> Option<PathIssue> {
+    let bytes = name.as_bytes();
+    if name.starts_with("\\\\") || name.starts_with("//") {
+        Some(PathIssue::Unc)
+    } else if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        Some(PathIssue::Drive)
+    } else if name.starts_with(['/', '\\']) {
+        Some(PathIssue::Absolute)
+    } else if name.split(['/', '\\']).any(|segment| segment == "..") {
+        Some(PathIssue::ParentDir)
+    } else {
+        None
+    }
+}
+
+/// 检查节点名称都是相对路径：盘符、UNC 前缀、绝对路径与 `..` 段会让生成的路径
+/// 离开输出目录（在 Windows 上 `Path::join` 会直接换掉输出目录），因此报错并指出路径
+///
+/// ```
+/// use treegen::Node;
+///
+/// let root = Node::root().child(Node::dir("C:\\Users").file_empty("a")).build()?;
+/// let err = treegen::check_names(&root).unwrap_err();
+/// assert!(err.to_string().contains("starts with a drive letter"));
+/// # Ok::<(), treegen::NodeError>(())
+/// ```
+pub fn check_names(root: &Node) -> Result<()> {
+    let mut bad = None;
+    root.walk(&mut |path, node| match name_path_issue(&node.name) {
+        Some(reason) if !path.as_os_str().is_empty() => {
+            // 先序遍历，上级的名称都已检查过；出错节点自己的路径在 Windows 上已被换掉
+            let parent = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            bad = Some((parent, node.name.clone(), reason));
+            WalkControl::Stop
+        }
+        _ => WalkControl::Continue,
+    });
+    let Some((parent, name, reason)) = bad else {
+        return Ok(());
+    };
+    let location = if parent.as_os_str().is_empty() {
+        String::new()
+    } else {
+        format!(" in '{}'", parent.display())
+    };
+    bail!(
+        "Node '{}'{} {}; spec paths must be relative to the output directory",
+        name,
+        location,
+        reason
+    )
+}
+
+/// 无法查询目标文件系统时，名称每段允许的最大字节数（大多数文件系统的上限）
+pub const DEFAULT_MAX_NAME_BYTES: usize = 255;
+
+/// 完整路径允许的最大字节数（Linux 的 PATH_MAX 与 macOS 的 MAXPATHLEN 都含结尾的 NUL）
+#[cfg(target_os = "macos")]
+pub const MAX_PATH_BYTES: usize = 1023;
+#[cfg(all(unix, not(target_os = "macos")))]
+pub const MAX_PATH_BYTES: usize = 4095;
+/// 完整路径允许的最大长度（Windows 的扩展长度路径）
+#[cfg(not(unix))]
+pub const MAX_PATH_BYTES: usize = 32_767;
+
+/// 在写入之前检查每段名称的字节数（UTF-8）与加上输出目录（规范化后）的完整路径长度，
+/// 列出所有超长的节点；`max_name_bytes` 为 `None` 时采用输出目录所在文件系统的上限
+///
+/// ```
+/// let root = treegen::Node::root().file(&"a".repeat(300), "").build()?;
+/// let out = std::env::temp_dir();
+/// let err = treegen::check_lengths(&root, &out, Some(255)).unwrap_err();
+/// assert!(err.to_string().contains("name is 300 bytes, the limit is 255"));
+/// assert!(treegen::check_lengths(&root, &out, Some(300)).is_ok());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn check_lengths(root: &Node, out: &Path, max_name_bytes: Option<usize>) -> Result<()> {
+    let existing = preflight::existing_ancestor(out);
+    let limit = max_name_bytes
+        .or_else(|| existing.as_deref().and_then(name_max))
+        .unwrap_or(DEFAULT_MAX_NAME_BYTES);
+    // 不存在的部分接在最近的已有上级（规范化后）之后
+    let prefix = match existing.as_deref().map(|dir| (dir, dir.canonicalize())) {
+        Some((dir, Ok(canonical))) => match out.strip_prefix(dir) {
+            Ok(rest) if !rest.as_os_str().is_empty() => canonical.join(rest),
+            _ => canonical,
+        },
+        _ => out.to_path_buf(),
+    };
+    let mut problems = Vec::new();
+    // 路径已经超长的目录，其下的路径不再重复列出
+    let mut too_long: Vec<PathBuf> = Vec::new();
+    for (rel, node) in root.iter() {
+        if rel.as_os_str().is_empty() || too_long.iter().any(|dir| rel.starts_with(dir)) {
+            continue;
+        }
+        let longest = Path::new(&node.name)
+            .components()
+            .map(|c| c.as_os_str().len())
+            .max()
+            .unwrap_or(0);
+        if longest > limit {
+            problems.push(format!(
+                "'{}': name is {} bytes, the limit is {}",
+                rel.display(),
+                longest,
+                limit
+            ));
+        }
+        let full = prefix.join(&rel).as_os_str().len();
+        if full > MAX_PATH_BYTES {
+            problems.push(format!(
+                "'{}': full path is {} bytes under '{}', the limit is {}",
+                rel.display(),
+                full,
+                prefix.display(),
+                MAX_PATH_BYTES
+            ));
+            too_long.push(rel);
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    bail!(
+        "{} path(s) too long (use --max-name-bytes to override the name limit):\n{}",
+        problems.len(),
+        problems
+            .iter()
+            .map(|problem| format!("  - {}", problem))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+/// `dir` 所在文件系统的名称长度上限，查询失败时为 `None`
+#[cfg(unix)]
+fn name_max(dir: &Path) -> Option<usize> {
+    let stat = nix::sys::statvfs::statvfs(dir).ok()?;
+    #[allow(clippy::useless_conversion)]
+    let max = usize::try_from(stat.name_max()).ok()?;
+    (max > 0).then_some(max)
+}
+
+#[cfg(not(unix))]
+fn name_max(_dir: &Path) -> Option<usize> {
+    None
+}
+
+/// 生成选项
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// 仅预览，不写入磁盘
+    pub dry_run: bool,
+    /// 打印每个文件/目录的创建情况
+    pub verbose: bool,
+    /// 新建文件的权限（仅类 Unix 平台生效）
+    pub mode: u32,
+    /// 以这些扩展名（不含 `.`，如 `sh`、`tar.gz`，不区分大小写）结尾的文件在 `mode` 上加可执行位；
+    /// 节点自己设置了 mode 时不起作用
+    pub exec_ext: Vec<String>,
+    /// 内容以 `#!` 开头的文件在 `mode` 上加可执行位；节点自己设置了 mode 时不起作用
+    pub auto_exec: bool,
+    /// 跳过没有内容（`content` 为 `None`，如 Markdown 树中的文件）的文件节点，只创建目录与有内容的文件；
+    /// 显式的空内容 `""` 仍会创建
+    pub no_touch: bool,
+    /// 已存在的文件原样保留：不重写内容，也不改权限
+    pub skip_existing: bool,
+    /// 内容未变化（或因 `skip_existing` 保留）的已有文件也按 `mode` 重设权限；
+    /// 默认只有新建或重写的文件才设置权限，不会覆盖用户改过的权限
+    pub fix_modes: bool,
+    /// 允许的最大嵌套深度，超出时在写入任何内容之前报错
+    pub max_depth: usize,
+    /// 名称每段允许的最大字节数，`None` 时采用输出目录所在文件系统的上限（见 [`check_lengths`]）
+    pub max_name_bytes: Option<usize>,
+    /// 可以降级处理的失败（如硬链接失败时改为复制）直接报错
+    pub strict: bool,
+    /// 所有生成路径的默认属主/属组（仅类 Unix 平台生效），节点自己的 owner/group 优先
+    pub chown: Option<Ownership>,
+    /// 每个写入的文件都 `sync_all`，生成结束后再 fsync 涉及的目录（Windows 上不同步目录），
+    /// 保证返回时内容已落盘
+    pub durable: bool,
+    /// Windows 上为名称以 `.` 开头的文件和目录（不含硬链接）设置 FILE_ATTRIBUTE_HIDDEN，
+    /// 与类 Unix 平台的隐藏文件约定一致；其他平台上不起作用
+    pub windows_hidden_dotfiles: bool,
+    /// 某个路径生成失败时记入 [`Stats::failures`] 并继续生成其余路径（失败目录下的路径一并跳过）；
+    /// 默认在第一个失败处返回 [`Aborted`]
+    pub keep_going: bool,
+    /// 只更新已存在的路径：不存在的文件、目录与链接都不创建，记入 [`Stats::missing`]
+    pub content_only: bool,
+    /// 复制目录时不默认忽略版本控制目录（见 [`DEFAULT_IGNORES`]）；`.treegenignore` 仍然生效
+    pub no_default_ignores: bool,
+    /// 每个文件写入后重新读取，确认内容与要写入的一致，不一致时报错并指出第一个不同的字节；
+    /// 读取时算出的 SHA-256 记入 [`Stats::verified`]
+    pub verify_writes: bool,
+    /// 随机填充的占位文件（[`Fill::Random`]）与内容生成器（[`ContentGenerator`]）的种子，
+    /// 相同的种子生成相同的内容；`None` 时 [`generate`] 每次随机选取
+    pub seed: Option<u64>,
+    /// `@timestamp` 写入的时间；`None` 时为开始生成的时间
+    pub timestamp: Option<std::time::SystemTime>,
+    /// 估计的写入量（文件内容、占位文件声明的大小与复制来源）超过它时拒绝生成
+    pub max_bytes: Option<u64>,
+    /// 设置后 [`execute`] 在下一个动作之前停止（正在写入的文件先写完），返回 [`Interrupted`]
+    pub cancel: CancelToken,
+}
+
+/// 取消生成的标志，可以在其他线程（如 Ctrl-C 的处理函数）中设置；克隆的副本共享同一个标志
+///
+/// ```
+/// use treegen::{generate, GenerateOptions, Interrupted, Node};
+///
+/// let root = Node::root().file("a.txt", "x").build()?;
+/// let opts = GenerateOptions { dry_run: true, ..Default::default() };
+/// opts.cancel.cancel();
+/// let err = generate(&root, std::path::Path::new("unused"), &opts).unwrap_err();
+/// let interrupted = err.downcast_ref::<Interrupted>().unwrap();
+/// assert_eq!((interrupted.stats.files, interrupted.remaining), (0, 1));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求取消
+    pub fn cancel(&self) {
+        self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::SeqCst)
+    }
+}
+
+/// 生成被 [`CancelToken`] 中断：已完成的路径保留在磁盘上（见 [`remove_created`]）
+#[derive(Debug)]
+pub struct Interrupted {
+    /// 到中断为止的结果
+    pub stats: Stats,
+    /// 计划中还没有生成的路径数（不含输出目录本身）
+    pub remaining: usize,
+}
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Generation was interrupted with {} planned path(s) not generated",
+            self.remaining
+        )
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// 没有 [`GenerateOptions::keep_going`] 时生成在第一个失败的路径处停下：显示与只含这一个失败的
+/// [`failure::FailureReport`] 相同，同时带上到那时为止的结果
+///
+/// ```
+/// use treegen::{generate, Aborted, GenerateOptions, Node};
+///
+/// let out = tempfile::tempdir()?;
+/// std::fs::write(out.path().join("b"), "a file where a directory is planned")?;
+/// let root = Node::root()
+///     .file("a.txt", "x")
+///     .child(Node::dir("b").file("c.txt", "y"))
+///     .build()?;
+/// let err = generate(&root, out.path(), &GenerateOptions::default()).unwrap_err();
+/// let aborted = err.downcast_ref::<Aborted>().unwrap();
+/// assert_eq!(aborted.stats.files, 1);
+/// assert_eq!(aborted.stats.failures[0].path, std::path::Path::new("b"));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Aborted {
+    /// 到失败为止的结果，失败的路径在 [`Stats::failures`] 中
+    pub stats: Stats,
+}
+
+impl fmt::Display for Aborted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        failure::FailureReport(self.stats.failures.clone()).fmt(f)
+    }
+}
+
+impl std::error::Error for Aborted {}
+
+/// 删除 `stats` 中本次新建（[`Outcome::Created`]）的路径，用于撤销中断的生成；新建的目录连同其内容
+/// 一起删除，重写过的已有文件无法恢复，保持原样。返回删除的路径（相对 `out`）
+///
+/// ```
+/// let out = tempfile::tempdir()?;
+/// std::fs::write(out.path().join("kept.txt"), "mine")?;
+/// let spec = treegen::parse_yaml("kept.txt: mine\nsrc: { main.rs: '' }\nnew.txt: x\n")?;
+/// let stats = treegen::generate(&spec.root, out.path(), &Default::default())?;
+/// let removed = treegen::remove_created(out.path(), &stats)?;
+/// assert_eq!(removed, ["new.txt", "src"].map(std::path::PathBuf::from));
+/// assert!(out.path().join("kept.txt").exists() && !out.path().join("src").exists());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn remove_created(out: &Path, stats: &Stats) -> Result<Vec<PathBuf>> {
+    let mut removed: Vec<PathBuf> = Vec::new();
+    // 按路径排序，上级目录在前；已删除目录之下的路径跳过
+    for (rel, outcome) in &stats.outcomes {
+        if *outcome != Outcome::Created || removed.iter().any(|dir| rel.starts_with(dir)) {
+            continue;
+        }
+        let path = out.join(rel);
+        let result = match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.is_dir() => fs::remove_dir_all(&path),
+            Ok(_) => fs::remove_file(&path),
+            Err(_) => continue,
+        };
+        result.with_context(|| format!("Failed to remove '{}'", path.display()))?;
+        removed.push(rel.clone());
+    }
+    Ok(removed)
+}
+
+/// 属主与属组，`--chown` 的值；两者都可以是名称或数字 id
+///
+/// ```
+/// use treegen::Ownership;
+///
+/// let own = Ownership::parse("www-data:1000").unwrap();
+/// assert_eq!(own.user.as_deref(), Some("www-data"));
+/// assert_eq!(own.group.as_deref(), Some("1000"));
+/// assert_eq!(Ownership::parse(":staff").unwrap().to_string(), ":staff");
+/// assert!(Ownership::parse(":").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ownership {
+    pub user: Option<String>,
+    pub group: Option<String>,
+}
+
+impl Ownership {
+    /// 解析 `USER`、`:GROUP` 或 `USER:GROUP`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (user, group) = s.split_once(':').unwrap_or((s, ""));
+        let field = |v: &str| (!v.is_empty()).then(|| v.to_string());
+        let own = Ownership {
+            user: field(user),
+            group: field(group),
+        };
+        if own.is_empty() {
+            return Err(format!(
+                "invalid owner '{}': expected USER, :GROUP or USER:GROUP",
+                s
+            ));
+        }
+        Ok(own)
+    }
+
+    /// 既没有属主也没有属组
+    pub fn is_empty(&self) -> bool {
+        self.user.is_none() && self.group.is_none()
+    }
+}
+
+impl fmt::Display for Ownership {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.user.as_deref().unwrap_or_default())?;
+        if let Some(group) = &self.group {
+            write!(f, ":{}", group)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            dry_run: false,
+            verbose: false,
+            mode: 0o644,
+            exec_ext: Vec::new(),
+            auto_exec: false,
+            no_touch: false,
+            skip_existing: false,
+            fix_modes: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_name_bytes: None,
+            strict: false,
+            chown: None,
+            durable: false,
+            windows_hidden_dotfiles: false,
+            keep_going: false,
+            content_only: false,
+            no_default_ignores: false,
+            verify_writes: false,
+            seed: None,
+            timestamp: None,
+            max_bytes: None,
+            cancel: CancelToken::default(),
+        }
+    }
+}
+
+/// 生成结果统计（dry-run 时为将要创建的数量）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// 创建（或确认存在）的目录数，不含输出目录本身
+    pub dirs: usize,
+    /// 写入的文件数
+    pub files: usize,
+    /// 内容未变化、因此没有重写的文件数
+    pub unchanged: usize,
+    /// 创建的硬链接数（改为复制的计入 `files`）
+    pub links: usize,
+    /// 因 [`GenerateOptions::no_touch`] 跳过的没有内容的文件数
+    pub skipped: usize,
+    /// 因 [`GenerateOptions::content_only`] 没有创建的不存在的路径数
+    pub missing: usize,
+    /// 每个节点的结果，键为 [`Node::iter`] 给出的相对路径（不含输出目录本身）
+    pub outcomes: BTreeMap<PathBuf, Outcome>,
+    /// 没能生成的路径，按生成顺序；没有 [`GenerateOptions::keep_going`] 时只有停下处的一个（见 [`Aborted`]）
+    pub failures: Vec<failure::Failure>,
+    /// 没能生成的可选节点（见 [`Attrs::optional`]），只作为警告，按生成顺序
+    pub warnings: Vec<failure::Failure>,
+    /// [`GenerateOptions::verify_writes`] 时回读校验过的文件（写入的路径）及其 SHA-256，
+    /// 写校验和清单时不必再读一遍（见 [`checksum::manifest_with`]）
+    pub verified: BTreeMap<PathBuf, String>,
+    /// 各类动作的耗时
+    pub timings: ExecuteTimings,
+}
+
+/// 一个阶段的耗时与处理量，可以据此算出吞吐量（节点/秒、MB/秒）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timing {
+    pub elapsed: Duration,
+    /// 处理的节点数
+    pub nodes: usize,
+    /// 读取或写入的字节数
+    pub bytes: u64,
+}
+
+impl Timing {
+    fn add(&mut self, elapsed: Duration, nodes: usize) {
+        self.elapsed += elapsed;
+        self.nodes += nodes;
+    }
+}
+
+/// [`execute`] 中按动作类型分开的耗时，总是记录（每个动作只多读两次时钟）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecuteTimings {
+    /// 创建目录（含输出目录本身）
+    pub dirs: Timing,
+    /// 写入、复制文件与创建硬链接，不含设置权限；`bytes` 为写入的字节数
+    pub files: Timing,
+    /// 设置文件权限
+    pub modes: Timing,
+    /// `durable` 时最后同步目录
+    pub sync: Timing,
+}
+
+/// 单个节点的生成结果（dry-run 时为预计结果）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Outcome {
+    /// 原先不存在，新建
+    Created,
+    /// 原先已存在，内容被重写（目录：其中有文件被写入）
+    Overwritten,
+    /// 原先已存在且无需改动
+    Unchanged,
+    /// 没有生成（如 `--no-touch` 下没有内容的文件）
+    Skipped,
+    /// 不存在，也没有创建（`--content-only`）
+    Missing,
+}
+
+impl Outcome {
+    /// 显示用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Created => "created",
+            Outcome::Overwritten => "overwritten",
+            Outcome::Unchanged => "unchanged",
+            Outcome::Skipped => "skipped",
+            Outcome::Missing => "missing",
+        }
+    }
+}
+
+thread_local! {
+    /// generate 期间缓冲的 `--verbose` 输出；逐行 println! 在几十万个节点上每行都是一次写入
+    static VERBOSE_LOG: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// 缓冲到这么多字节就写出一次
+const VERBOSE_FLUSH_BYTES: usize = 64 * 1024;
+
+/// 写一行 `--verbose` 输出：generate 期间进缓冲，其他时候直接打印
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        log_line(format_args!($($arg)*))
+    };
+}
+
+fn log_line(args: fmt::Arguments) {
+    use fmt::Write as _;
+    VERBOSE_LOG.with(|log| match log.borrow_mut().as_mut() {
+        Some(buf) => {
+            let _ = writeln!(buf, "{}", args);
+            if buf.len() >= VERBOSE_FLUSH_BYTES {
+                print!("{}", buf);
+                buf.clear();
+            }
+        }
+        None => println!("{}", args),
+    });
+}
+
+/// 写出缓冲的输出；往 stderr 写警告之前调用，保持两者的先后顺序
+fn flush_verbose() {
+    VERBOSE_LOG.with(|log| {
+        if let Some(buf) = log.borrow_mut().as_mut() {
+            print!("{}", buf);
+            buf.clear();
+        }
+    });
+}
+
+/// 在作用域内缓冲 `--verbose` 输出，离开时（包括出错返回）写出
+struct VerboseBatch {
+    owner: bool,
+}
+
+impl VerboseBatch {
+    fn start() -> Self {
+        VERBOSE_LOG.with(|log| {
+            let mut log = log.borrow_mut();
+            let owner = log.is_none();
+            if owner {
+                *log = Some(String::with_capacity(VERBOSE_FLUSH_BYTES));
+            }
+            VerboseBatch { owner }
+        })
+    }
+}
+
+impl Drop for VerboseBatch {
+    fn drop(&mut self) {
+        if self.owner {
+            if let Some(buf) = VERBOSE_LOG.with(|log| log.borrow_mut().take()) {
+                print!("{}", buf);
+            }
+        }
+    }
+}
+
+/// 在 `out` 下生成 `root` 描述的文件树；名称为空的根节点对应 `out` 本身。
+/// 相当于先 [`plan`] 再 [`execute`]
+///
+/// ```
+/// use treegen::{generate, GenerateOptions, Node};
+///
+/// let mut root = Node::new_dir("".to_string());
+/// root.children.push(Node::new_file("notes.txt".to_string(), Some("hi".to_string())));
+/// let opts = GenerateOptions { dry_run: true, ..Default::default() };
+/// let stats = generate(&root, std::path::Path::new("unused"), &opts)?;
+/// assert_eq!(stats.files, 1);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn generate(root: &Node, out: &Path, opts: &GenerateOptions) -> Result<Stats> {
+    execute(&plan(root, out, opts)?, opts)
+}
+
+/// [`plan`] 的结果：按执行顺序排列的动作与预计的数量。可以序列化为 JSON（`--dry-run --json`），
+/// 动作借用原来的树，由 [`execute`] 执行
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan<'a> {
+    /// 输出目录
+    pub out: PathBuf,
+    /// 随机内容（占位文件与内容生成器）在整次生成中使用的种子
+    pub seed: u64,
+    /// `@timestamp` 写入的时间
+    #[serde(skip)]
+    pub timestamp: std::time::SystemTime,
+    /// 先序排列的节点，硬链接在最后（按依赖排好顺序）
+    pub actions: Vec<PlannedAction<'a>>,
+    pub stats: PlanStats,
+    /// 用于 `durable` 时同步目录
+    #[serde(skip)]
+    root: &'a Node,
+}
+
+/// 计划中的一个动作，对应树中的一个节点
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedAction<'a> {
+    /// 相对输出目录的路径；未命名的根节点为空路径，即输出目录本身
+    pub path: PathBuf,
+    pub kind: ActionKind,
+    /// 文件内容的来源
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_ref: Option<ContentRef>,
+    /// 新建或重写的文件设置的权限（仅类 Unix 平台生效）
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_mode"
+    )]
+    pub mode: Option<u32>,
+    /// 文件已存在时的处理
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<IfExists>,
+    /// 位于可选节点（[`Attrs::optional`]）之内，失败时只警告
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// 动作所属的节点
+    #[serde(skip)]
+    pub node: &'a Node,
+}
+
+/// 动作的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionKind {
+    Dir,
+    File,
+    Copy,
+    Hardlink,
+    /// 不生成（[`GenerateOptions::no_touch`] 下没有内容的文件）
+    Skip,
+}
+
+impl ActionKind {
+    /// 显示用的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            ActionKind::Dir => "dir",
+            ActionKind::File => "file",
+            ActionKind::Copy => "copy",
+            ActionKind::Hardlink => "hardlink",
+            ActionKind::Skip => "skip",
+        }
+    }
+}
+
+/// 文件内容从哪里来
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ContentRef {
+    /// 描述中的内容（文本或解码后的字节）
+    Inline { bytes: usize },
+    /// 占位文件（[`Placeholder`]）
+    Placeholder { size: u64, fill: &'static str },
+    /// 写入时才算出的内容（[`ContentGenerator`]）
+    Generator { name: String },
+    /// 从磁盘上复制
+    Copy { from: PathBuf },
+    /// 硬链接到输出目录中的另一个文件
+    Link { target: PathBuf },
+}
+
+/// 计划中各类动作的数量
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PlanStats {
+    /// 目录数，不含输出目录本身
+    pub dirs: usize,
+    pub files: usize,
+    pub copies: usize,
+    pub links: usize,
+    /// [`ActionKind::Skip`] 的文件数
+    pub skipped: usize,
+    /// 文件内容与占位文件的字节数（不含复制来源）
+    pub bytes: u64,
+}
+
+fn serialize_mode<S: serde::Serializer>(mode: &Option<u32>, s: S) -> Result<S::Ok, S::Error> {
+    match mode {
+        Some(mode) => s.serialize_str(&format!("0o{:o}", mode)),
+        None => s.serialize_none(),
+    }
+}
+
+/// 检查树（嵌套深度、名称、路径长度、写入量与硬链接目标）并算出生成的每一步，不写入任何东西。
+/// 随机内容的种子与 `@timestamp` 的时间在这里确定，执行同一个计划得到相同的内容
+///
+/// ```
+/// use treegen::{plan, ActionKind, GenerateOptions};
+///
+/// let spec = treegen::parse_yaml("src:\n  main.rs: 'fn main() {}'\ncopy.rs: { type: hardlink, target: src/main.rs }\n")?;
+/// let plan = plan(&spec.root, std::path::Path::new("out"), &GenerateOptions::default())?;
+/// let kinds: Vec<_> = plan.actions.iter().map(|a| a.kind).collect();
+/// assert_eq!(kinds, [ActionKind::Dir, ActionKind::Dir, ActionKind::File, ActionKind::Hardlink]);
+/// assert_eq!((plan.stats.files, plan.stats.links, plan.stats.bytes), (1, 1, 12));
+/// let json = serde_json::to_value(&plan)?;
+/// assert_eq!(json["actions"][2]["mode"], "0o644");
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn plan<'a>(root: &'a Node, out: &Path, opts: &GenerateOptions) -> Result<Plan<'a>> {
+    check_depth(root, opts.max_depth)?;
+    check_names(root)?;
+    check_lengths(root, out, opts.max_name_bytes)?;
+    check_max_bytes(root, opts.max_bytes)?;
+    // --keep-going 时冲突的文件在写入时各自记为失败
+    if !opts.keep_going {
+        let conflicts = preflight::if_exists_conflicts(root, out, opts);
+        if !conflicts.is_empty() {
+            bail!("{}", conflicts.join("\n"));
+        }
+    }
+    // 随机内容与时间戳在整次生成中使用同一个种子和时间，`:shared:` 的引用才能得到相同的值
+    let seed = opts.seed.unwrap_or_else(|| {
+        use std::hash::{BuildHasher, Hasher};
+        std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish()
+    });
+    let timestamp = opts.timestamp.unwrap_or_else(std::time::SystemTime::now);
+    // 硬链接在其他节点之后创建，先确认每个目标都存在并排好顺序
+    let links = plan_hardlinks(root, out)?;
+    // 可选节点：它们及其下的路径失败时只警告
+    let optional: Vec<PathBuf> = root
+        .iter()
+        .filter(|(_, node)| node.attrs.optional)
+        .map(|(rel, _)| rel)
+        .collect();
+    let is_optional = |rel: &Path| optional.iter().any(|dir| rel.starts_with(dir));
+    let mut actions = Vec::new();
+    let mut link_nodes = BTreeMap::new();
+    for (rel, node) in root.iter() {
+        if node.node_type == NodeType::Hardlink {
+            link_nodes.insert(normalize(&rel), node);
+            continue;
+        }
+        let kind = match node.node_type {
+            NodeType::File if opts.no_touch && node.content.is_none() => ActionKind::Skip,
+            NodeType::File => ActionKind::File,
+            NodeType::Dir => ActionKind::Dir,
+            NodeType::Copy => ActionKind::Copy,
+            NodeType::Hardlink => unreachable!("hardlinks are planned last"),
+        };
+        let content_ref = match kind {
+            ActionKind::File => Some(file_content_ref(node)),
+            ActionKind::Copy => node.copy.as_ref().map(|copy| ContentRef::Copy {
+                from: copy.from.clone(),
+            }),
+            _ => None,
+        };
+        let (mode, policy) = match kind {
+            ActionKind::File => {
+                let exec_bits = if exec_reason(&rel, node, opts).is_some() {
+                    0o111
+                } else {
+                    0
+                };
+                let policy = node.attrs.if_exists.unwrap_or(if opts.skip_existing {
+                    IfExists::Skip
+                } else {
+                    IfExists::Overwrite
+                });
+                (
+                    Some(node.attrs.mode.unwrap_or(opts.mode | exec_bits)),
+                    Some(policy),
+                )
+            }
+            _ => (None, None),
+        };
+        actions.push(PlannedAction {
+            optional: is_optional(&rel),
+            path: rel,
+            kind,
+            content_ref,
+            mode,
+            policy,
+            node,
+        });
+    }
+    for (rel, target) in links {
+        actions.push(PlannedAction {
+            optional: is_optional(&rel),
+            node: link_nodes[&rel],
+            path: rel,
+            kind: ActionKind::Hardlink,
+            content_ref: Some(ContentRef::Link {
+                target: target.to_path_buf(),
+            }),
+            mode: None,
+            policy: None,
+        });
+    }
+    let mut stats = PlanStats::default();
+    for action in &actions {
+        match action.kind {
+            ActionKind::Dir if !action.path.as_os_str().is_empty() => stats.dirs += 1,
+            ActionKind::Dir => {}
+            ActionKind::File => {
+                stats.files += 1;
+                stats.bytes += preflight::file_bytes(action.node);
+            }
+            ActionKind::Copy => stats.copies += 1,
+            ActionKind::Hardlink => stats.links += 1,
+            ActionKind::Skip => stats.skipped += 1,
+        }
+    }
+    Ok(Plan {
+        out: out.to_path_buf(),
+        seed,
+        timestamp,
+        actions,
+        stats,
+        root,
+    })
+}
+
+/// 文件节点内容的来源
+fn file_content_ref(node: &Node) -> ContentRef {
+    if let Some(p) = node.attrs.placeholder {
+        return ContentRef::Placeholder {
+            size: p.size,
+            fill: p.fill.name(),
+        };
+    }
+    if let Some(Ok(generator)) = content_generator(node) {
+        return ContentRef::Generator {
+            name: generator.name(),
+        };
+    }
+    let bytes = match &node.attrs.bytes {
+        Some(bytes) => bytes.len(),
+        None => node.content.as_ref().map_or(0, String::len),
+    };
+    ContentRef::Inline { bytes }
+}
+
+/// 按 [`plan`] 算出的顺序执行，`opts` 中的种子与时间由计划决定；
+/// 结果与 [`generate`] 相同（`dry_run` 时不写入，只统计并打印将要进行的操作）。
+///
+/// 整次执行在 `generate` span 中，每个顶层目录一个 `dir` span；每个节点的结果是一个
+/// info 级别的 `tracing` 事件（`path`、`kind`、`outcome`），失败的路径是 warn 级别
+pub fn execute(plan: &Plan, opts: &GenerateOptions) -> Result<Stats> {
+    let opts = &GenerateOptions {
+        seed: Some(plan.seed),
+        timestamp: Some(plan.timestamp),
+        ..opts.clone()
+    };
+    let out = plan.out.as_path();
+    let _span =
+        tracing::info_span!("generate", out = %out.display(), dry_run = opts.dry_run).entered();
+    let _batch = opts.verbose.then(VerboseBatch::start);
+    let mut stats = Stats::default();
+    // 生成失败的目录，其下的路径不再尝试
+    let mut failed_dirs: Vec<PathBuf> = Vec::new();
+    // 当前所在的顶层目录及其 span
+    let mut top_span: Option<(std::ffi::OsString, tracing::span::EnteredSpan)> = None;
+    for (i, action) in plan.actions.iter().enumerate() {
+        // 只在动作之间检查，正在写入的文件总会写完
+        if opts.cancel.is_cancelled() {
+            // 输出目录本身（空路径）不算计划中的路径
+            let remaining = plan.actions[i..]
+                .iter()
+                .filter(|action| !action.path.as_os_str().is_empty())
+                .count();
+            tracing::warn!(remaining, "interrupted");
+            return Err(Interrupted { stats, remaining }.into());
+        }
+        let rel = &action.path;
+        let node = action.node;
+        if let Some(top) = rel.iter().next() {
+            if top_span.as_ref().is_none_or(|(name, _)| name != top) {
+                // 先离开上一个顶层目录的 span
+                drop(top_span.take());
+                let span = tracing::info_span!("dir", path = %Path::new(top).display());
+                top_span = Some((top.to_os_string(), span.entered()));
+            }
+        }
+        // 未命名的根节点即 out 本身
+        let path = if rel.as_os_str().is_empty() {
+            out.to_path_buf()
+        } else {
+            out.join(rel)
+        };
+        if action.kind == ActionKind::Skip {
+            if opts.verbose {
+                let prefix = if opts.dry_run { "[Dry-Run] " } else { "" };
+                verbose!("{}Skip file (no content): {}", prefix, path.display());
+            }
+            stats.skipped += 1;
+            stats.outcomes.insert(rel.clone(), Outcome::Skipped);
+            tracing::info!(path = %rel.display(), kind = "file", outcome = "skipped", reason = "no content");
+            continue;
+        }
+        if failed_dirs.iter().any(|dir| rel.starts_with(dir)) {
+            tracing::debug!(path = %rel.display(), "inside a failed directory");
+            continue;
+        }
+        if skip_missing(&path, opts, &mut stats) {
+            if !rel.as_os_str().is_empty() {
+                stats.outcomes.insert(rel.clone(), Outcome::Missing);
+            }
+            tracing::info!(path = %rel.display(), kind = action.kind.name(), outcome = "missing");
+            continue;
+        }
+        let before = Snapshot::take(&path, &stats);
+        let modes_before = stats.timings.modes.elapsed;
+        let started = Instant::now();
+        let result = match action.kind {
+            ActionKind::Hardlink => {
+                let target = node.target.as_deref().expect("hardlink node has a target");
+                create_hardlink(&path, target, out, opts, &mut stats)
+            }
+            _ => {
+                // 输出目录本身不改属主
+                let own = if rel.as_os_str().is_empty() {
+                    Ownership::default()
+                } else {
+                    ownership(node, opts)
+                };
+                create_entry(&path, rel, node, &own, opts, before.existed, &mut stats)
+            }
+        };
+        let elapsed = started.elapsed();
+        if action.kind == ActionKind::Dir {
+            stats.timings.dirs.add(elapsed, 1);
+        } else {
+            // 设置权限的时间单独记在 modes 中
+            let chmod = stats.timings.modes.elapsed - modes_before;
+            stats.timings.files.add(elapsed.saturating_sub(chmod), 1);
+        }
+        if let Err(e) = result {
+            if !record_failure(rel, e, action.optional, opts, &mut stats) {
+                return Err(Aborted { stats }.into());
+            }
+            if action.kind == ActionKind::Dir {
+                failed_dirs.push(rel.clone());
+            }
+            continue;
+        }
+        if !rel.as_os_str().is_empty() {
+            if action.kind == ActionKind::Dir {
+                stats.dirs += 1;
+            }
+            let outcome = before.outcome(&stats);
+            stats.outcomes.insert(rel.clone(), outcome);
+            tracing::info!(path = %rel.display(), kind = action.kind.name(), outcome = outcome.name());
+        }
+    }
+    drop(top_span);
+    if opts.durable && !opts.dry_run {
+        let started = Instant::now();
+        sync_dirs(plan.root, out)?;
+        stats.timings.sync.add(started.elapsed(), stats.dirs);
+    }
+    Ok(stats)
+}
+
+/// `content_only` 时不存在的路径不创建：记入 [`Stats::missing`]，返回 true
+fn skip_missing(path: &Path, opts: &GenerateOptions, stats: &mut Stats) -> bool {
+    if !opts.content_only || fs::symlink_metadata(path).is_ok() {
+        return false;
+    }
+    if opts.verbose {
+        let prefix = if opts.dry_run { "[Dry-Run] " } else { "" };
+        verbose!(
+            "{}Skip missing path (content-only): {}",
+            prefix,
+            path.display()
+        );
+    }
+    stats.missing += 1;
+    true
+}
+
+/// 把生成失败的路径归类：可选节点记为警告，其余记入 [`Stats::failures`]；
+/// 返回是否继续生成（没有 `keep_going` 时在这里停下）
+fn record_failure(
+    rel: &Path,
+    e: anyhow::Error,
+    optional: bool,
+    opts: &GenerateOptions,
+    stats: &mut Stats,
+) -> bool {
+    let failure = failure::Failure::from_error(rel.to_path_buf(), &e);
+    tracing::warn!(path = %rel.display(), optional, error = %failure.error, "failed");
+    if optional {
+        if opts.verbose {
+            verbose!("Skipped optional: {}", failure.error);
+        }
+        stats.warnings.push(failure);
+        return true;
+    }
+    if opts.keep_going && opts.verbose {
+        verbose!("Failed: {}", failure.error);
+    }
+    stats.failures.push(failure);
+    opts.keep_going
+}
+
+/// 创建单个节点之前的状态，用于推断 [`Outcome`]
+struct Snapshot {
+    existed: bool,
+    written: usize,
+}
+
+impl Snapshot {
+    fn take(path: &Path, stats: &Stats) -> Self {
+        Snapshot {
+            existed: fs::symlink_metadata(path).is_ok(),
+            written: stats.files + stats.links,
+        }
+    }
+
+    /// 原先不存在的是新建；已存在的看这一步有没有写入文件
+    fn outcome(&self, stats: &Stats) -> Outcome {
+        if !self.existed {
+            Outcome::Created
+        } else if stats.files + stats.links > self.written {
+            Outcome::Overwritten
+        } else {
+            Outcome::Unchanged
+        }
+    }
+}
+
+/// `--durable`：把写入的文件刷到磁盘（unchanged 的文件没有写入，不需要）
+fn sync_file(path: &Path, opts: &GenerateOptions) -> Result<()> {
+    if !opts.durable || opts.dry_run {
+        return Ok(());
+    }
+    // Windows 上刷新文件缓冲需要写权限；Unix 上只读打开即可（文件本身可能是只读的）
+    #[cfg(unix)]
+    let file = fs::File::open(path);
+    #[cfg(not(unix))]
+    let file = fs::OpenOptions::new().write(true).open(path);
+    file.and_then(|file| file.sync_all())
+        .with_context(|| format!("Failed to sync '{}' to disk", path.display()))
+}
+
+/// `--durable`：fsync 新建或增删过条目的目录，使目录项本身也落盘；
+/// 子目录先于父目录同步。Windows 上无法打开目录同步，跳过
+fn sync_dirs(root: &Node, out: &Path) -> Result<()> {
+    let mut dirs = BTreeSet::new();
+    for (rel, node) in root.iter() {
+        let path = if rel.as_os_str().is_empty() {
+            out.to_path_buf()
+        } else {
+            out.join(&rel)
+        };
+        // 相对路径的父目录可能为空，即当前目录
+        match path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => dirs.insert(PathBuf::from(".")),
+            Some(parent) => dirs.insert(parent.to_path_buf()),
+            None => false,
+        };
+        match node.node_type {
+            NodeType::Dir => {
+                dirs.insert(path);
+            }
+            // 递归复制出的子目录只能从磁盘上找
+            NodeType::Copy if path.is_dir() => collect_dirs(&path, &mut dirs)?,
+            _ => {}
+        }
+    }
+    for dir in dirs.iter().rev() {
+        #[cfg(unix)]
+        fs::File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .with_context(|| format!("Failed to sync directory '{}' to disk", dir.display()))?;
+        #[cfg(not(unix))]
+        let _ = dir;
+    }
+    Ok(())
+}
+
+/// `dir` 及其下所有子目录
+fn collect_dirs(dir: &Path, dirs: &mut BTreeSet<PathBuf>) -> Result<()> {
+    dirs.insert(dir.to_path_buf());
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read '{}'", dir.display()))? {
+        let entry = entry.with_context(|| format!("Failed to read '{}'", dir.display()))?;
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            collect_dirs(&entry.path(), dirs)?;
+        }
+    }
+    Ok(())
+}
+
+/// 硬链接目标在树中的情况
+enum LinkTarget {
+    /// 本次生成的文件（包括复制来的文件）
+    File,
+    /// 另一个硬链接，需要排在它之后
+    Link(PathBuf),
+    Dir,
+    /// 不在树中，只能是磁盘上已有的文件
+    Absent,
+}
+
+/// 去掉路径中的 `.`，用于比较
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
+
+/// 在树中查找硬链接目标；位于递归复制节点之内的路径按复制来源判断
+fn lookup_target(root: &Node, target: &Path) -> LinkTarget {
+    let target = normalize(target);
+    let names: Vec<_> = target.iter().collect();
+    let mut node = root;
+    for (i, name) in names.iter().enumerate() {
+        let Some(child) = node
+            .children
+            .iter()
+            .find(|c| Some(c.key()) == name.to_str())
+        else {
+            return LinkTarget::Absent;
+        };
+        if child.node_type == NodeType::Copy && i + 1 < names.len() {
+            let source = child.copy.as_ref().expect("copy node has a source");
+            let inside: PathBuf = names[i + 1..].iter().collect();
+            return if source.recursive && source.has_file(&inside) {
+                LinkTarget::File
+            } else {
+                LinkTarget::Absent
+            };
+        }
+        node = child;
+    }
+    match node.node_type {
+        NodeType::File => LinkTarget::File,
+        NodeType::Copy if node.copy.as_ref().is_some_and(CopySource::is_dir) => LinkTarget::Dir,
+        NodeType::Copy => LinkTarget::File,
+        NodeType::Hardlink => LinkTarget::Link(target),
+        NodeType::Dir => LinkTarget::Dir,
+    }
+}
+
+/// 硬链接的创建计划：目标必须是本次生成的文件或磁盘上已有的文件，
+/// 指向其他硬链接的链接排在其目标之后，循环引用报错。返回 (链接路径, 目标路径)，都相对 `out`
+fn plan_hardlinks<'a>(root: &'a Node, out: &Path) -> Result<Vec<(PathBuf, &'a Path)>> {
+    let mut pending = Vec::new();
+    for (rel, node) in root.iter() {
+        let Some(target) = node.target.as_deref() else {
+            continue;
+        };
+        let dependency = match lookup_target(root, target) {
+            LinkTarget::File => None,
+            LinkTarget::Link(other) => Some(other),
+            LinkTarget::Dir => bail!(
+                "Hardlink '{}' points to directory '{}'; hardlinks must point to files",
+                rel.display(),
+                target.display()
+            ),
+            LinkTarget::Absent if out.join(target).is_file() => None,
+            LinkTarget::Absent => bail!(
+                "Hardlink target '{}' of '{}' is neither generated by the spec nor an existing file in '{}'",
+                target.display(),
+                rel.display(),
+                out.display()
+            ),
+        };
+        pending.push((normalize(&rel), target, dependency));
+    }
+
+    let mut ordered = Vec::new();
+    let mut placed = BTreeSet::new();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|(rel, target, dependency)| {
+            let ready = dependency.as_ref().is_none_or(|d| placed.contains(d));
+            if ready {
+                placed.insert(rel.clone());
+                ordered.push((rel.clone(), *target));
+            }
+            !ready
+        });
+        if pending.len() == before {
+            bail!(
+                "Hardlinks form a cycle: {}",
+                link_cycle(&pending)
+                    .iter()
+                    .map(|rel| format!("'{}'", rel.display()))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            );
+        }
+    }
+    Ok(ordered)
+}
+
+/// 无法排序的硬链接中的一个循环，首尾是同一个链接，如 `a -> b -> a`
+fn link_cycle(pending: &[(PathBuf, &Path, Option<PathBuf>)]) -> Vec<PathBuf> {
+    let mut chain = vec![pending[0].0.clone()];
+    loop {
+        let current = chain.last().expect("chain is never empty");
+        let next = pending
+            .iter()
+            .find(|(rel, _, _)| rel == current)
+            .and_then(|(_, _, dependency)| dependency.clone());
+        let Some(next) = next else {
+            return chain;
+        };
+        // 从第一个链接出发可能先经过循环外的链接，只报告循环本身
+        if let Some(start) = chain.iter().position(|rel| *rel == next) {
+            chain.drain(..start);
+            chain.push(next);
+            return chain;
+        }
+        chain.push(next);
+    }
+}
+
+/// 创建硬链接；失败时（如文件系统不支持）改为复制并警告，`strict` 时报错
+fn create_hardlink(
+    path: &Path,
+    target: &Path,
+    out: &Path,
+    opts: &GenerateOptions,
+    stats: &mut Stats,
+) -> Result<()> {
+    let source = out.join(target);
+    if same_inode(path, &source) {
+        if opts.verbose {
+            let prefix = if opts.dry_run { "[Dry-Run] " } else { "" };
+            verbose!("{}Unchanged hardlink: {}", prefix, path.display());
+        }
+        stats.unchanged += 1;
+        return Ok(());
+    }
+    if opts.dry_run {
+        if opts.verbose {
+            verbose!(
+                "[Dry-Run] Create hardlink: {} -> {}",
+                path.display(),
+                target.display()
+            );
+        }
+        stats.links += 1;
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    // hard_link 不会覆盖已有文件
+    if fs::symlink_metadata(path).is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(path).with_context(|| format!("Failed to replace '{}'", path.display()))?;
+    }
+    if opts.verbose {
+        verbose!(
+            "Create hardlink: {} -> {}",
+            path.display(),
+            target.display()
+        );
+    }
+    match fs::hard_link(&source, path) {
+        Ok(()) => stats.links += 1,
+        Err(e) if opts.strict => {
+            return Err(e).with_context(|| {
+                format!(
+                    "Failed to hardlink '{}' to '{}'",
+                    path.display(),
+                    source.display()
+                )
+            });
+        }
+        Err(e) => {
+            flush_verbose();
+            eprintln!(
+                "Warning: failed to hardlink '{}' to '{}' ({}); copying instead",
+                path.display(),
+                source.display(),
+                e
+            );
+            let size = fs::copy(&source, path).with_context(|| {
+                format!(
+                    "Failed to copy '{}' to '{}'",
+                    source.display(),
+                    path.display()
+                )
+            })?;
+            sync_file(path, opts)?;
+            if opts.verify_writes {
+                let expected = fs::File::open(&source)
+                    .with_context(|| format!("Failed to open '{}'", source.display()))?;
+                verify_write(path, 0, expected, size, opts, stats)?;
+            }
+            stats.files += 1;
+            stats.timings.files.bytes += size;
+        }
+    }
+    Ok(())
+}
+
+/// 两个路径是否是同一个文件（仅类 Unix 平台能判断）
+fn same_inode(a: &Path, b: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        match (fs::metadata(a), fs::metadata(b)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        false
+    }
+}
+
+/// 节点生效的属主/属组：节点的 owner/group 逐项覆盖 `--chown`
+fn ownership(node: &Node, opts: &GenerateOptions) -> Ownership {
+    let global = opts.chown.clone().unwrap_or_default();
+    Ownership {
+        user: node.attrs.owner.clone().or(global.user),
+        group: node.attrs.group.clone().or(global.group),
+    }
+}
+
+/// 把属主/属组解析成 uid/gid，数字 id 原样使用，找不到的用户或组报错；
+/// 不支持属主的平台上总是 `(None, None)`
+///
+/// ```
+/// # #[cfg(unix)] {
+/// use treegen::{resolve_ownership, Ownership};
+///
+/// let own = Ownership::parse("0:0").unwrap();
+/// assert_eq!(resolve_ownership(&own).unwrap(), (Some(0), Some(0)));
+/// let err = resolve_ownership(&Ownership::parse(":nogroupxx").unwrap()).unwrap_err();
+/// assert_eq!(err.to_string(), "Unknown group 'nogroupxx'");
+/// # }
+/// ```
+pub fn resolve_ownership(own: &Ownership) -> Result<(Option<u32>, Option<u32>)> {
+    #[cfg(unix)]
+    {
+        use nix::unistd::{Group, User};
+
+        let uid = match &own.user {
+            Some(user) => Some(match user.parse::<u32>() {
+                Ok(uid) => uid,
+                Err(_) => User::from_name(user)
+                    .with_context(|| format!("Failed to look up user '{}'", user))?
+                    .with_context(|| format!("Unknown user '{}'", user))?
+                    .uid
+                    .as_raw(),
+            }),
+            None => None,
+        };
+        let gid = match &own.group {
+            Some(group) => Some(match group.parse::<u32>() {
+                Ok(gid) => gid,
+                Err(_) => Group::from_name(group)
+                    .with_context(|| format!("Failed to look up group '{}'", group))?
+                    .with_context(|| format!("Unknown group '{}'", group))?
+                    .gid
+                    .as_raw(),
+            }),
+            None => None,
+        };
+        Ok((uid, gid))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = own;
+        Ok((None, None))
+    }
+}
+
+/// 解析不了的属主/属组（`--chown` 与节点的 owner/group），每个不同的值报告一次并指出第一个用到它的路径
+pub(crate) fn ownership_problems(root: &Node, opts: &GenerateOptions) -> Vec<String> {
+    let mut seen: Vec<Ownership> = Vec::new();
+    let mut problems = Vec::new();
+    for (rel, node) in root.iter() {
+        // 输出目录本身与硬链接不改属主
+        if rel.as_os_str().is_empty() || node.node_type == NodeType::Hardlink {
+            continue;
+        }
+        let own = ownership(node, opts);
+        if own.is_empty() || seen.contains(&own) {
+            continue;
+        }
+        if let Err(e) = resolve_ownership(&own) {
+            problems.push(format!("{:#} (for '{}')", e, rel.display()));
+        }
+        seen.push(own);
+    }
+    problems
+}
+
+/// 在写入任何东西之前确认所有属主/属组都能解析，不会改到一半才因为不存在的组失败；
+/// 预检（[`preflight::preflight`]）同样报告这些问题
+pub fn check_ownership(root: &Node, opts: &GenerateOptions) -> Result<()> {
+    match ownership_problems(root, opts).into_iter().next() {
+        Some(problem) => bail!(problem),
+        None => Ok(()),
+    }
+}
+
+/// 设置路径的属主/属组
+fn apply_ownership(path: &Path, own: &Ownership, opts: &GenerateOptions) -> Result<()> {
+    if own.is_empty() {
+        return Ok(());
+    }
+    if opts.dry_run {
+        if opts.verbose {
+            verbose!("[Dry-Run] Set owner of {}: {}", path.display(), own);
+        }
+        return Ok(());
+    }
+    #[cfg(unix)]
+    {
+        let (uid, gid) = resolve_ownership(own)?;
+        if opts.verbose {
+            verbose!("Set owner of {}: {}", path.display(), own);
+        }
+        std::os::unix::fs::chown(path, uid, gid).with_context(|| {
+            format!("Failed to change owner of '{}' to {}", path.display(), own)
+        })?;
+    }
+    #[cfg(not(unix))]
+    {
+        flush_verbose();
+        eprintln!(
+            "Warning: ownership is not supported on this platform; ignoring owner of '{}'",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// 设置路径的扩展属性；在改属主之后调用（chown 会清除部分安全相关属性）
+fn apply_xattrs(
+    path: &Path,
+    xattrs: &BTreeMap<String, String>,
+    opts: &GenerateOptions,
+) -> Result<()> {
+    for (name, raw) in xattrs {
+        let value = xattr_value(raw).with_context(|| {
+            format!("Invalid value of xattr '{}' for '{}'", name, path.display())
+        })?;
+        if opts.dry_run {
+            if opts.verbose {
+                verbose!(
+                    "[Dry-Run] Set xattr {} ({} bytes) on {}",
+                    name,
+                    value.len(),
+                    path.display()
+                );
+            }
+            continue;
+        }
+        #[cfg(unix)]
+        if xattr::SUPPORTED_PLATFORM {
+            if opts.verbose {
+                verbose!(
+                    "Set xattr {} ({} bytes) on {}",
+                    name,
+                    value.len(),
+                    path.display()
+                );
+            }
+            xattr::set(path, name, &value).with_context(|| {
+                format!(
+                    "Failed to set extended attribute '{}' on '{}'",
+                    name,
+                    path.display()
+                )
+            })?;
+            continue;
+        }
+        flush_verbose();
+        eprintln!(
+            "Warning: extended attributes are not supported on this platform; skipping '{}' on '{}'",
+            name,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// 该路径是否要设置隐藏属性：仅 Windows 上开启 `windows_hidden_dotfiles` 时，名称以 `.` 开头的路径
+fn hides(path: &Path, opts: &GenerateOptions) -> bool {
+    cfg!(windows)
+        && opts.windows_hidden_dotfiles
+        && path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// 日志中标注将被隐藏的路径
+fn hidden_note(path: &Path, opts: &GenerateOptions) -> &'static str {
+    if hides(path, opts) {
+        " (hidden)"
+    } else {
+        ""
+    }
+}
+
+/// 为点开头的路径加上 FILE_ATTRIBUTE_HIDDEN（见 [`GenerateOptions::windows_hidden_dotfiles`]）
+fn apply_hidden(path: &Path, opts: &GenerateOptions) -> Result<()> {
+    if opts.dry_run || !hides(path, opts) {
+        return Ok(());
+    }
+    set_hidden(path)
+        .with_context(|| format!("Failed to set the hidden attribute on '{}'", path.display()))
+}
+
+#[cfg(windows)]
+fn set_hidden(path: &Path) -> std::io::Result<()> {
+    use std::os::windows::{ffi::OsStrExt, fs::MetadataExt};
+    use windows_sys::Win32::Storage::FileSystem::{
+        SetFileAttributesW, FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_NORMAL,
+    };
+
+    // FILE_ATTRIBUTE_NORMAL 只能单独使用，与其他属性组合时去掉
+    let attrs = fs::metadata(path)?.file_attributes() & !FILE_ATTRIBUTE_NORMAL;
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    // SAFETY: wide 是以 0 结尾的 UTF-16 路径，调用期间一直有效
+    if unsafe { SetFileAttributesW(wide.as_ptr(), attrs | FILE_ATTRIBUTE_HIDDEN) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn set_hidden(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// === 在磁盘上创建单个目录或文件（遍历顺序由 Node::iter 决定）===
+/// `existed` 是生成前路径是否已存在，不存在时省去比较旧内容的读取
+fn create_entry(
+    path: &Path,
+    rel: &Path,
+    node: &Node,
+    own: &Ownership,
+    opts: &GenerateOptions,
+    existed: bool,
+    stats: &mut Stats,
+) -> Result<()> {
+    let GenerateOptions {
+        dry_run, verbose, ..
+    } = *opts;
+    let hidden = hidden_note(path, opts);
+
+    match node.node_type {
+        NodeType::Dir => {
+            let note = hidden;
+            if dry_run {
+                if verbose {
+                    verbose!("[Dry-Run] Create directory: {}{}", path.display(), note);
+                }
+            } else {
+                if verbose {
+                    verbose!("Create directory: {}{}", path.display(), note);
+                }
+                fs::create_dir_all(path)
+                    .with_context(|| format!("Failed to create directory '{}'", path.display()))?;
+            }
+            apply_ownership(path, own, opts)?;
+            apply_xattrs(path, &node.attrs.xattrs, opts)?;
+            apply_hidden(path, opts)?;
+        }
+        NodeType::File => {
+            // 先序遍历，上级目录节点已在本次生成中创建；
+            // 只有名称本身带分隔符（如 `src/main.rs` 键）时才需要补齐
+            let nested = Path::new(&node.name).components().nth(1).is_some();
+            if let Some(parent) = path.parent() {
+                if !dry_run {
+                    if nested {
+                        fs::create_dir_all(parent).ok();
+                    }
+                } else if verbose {
+                    verbose!("[Dry-Run] Ensure parent dirs for: {}", path.display());
+                }
+            }
+            // 内容生成器在写入时才算出值，dry-run 只显示生成器的名称
+            let generator = content_generator(node)
+                .transpose()
+                .with_context(|| format!("Invalid content of '{}'", path.display()))?;
+            let generated = match &generator {
+                Some(generator) if !dry_run => Some(generator.generate(rel, opts)?),
+                _ => None,
+            };
+            let content = match (&generated, &node.attrs.bytes) {
+                (Some(bytes), _) | (None, Some(bytes)) => bytes.as_slice(),
+                (None, None) => node.content.as_deref().unwrap_or("").as_bytes(),
+            };
+            let placeholder = node.attrs.placeholder;
+            // 节点自己的 if_exists 优先于命令行（--skip-existing）
+            let policy = node.attrs.if_exists.unwrap_or(if opts.skip_existing {
+                IfExists::Skip
+            } else {
+                IfExists::Overwrite
+            });
+            let existing = existed && path.is_file();
+            // 内容未变化的文件不重写，避免重复生成（如 --watch）时无谓地改动输出
+            let kept = existing && policy == IfExists::Skip;
+            let unchanged = kept
+                || existing
+                    && match (placeholder, policy) {
+                        // 占位文件只比较大小，不读取内容
+                        (Some(p), _) => fs::metadata(path).is_ok_and(|m| m.len() == p.size),
+                        (None, IfExists::Append) => content.is_empty(),
+                        (None, _) => fs::read(path).is_ok_and(|old| old == content),
+                    };
+            if existing && !unchanged && policy == IfExists::Error {
+                bail!(
+                    "'{}' already exists with different content (if_exists: error)",
+                    path.display()
+                );
+            }
+            // 不设置权限的文件也就不加可执行位
+            let exec = if unchanged && !opts.fix_modes {
+                None
+            } else {
+                exec_reason(path, node, opts)
+            };
+            let policy_note = node
+                .attrs
+                .if_exists
+                .map(|policy| format!(" (if_exists: {})", policy.name()))
+                .unwrap_or_default();
+            let size_note = placeholder
+                .map(|p| format!(" ({}, {})", preflight::human_bytes(p.size), p.fill.name()))
+                .or_else(|| {
+                    generator
+                        .as_ref()
+                        .map(|generator| format!(" ({})", generator.name()))
+                })
+                .unwrap_or_default();
+            let note = format!(
+                "{}{}{}{}",
+                hidden,
+                exec.unwrap_or_default(),
+                policy_note,
+                size_note
+            );
+            let verb = match (unchanged, existing, policy) {
+                (true, _, _) if kept => "Skip existing file",
+                (true, _, _) => "Unchanged file",
+                (false, true, IfExists::Backup) => "Back up and overwrite file",
+                (false, true, IfExists::Append) => "Append to file",
+                _ => "Create file",
+            };
+            // dry-run 总是列出节点自己的策略对已有文件的决定，以及占位文件的大小与内容生成器
+            let show = verbose
+                || dry_run
+                    && (existing && node.attrs.if_exists.is_some()
+                        || placeholder.is_some()
+                        || generator.is_some());
+            if dry_run {
+                if show {
+                    verbose!("[Dry-Run] {}: {}{}", verb, path.display(), note);
+                }
+                if unchanged {
+                    stats.unchanged += 1;
+                } else {
+                    stats.files += 1;
+                }
+            } else {
+                if verbose {
+                    verbose!("{}: {}{}", verb, path.display(), note);
+                }
+                if unchanged {
+                    stats.unchanged += 1;
+                } else {
+                    // 追加时只校验追加的部分
+                    let start = match (existing, policy) {
+                        (true, IfExists::Append) => fs::metadata(path).map_or(0, |m| m.len()),
+                        _ => 0,
+                    };
+                    match placeholder {
+                        Some(p) => {
+                            if existing && policy == IfExists::Backup {
+                                backup_file(path)?;
+                            }
+                            let seed = derive_seed(opts.seed.unwrap_or_default(), &seed_key(rel));
+                            p.write(path, seed)?;
+                            sync_file(path, opts)?;
+                            verify_write(path, 0, p.reader(seed), p.size, opts, stats)?;
+                            stats.timings.files.bytes += p.size;
+                        }
+                        None => {
+                            write_file(path, content, existing.then_some(policy))?;
+                            sync_file(path, opts)?;
+                            verify_write(path, start, content, content.len() as u64, opts, stats)?;
+                            stats.timings.files.bytes += content.len() as u64;
+                        }
+                    }
+                    stats.files += 1;
+                }
+                // 权限与内容一起决定：没有写入的已有文件保留原来的权限，除非 --fix-modes
+                #[cfg(unix)]
+                if !unchanged || opts.fix_modes {
+                    let exec_bits = if exec.is_some() { 0o111 } else { 0 };
+                    let mode = node.attrs.mode.unwrap_or(opts.mode | exec_bits);
+                    let started = Instant::now();
+                    fs::set_permissions(path, fs::Permissions::from_mode(mode)).with_context(
+                        || format!("Failed to set permissions for '{}'", path.display()),
+                    )?;
+                    stats.timings.modes.add(started.elapsed(), 1);
+                }
+            }
+            apply_ownership(path, own, opts)?;
+            apply_xattrs(path, &node.attrs.xattrs, opts)?;
+            apply_hidden(path, opts)?;
+        }
+        NodeType::Copy => {
+            let source = node.copy.as_ref().expect("copy node has a source");
+            copy_entry(source, path, own, opts, stats)?;
+        }
+        // 硬链接由 generate 在其他节点之后按计划创建
+        NodeType::Hardlink => {}
+    }
+    Ok(())
+}
+
+/// `--verify-writes`：重新读取刚写入的 `path`，确认从第 `start` 个字节起到文件末尾正是 `expected`
+/// （共 `len` 字节），并把整个文件的 SHA-256 记入 [`Stats::verified`]；两者都流式读取
+fn verify_write(
+    path: &Path,
+    start: u64,
+    mut expected: impl std::io::Read,
+    len: u64,
+    opts: &GenerateOptions,
+    stats: &mut Stats,
+) -> Result<()> {
+    use sha2::{Digest, Sha256};
+
+    /// 尽量读满 `buf`，返回读到的字节数（不足时已到末尾）
+    fn fill(reader: &mut impl std::io::Read, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match reader.read(&mut buf[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        Ok(filled)
+    }
+
+    if !opts.verify_writes || opts.dry_run {
+        return Ok(());
+    }
+    let read_error = || format!("Failed to read back '{}'", path.display());
+    let mut file = fs::File::open(path).with_context(read_error)?;
+    let mut hasher = Sha256::new();
+    let (mut actual_buf, mut expected_buf) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    let mut offset = 0u64;
+    let mismatch = loop {
+        let n = fill(&mut file, &mut actual_buf).with_context(read_error)?;
+        hasher.update(&actual_buf[..n]);
+        // 追加之前的部分只参与哈希
+        let skip = start.saturating_sub(offset).min(n as u64) as usize;
+        let compared = &actual_buf[skip..n];
+        let m = fill(&mut expected, &mut expected_buf[..compared.len()])
+            .with_context(|| format!("Failed to read the source of '{}'", path.display()))?;
+        let base = offset + skip as u64;
+        if let Some(i) = compared[..m]
+            .iter()
+            .zip(&expected_buf[..m])
+            .position(|(a, b)| a != b)
+        {
+            break Some(base + i as u64);
+        }
+        // 读回的比要写入的长
+        if m < compared.len() {
+            break Some(base + m as u64);
+        }
+        offset += n as u64;
+        if n < actual_buf.len() {
+            // 读回的比要写入的短
+            break (expected
+                .read(&mut expected_buf[..1])
+                .with_context(read_error)?
+                > 0)
+            .then_some(offset);
+        }
+    };
+    if let Some(at) = mismatch {
+        bail!(
+            "Verification of '{}' failed: first difference at byte {} (wrote {} bytes, read back {})",
+            path.display(),
+            at,
+            start + len,
+            fs::metadata(path).map_or(0, |m| m.len())
+        );
+    }
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    if opts.verbose {
+        verbose!("Verified: {}", path.display());
+    }
+    stats.verified.insert(path.to_path_buf(), hash);
+    Ok(())
+}
+
+/// 写入文件内容；`existing` 为已有文件的处理策略（新建时为 `None`）
+fn write_file(path: &Path, content: &[u8], existing: Option<IfExists>) -> Result<()> {
+    match existing {
+        Some(IfExists::Append) => {
+            use std::io::Write as _;
+            fs::OpenOptions::new()
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(content))
+                .with_context(|| format!("Failed to append to file '{}'", path.display()))
+        }
+        Some(IfExists::Backup) => {
+            backup_file(path)?;
+            fs::write(path, content)
+                .with_context(|| format!("Failed to write file '{}'", path.display()))
+        }
+        _ if content.is_empty() => fs::write(path, "")
+            .with_context(|| format!("Failed to create empty file '{}'", path.display())),
+        _ => fs::write(path, content)
+            .with_context(|| format!("Failed to write file '{}'", path.display())),
+    }
+}
+
+/// `if_exists: backup`：已有文件改名为 `<名称>.bak`（覆盖之前的备份）
+fn backup_file(path: &Path) -> Result<()> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    fs::rename(path, &backup).with_context(|| {
+        format!(
+            "Failed to back up '{}' to '{}'",
+            path.display(),
+            Path::new(&backup).display()
+        )
+    })
+}
+
+/// 文件在 `--mode` 之上加可执行位的原因（用于日志），不加时为 `None`：
+/// 名称以 `--exec-ext` 中的扩展名结尾，或（`--auto-exec` 时）内容以 `#!` 开头。
+/// 节点自己（或规则）设置了 mode 时原样使用，`--mode` 已有可执行位时也不再说明
+fn exec_reason(path: &Path, node: &Node, opts: &GenerateOptions) -> Option<&'static str> {
+    if node.attrs.mode.is_some() || opts.mode & 0o111 == 0o111 {
+        return None;
+    }
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    let by_ext = opts.exec_ext.iter().any(|ext| {
+        let ext = ext.to_lowercase();
+        name.len() > ext.len() + 1
+            && name.ends_with(&ext)
+            && name[..name.len() - ext.len()].ends_with('.')
+    });
+    if by_ext {
+        Some(" (+x: --exec-ext)")
+    } else if opts.auto_exec && node.content.as_deref().is_some_and(|c| c.starts_with("#!")) {
+        Some(" (+x: shebang)")
+    } else {
+        None
+    }
+}
+
+/// 复制节点：文件直接复制（连同权限），目录需要 `recursive`，并跳过 `exclude` 匹配的路径
+fn copy_entry(
+    source: &CopySource,
+    dest: &Path,
+    own: &Ownership,
+    opts: &GenerateOptions,
+    stats: &mut Stats,
+) -> Result<()> {
+    if let Some(zip) = &source.archive {
+        return copy_from_archive(zip, source, dest, own, opts, stats);
+    }
+    let from = &source.from;
+    if !from.exists() {
+        bail!(
+            "Copy source '{}' for '{}' does not exist",
+            from.display(),
+            dest.display()
+        );
+    }
+    let meta = fs::metadata(from)
+        .with_context(|| format!("Failed to read copy source '{}'", from.display()))?;
+    if !meta.is_dir() {
+        let rules = CopyRules::new(source, None, opts)?;
+        return copy_file(
+            FileSource::Path(from),
+            dest,
+            meta.len(),
+            &rules,
+            own,
+            opts,
+            stats,
+        );
+    }
+    if !source.recursive {
+        bail!(
+            "Copy source '{}' for '{}' is a directory; set \"recursive\": true to copy it",
+            from.display(),
+            dest.display()
+        );
+    }
+    let rules = CopyRules::for_dir(source, dest, opts)?;
+    copy_dir(from, dest, Path::new(""), &rules, own, opts, stats)?;
+    rules.report(source, dest, opts);
+    Ok(())
+}
+
+/// 复制来源根目录下的忽略文件（gitignore 语法），其中的规则先于 `exclude`/`include` 生效
+pub const IGNORE_FILE: &str = ".treegenignore";
+
+/// 复制目录时默认忽略的版本控制目录
+pub const DEFAULT_IGNORES: &[&str] = &[".git/", ".hg/", ".svn/", ".bzr/"];
+
+/// 复制来源的筛选与变量替换
+pub(crate) struct CopyRules<'a> {
+    /// 默认规则与 `.treegenignore`
+    ignore: Gitignore,
+    /// 已忽略的条目数（被忽略的目录算一个）
+    ignored: Cell<usize>,
+    /// `exclude` 编译的 GlobSet
+    exclude: GlobSet,
+    /// 设置了 `include` 时选中的文件及其上级目录（相对来源目录）
+    selected: Option<BTreeSet<PathBuf>>,
+    /// `substitute` 时替换文本文件中模板变量所用的变量
+    pub(crate) vars: Option<&'a BTreeMap<String, String>>,
+}
+
+impl<'a> CopyRules<'a> {
+    /// `ignore_file` 为来源根目录下 [`IGNORE_FILE`] 的内容
+    fn new(
+        source: &'a CopySource,
+        ignore_file: Option<&str>,
+        opts: &GenerateOptions,
+    ) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new("");
+        let defaults = DEFAULT_IGNORES.iter().filter(|_| !opts.no_default_ignores);
+        for line in defaults
+            .copied()
+            .chain(ignore_file.into_iter().flat_map(str::lines))
+        {
+            builder.add_line(None, line).with_context(|| {
+                format!(
+                    "Invalid pattern '{}' in {} of copy source '{}'",
+                    line,
+                    IGNORE_FILE,
+                    source.display()
+                )
+            })?;
+        }
+        Ok(CopyRules {
+            ignore: builder.build()?,
+            ignored: Cell::new(0),
+            exclude: exclude_set(source)?,
+            selected: None,
+            vars: source.substitute.then_some(&source.vars),
+        })
+    }
+
+    /// 磁盘上的来源目录：读入其中的 [`IGNORE_FILE`]，按 `include` 选出要复制的文件
+    pub(crate) fn for_dir(
+        source: &'a CopySource,
+        dest: &Path,
+        opts: &GenerateOptions,
+    ) -> Result<Self> {
+        let ignore_file = source.from.join(IGNORE_FILE);
+        let ignore_file = if ignore_file.is_file() {
+            Some(
+                fs::read_to_string(&ignore_file)
+                    .with_context(|| format!("Failed to read '{}'", ignore_file.display()))?,
+            )
+        } else {
+            None
+        };
+        let mut rules = CopyRules::new(source, ignore_file.as_deref(), opts)?;
+        if !source.include.is_empty() {
+            let mut files = Vec::new();
+            list_files(&source.from, Path::new(""), &rules, &mut files)?;
+            rules.select(source, dest, files.iter().map(PathBuf::as_path))?;
+        }
+        Ok(rules)
+    }
+
+    /// 压缩包中的来源目录（`files` 为其下的所有文件）：读入其中的 [`IGNORE_FILE`]，按 `include` 选出要复制的文件
+    pub(crate) fn for_archive(
+        archive: &mut archive::Archive,
+        source: &'a CopySource,
+        files: &[(PathBuf, u64)],
+        dest: &Path,
+        opts: &GenerateOptions,
+    ) -> Result<Self> {
+        let ignore_file = source.from.join(IGNORE_FILE);
+        let ignore_file = match archive.member(&ignore_file) {
+            Some(archive::Member::File(_)) => Some(
+                String::from_utf8(archive.read(&ignore_file)?)
+                    .with_context(|| format!("'{}' is not UTF-8", archive.display(&ignore_file)))?,
+            ),
+            _ => None,
+        };
+        let mut rules = CopyRules::new(source, ignore_file.as_deref(), opts)?;
+        if !source.include.is_empty() {
+            let kept: Vec<&Path> = files
+                .iter()
+                .map(|(rel, _)| rel.as_path())
+                .filter(|rel| !rules.drops_member(rel))
+                .collect();
+            rules.select(source, dest, kept.into_iter())?;
+        }
+        Ok(rules)
+    }
+
+    /// 按 `include` 从 `files`（未被 exclude 的文件）中选出要复制的文件，一个都没有时报错
+    fn select<'p>(
+        &mut self,
+        source: &CopySource,
+        dest: &Path,
+        files: impl Iterator<Item = &'p Path>,
+    ) -> Result<()> {
+        let include = glob_set(&source.include)?;
+        let mut selected = BTreeSet::new();
+        for file in files.filter(|file| include.is_match(file)) {
+            selected.extend(
+                file.ancestors()
+                    .filter(|path| !path.as_os_str().is_empty())
+                    .map(Path::to_path_buf),
+            );
+        }
+        if selected.is_empty() {
+            bail!(
+                "No files in copy source '{}' for '{}' match include {}",
+                source.display(),
+                dest.display(),
+                source.include.join(", ")
+            );
+        }
+        self.selected = Some(selected);
+        Ok(())
+    }
+
+    /// 忽略规则匹配 `rel`；忽略文件本身也不复制
+    fn is_ignored(&self, rel: &Path, is_dir: bool) -> bool {
+        rel == Path::new(IGNORE_FILE) || self.ignore.matched(rel, is_dir).is_ignore()
+    }
+
+    /// 同 [`CopyRules::is_ignored`]，并计入忽略的条目数
+    fn ignores(&self, rel: &Path, is_dir: bool) -> bool {
+        if rel == Path::new(IGNORE_FILE) {
+            return true;
+        }
+        let ignored = self.is_ignored(rel, is_dir);
+        if ignored {
+            self.ignored.set(self.ignored.get() + 1);
+        }
+        ignored
+    }
+
+    /// 逐级遍历来源目录时不复制 `rel`：被忽略、排除或没有被 `include` 选中
+    pub(crate) fn skips(&self, rel: &Path, is_dir: bool) -> bool {
+        self.is_ignored(rel, is_dir) || self.exclude.is_match(rel) || !self.keeps(rel)
+    }
+
+    /// 压缩包成员不是逐级遍历的：它或它的上级目录被忽略或排除时都跳过
+    pub(crate) fn drops_member(&self, rel: &Path) -> bool {
+        rel.ancestors()
+            .filter(|path| !path.as_os_str().is_empty())
+            .enumerate()
+            .any(|(i, path)| self.exclude.is_match(path) || self.is_ignored(path, i > 0))
+    }
+
+    /// verbose 时报告这个复制节点忽略的条目数
+    fn report(&self, source: &CopySource, dest: &Path, opts: &GenerateOptions) {
+        let ignored = self.ignored.get();
+        if opts.verbose && ignored > 0 {
+            verbose!(
+                "Ignored {} entries in copy source '{}' for '{}'",
+                ignored,
+                source.display(),
+                dest.display()
+            );
+        }
+    }
+
+    /// 相对来源目录的路径 `rel` 要复制（没有 `include` 时总是）
+    fn keeps(&self, rel: &Path) -> bool {
+        self.selected
+            .as_ref()
+            .is_none_or(|selected| selected.contains(rel))
+    }
+}
+
+/// 列出 `root/rel` 下未被忽略、也未被 `exclude` 跳过的所有文件（相对 `root`）
+fn list_files(root: &Path, rel: &Path, rules: &CopyRules, out: &mut Vec<PathBuf>) -> Result<()> {
+    let dir = root.join(rel);
+    let entries = fs::read_dir(&dir)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read copy source '{}'", dir.display()))?;
+    for entry in entries {
+        let child = rel.join(entry.file_name());
+        let meta = fs::symlink_metadata(entry.path())
+            .with_context(|| format!("Failed to read '{}'", entry.path().display()))?;
+        if rules.is_ignored(&child, meta.is_dir()) || rules.exclude.is_match(&child) {
+            continue;
+        }
+        refuse_symlink(&entry.path(), &meta)?;
+        if meta.is_dir() {
+            list_files(root, &child, rules, out)?;
+        } else {
+            out.push(child);
+        }
+    }
+    Ok(())
+}
+
+/// 从压缩包复制，规则同 [`copy_entry`]：目录按成员路径排序，跳过 `exclude` 匹配的路径
+/// （及其下的所有内容）；文件直接从压缩包流式写出
+fn copy_from_archive(
+    zip: &Path,
+    source: &CopySource,
+    dest: &Path,
+    own: &Ownership,
+    opts: &GenerateOptions,
+    stats: &mut Stats,
+) -> Result<()> {
+    let mut archive = archive::Archive::open(zip)?;
+    let from = &source.from;
+    let (dirs, files) = match archive.member(from) {
+        None => bail!(
+            "Copy source '{}' for '{}' does not exist",
+            source.display(),
+            dest.display()
+        ),
+        Some(archive::Member::File(size)) => {
+            let rules = CopyRules::new(source, None, opts)?;
+            let source = FileSource::Member(&mut archive, from);
+            return copy_file(source, dest, size, &rules, own, opts, stats);
+        }
+        Some(_) if !source.recursive => bail!(
+            "Copy source '{}' for '{}' is a directory; set \"recursive\": true to copy it",
+            source.display(),
+            dest.display()
+        ),
+        Some(archive::Member::Dir { dirs, files }) => (dirs, files),
+    };
+
+    let rules = CopyRules::for_archive(&mut archive, source, &files, dest, opts)?;
+    // 只有上级没有被忽略的条目计入忽略数
+    let parent_ignored = |rel: &Path| {
+        rel.ancestors()
+            .skip(1)
+            .any(|dir| !dir.as_os_str().is_empty() && rules.is_ignored(dir, true))
+    };
+    let skip = |rel: &Path, is_dir: bool| {
+        if !parent_ignored(rel) && rules.ignores(rel, is_dir) {
+            return true;
+        }
+        if rules.exclude.is_match(rel) && opts.verbose {
+            verbose!("Skip excluded: {}", archive::display(zip, &from.join(rel)));
+        }
+        rules.drops_member(rel) || !rules.keeps(rel)
+    };
+    let dirs: Vec<PathBuf> = std::iter::once(PathBuf::new())
+        .chain(dirs.into_iter().filter(|dir| !skip(dir, true)))
+        .collect();
+    for dir in &dirs {
+        // join("") 会在路径末尾加上分隔符
+        let (src, path) = if dir.as_os_str().is_empty() {
+            (from.clone(), dest.to_path_buf())
+        } else {
+            (from.join(dir), dest.join(dir))
+        };
+        // 根目录已由调用方确认存在；其下不存在的目录不创建，其中的文件各自记为 missing
+        if skip_missing(&path, opts, stats) {
+            continue;
+        }
+        let note = hidden_note(&path, opts);
+        let src = archive::display(zip, &src);
+        if opts.dry_run {
+            if opts.verbose {
+                verbose!(
+                    "[Dry-Run] Copy directory: {}{} from {}",
+                    path.display(),
+                    note,
+                    src
+                );
+            }
+        } else {
+            if opts.verbose {
+                verbose!("Copy directory: {}{} from {}", path.display(), note, src);
+            }
+            fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create directory '{}'", path.display()))?;
+        }
+        stats.dirs += 1;
+        apply_ownership(&path, own, opts)?;
+        apply_hidden(&path, opts)?;
+    }
+    for (rel, size) in files {
+        if skip(&rel, false) {
+            continue;
+        }
+        let member = from.join(&rel);
+        let source = FileSource::Member(&mut archive, &member);
+        copy_file(source, &dest.join(&rel), size, &rules, own, opts, stats)?;
+    }
+    rules.report(source, dest, opts);
+    Ok(())
+}
+
+/// `exclude` 编译为 GlobSet
+fn exclude_set(source: &CopySource) -> Result<GlobSet> {
+    glob_set(&source.exclude)
+}
+
+/// 一组 glob 编译为 GlobSet
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob '{}'", pattern))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// 递归复制 `src_root/rel` 到 `dest_root/rel`；同级条目按名称排序，
+/// 目录权限在其内容复制完之后再设置（源目录可能是只读的）
+fn copy_dir(
+    src_root: &Path,
+    dest_root: &Path,
+    rel: &Path,
+    rules: &CopyRules,
+    own: &Ownership,
+    opts: &GenerateOptions,
+    stats: &mut Stats,
+) -> Result<()> {
+    // join("") 会在路径末尾加上分隔符
+    let (src, dest) = if rel.as_os_str().is_empty() {
+        (src_root.to_path_buf(), dest_root.to_path_buf())
+    } else {
+        (src_root.join(rel), dest_root.join(rel))
+    };
+    // 不存在的目录不创建，但仍然遍历，其中的文件各自记为 missing
+    let missing = skip_missing(&dest, opts, stats);
+    let note = hidden_note(&dest, opts);
+    if missing {
+        // 不创建，只遍历
+    } else if opts.dry_run {
+        if opts.verbose {
+            verbose!(
+                "[Dry-Run] Copy directory: {}{} from {}",
+                dest.display(),
+                note,
+                src.display()
+            );
+        }
+    } else {
+        if opts.verbose {
+            verbose!(
+                "Copy directory: {}{} from {}",
+                dest.display(),
+                note,
+                src.display()
+            );
+        }
+        fs::create_dir_all(&dest)
+            .with_context(|| format!("Failed to create directory '{}'", dest.display()))?;
+    }
+    if !missing {
+        stats.dirs += 1;
+    }
+
+    let mut entries = fs::read_dir(&src)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read copy source '{}'", src.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let child = rel.join(entry.file_name());
+        let meta = fs::symlink_metadata(entry.path())
+            .with_context(|| format!("Failed to read '{}'", entry.path().display()))?;
+        if rules.ignores(&child, meta.is_dir()) {
+            continue;
+        }
+        if rules.exclude.is_match(&child) {
+            if opts.verbose {
+                verbose!("Skip excluded: {}", src_root.join(&child).display());
+            }
+            continue;
+        }
+        if !rules.keeps(&child) {
+            continue;
+        }
+        refuse_symlink(&entry.path(), &meta)?;
+        if meta.is_dir() {
+            copy_dir(src_root, dest_root, &child, rules, own, opts, stats)?;
+        } else {
+            copy_file(
+                FileSource::Path(&entry.path()),
+                &dest_root.join(&child),
+                meta.len(),
+                rules,
+                own,
+                opts,
+                stats,
+            )?;
+        }
+    }
+
+    if missing {
+        return Ok(());
+    }
+    if !opts.dry_run {
+        let perms = fs::metadata(&src)
+            .with_context(|| format!("Failed to read '{}'", src.display()))?
+            .permissions();
+        fs::set_permissions(&dest, perms)
+            .with_context(|| format!("Failed to set permissions for '{}'", dest.display()))?;
+    }
+    apply_ownership(&dest, own, opts)?;
+    apply_hidden(&dest, opts)
+}
+
+/// 复制来源目录中的符号链接不跟随也不复制：指向上级的循环会复制出无穷深的树，
+/// 指向 `/` 会把整个主机复制进输出目录（来源本身是链接时照常跟随）
+pub(crate) fn refuse_symlink(path: &Path, meta: &fs::Metadata) -> Result<()> {
+    if meta.file_type().is_symlink() {
+        bail!(
+            "Copy source contains a symbolic link '{}'; symbolic links inside copied directories are not supported",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// 要复制的单个文件：磁盘上的文件，或压缩包中的成员
+enum FileSource<'a> {
+    Path(&'a Path),
+    Member(&'a mut archive::Archive, &'a Path),
+}
+
+impl FileSource<'_> {
+    /// 用于日志的来源
+    fn display(&self) -> String {
+        match self {
+            FileSource::Path(path) => path.display().to_string(),
+            FileSource::Member(archive, member) => archive.display(member),
+        }
+    }
+
+    fn read(&mut self) -> Result<Vec<u8>> {
+        match self {
+            FileSource::Path(path) => Ok(fs::read(path)?),
+            FileSource::Member(archive, member) => archive.read(member),
+        }
+    }
+
+    /// 写到 `dest`（`fs::copy` 同时复制权限，压缩包成员按其记录的 Unix 权限）
+    fn copy_to(&mut self, dest: &Path) -> Result<()> {
+        match self {
+            FileSource::Path(src) => {
+                fs::copy(&src, dest).with_context(|| {
+                    format!("Failed to copy '{}' to '{}'", src.display(), dest.display())
+                })?;
+            }
+            FileSource::Member(archive, member) => {
+                archive.extract(member, dest)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 把替换过变量的内容写到 `dest`；磁盘上的来源同时复制其权限
+    fn write_to(&self, dest: &Path, content: &str) -> Result<()> {
+        fs::write(dest, content)
+            .with_context(|| format!("Failed to write file '{}'", dest.display()))?;
+        if let FileSource::Path(src) = self {
+            let perms = fs::metadata(src)
+                .with_context(|| format!("Failed to read '{}'", src.display()))?
+                .permissions();
+            fs::set_permissions(dest, perms)
+                .with_context(|| format!("Failed to set permissions for '{}'", dest.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// 复制单个文件；内容相同的目标不重写。`substitute` 时文本文件替换变量后写入，
+/// 按 `include` 选出的文件在 dry-run 时逐个列出（连同大小）
+fn copy_file(
+    mut src: FileSource<'_>,
+    dest: &Path,
+    size: u64,
+    rules: &CopyRules,
+    own: &Ownership,
+    opts: &GenerateOptions,
+    stats: &mut Stats,
+) -> Result<()> {
+    if skip_missing(dest, opts, stats) {
+        return Ok(());
+    }
+    // 含 NUL 字节或不是 UTF-8 的文件视为二进制，原样复制
+    let rendered = match rules.vars {
+        Some(vars) => match String::from_utf8(src.read()?) {
+            Ok(text) if !text.contains('\0') => Some(render_vars(&text, vars, dest)?),
+            _ => None,
+        },
+        None => None,
+    };
+    let size = rendered.as_ref().map_or(size, |text| text.len() as u64);
+    let unchanged = dest.is_file()
+        && fs::metadata(dest).is_ok_and(|m| m.len() == size)
+        && match &rendered {
+            Some(text) => fs::read(dest).is_ok_and(|data| data == text.as_bytes()),
+            None => matches!((src.read(), fs::read(dest)), (Ok(a), Ok(b)) if a == b),
+        };
+    let note = hidden_note(dest, opts);
+    if opts.dry_run {
+        if unchanged {
+            if opts.verbose {
+                verbose!("[Dry-Run] Unchanged file: {}{}", dest.display(), note);
+            }
+            stats.unchanged += 1;
+        } else {
+            if opts.verbose || rules.selected.is_some() {
+                verbose!(
+                    "[Dry-Run] Copy file: {}{} ({} bytes) from {}",
+                    dest.display(),
+                    note,
+                    size,
+                    src.display()
+                );
+            }
+            stats.files += 1;
+        }
+        return apply_ownership(dest, own, opts);
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).ok();
+    }
+    if unchanged {
+        if opts.verbose {
+            verbose!("Unchanged file: {}{}", dest.display(), note);
+        }
+        stats.unchanged += 1;
+    } else {
+        if opts.verbose {
+            verbose!(
+                "Copy file: {}{} ({} bytes) from {}",
+                dest.display(),
+                note,
+                size,
+                src.display()
+            );
+        }
+        match &rendered {
+            Some(text) => src.write_to(dest, text)?,
+            None => src.copy_to(dest)?,
+        }
+        sync_file(dest, opts)?;
+        if opts.verify_writes {
+            match (&rendered, &mut src) {
+                (Some(text), _) => {
+                    verify_write(dest, 0, text.as_bytes(), size, opts, stats)?;
+                }
+                (None, FileSource::Path(from)) => {
+                    let expected = fs::File::open(&from)
+                        .with_context(|| format!("Failed to open '{}'", from.display()))?;
+                    verify_write(dest, 0, expected, size, opts, stats)?;
+                }
+                (None, FileSource::Member(..)) => {
+                    let expected = src.read()?;
+                    verify_write(dest, 0, expected.as_slice(), size, opts, stats)?;
+                }
+            }
+        }
+        stats.files += 1;
+        stats.timings.files.bytes += size;
+    }
+    apply_ownership(dest, own, opts)?;
+    apply_hidden(dest, opts)
+}
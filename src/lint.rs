@@ -0,0 +1,268 @@
+//! 描述文件的建议性检查（`treegen lint`）：在解析后的 [`Node`] 树上运行，
+//! 因此对所有输入格式都适用。与解析/生成时的硬性错误不同，这里的结果只是警告，
+//! 调用方可以把其中一部分视为错误（`--deny`）。
+//!
+//! ```
+//! use treegen::lint::{lint, Lint};
+//!
+//! let spec = treegen::parse_yaml("src:\n  Main.rs: ''\n  main.rs: ''\ndocs: {}\n")?;
+//! let warnings = lint(&spec.root, &Default::default());
+//! let found: Vec<Lint> = warnings.iter().map(|w| w.lint).collect();
+//! assert_eq!(found, [Lint::EmptyDir, Lint::CaseConflict]);
+//! assert_eq!(warnings[1].path, std::path::Path::new("src/main.rs"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::{referenced_vars, Node, NodeType, WalkControl};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// 超过这个嵌套深度时给出 deep-nesting 警告（远低于生成时的硬性上限）
+pub const DEEP_NESTING: usize = 12;
+
+/// 内容至少这么长才参与 duplicate-content 检查，避免 `{}`、空行之类的噪音
+const DUPLICATE_MIN_LEN: usize = 64;
+
+/// Windows 上的保留设备名（不区分大小写，也不能带扩展名）
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 检查项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lint {
+    /// 没有任何子节点的目录
+    EmptyDir,
+    /// 同一目录下仅大小写不同的名称（在大小写不敏感的文件系统上会冲突）
+    CaseConflict,
+    /// 嵌套层数超过 [`DEEP_NESTING`]
+    DeepNesting,
+    /// 文件内容中有行尾空白
+    TrailingWhitespace,
+    /// 文件内容的缩进混用了制表符和空格
+    MixedIndent,
+    /// 在 Windows 上无法创建的名称
+    WindowsName,
+    /// 与另一个文件内容完全相同
+    DuplicateContent,
+    /// 提供了但树中没有引用的模板变量
+    UnusedVar,
+}
+
+impl Lint {
+    /// 所有检查项，按报告顺序
+    pub const ALL: &'static [Lint] = &[
+        Lint::EmptyDir,
+        Lint::CaseConflict,
+        Lint::DeepNesting,
+        Lint::TrailingWhitespace,
+        Lint::MixedIndent,
+        Lint::WindowsName,
+        Lint::DuplicateContent,
+        Lint::UnusedVar,
+    ];
+
+    /// 命令行中使用的名称（如 `empty-dir`）
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::EmptyDir => "empty-dir",
+            Lint::CaseConflict => "case-conflict",
+            Lint::DeepNesting => "deep-nesting",
+            Lint::TrailingWhitespace => "trailing-whitespace",
+            Lint::MixedIndent => "mixed-indent",
+            Lint::WindowsName => "windows-name",
+            Lint::DuplicateContent => "duplicate-content",
+            Lint::UnusedVar => "unused-var",
+        }
+    }
+
+    /// 按名称查找检查项
+    pub fn from_name(name: &str) -> Option<Self> {
+        Lint::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// 一条警告：检查项、节点路径（unused-var 时为空）与说明
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub lint: Lint,
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// 对整棵树运行所有检查；`vars` 为调用方提供的模板变量，用于 unused-var。
+/// 结果按树的前序遍历顺序排列，unused-var 在最后
+pub fn lint(root: &Node, vars: &BTreeMap<String, String>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut warn = |lint, path: &Path, message: String| {
+        warnings.push(Warning {
+            lint,
+            path: path.to_path_buf(),
+            message,
+        })
+    };
+    // 内容 -> 第一个拥有该内容的文件
+    let mut contents: HashMap<String, PathBuf> = HashMap::new();
+
+    root.walk(&mut |path, node| {
+        // 未命名的根节点只检查其子节点的名称冲突
+        let is_root = path.as_os_str().is_empty();
+        if let Some(problem) = windows_name_problem(node.name.trim_end_matches('/')) {
+            warn(Lint::WindowsName, path, problem);
+        }
+        match node.node_type {
+            NodeType::Dir => {
+                if node.children.is_empty() && !is_root {
+                    warn(Lint::EmptyDir, path, "directory is empty".to_string());
+                }
+            }
+            // 复制/链接的内容在生成时才读取，不参与内容检查
+            NodeType::Copy | NodeType::Hardlink => {}
+            NodeType::File => {
+                let content = node.content.as_deref().unwrap_or("");
+                if let Some(lines) = trailing_whitespace_lines(content) {
+                    warn(
+                        Lint::TrailingWhitespace,
+                        path,
+                        format!("trailing whitespace on {}", lines),
+                    );
+                }
+                if let Some(problem) = mixed_indent(content) {
+                    warn(Lint::MixedIndent, path, problem);
+                }
+                if content.len() >= DUPLICATE_MIN_LEN {
+                    match contents.get(content) {
+                        Some(first) => warn(
+                            Lint::DuplicateContent,
+                            path,
+                            format!("same content as '{}'", first.display()),
+                        ),
+                        None => {
+                            contents.insert(content.to_string(), path.to_path_buf());
+                        }
+                    }
+                }
+            }
+        }
+
+        // 子节点之间的冲突在访问父节点时报告
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for child in &node.children {
+            let child_name = child.name.trim_end_matches('/');
+            match seen.get(&child_name.to_lowercase()) {
+                Some(other) if *other != child_name => warn(
+                    Lint::CaseConflict,
+                    &path.join(child_name),
+                    format!("differs from '{}' only by case", other),
+                ),
+                Some(_) => {}
+                None => {
+                    seen.insert(child_name.to_lowercase(), child_name);
+                }
+            }
+        }
+
+        // 每条过深的分支只报告一次
+        if path.components().count() > DEEP_NESTING {
+            warn(
+                Lint::DeepNesting,
+                path,
+                format!("nested deeper than {} levels", DEEP_NESTING),
+            );
+            return WalkControl::SkipChildren;
+        }
+        WalkControl::Continue
+    });
+
+    let used = referenced_vars(root);
+    for name in vars.keys().filter(|name| !used.contains(*name)) {
+        warnings.push(Warning {
+            lint: Lint::UnusedVar,
+            path: PathBuf::new(),
+            message: format!("variable '{}' is not referenced by the spec", name),
+        });
+    }
+    warnings
+}
+
+/// 名称在 Windows 上不可用的原因
+fn windows_name_problem(name: &str) -> Option<String> {
+    if let Some(c) = name
+        .chars()
+        .find(|c| matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*') || c.is_control())
+    {
+        return Some(format!(
+            "name contains '{}', which Windows does not allow",
+            c.escape_default()
+        ));
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Some("name ends with a dot or space, which Windows strips".to_string());
+    }
+    let stem = name.split('.').next().unwrap_or(name).trim_end();
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        return Some(format!("'{}' is a reserved device name on Windows", stem));
+    }
+    None
+}
+
+/// 有行尾空白的行号，如 "line 3" 或 "lines 3, 7, 9 and 2 more"
+fn trailing_whitespace_lines(content: &str) -> Option<String> {
+    let lines: Vec<usize> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.ends_with([' ', '\t']))
+        .map(|(i, _)| i + 1)
+        .collect();
+    describe_lines(&lines)
+}
+
+/// 缩进混用制表符和空格：同一行缩进中两者都有，或有的行用制表符、有的行用空格
+fn mixed_indent(content: &str) -> Option<String> {
+    let mut tabs = None;
+    let mut spaces = None;
+    for (i, line) in content.lines().enumerate() {
+        let indent: &str = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+        let has_tab = indent.contains('\t');
+        let has_space = indent.contains(' ');
+        if has_tab && has_space {
+            return Some(format!("line {} indents with both tabs and spaces", i + 1));
+        }
+        if has_tab {
+            tabs.get_or_insert(i + 1);
+        } else if has_space {
+            spaces.get_or_insert(i + 1);
+        }
+    }
+    match (tabs, spaces) {
+        (Some(tab), Some(space)) => Some(format!(
+            "indents with tabs (line {}) and spaces (line {})",
+            tab, space
+        )),
+        _ => None,
+    }
+}
+
+/// 行号列表的简短描述，最多列出前三个
+fn describe_lines(lines: &[usize]) -> Option<String> {
+    let shown: Vec<String> = lines.iter().take(3).map(|n| n.to_string()).collect();
+    match lines.len() {
+        0 => None,
+        1 => Some(format!("line {}", lines[0])),
+        n if n <= 3 => Some(format!("lines {}", shown.join(", "))),
+        n => Some(format!("lines {} and {} more", shown.join(", "), n - 3)),
+    }
+}
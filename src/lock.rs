@@ -0,0 +1,216 @@
+//! 输出目录的建议锁：写入期间持有输出目录中的 [`LOCK_FILE`]，同时往同一目录生成的其他
+//! treegen 进程（或线程）等待它释放。锁文件中记录持有者的 PID 与主机名，等待超时的错误
+//! 中会给出；同一主机上 PID 已不存在的持有者视为陈旧的锁，删掉锁文件后重新加锁。
+//!
+//! ```
+//! use std::time::Duration;
+//! use treegen::lock::{OutputLock, LOCK_FILE};
+//!
+//! let out = tempfile::tempdir()?;
+//! let lock = OutputLock::acquire(out.path(), Duration::from_secs(5))?;
+//! let err = OutputLock::acquire(out.path(), Duration::ZERO).unwrap_err();
+//! assert!(err.to_string().contains(&format!("PID {}", std::process::id())));
+//! drop(lock);
+//! assert!(!out.path().join(LOCK_FILE).exists());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{bail, Context, Result};
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 输出目录中锁文件的名称
+pub const LOCK_FILE: &str = ".treegen.lock";
+
+/// 等待锁时两次尝试之间的间隔
+const RETRY: Duration = Duration::from_millis(100);
+
+/// 锁文件中记录的持有者
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Holder {
+    pub pid: u32,
+    pub host: String,
+}
+
+impl Holder {
+    fn current() -> Self {
+        Holder {
+            pid: std::process::id(),
+            host: host_name(),
+        }
+    }
+
+    /// `pid: 123` 与 `host: name` 两行，缺少任一行时不是有效的记录
+    fn parse(text: &str) -> Option<Self> {
+        let field = |key: &str| {
+            text.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+                .map(str::trim)
+        };
+        Some(Holder {
+            pid: field("pid")?.parse().ok()?,
+            host: field("host")?.to_string(),
+        })
+    }
+
+    /// 同一主机上的进程已经退出（不同主机上的持有者无法判断，总是视为仍在运行）
+    fn is_stale(&self) -> bool {
+        self.host == host_name() && !process_alive(self.pid)
+    }
+}
+
+impl fmt::Display for Holder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PID {} on {}", self.pid, self.host)
+    }
+}
+
+/// 持有中的输出目录锁；drop 时删除锁文件并释放锁
+#[derive(Debug)]
+pub struct OutputLock {
+    file: File,
+    path: PathBuf,
+    stolen: Option<Holder>,
+}
+
+impl OutputLock {
+    /// 锁住已存在的目录 `out`，被占用时每隔 100ms 重试，最多等待 `timeout`（为零时只尝试一次）
+    pub fn acquire(out: &Path, timeout: Duration) -> Result<Self> {
+        let path = out.join(LOCK_FILE);
+        let deadline = Instant::now() + timeout;
+        let mut stolen = None;
+        loop {
+            let mut file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .with_context(|| format!("Failed to open lock file '{}'", path.display()))?;
+            match file.try_lock() {
+                Ok(()) => {
+                    // 等待期间持有者可能已删除锁文件（释放时）或别人替换了它（抢占陈旧的锁），
+                    // 锁住的必须仍是该路径上的文件，否则重新打开
+                    if !same_file(&file, &path) {
+                        continue;
+                    }
+                    let holder = Holder::current();
+                    let record = format!("pid: {}\nhost: {}\n", holder.pid, holder.host);
+                    file.set_len(0)
+                        .and_then(|_| file.write_all(record.as_bytes()))
+                        .and_then(|_| file.flush())
+                        .with_context(|| {
+                            format!("Failed to write lock file '{}'", path.display())
+                        })?;
+                    return Ok(OutputLock { file, path, stolen });
+                }
+                Err(fs::TryLockError::WouldBlock) => {}
+                Err(fs::TryLockError::Error(e)) => {
+                    return Err(e).with_context(|| format!("Failed to lock '{}'", path.display()));
+                }
+            }
+            let mut text = String::new();
+            let holder = file
+                .rewind()
+                .and_then(|_| file.read_to_string(&mut text))
+                .ok()
+                .and_then(|_| Holder::parse(&text));
+            if let Some(stale) = holder.as_ref().filter(|holder| holder.is_stale()) {
+                // 锁仍被持有，但记录的进程已经退出（如锁被它留下的子进程继承）：换一个新的锁文件
+                tracing::warn!(path = %path.display(), holder = %stale, "stealing stale lock");
+                match fs::remove_file(&path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(e).with_context(|| {
+                            format!("Failed to remove stale lock file '{}'", path.display())
+                        });
+                    }
+                    _ => {}
+                }
+                stolen = Some(stale.clone());
+                continue;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                // 持有者刚加锁、还没写入记录时读不到
+                let holder =
+                    holder.map_or_else(|| "another process".to_string(), |h| h.to_string());
+                bail!(
+                    "Output directory '{}' is locked by {} (lock file '{}'); waited {}s (see --lock-timeout)",
+                    out.display(),
+                    holder,
+                    path.display(),
+                    timeout.as_secs_f64()
+                );
+            }
+            thread::sleep(RETRY.min(deadline - now));
+        }
+    }
+
+    /// 锁文件的路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 加锁前删掉的陈旧锁的持有者
+    pub fn stolen(&self) -> Option<&Holder> {
+        self.stolen.as_ref()
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        // 先删除再释放，等待者加锁后会发现路径上已不是这个文件，重新创建
+        let _ = fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(unix)]
+fn same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Windows 上标准库取不到文件编号，只检查路径仍然存在
+#[cfg(not(unix))]
+fn same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+#[cfg(unix)]
+fn host_name() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+#[cfg(not(unix))]
+fn host_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "localhost".to_string())
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    use nix::{errno::Errno, sys::signal::kill, unistd::Pid};
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    // 信号 0 只检查进程是否存在；没有权限发送信号（EPERM）说明进程存在
+    !matches!(kill(Pid::from_raw(pid), None), Err(Errno::ESRCH))
+}
+
+/// 其他平台上无法判断，总是视为仍在运行（不会抢占）
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
@@ -1,10 +1,56 @@
 use anyhow::{bail, Context, Result};
-use clap::Parser;
-use regex::Regex;
-use serde::Deserialize;
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
-use std::{collections::BTreeMap, env, fs, path::PathBuf};
+use clap::{
+    builder::{BoolishValueParser, PossibleValuesParser, TypedValueParser},
+    parser::ValueSource,
+    ArgAction, ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+    ValueHint,
+};
+use clap_complete::Shell;
+use globset::{Glob, GlobSetBuilder};
+use notify::{RecursiveMode, Watcher};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+mod messages;
+mod report;
+
+use messages::{CleanRefusal, Lang, Msg};
+use treegen::{
+    diff::{Change, Difference, Status as DiffStatus},
+    failure::FailureReport,
+    lint::Lint,
+    lock::{OutputLock, LOCK_FILE},
+    merge_inputs, parse_file, parse_specs_with, parse_yaml,
+    remote::{FetchOptions, Remote},
+    Aborted, CancelToken, Compression, Detection, ExecuteTimings, Format, GenerateOptions,
+    IfExists, Input, Interrupted, MdStyle, Node, NodeType, Normalization, OnDuplicate, Outcome,
+    Ownership, ParseOptions, Rule, SortOrder, Spec, Stats, Timing, VarDecl, WalkControl,
+    SPEC_EXTENSIONS,
+};
+
+/// --list 时标准输出只留给路径列表，日志改写到标准错误
+static LOG_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// 打印一行日志：通常到标准输出，--list 时到标准错误
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if LOG_TO_STDERR.load(Ordering::Relaxed) {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
 
 /// CLI 参数定义
 #[derive(Parser, Debug)]
@@ -15,475 +61,3868 @@ use std::{collections::BTreeMap, env, fs, path::PathBuf};
     about = "Generate file/folder trees from Markdown/YAML/JSON/TOML/JSON5 specifications",
     long_about = None
 )]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Args {
-    /// 要解析的一个或多个输入文件（支持 .md/.yaml/.yml/.json/.toml/.json5）
-    #[arg(required = true)]
-    input: Vec<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// 要解析的一个或多个输入文件（支持 .md/.yaml/.yml/.json/.toml/.json5/.paths/.list）；可写成 FORMAT:PATH 为单个输入指定格式
+    ///
+    /// 写成 SPEC=OUTDIR 时该输入单独生成到 OUTDIR，未映射的输入合并后生成到 --out
+    /// 输入 `-` 表示从标准输入读取，此时只能有这一个输入
+    /// 不存在的路径若含 * ? [ 则按 glob 展开（如 'specs/**/*.yaml'），结果按路径排序
+    #[arg(
+        required_unless_present_any = ["clipboard", "recursive"],
+        value_hint = ValueHint::FilePath,
+        value_parser = parse_mapped_input
+    )]
+    input: Vec<MappedInput>,
+
+    /// 递归查找目录下所有支持扩展名的描述文件作为输入（按路径排序，可重复）
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+    recursive: Vec<PathBuf>,
+
+    /// 允许一个 SPEC=OUTDIR 的输出目录位于另一个输出目录之内
+    #[arg(long)]
+    allow_nested_out: bool,
+
+    /// 每个输入生成到 --out（默认当前目录）下以其文件名（去掉扩展名）命名的目录中，
+    /// 如 blog-engine.yaml 生成到 ./blog-engine/；不能与 SPEC=OUTDIR 同时使用
+    #[arg(long, conflicts_with = "clipboard")]
+    root_from_spec: bool,
+
+    /// 从系统剪贴板读取描述内容（代替输入文件），格式由 --format 指定或根据内容推断
+    #[arg(long, conflicts_with_all = ["input", "recursive", "watch"])]
+    clipboard: bool,
+
+    /// 结构化日志（tracing）的级别，写到标准错误：每个输入的解析、每个顶层目录的生成、
+    /// 每个路径的结果（info）等；不指定时按 RUST_LOG（如 treegen=debug），两者都没有时不输出
+    #[arg(long, value_enum, value_name = "LEVEL", global = true)]
+    log_level: Option<LogLevel>,
+
+    /// 结构化日志的格式：text 为可读的文本，json 每个事件一行 JSON
+    #[arg(long, value_enum, default_value = "text", global = true)]
+    log_format: LogFormat,
+
+    /// 结果提示、确认提示与常见错误的语言；不指定时按 LC_ALL/LC_MESSAGES/LANG（zh* 为中文），否则为英文
+    #[arg(long, value_enum, global = true)]
+    lang: Option<Lang>,
+
+    /// 多个输入（或同一输入内）重复定义同一路径时的处理：error 报错，first/last 保留先/后出现的，
+    /// rename 都保留并给后出现的加 _1、_2 后缀；同名目录总是合并
+    #[arg(long, value_enum, default_value = "last")]
+    on_duplicate: DuplicateKey,
+
+    /// 来自 git 仓库的输入（gh:OWNER/REPO//PATH 或 git+URL//PATH）使用的分支或标签，默认是远端的默认分支
+    #[arg(long = "ref", value_name = "REF", value_parser = parse_git_ref)]
+    git_ref: Option<String>,
+
+    /// 不访问网络：git 仓库中的描述只使用缓存中已有的副本
+    #[arg(long)]
+    offline: bool,
+
+    /// 缓存目录（默认 $XDG_CACHE_HOME/treegen 或 ~/.cache/treegen），git 仓库检出在其下的 git/ 中
+    #[arg(long, value_name = "DIR", env = "TREEGEN_CACHE_DIR", value_hint = ValueHint::DirPath)]
+    cache_dir: Option<PathBuf>,
+
+    /// 所有输入文件的格式，覆盖扩展名与内容推断（FORMAT:PATH 形式的输入除外）
+    #[arg(long, value_enum)]
+    format: Option<SpecFormat>,
+
+    /// 标准输入（`-`）中的描述经过压缩，先按该格式解压；文件按 .gz/.zst 扩展名自动解压
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    compressed: Option<CompressedFormat>,
+
+    /// 转换前按 JSON Schema（见 `treegen schema`）校验 YAML/JSON/TOML/JSON5 输入，报告每处违反的 JSON Pointer
+    #[arg(long)]
+    schema_validate: bool,
+
+    /// 不展开名称中的花括号（{a,b}、{01..16}），用于真实文件名含花括号的描述
+    #[arg(long)]
+    no_brace_expansion: bool,
+
+    /// 名称的 Unicode 规范化形式：nfc（默认）、nfd 或 none；同一名称的不同写法规范化后视为重复
+    #[arg(long, value_enum, value_name = "FORM", default_value_t = NormalizeForm::Nfc)]
+    normalize: NormalizeForm,
+
+    /// Markdown 树中只有省略号的行（...、… (20 more files)）也生成为文件，而不是跳过
+    #[arg(long)]
+    keep_ellipsis: bool,
+
+    /// Markdown 树中整行注释的前缀（可重复，指定后替换默认的 # 与 //；传入空字符串表示没有注释）
+    #[arg(long, value_name = "PREFIX", default_values = treegen::DEFAULT_COMMENT_PREFIXES)]
+    comment_prefix: Vec<String>,
+
+    /// Markdown 树中下一行缩进更深的文件（漏写了结尾的 /）当作目录，而不是报错
+    #[arg(long)]
+    infer_dirs: bool,
+
+    /// Markdown 描述的写法：auto（默认，有 ## 标题与围栏代码块时按分节文档）、tree 或 sections
+    /// （每个二级标题是以其 slug 命名的子目录，其下代码块中的树生成在该目录中）
+    #[arg(long, value_enum, value_name = "STYLE", default_value_t = MdStyleArg::Auto)]
+    md_style: MdStyleArg,
+
+    /// 分节的 Markdown 描述中只生成该节（标题或其 slug，可重复）
+    #[arg(long, value_name = "NAME")]
+    section: Vec<String>,
+
+    #[command(flatten)]
+    gen: GenerateArgs,
+
+    #[command(flatten)]
+    list: ListArgs,
+
+    /// 生成后持续监视输入文件，变化时重新生成（Ctrl-C 退出）
+    #[arg(long)]
+    watch: bool,
+}
+
+/// 生成文件树的通用参数（顶层命令与 `treegen new` 共用）
+#[derive(clap::Args, Debug)]
+#[command(group(ArgGroup::new("verify").args(["check", "fix"])))]
+#[command(group(ArgGroup::new("filtered").args(["check", "fix", "list"]).multiple(true)))]
+struct GenerateArgs {
+    /// 输出根目录（可选，默认是当前工作目录）；可以引用模板变量，如 services/{{name}}
+    #[arg(short, long, env = "TREEGEN_OUT")]
+    out: Option<PathBuf>,
+
+    /// 仅预览将要创建的文件/目录，不写入磁盘
+    #[arg(long, env = "TREEGEN_DRY_RUN", value_parser = BoolishValueParser::new())]
+    dry_run: bool,
+
+    /// 配合 --dry-run：把生成计划（每个路径的动作、内容来源、权限与已存在时的策略）以 JSON 打印到标准输出
+    #[arg(long, requires = "dry_run")]
+    json: bool,
+
+    /// 打印详细日志（每个文件/目录创建情况）
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// 如果输出目录已存在同名路径，先删除再创建（谨慎使用）；需要 --yes 或在终端中确认，
+    /// 拒绝删除根目录、用户主目录、当前目录与 git 仓库
+    #[arg(long)]
+    clean: bool,
+
+    /// 配合 --clean：允许删除当前工作目录（或其上级）
+    #[arg(long, requires = "clean")]
+    allow_cwd: bool,
+
+    /// 配合 --clean：允许删除含有 .git 的目录
+    #[arg(long, requires = "clean")]
+    allow_git: bool,
+
+    /// 配合 --clean：不询问，直接删除输出目录
+    #[arg(long, short = 'y', requires = "clean")]
+    yes: bool,
+
+    /// 新建文件的权限（八进制，如 0o644，仅类 Unix 平台生效）
+    #[arg(long, default_value = "0o644", env = "TREEGEN_MODE")]
+    mode: String,
+
+    /// treegen 为输出目录新建的各级目录的权限（八进制，如 0o750，仅类 Unix 平台）；已存在的目录不改
+    #[arg(long, value_name = "MODE", value_parser = parse_out_mode)]
+    out_mode: Option<u32>,
+
+    /// 以这些扩展名结尾的文件加上可执行位（逗号分隔，如 sh,py,pl；仅类 Unix 平台），节点自己的 mode 优先
+    #[arg(long, value_name = "EXT,...", value_delimiter = ',')]
+    exec_ext: Vec<String>,
+
+    /// 内容以 #! 开头的文件加上可执行位（仅类 Unix 平台），节点自己的 mode 优先
+    #[arg(long)]
+    auto_exec: bool,
+
+    /// 不创建没有内容的文件（如 Markdown 树中的文件），只生成目录与有内容的文件；
+    /// 配合 --check/--fix 时这些文件也不检查。显式的空内容 "" 仍会创建
+    #[arg(long)]
+    no_touch: bool,
+
+    /// 已存在的文件原样保留，不重写内容也不改权限
+    #[arg(long)]
+    skip_existing: bool,
+
+    /// 复制目录时不默认忽略 .git/.hg/.svn/.bzr；来源中的 .treegenignore 仍然生效
+    #[arg(long)]
+    no_default_ignores: bool,
+
+    /// 每个文件写入后重新读取并与要写入的内容比较，不一致时报错（指出第一个不同的字节）；
+    /// 回读时算出的哈希直接用于 --checksums
+    #[arg(long)]
+    verify_writes: bool,
+
+    /// 只更新输出目录中已存在的文件，不创建任何新的文件、目录或链接（不存在的路径记为 missing）
+    #[arg(long, conflicts_with = "clean")]
+    content_only: bool,
+
+    /// 内容未变化（或因 --skip-existing 保留）的已有文件也按 --mode/节点 mode 重设权限
+    #[arg(long)]
+    fix_modes: bool,
+
+    /// 模板变量，替换名称与内容中的 {{NAME}}（可重复；环境变量 TREEGEN_VAR_<NAME> 同样生效，NAME 转为小写）
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+
+    /// 从 YAML/JSON/TOML 文件（按扩展名，默认 YAML）读取模板变量：名称到字符串、数字或布尔值的映射。
+    /// 可重复，后面的文件覆盖前面的；优先级低于 TREEGEN_VAR_<NAME> 与 --var
+    #[arg(long = "var-file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    var_files: Vec<PathBuf>,
+
+    /// 为没有内容的文件（如 Markdown 树中的文件）按文件名/扩展名填入默认内容片段
+    #[arg(long)]
+    default_content: bool,
+
+    /// 去掉文件内容每行末尾的空格与制表符，并把两个以上连续的空行压成两个（在变量替换之后）；
+    /// 复制的文件、二进制内容、结构化内容与 --stdin-content 不处理。单个文件可以写 trim: true
+    #[arg(long)]
+    trim_trailing_whitespace: bool,
+
+    /// 读取标准输入直到 EOF，作为树中该路径文件的内容（如 `gen-api | treegen spec.yaml --stdin-content src/api.rs`）：
+    /// 描述中没有的文件会被创建，已写了内容的文件改用标准输入并给出警告；内容按字节原样写入，不做变量替换
+    #[arg(long, value_name = "PATH")]
+    stdin_content: Option<PathBuf>,
+
+    /// 生成成功后在输出目录中执行的命令（可重复，在描述文件的 $hooks 之后执行）
+    #[arg(long, value_name = "CMD")]
+    run: Vec<String>,
+
+    /// 不执行任何钩子（包括描述文件中的 $hooks），用于不受信任的描述文件
+    #[arg(long)]
+    no_hooks: bool,
+
+    /// 钩子失败时只打印警告并继续
+    #[arg(long)]
+    hooks_keep_going: bool,
+
+    /// 执行从 git 仓库获取的描述（gh:、git+URL）中的 $hooks；默认不执行，在终端中会先询问
+    #[arg(long)]
+    allow_remote_hooks: bool,
+
+    /// 允许的最大嵌套深度，超出时报错而不是生成
+    #[arg(long, value_name = "N", default_value_t = treegen::DEFAULT_MAX_DEPTH)]
+    max_tree_depth: usize,
+
+    /// 估计的写入量（文件内容、占位文件的 size 与复制来源）超过它时拒绝生成，如 1GiB；
+    /// dry-run 同样检查
+    #[arg(long, value_name = "SIZE", value_parser = treegen::parse_size)]
+    max_bytes: Option<u64>,
+
+    /// fill: random 的占位文件与 @uuid、@random 内容使用的随机种子，相同的种子生成相同的内容
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// 名称每段允许的最大字节数（默认按输出目录所在的文件系统，查询不到时为 255），用于特殊的文件系统
+    #[arg(long, value_name = "N")]
+    max_name_bytes: Option<usize>,
+
+    /// 从不询问输入（用于 CI）：没有提供、也没有默认值的模板变量直接报错
+    #[arg(long)]
+    no_input: bool,
+
+    /// 可以降级处理的失败直接报错（如硬链接失败时不改为复制）
+    #[arg(long)]
+    strict: bool,
+
+    /// 设置所有生成路径的属主/属组（USER、:GROUP 或 USER:GROUP，仅类 Unix 平台）
+    #[arg(long, value_name = "USER:GROUP", value_parser = parse_chown)]
+    chown: Option<Ownership>,
+
+    /// 每个写入的文件与涉及的目录都 fsync，返回前确保已落盘（崩溃/断电安全）。
+    /// 代价取决于磁盘：生成 1000 个小文件在 SSD 上约从 0.07s 增加到 0.2s（慢 2~4 倍），机械硬盘上更明显
+    #[arg(long)]
+    durable: bool,
+
+    /// Windows 上为名称以 . 开头的文件和目录设置隐藏属性（其他平台上不起作用）
+    #[arg(long)]
+    windows_hidden_dotfiles: bool,
+
+    /// 生成前先做与 --dry-run 相同的预检：输出目录可写、mode 有效、复制来源存在、
+    /// 已有路径类型相符、磁盘空间足够；所有问题一次列出，有问题时不写入任何东西
+    #[arg(long)]
+    preflight: bool,
+
+    /// 生成后打印最终的目录树，并标注每个节点的结果（created/overwritten/unchanged）
+    #[arg(long)]
+    print_tree: bool,
+
+    /// 只检查输出目录是否与描述一致（缺失、内容不符），不写入任何内容；不一致时以非零状态退出
+    #[arg(long, conflicts_with = "clean")]
+    check: bool,
+
+    /// 修正与描述不一致的路径后生成（类型不符的路径先删除再创建）
+    #[arg(long)]
+    fix: bool,
+
+    /// 配合 --check/--fix：输出目录中描述之外的路径也视为不一致（--fix 会删除它们）
+    #[arg(long, requires = "verify")]
+    sync: bool,
+
+    /// 配合 --check/--fix：忽略匹配的路径（相对输出目录的 glob，如 .git、target，可重复）；
+    /// 配合 --list：不列出匹配的路径及其下的路径
+    #[arg(long, value_name = "GLOB", requires = "filtered")]
+    exclude: Vec<String>,
+
+    /// 生成后写出所有生成文件的 SHA-256 清单（sha256sum 格式，路径相对输出目录）
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    checksums: Option<PathBuf>,
+
+    /// 生成后写出 JSON 格式的结果摘要：各类数量与没能生成的路径（按失败类型归类）
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    summary_json: Option<PathBuf>,
+
+    /// 写出 JSON 格式的运行报告供审计留档：调用参数、描述文件的哈希、模板变量、每个路径的动作与原因、
+    /// 写入内容的哈希、各阶段耗时与最终状态；运行中途失败时也写出已记录的部分
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    report: Option<PathBuf>,
+
+    /// 配合 --report：隐去这个模板变量的值（包括参数中的 --var NAME=VALUE，可重复）
+    #[arg(long, value_name = "NAME", requires = "report")]
+    redact_var: Vec<String>,
+
+    /// 生成后打印各阶段（读取解析、合并、检查、计划、创建目录、写文件、设置权限、钩子）的耗时与处理量
+    #[arg(long)]
+    timings: bool,
+
+    /// 某个路径生成失败时继续生成其余路径，最后按失败类型分组列出所有失败的路径
+    #[arg(long)]
+    keep_going: bool,
+
+    /// 生成被 Ctrl-C 中断时删除本次新建的文件和目录（重写过的已有文件无法恢复）
+    #[arg(long)]
+    clean_on_interrupt: bool,
+
+    /// 输出目录被另一个 treegen 锁住时最多等待的秒数（0 表示不等待）
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    lock_timeout: u64,
+
+    /// 把所有文件放到输出目录的同一层，路径编码进文件名（src/main.rs -> src__main.rs）；空目录不生成
+    #[arg(long)]
+    flatten: bool,
+
+    /// 配合 --flatten：路径各段之间的分隔符
+    #[arg(long, value_name = "SEP", default_value = treegen::DEFAULT_FLATTEN_SEP, requires = "flatten")]
+    flatten_sep: String,
+
+    /// 子节点的创建顺序：spec 为描述中的顺序，name 为按名称排序（日志与结果完全可复现）
+    #[arg(long, value_enum, default_value = "spec")]
+    sort: SortKey,
+
+    /// 只生成没有标签或带有该 profile 标签的节点（可重复，取并集；`treegen profiles` 列出可用的 profile）
+    #[arg(long, value_name = "NAME")]
+    profile: Vec<String>,
+}
+
+/// 只列出计划生成的路径（顶层命令）
+#[derive(clap::Args, Debug)]
+struct ListArgs {
+    /// 不生成，只按创建顺序把将要生成的相对路径（目录以 / 结尾）逐行打印到标准输出，日志改写到标准错误；
+    /// 值选择列出的路径：all（默认）、files 或 dirs
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KIND",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "all",
+        conflicts_with_all = ["check", "fix", "clean"]
+    )]
+    list: Option<ListKind>,
+
+    /// 配合 --list：路径之间用 NUL 分隔（用于 xargs -0）
+    #[arg(long, requires = "list")]
+    print0: bool,
+}
+
+/// 子命令
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 写出一个带注释的示例描述文件，作为编写 spec 的起点
+    Init(InitArgs),
+
+    /// 打印指定 shell 的补全脚本到标准输出
+    Completions {
+        /// 目标 shell
+        shell: Shell,
+    },
+
+    /// 查看用户配置文件（~/.config/treegen/config.toml 或 $TREEGEN_CONFIG）
+    Config(ConfigArgs),
+
+    /// 用模板库中的模板生成文件树
+    New(Box<NewArgs>),
+
+    /// 管理模板库（list/add/remove）
+    Template(TemplateArgs),
+
+    /// 检查描述文件中可能的问题（空目录、大小写冲突、行尾空白等），只给出警告
+    Lint(LintArgs),
+
+    /// 打印描述文件结构的 JSON Schema（用于编辑器补全与 CI 校验）
+    Schema,
+
+    /// 比较两个描述文件（格式可以不同）的结构与内容，有差异时以非零状态退出
+    Diff(DiffArgs),
+
+    /// 按 --checksums 写出的清单重新计算输出目录中文件的哈希，报告不符、缺失与多出的文件
+    Verify(VerifyArgs),
+
+    /// 列出描述文件在 $vars 中声明的模板变量（类型、默认值与说明）
+    Vars(VarsArgs),
+
+    /// 列出描述文件中出现的 profile（节点的 tags）及各自的节点数
+    Profiles(ProfilesArgs),
+
+    /// 合并多个描述文件，写出一个合并后的描述（而不是生成文件树）
+    Merge(MergeArgs),
+
+    /// 查看文件内容中可以用 @snippet:NAME 引用的命名片段（内置与 ~/.config/treegen/snippets）
+    Snippets(SnippetsArgs),
+
+    /// 把描述文件渲染成可折叠的 HTML 页面或方框树 SVG 图（用于评审），不生成文件树
+    Render(RenderArgs),
+}
+
+/// 模板库目录参数（默认 ~/.local/share/treegen/templates）
+#[derive(clap::Args, Debug)]
+struct TemplatesDirArg {
+    /// 模板库目录
+    #[arg(long, env = "TREEGEN_TEMPLATES_DIR", value_hint = ValueHint::DirPath)]
+    templates_dir: Option<PathBuf>,
+}
+
+/// `treegen new` 的参数
+#[derive(clap::Args, Debug)]
+struct NewArgs {
+    /// 模板名；以 @ 开头的是内置预设（如 @rust-bin）
+    #[arg(required_unless_present = "list")]
+    template: Option<String>,
+
+    /// 列出内置预设与模板库中的模板
+    #[arg(long)]
+    list: bool,
+
+    #[command(flatten)]
+    dir: TemplatesDirArg,
+
+    #[command(flatten)]
+    gen: GenerateArgs,
+}
+
+/// `treegen template` 的参数
+#[derive(clap::Args, Debug)]
+struct TemplateArgs {
+    #[command(subcommand)]
+    action: TemplateAction,
+
+    #[command(flatten)]
+    dir: TemplatesDirArg,
+}
+
+/// 模板库操作
+#[derive(Subcommand, Debug)]
+enum TemplateAction {
+    /// 列出模板库中的模板
+    List,
+
+    /// 把描述文件加入模板库（模板名默认取文件名）
+    Add {
+        /// 描述文件
+        #[arg(value_hint = ValueHint::FilePath)]
+        spec: PathBuf,
+
+        /// 模板名
+        #[arg(long)]
+        name: Option<String>,
+
+        /// 覆盖同名模板
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// 从模板库删除模板
+    Remove {
+        /// 模板名
+        name: String,
+    },
+}
+
+/// `treegen snippets` 的参数
+#[derive(clap::Args, Debug)]
+struct SnippetsArgs {
+    #[command(subcommand)]
+    action: SnippetsAction,
+}
+
+/// 命名片段操作
+#[derive(Subcommand, Debug)]
+enum SnippetsAction {
+    /// 列出可用的片段及来源（用户目录中的同名片段覆盖内置片段）
+    List,
+}
+
+/// `treegen lint` 的参数
+#[derive(clap::Args, Debug)]
+struct LintArgs {
+    /// 要检查的一个或多个输入文件（与生成时相同，会先合并）
+    #[arg(required = true, value_hint = ValueHint::FilePath, value_parser = parse_input_arg)]
+    input: Vec<Input>,
+
+    /// 所有输入文件的格式，覆盖扩展名与内容推断
+    #[arg(long, value_enum)]
+    format: Option<SpecFormat>,
+
+    /// 将要传入的模板变量，用于检查未被引用的变量（可重复；同样读取 TREEGEN_VAR_<NAME>）
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+
+    /// 把指定检查项视为错误，出现时以非零状态退出（可重复）
+    #[arg(
+        long,
+        value_name = "LINT",
+        value_parser = PossibleValuesParser::new(Lint::ALL.iter().map(|lint| lint.name()))
+            .map(|name| Lint::from_name(&name).unwrap())
+    )]
+    deny: Vec<Lint>,
+}
+
+/// `treegen diff` 的参数
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// 旧的描述文件
+    #[arg(value_hint = ValueHint::FilePath, value_parser = parse_input_arg)]
+    old: Input,
+
+    /// 新的描述文件
+    #[arg(value_hint = ValueHint::FilePath, value_parser = parse_input_arg)]
+    new: Input,
+
+    /// 同时打印内容有变化的文件的统一格式 diff
+    #[arg(long, short)]
+    unified: bool,
+}
+
+/// `treegen verify` 的参数
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// sha256sum 格式的清单
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    checksums: PathBuf,
+
+    /// 要检查的输出目录（默认是当前工作目录）
+    #[arg(short, long, value_hint = ValueHint::DirPath)]
+    out: Option<PathBuf>,
+}
+
+/// `treegen vars` 的参数
+#[derive(clap::Args, Debug)]
+struct VarsArgs {
+    /// 一个或多个输入文件（会先合并）
+    #[arg(required = true, value_hint = ValueHint::FilePath, value_parser = parse_input_arg)]
+    input: Vec<Input>,
+
+    /// 所有输入文件的格式，覆盖扩展名与内容推断
+    #[arg(long, value_enum)]
+    format: Option<SpecFormat>,
+}
+
+/// `treegen profiles` 的参数
+#[derive(clap::Args, Debug)]
+struct ProfilesArgs {
+    /// 一个或多个输入文件（会先合并）
+    #[arg(required = true, value_hint = ValueHint::FilePath, value_parser = parse_input_arg)]
+    input: Vec<Input>,
+
+    /// 所有输入文件的格式，覆盖扩展名与内容推断
+    #[arg(long, value_enum)]
+    format: Option<SpecFormat>,
+}
+
+/// `treegen merge` 的参数
+#[derive(clap::Args, Debug)]
+struct MergeArgs {
+    /// 一个或多个输入文件，按顺序合并
+    #[arg(required = true, value_hint = ValueHint::FilePath, value_parser = parse_input_arg)]
+    input: Vec<Input>,
+
+    /// 所有输入文件的格式，覆盖扩展名与内容推断
+    #[arg(long, value_enum)]
+    format: Option<SpecFormat>,
+
+    /// 写出的格式（默认按 --output 的扩展名推断，无法推断时为 yaml）
+    #[arg(long, value_enum)]
+    to: Option<SpecFormat>,
+
+    /// 写到该文件（默认写到标准输出）
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+
+    /// 多个输入重复定义同一路径时的处理，同顶层命令的 --on-duplicate
+    #[arg(long, value_enum, default_value = "last")]
+    on_duplicate: DuplicateKey,
+}
+
+/// `treegen render` 的参数
+#[derive(clap::Args, Debug)]
+struct RenderArgs {
+    /// 一个或多个输入文件（会先合并）
+    #[arg(required = true, value_hint = ValueHint::FilePath, value_parser = parse_input_arg)]
+    input: Vec<Input>,
+
+    /// 所有输入文件的格式，覆盖扩展名与内容推断
+    #[arg(long, value_enum)]
+    format: Option<SpecFormat>,
+
+    /// 渲染的格式（默认按 --output 的扩展名推断，无法推断时为 html）
+    #[arg(long, value_enum)]
+    to: Option<RenderFormat>,
+
+    /// 写到该文件（默认写到标准输出）
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+}
+
+/// --log-level 的取值
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => tracing::Level::ERROR,
+            LogLevel::Warn => tracing::Level::WARN,
+            LogLevel::Info => tracing::Level::INFO,
+            LogLevel::Debug => tracing::Level::DEBUG,
+            LogLevel::Trace => tracing::Level::TRACE,
+        }
+    }
+}
+
+/// --log-format 的取值
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum LogFormat {
+    Text,
+    Json,
+}
+
+/// `treegen render` 的输出格式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum RenderFormat {
+    /// 独立的 HTML 页面：可折叠的目录树与文件内容预览
+    Html,
+    /// 静态的方框树图
+    Svg,
+}
+
+/// `treegen init` 的参数
+#[derive(clap::Args, Debug)]
+struct InitArgs {
+    /// 示例文件格式（默认按 PATH 的扩展名推断，无法推断时为 yaml）
+    #[arg(long, value_enum)]
+    format: Option<SpecFormat>,
+
+    /// 写出的路径（默认 ./treegen.<ext>，即 ./treegen.yaml）
+    path: Option<PathBuf>,
+
+    /// 目标文件已存在时直接覆盖
+    #[arg(long, env = "TREEGEN_FORCE", value_parser = BoolishValueParser::new())]
+    force: bool,
+}
+
+/// `treegen config` 的参数
+#[derive(clap::Args, Debug)]
+#[group(required = true, multiple = false)]
+struct ConfigArgs {
+    /// 打印合并后的有效配置及每个值的来源（cli/env/config/default）
+    #[arg(long)]
+    show: bool,
+
+    /// 打印配置文件路径
+    #[arg(long)]
+    path: bool,
+}
+
+/// 命令行中的描述文件格式名
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SpecFormat {
+    #[value(alias = "markdown")]
+    Md,
+    #[value(alias = "yml")]
+    Yaml,
+    Json,
+    Toml,
+    Json5,
+    #[value(alias = "list")]
+    Paths,
+}
+
+impl From<SpecFormat> for Format {
+    fn from(format: SpecFormat) -> Self {
+        match format {
+            SpecFormat::Md => Format::Markdown,
+            SpecFormat::Yaml => Format::Yaml,
+            SpecFormat::Json => Format::Json,
+            SpecFormat::Toml => Format::Toml,
+            SpecFormat::Json5 => Format::Json5,
+            SpecFormat::Paths => Format::Paths,
+        }
+    }
+}
+
+/// 命令行中的压缩格式名
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CompressedFormat {
+    #[value(alias = "gz")]
+    Gzip,
+    #[value(alias = "zst")]
+    Zstd,
+}
+
+impl From<CompressedFormat> for Compression {
+    fn from(format: CompressedFormat) -> Self {
+        match format {
+            CompressedFormat::Gzip => Compression::Gzip,
+            CompressedFormat::Zstd => Compression::Zstd,
+        }
+    }
+}
+
+/// 命令行中的名称规范化形式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum NormalizeForm {
+    Nfc,
+    Nfd,
+    None,
+}
+
+impl From<NormalizeForm> for Normalization {
+    fn from(form: NormalizeForm) -> Self {
+        match form {
+            NormalizeForm::Nfc => Normalization::Nfc,
+            NormalizeForm::Nfd => Normalization::Nfd,
+            NormalizeForm::None => Normalization::None,
+        }
+    }
+}
+
+/// 命令行中的 Markdown 描述写法
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum MdStyleArg {
+    Auto,
+    Tree,
+    Sections,
+}
+
+impl From<MdStyleArg> for MdStyle {
+    fn from(style: MdStyleArg) -> Self {
+        match style {
+            MdStyleArg::Auto => MdStyle::Auto,
+            MdStyleArg::Tree => MdStyle::Tree,
+            MdStyleArg::Sections => MdStyle::Sections,
+        }
+    }
+}
+
+/// --list 列出的路径
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ListKind {
+    All,
+    Files,
+    Dirs,
+}
+
+/// 命令行中的生成顺序
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SortKey {
+    Spec,
+    Name,
+}
+
+impl From<SortKey> for SortOrder {
+    fn from(key: SortKey) -> Self {
+        match key {
+            SortKey::Spec => SortOrder::Spec,
+            SortKey::Name => SortOrder::Name,
+        }
+    }
+}
+
+/// 命令行中的重复路径处理方式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum DuplicateKey {
+    Error,
+    First,
+    Last,
+    Rename,
+}
+
+impl From<DuplicateKey> for OnDuplicate {
+    fn from(key: DuplicateKey) -> Self {
+        match key {
+            DuplicateKey::Error => OnDuplicate::Error,
+            DuplicateKey::First => OnDuplicate::First,
+            DuplicateKey::Last => OnDuplicate::Last,
+            DuplicateKey::Rename => OnDuplicate::Rename,
+        }
+    }
+}
+
+/// 解析输入参数：FORMAT:PATH 为单个输入指定格式；
+/// 同名文件确实存在时（如名为 `yaml:x` 的文件）按普通路径处理
+fn parse_input_arg(s: &str) -> Result<Input, String> {
+    let path = PathBuf::from(s);
+    if let Some((prefix, rest)) = s.split_once(':') {
+        if let Ok(format) = SpecFormat::from_str(prefix, true) {
+            if !rest.is_empty() && !path.exists() {
+                return Ok(Input {
+                    path: PathBuf::from(rest),
+                    format: Some(format.into()),
+                });
+            }
+        }
+    }
+    Ok(Input::from(path))
+}
+
+/// 顶层命令的输入：描述文件及其单独的输出目录（SPEC=OUTDIR）
+#[derive(Debug, Clone)]
+struct MappedInput {
+    input: Input,
+    out: Option<PathBuf>,
+    /// 从 git 仓库获取的描述：命令行中的原始写法与检出的仓库
+    remote: Option<(String, PathBuf)>,
+}
+
+fn parse_mapped_input(s: &str) -> Result<MappedInput, String> {
+    // 文件名本身可能含有 '='，已存在的路径按原样处理
+    if !Path::new(s).exists() {
+        if let Some((spec, out)) = s.rsplit_once('=') {
+            if spec.is_empty() || out.is_empty() {
+                return Err(format!("expected SPEC=OUTDIR, got '{}'", s));
+            }
+            return Ok(MappedInput {
+                input: parse_input_arg(spec)?,
+                out: Some(PathBuf::from(out)),
+                remote: None,
+            });
+        }
+    }
+    Ok(MappedInput {
+        input: parse_input_arg(s)?,
+        out: None,
+        remote: None,
+    })
+}
+
+/// 以 `-` 开头的 ref 会被 git 当作选项（如 `--upload-pack=...`）
+fn parse_git_ref(s: &str) -> Result<String, String> {
+    if s.is_empty() || s.starts_with('-') {
+        return Err(format!("'{}' is not a branch or tag name", s));
+    }
+    Ok(s.to_string())
+}
+
+// === treegen init ===
+
+/// 示例树：包含 src/、带内容的文件、空文件与空目录
+fn example_tree() -> Node {
+    let project = Node::dir("my_project")
+        .file(
+            "Cargo.toml",
+            "[package]\nname = \"my_project\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .file("README.md", "# my_project\n\nGenerated by treegen.\n")
+        .child(Node::dir("assets"))
+        .child(
+            Node::dir("src")
+                .file(
+                    "main.rs",
+                    "fn main() {\n    println!(\"Hello from treegen!\");\n}\n",
+                )
+                .file_empty("lib.rs"),
+        );
+    Node::root()
+        .child(project)
+        .build()
+        .expect("example tree is valid")
+}
+
+/// 示例文件头部的注释
+fn example_header(format: Format, path: &Path) -> String {
+    // JSON 与路径列表不支持注释
+    let prefix = match format {
+        Format::Json | Format::Paths => return String::new(),
+        Format::Markdown | Format::Yaml | Format::Toml => "#",
+        Format::Json5 => "//",
+    };
+    let rules: &[&str] = match format {
+        Format::Markdown => &[
+            "- a name ending in / is a directory, any other name is an empty file",
+            "- add [d] or [f] after a name to force its type",
+        ],
+        _ => &[
+            "- a map (object) is a directory, a string is the content of a file",
+            "- \"\" creates an empty file, {} creates an empty directory",
+        ],
+    };
+    let mut lines = vec!["treegen example spec.".to_string()];
+    lines.extend(rules.iter().map(|rule| rule.to_string()));
+    lines.push(format!(
+        "Try it: treegen {} --out output --dry-run --verbose",
+        path.display()
+    ));
+    lines
+        .iter()
+        .map(|line| format!("{} {}\n", prefix, line))
+        .collect::<String>()
+        + "\n"
+}
+
+/// 写出示例描述文件，已存在时除非 --force 否则拒绝覆盖
+fn run_init(init: &InitArgs) -> Result<()> {
+    let format = init
+        .format
+        .map(Format::from)
+        .or_else(|| init.path.as_deref().and_then(Format::from_path))
+        .unwrap_or(Format::Yaml);
+    let path = init
+        .path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format!("treegen.{}", format.extension())));
+    if Compression::from_path(&path).is_some() {
+        bail!(
+            "'{}' is a compressed file name; init writes plain text specs",
+            path.display()
+        );
+    }
+
+    if path.exists() && !init.force {
+        bail!(
+            "'{}' already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+
+    let body = Spec::from_tree(example_tree()).write(format)?;
+    fs::write(&path, example_header(format, &path) + &body)
+        .with_context(|| format!("Failed to write example spec '{}'", path.display()))?;
+
+    println!("{}", Msg::ExampleWritten { path: &path });
+    Ok(())
+}
+
+// === treegen completions ===
+
+/// 生成补全脚本；在支持按扩展名过滤的 shell（zsh/fish）中，把输入文件补全限制为描述文件
+fn run_completions(shell: Shell) -> Result<()> {
+    let mut buf = Vec::new();
+    clap_complete::generate(shell, &mut Args::command(), "treegen", &mut buf);
+    let mut script = String::from_utf8(buf).context("Generated completion script is not UTF-8")?;
+
+    match shell {
+        Shell::Zsh => {
+            // 只替换位置参数 input 那一行的 _files（':input、'::input 或 '*::input，取决于是否必填、可重复）
+            let glob = format!(":_files -g \"*.({})\"' \\", SPEC_EXTENSIONS.join("|"));
+            script = script
+                .lines()
+                .map(|line| {
+                    let spec = line
+                        .strip_prefix('\'')
+                        .map(|rest| rest.trim_start_matches(['*', ':']));
+                    if spec.is_some_and(|spec| spec.starts_with("input -- ")) {
+                        line.replacen(":_files' \\", &glob, 1)
+                    } else {
+                        line.to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+                + "\n";
+        }
+        Shell::Fish => {
+            for ext in SPEC_EXTENSIONS {
+                script.push_str(&format!(
+                    "complete -c treegen -n \"__fish_treegen_needs_command\" -k -a \"(__fish_complete_suffix .{})\"\n",
+                    ext
+                ));
+            }
+        }
+        _ => {}
+    }
+
+    print!("{}", script);
+    Ok(())
+}
+
+// === 模板变量 ===
+
+/// 解析 --var 的 NAME=VALUE
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{}'", s))?;
+    if name.is_empty() {
+        return Err(format!("missing variable name in '{}'", s));
+    }
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn parse_out_mode(s: &str) -> Result<u32, String> {
+    treegen::parse_mode(s).map_err(|e| e.to_string())
+}
+
+fn parse_chown(s: &str) -> Result<Ownership, String> {
+    if cfg!(not(unix)) {
+        return Err("--chown is only supported on Unix platforms".to_string());
+    }
+    // 名称在这里就解析，不存在的用户或组不会等到生成到一半才报错
+    let own = Ownership::parse(s)?;
+    treegen::resolve_ownership(&own).map_err(|e| format!("{:#}", e))?;
+    Ok(own)
+}
+
+/// 参数是否由命令行或环境变量提供；描述文件自带的设置只在没有提供时使用（优先于配置文件）
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches.try_contains_id(id).is_ok()
+        && matches!(
+            matches.value_source(id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        )
+}
+
+/// 汇总模板变量：命令行 --var > 环境变量 TREEGEN_VAR_* > 配置文件中的 var
+fn collect_vars(cli_vars: &[(String, String)], matches: &ArgMatches) -> BTreeMap<String, String> {
+    let from_cli = matches.value_source("vars") == Some(ValueSource::CommandLine);
+    let mut vars = BTreeMap::new();
+    if !from_cli {
+        vars.extend(cli_vars.iter().cloned());
+    }
+    for (key, value) in env::vars() {
+        if let Some(name) = key.strip_prefix("TREEGEN_VAR_") {
+            vars.insert(name.to_lowercase(), value);
+        }
+    }
+    if from_cli {
+        vars.extend(cli_vars.iter().cloned());
+    }
+    vars
+}
+
+/// --var-file：依次读取变量文件，后面的覆盖前面的
+fn read_var_files(paths: &[PathBuf]) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    for path in paths {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read var file '{}'", path.display()))?;
+        let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let parsed: Result<serde_json::Value> = match ext.to_ascii_lowercase().as_str() {
+            "json" => serde_json::from_str(&text).map_err(Into::into),
+            "toml" => toml::from_str(&text).map_err(Into::into),
+            _ => serde_yaml::from_str(&text).map_err(Into::into),
+        };
+        let parsed =
+            parsed.with_context(|| format!("Failed to parse var file '{}'", path.display()))?;
+        let serde_json::Value::Object(map) = parsed else {
+            bail!(
+                "Var file '{}' must be a mapping of variable names to values",
+                path.display()
+            );
+        };
+        for (name, value) in map {
+            let value = match value {
+                serde_json::Value::String(text) => text,
+                serde_json::Value::Number(_) | serde_json::Value::Bool(_) => value.to_string(),
+                _ => bail!(
+                    "Variable '{}' in var file '{}' must be a string, number or boolean",
+                    name,
+                    path.display()
+                ),
+            };
+            vars.insert(name, value);
+        }
+    }
+    Ok(vars)
+}
+
+/// 在生成之前一次性处理模板变量：
+/// - 描述声明了 `$vars` 时，--var 给出既未声明也未引用的变量报错（多半是拼写错误），已声明的按类型检查；
+/// - 没有提供的变量取默认值；引用了的在终端中逐个询问（直接回车采用默认值），非交互或 --no-input 时采用默认值，
+///   没有默认值的留给替换时报错
+fn resolve_vars(
+    referenced: &BTreeSet<String>,
+    declared: &BTreeMap<String, VarDecl>,
+    vars: &mut BTreeMap<String, String>,
+    gen: &GenerateArgs,
+) -> Result<()> {
+    if !declared.is_empty() {
+        if let Some((name, _)) = gen
+            .vars
+            .iter()
+            .find(|(name, _)| !declared.contains_key(name) && !referenced.contains(name))
+        {
+            let names: Vec<&str> = declared.keys().map(String::as_str).collect();
+            bail!(
+                "Unknown variable '{}' (the spec declares: {})",
+                name,
+                names.join(", ")
+            );
+        }
+    }
+    for (name, value) in vars.iter() {
+        if let Some(decl) = declared.get(name) {
+            decl.check(name, value)?;
+        }
+    }
+
+    let missing: BTreeSet<String> = referenced
+        .iter()
+        .chain(declared.keys())
+        .filter(|name| !vars.contains_key(*name))
+        .cloned()
+        .collect();
+    let interactive = !gen.no_input && io::stdin().is_terminal();
+    for name in missing {
+        let decl = declared.get(&name).cloned().unwrap_or_default();
+        // 声明了但没有引用的变量只取默认值，不必询问
+        let value = if interactive && referenced.contains(&name) {
+            prompt_var(&name, &decl)?
+        } else if let Some(default) = decl.default {
+            default
+        } else {
+            continue;
+        };
+        vars.insert(name, value);
+    }
+    Ok(())
+}
+
+/// 在终端中询问变量的值（带上说明与默认值）；没有默认值时不接受空输入，不符合类型时重新询问
+fn prompt_var(name: &str, decl: &VarDecl) -> Result<String> {
+    let stdin = io::stdin();
+    loop {
+        eprint!("{}", name);
+        if let Some(description) = &decl.description {
+            eprint!(" ({})", description);
+        }
+        if let Some(default) = &decl.default {
+            eprint!(" [{}]", default);
+        }
+        eprint!(": ");
+        io::stderr().flush().ok();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            bail!("No value given for template variable '{}'", name);
+        }
+        let value = match (line.trim_end_matches(['\r', '\n']), &decl.default) {
+            ("", Some(default)) => return Ok(default.clone()),
+            ("", None) => continue,
+            (value, _) => value.to_string(),
+        };
+        match decl.check(name, &value) {
+            Ok(()) => return Ok(value),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+// === 默认内容片段 ===
+
+/// 内置默认内容：按文件名精确匹配
+const DEFAULT_BY_NAME: &[(&str, &str)] = &[
+    (".gitignore", include_str!("snippets/gitignore")),
+    (".editorconfig", include_str!("snippets/editorconfig")),
+    ("LICENSE", include_str!("snippets/mit-license")),
+    ("README.md", "# {{name}}\n"),
+    (
+        "Cargo.toml",
+        "[package]\nname = \"{{name}}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n",
+    ),
+];
+
+/// 内置默认内容：按扩展名匹配
+const DEFAULT_BY_EXT: &[(&str, &str)] = &[
+    ("sh", "#!/usr/bin/env bash\nset -euo pipefail\n"),
+    ("json", "{}\n"),
+    (
+        "html",
+        "<!DOCTYPE html>\n<html>\n<head>\n  <meta charset=\"utf-8\">\n  <title>{{name}}</title>\n</head>\n<body>\n</body>\n</html>\n",
+    ),
+];
+
+/// 用户片段目录：$XDG_CONFIG_HOME/treegen/snippets 或 ~/.config/treegen/snippets
+fn snippets_dir() -> Option<PathBuf> {
+    Some(config_home()?.join("treegen").join("snippets"))
+}
+
+/// --default-content 的查找表：值为 (来源说明, 内容)。
+/// 用户目录下 snippets/by-name/<文件名> 与 snippets/by-ext/<扩展名> 覆盖内置条目
+struct DefaultContent {
+    by_name: BTreeMap<String, (String, String)>,
+    by_ext: BTreeMap<String, (String, String)>,
+}
+
+impl DefaultContent {
+    fn load() -> Result<Self> {
+        let builtin = |table: &[(&str, &str)], kind: &str| {
+            table
+                .iter()
+                .map(|(key, content)| {
+                    let source = format!("built-in {} '{}'", kind, key);
+                    (key.to_string(), (source, content.to_string()))
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let mut table = DefaultContent {
+            by_name: builtin(DEFAULT_BY_NAME, "by-name"),
+            by_ext: builtin(DEFAULT_BY_EXT, "by-ext"),
+        };
+        if let Some(dir) = snippets_dir() {
+            for (sub, map) in [
+                ("by-name", &mut table.by_name),
+                ("by-ext", &mut table.by_ext),
+            ] {
+                let sub_dir = dir.join(sub);
+                if !sub_dir.is_dir() {
+                    continue;
+                }
+                for entry in fs::read_dir(&sub_dir).with_context(|| {
+                    format!("Failed to read snippet directory '{}'", sub_dir.display())
+                })? {
+                    let path = entry?.path();
+                    if !path.is_file() {
+                        continue;
+                    }
+                    let content = fs::read_to_string(&path)
+                        .with_context(|| format!("Failed to read snippet '{}'", path.display()))?;
+                    let key = path.file_name().unwrap().to_string_lossy().into_owned();
+                    map.insert(key, (format!("'{}'", path.display()), content));
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    /// 文件名精确匹配优先，其次按扩展名
+    fn lookup(&self, name: &str) -> Option<&(String, String)> {
+        self.by_name.get(name).or_else(|| {
+            let ext = Path::new(name)
+                .extension()?
+                .to_string_lossy()
+                .to_lowercase();
+            self.by_ext.get(&ext)
+        })
+    }
+}
+
+/// 为没有内容的文件节点（如 Markdown 解析出的文件）填入默认内容；
+/// 显式写了内容的节点（包括 ""）保持不变。片段内容同样做变量替换：没有定义的 `name`
+/// 取所在目录的名称（顶层文件取 `project`，即输出目录的名称），用到其他未定义变量的片段
+/// 不填入（文件保持为空）并给出警告，而不是让整次生成失败
+fn fill_default_content(
+    node: &mut Node,
+    table: &DefaultContent,
+    vars: &BTreeMap<String, String>,
+    project: &str,
+    verbose: bool,
+) -> Result<()> {
+    fn walk(
+        node: &mut Node,
+        parent: &Path,
+        dir_name: &str,
+        table: &DefaultContent,
+        vars: &BTreeMap<String, String>,
+        verbose: bool,
+    ) -> Result<()> {
+        let path = parent.join(&node.name);
+        if let (NodeType::File, None) = (&node.node_type, &node.content) {
+            if let Some((source, content)) = table.lookup(&node.name) {
+                let mut vars = vars.clone();
+                vars.entry("name".to_string())
+                    .or_insert_with(|| dir_name.to_string());
+                match treegen::vars_in(content).find(|name| !vars.contains_key(name)) {
+                    Some(missing) => eprintln!(
+                        "Warning: No default content for '{}': {} uses undefined variable '{}' (pass --var {}=...)",
+                        path.display(),
+                        source,
+                        missing,
+                        missing
+                    ),
+                    None => {
+                        if verbose {
+                            log!("Default content for {}: {}", path.display(), source);
+                        }
+                        node.content = Some(treegen::render_vars(content, &vars, &path)?);
+                    }
+                }
+            }
+        }
+        let dir_name = match node.name.trim_end_matches('/') {
+            "" => dir_name,
+            name => name,
+        };
+        for child in node.children.iter_mut() {
+            walk(child, &path, dir_name, table, vars, verbose)?;
+        }
+        Ok(())
+    }
+
+    walk(node, Path::new(""), project, table, vars, verbose)
+}
+
+// === 命名片段（@snippet:NAME） ===
+
+/// 内置的命名片段
+const SNIPPETS: &[(&str, &str)] = &[
+    ("editorconfig", include_str!("snippets/editorconfig")),
+    ("gitignore", include_str!("snippets/gitignore")),
+    ("mit-license", include_str!("snippets/mit-license")),
+    ("rustfmt", include_str!("snippets/rustfmt")),
+];
+
+/// 引用命名片段的文件内容前缀
+const SNIPPET_PREFIX: &str = "@snippet:";
+
+/// 可用的命名片段：名称 -> (来源说明, 内容)。用户片段目录下的文件（不含 by-name/by-ext
+/// 子目录）以文件名为名称，覆盖同名的内置片段
+fn load_snippets() -> Result<BTreeMap<String, (String, String)>> {
+    let mut snippets: BTreeMap<String, (String, String)> = SNIPPETS
+        .iter()
+        .map(|(name, content)| {
+            (
+                name.to_string(),
+                ("built-in".to_string(), content.to_string()),
+            )
+        })
+        .collect();
+    let Some(dir) = snippets_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(snippets);
+    };
+    for entry in fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read snippet directory '{}'", dir.display()))?
+    {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read snippet '{}'", path.display()))?;
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        snippets.insert(name, (path.display().to_string(), content));
+    }
+    Ok(snippets)
+}
+
+/// 名称接近 `name` 的片段（拼写错误时的建议），按相似度排序
+fn close_snippets<'a>(
+    name: &str,
+    snippets: &'a BTreeMap<String, (String, String)>,
+) -> Vec<&'a str> {
+    let mut close: Vec<(f64, &str)> = snippets
+        .keys()
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate.as_str()))
+        .filter(|(score, _)| *score >= 0.8)
+        .collect();
+    close.sort_by(|a, b| b.0.total_cmp(&a.0));
+    close.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// 把内容为 `@snippet:NAME` 的文件换成片段的原文；在规则与变量替换之前进行，
+/// 片段中的变量与描述中的一样解析、替换。`raw: true` 的文件不解析
+fn expand_snippets(root: &mut Node, verbose: bool) -> Result<()> {
+    type Snippets = BTreeMap<String, (String, String)>;
+
+    fn walk(
+        node: &mut Node,
+        parent: &Path,
+        snippets: &mut Option<Snippets>,
+        verbose: bool,
+    ) -> Result<()> {
+        let path = parent.join(&node.name);
+        let name = node
+            .content
+            .as_deref()
+            .and_then(|content| content.strip_prefix(SNIPPET_PREFIX))
+            .filter(|_| node.node_type == NodeType::File && !node.attrs.raw);
+        if let Some(name) = name {
+            // 用到片段时才读取用户目录
+            if snippets.is_none() {
+                *snippets = Some(load_snippets()?);
+            }
+            let table = snippets.as_ref().unwrap();
+            let Some((source, content)) = table.get(name) else {
+                let close = close_snippets(name, table);
+                let hint = if close.is_empty() {
+                    let names: Vec<&str> = table.keys().map(String::as_str).collect();
+                    format!("available: {}", names.join(", "))
+                } else {
+                    format!("did you mean: {}?", close.join(", "))
+                };
+                bail!(
+                    "Unknown snippet '{}' for '{}'; {} (see `treegen snippets list`)",
+                    name,
+                    path.display(),
+                    hint
+                );
+            };
+            if verbose {
+                log!("Snippet '{}' for {}: {}", name, path.display(), source);
+            }
+            node.content = Some(content.clone());
+        }
+        for child in node.children.iter_mut() {
+            walk(child, &path, snippets, verbose)?;
+        }
+        Ok(())
+    }
+
+    walk(root, Path::new(""), &mut None, verbose)
+}
+
+/// `treegen snippets`：列出可用的命名片段及来源
+fn run_snippets(snippets: &SnippetsArgs) -> Result<()> {
+    match snippets.action {
+        SnippetsAction::List => {
+            for (name, (source, _)) in load_snippets()? {
+                println!("{:<20} {}", name, source);
+            }
+        }
+    }
+    Ok(())
+}
+
+// === 模板库 ===
+
+/// 模板库目录：--templates-dir/$TREEGEN_TEMPLATES_DIR，否则 $XDG_DATA_HOME/treegen/templates 或 ~/.local/share/treegen/templates
+fn templates_dir(arg: &TemplatesDirArg) -> Result<PathBuf> {
+    if let Some(dir) = &arg.templates_dir {
+        return Ok(dir.clone());
+    }
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".local").join("share")))
+        .context("Cannot determine the template directory; pass --templates-dir")?;
+    Ok(data_home.join("treegen").join("templates"))
+}
+
+/// 列出模板库中的模板：(模板名, 描述文件路径)，按名称排序
+fn list_templates(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut templates = Vec::new();
+    for entry in fs::read_dir(dir)
+        .with_context(|| format!("Failed to read template directory '{}'", dir.display()))?
+    {
+        let path = entry?.path();
+        let supported = path.extension().is_some_and(|e| {
+            SPEC_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str())
+        });
+        if path.is_file() && supported {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            templates.push((name, path));
+        }
+    }
+    templates.sort();
+    Ok(templates)
+}
+
+/// 按模板名查找描述文件，找不到时列出可用模板
+fn resolve_template(dir: &Path, name: &str) -> Result<PathBuf> {
+    let templates = list_templates(dir)?;
+    let mut found = templates.iter().filter(|(n, _)| n == name);
+    match (found.next(), found.next()) {
+        (Some((_, path)), None) => Ok(path.clone()),
+        (Some(_), Some(_)) => bail!(
+            "Template '{}' is ambiguous: several spec files in '{}' share that name",
+            name,
+            dir.display()
+        ),
+        (None, _) if templates.is_empty() => bail!(
+            "Unknown template '{}': the template directory '{}' is empty",
+            name,
+            dir.display()
+        ),
+        (None, _) => bail!(
+            "Unknown template '{}'. Available templates: {}",
+            name,
+            templates
+                .iter()
+                .map(|(n, _)| n.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// 内置项目预设（`treegen new @<name>`），与用户描述文件走同一套解析与变量替换流程
+struct Preset {
+    name: &'static str,
+    description: &'static str,
+    spec: &'static str,
+}
+
+const PRESETS: &[Preset] = &[
+    Preset {
+        name: "rust-bin",
+        description: "Rust binary crate (Cargo.toml, src/main.rs); needs --var name=...",
+        spec: include_str!("presets/rust-bin.yaml"),
+    },
+    Preset {
+        name: "rust-lib",
+        description: "Rust library crate (Cargo.toml, src/lib.rs); needs --var name=...",
+        spec: include_str!("presets/rust-lib.yaml"),
+    },
+    Preset {
+        name: "python-pkg",
+        description: "Python package with src layout and pyproject.toml; needs --var name=...",
+        spec: include_str!("presets/python-pkg.yaml"),
+    },
+    Preset {
+        name: "node",
+        description: "Node.js project (package.json, index.js); needs --var name=...",
+        spec: include_str!("presets/node.yaml"),
+    },
+];
+
+/// 按名称查找内置预设（不含 @ 前缀）
+fn find_preset(name: &str) -> Result<&'static Preset> {
+    PRESETS.iter().find(|p| p.name == name).with_context(|| {
+        format!(
+            "Unknown built-in preset '@{}'. Available presets: {}",
+            name,
+            PRESETS
+                .iter()
+                .map(|p| format!("@{}", p.name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })
+}
+
+/// treegen new：解析模板后按通常流程生成
+fn run_new(new: &NewArgs, matches: &ArgMatches) -> Result<()> {
+    let dir = templates_dir(&new.dir)?;
+    if new.list {
+        for preset in PRESETS {
+            println!("@{:<19} {}", preset.name, preset.description);
+        }
+        for (name, path) in list_templates(&dir)? {
+            println!("{:<20} {}", name, path.display());
+        }
+        return Ok(());
+    }
+
+    let template = new.template.as_deref().unwrap();
+    let spec = if let Some(name) = template.strip_prefix('@') {
+        let preset = find_preset(name)?;
+        report::record_spec_text(&format!("@{}", preset.name), preset.spec);
+        if new.gen.verbose {
+            println!("Using built-in preset '@{}'", preset.name);
+        }
+        parse_yaml(preset.spec)
+            .with_context(|| format!("Failed to parse built-in preset '@{}'", preset.name))?
+    } else {
+        let spec = resolve_template(&dir, template)?;
+        report::record_spec_file(&spec);
+        if new.gen.verbose {
+            println!("Using template '{}': {}", template, spec.display());
+        }
+        parse_file(&spec)?
+    };
+    run_generate(&new.gen, spec, None, None, matches, Timings::default())
+}
+
+/// treegen template list/add/remove
+fn run_template(template: &TemplateArgs) -> Result<()> {
+    let dir = templates_dir(&template.dir)?;
+    match &template.action {
+        TemplateAction::List => {
+            let templates = list_templates(&dir)?;
+            if templates.is_empty() {
+                println!("No templates in '{}'", dir.display());
+            }
+            for (name, path) in templates {
+                println!("{:<20} {}", name, path.display());
+            }
+        }
+        TemplateAction::Add { spec, name, force } => {
+            // 先确认描述文件可以解析；没有扩展名（或扩展名不对）的描述按识别出的格式保存
+            let format = treegen::parse_spec(spec, None)?.format;
+            let name = match name {
+                Some(name) => name.clone(),
+                None => spec
+                    .file_stem()
+                    .with_context(|| {
+                        format!("Cannot derive a template name from '{}'", spec.display())
+                    })?
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            // 模板名直接用作文件名，不能带出模板目录
+            let plain = matches!(
+                Path::new(&name).components().collect::<Vec<_>>()[..],
+                [std::path::Component::Normal(_)]
+            );
+            if !plain || name.contains(['/', '\\']) {
+                bail!(
+                    "Invalid template name '{}': use a plain name without path separators, '.' or '..'",
+                    name
+                );
+            }
+            let existing: Vec<PathBuf> = list_templates(&dir)?
+                .into_iter()
+                .filter(|(n, _)| *n == name)
+                .map(|(_, path)| path)
+                .collect();
+            if !existing.is_empty() && !force {
+                bail!(
+                    "Template '{}' already exists; pass --force to replace it",
+                    name
+                );
+            }
+            for path in existing {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove '{}'", path.display()))?;
+            }
+            fs::create_dir_all(&dir).with_context(|| {
+                format!("Failed to create template directory '{}'", dir.display())
+            })?;
+            let dest = dir.join(format!("{}.{}", name, format.extension()));
+            fs::copy(spec, &dest).with_context(|| {
+                format!(
+                    "Failed to copy '{}' to '{}'",
+                    spec.display(),
+                    dest.display()
+                )
+            })?;
+            println!(
+                "{}",
+                Msg::TemplateAdded {
+                    name: &name,
+                    dest: &dest
+                }
+            );
+        }
+        TemplateAction::Remove { name } => {
+            let path = resolve_template(&dir, name)?;
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove '{}'", path.display()))?;
+            println!("{}", Msg::TemplateRemoved { name });
+        }
+    }
+    Ok(())
+}
+
+// === 用户配置文件 ===
+
+/// 用户主目录（$HOME，Windows 上为 %USERPROFILE%）
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// 配置文件路径：$TREEGEN_CONFIG，否则 $XDG_CONFIG_HOME/treegen/config.toml 或 ~/.config/treegen/config.toml
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("TREEGEN_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    Some(config_home()?.join("treegen").join("config.toml"))
+}
+
+/// 配置根目录：$XDG_CONFIG_HOME，否则 ~/.config
+fn config_home() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+}
+
+/// 用户配置：键名即长参数名（`dry-run` 与 `dry_run` 均可），值为该参数的默认值
+struct Config {
+    path: PathBuf,
+    values: BTreeMap<String, toml::Value>,
+}
+
+/// 读取配置文件；默认位置不存在时视为没有配置，$TREEGEN_CONFIG 指向的文件必须存在
+fn load_config() -> Result<Option<Config>> {
+    let Some(path) = config_path() else {
+        return Ok(None);
+    };
+    if !path.exists() {
+        if env::var_os("TREEGEN_CONFIG").is_some() {
+            bail!(
+                "Config file '{}' (from TREEGEN_CONFIG) does not exist",
+                path.display()
+            );
+        }
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file '{}'", path.display()))?;
+    let table: BTreeMap<String, toml::Value> = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file '{}'", path.display()))?;
+    let values = table
+        .into_iter()
+        .map(|(key, value)| (key.replace('_', "-"), value))
+        .collect();
+    Ok(Some(Config { path, values }))
+}
+
+/// 某个命令自身可以由配置文件提供默认值的参数：所有带长参数名的普通选项/开关
+fn own_configurable_args(cmd: &clap::Command) -> impl Iterator<Item = &clap::Arg> {
+    cmd.get_arguments()
+        .filter(|arg| arg.get_long().is_some())
+        .filter(|arg| !matches!(arg.get_action(), ArgAction::Help | ArgAction::Version))
+}
+
+/// 顶层命令及所有子命令中可配置的参数（按长参数名去重，顶层优先）
+fn configurable_args(cmd: &clap::Command) -> Vec<&clap::Arg> {
+    let mut args: Vec<&clap::Arg> = Vec::new();
+    let mut pending = vec![cmd];
+    while let Some(cmd) = pending.pop() {
+        for arg in own_configurable_args(cmd) {
+            if !args.iter().any(|a| a.get_long() == arg.get_long()) {
+                args.push(arg);
+            }
+        }
+        // `treegen config` 自身的参数不是默认值
+        let subcommands = cmd
+            .get_subcommands()
+            .filter(|sub| sub.get_name() != "config");
+        pending.extend(subcommands.collect::<Vec<_>>().into_iter().rev());
+    }
+    args
+}
+
+/// 配置值转成命令行参数：开关用布尔值，其余为字符串/数字，数组表示多次传入
+fn config_tokens(arg: &clap::Arg, value: &toml::Value, config: &Config) -> Result<Vec<OsString>> {
+    let long = arg.get_long().unwrap();
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid value for '{}' in config file '{}'",
+            long,
+            config.path.display()
+        )
+    };
+    if !arg.get_action().takes_values() {
+        return match value {
+            toml::Value::Boolean(true) => Ok(vec![format!("--{}", long).into()]),
+            toml::Value::Boolean(false) => Ok(Vec::new()),
+            _ => Err(invalid()),
+        };
+    }
+    let items = match value {
+        toml::Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    items
+        .into_iter()
+        .map(|item| {
+            let text = match item {
+                toml::Value::String(s) => s.clone(),
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                _ => return Err(invalid()),
+            };
+            Ok(format!("--{}={}", long, text).into())
+        })
+        .collect()
+}
+
+/// 参数来源：命令行 > 环境变量 > 配置文件 > 内置默认值
+fn value_origin(matches: &ArgMatches, arg: &clap::Arg, config: Option<&Config>) -> &'static str {
+    let id = arg.get_id().as_str();
+    let env_set = arg
+        .get_env()
+        .is_some_and(|name| env::var_os(name).is_some());
+    // 子命令的参数不在顶层解析结果中
+    let source = match matches.try_contains_id(id) {
+        Ok(_) => matches.value_source(id),
+        Err(_) => None,
+    };
+    match source {
+        Some(ValueSource::CommandLine) => "cli",
+        Some(ValueSource::EnvVariable) => "env",
+        _ if env_set => "env",
+        _ if config.is_some_and(|c| c.values.contains_key(arg.get_long().unwrap())) => "config",
+        _ => "default",
+    }
+}
+
+/// 解析命令行参数，并把配置文件中的默认值合并进 Args：
+/// 只为命令行和环境变量都没有提供的参数注入配置值，再重新解析一次。
+/// 返回的 ArgMatches 是合并前的结果，用于判断每个值的来源
+fn load_args() -> Result<(Args, ArgMatches, Option<Config>)> {
+    let argv: Vec<OsString> = env::args_os().collect();
+    let cmd = Args::command();
+    let first = cmd.clone().get_matches_from(&argv);
+    let config = load_config()?;
+
+    // 注入目标：没有子命令时为顶层参数，否则为子命令自己的参数
+    // （顶层参数与子命令互斥，所以子命令名总在 argv[1]）
+    let (target, target_matches, insert_at) = match first.subcommand() {
+        Some((name, sub)) => (cmd.find_subcommand(name).unwrap(), sub, 2),
+        None => (&cmd, &first, 1),
+    };
+    let mut injected = Vec::new();
+    if let Some(config) = &config {
+        let known = configurable_args(&cmd);
+        for (key, value) in &config.values {
+            if !known.iter().any(|arg| arg.get_long() == Some(key.as_str())) {
+                bail!(
+                    "Unknown key '{}' in config file '{}'",
+                    key,
+                    config.path.display()
+                );
+            }
+            let Some(arg) =
+                own_configurable_args(target).find(|arg| arg.get_long() == Some(key.as_str()))
+            else {
+                continue;
+            };
+            if value_origin(target_matches, arg, None) == "default" {
+                injected.extend(config_tokens(arg, value, config)?);
+            }
+        }
+    }
+
+    let matches = if injected.is_empty() {
+        first.clone()
+    } else {
+        let merged = argv[..insert_at]
+            .iter()
+            .cloned()
+            .chain(injected)
+            .chain(argv[insert_at..].iter().cloned());
+        cmd.get_matches_from(merged)
+    };
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    Ok((args, first, config))
+}
+
+/// treegen config：打印配置文件路径，或合并后的有效配置及来源
+fn run_config(cfg: &ConfigArgs, matches: &ArgMatches, config: Option<&Config>) -> Result<()> {
+    if cfg.path {
+        match config_path() {
+            Some(path) => println!("{}", path.display()),
+            None => bail!("Cannot determine the config file location; set TREEGEN_CONFIG"),
+        }
+        return Ok(());
+    }
+
+    match config {
+        Some(config) => println!("# config file: {}", config.path.display()),
+        None => println!("# config file: (none)"),
+    }
+    let mut cmd = Args::command();
+    cmd.build();
+    for arg in configurable_args(&cmd) {
+        let long = arg.get_long().unwrap();
+        let origin = value_origin(matches, arg, config);
+        let join = |values: Vec<String>| {
+            if values.is_empty() {
+                String::from("(unset)")
+            } else {
+                values.join(",")
+            }
+        };
+        let value = match (origin, config) {
+            ("config", Some(config)) => config.values[long].to_string(),
+            _ => match matches.try_get_raw(arg.get_id().as_str()) {
+                Ok(Some(raw)) => join(raw.map(|v| v.to_string_lossy().into_owned()).collect()),
+                // 子命令的参数不在顶层解析结果中
+                _ => match arg.get_env().and_then(env::var_os) {
+                    Some(value) => value.to_string_lossy().into_owned(),
+                    None => join(
+                        arg.get_default_values()
+                            .iter()
+                            .map(|v| v.to_string_lossy().into_owned())
+                            .collect(),
+                    ),
+                },
+            },
+        };
+        println!("{:<14} = {:<24} # {}", long, value, origin);
+    }
+    Ok(())
+}
+
+/// 解析并合并输入文件：FORMAT:PATH 的格式 > --format > 扩展名 > 内容推断；
+/// --verbose 时报告根据内容推断出格式的输入
+fn load_inputs(
+    inputs: &[Input],
+    format: Option<SpecFormat>,
+    opts: &ParseOptions,
+    on_duplicate: OnDuplicate,
+    verbose: bool,
+) -> Result<Spec> {
+    let mut timings = Timings::default();
+    load_inputs_timed(
+        inputs,
+        format,
+        opts,
+        on_duplicate,
+        verbose,
+        &[],
+        &mut timings,
+    )
+}
+
+/// 同 [`load_inputs`]，并把每个输入的读取解析与合并的耗时记入 `timings`；
+/// `remote` 中的（描述，检出的仓库）只能引用仓库中的文件（见 [`treegen::remote::confine`]）
+fn load_inputs_timed(
+    inputs: &[Input],
+    format: Option<SpecFormat>,
+    opts: &ParseOptions,
+    on_duplicate: OnDuplicate,
+    verbose: bool,
+    remote: &[(PathBuf, PathBuf)],
+    timings: &mut Timings,
+) -> Result<Spec> {
+    let inputs: Vec<Input> = inputs
+        .iter()
+        .map(|input| Input {
+            path: input.path.clone(),
+            format: input.format.or(format.map(Format::from)),
+        })
+        .collect();
+    for input in &inputs {
+        report::record_spec_file(&input.path);
+    }
+    let parsed = parse_specs_with(&inputs, opts)?;
+    if verbose {
+        for (input, p) in inputs.iter().zip(&parsed) {
+            if p.detection == Detection::Sniffed {
+                log!(
+                    "Detected {} format for '{}' from its content",
+                    p.format.name(),
+                    input.path.display()
+                );
+            }
+            for note in &p.notes {
+                log!("{} ({})", note, input.path.display());
+            }
+        }
+    }
+    let specs = inputs
+        .into_iter()
+        .zip(parsed)
+        .map(|(input, p)| {
+            timings.record(format!("parse {}", input.path.display()), p.timing);
+            if let Some((_, checkout)) = remote.iter().find(|(spec, _)| *spec == input.path) {
+                treegen::remote::confine(&p.spec, checkout)
+                    .with_context(|| format!("Refusing spec '{}'", input.path.display()))?;
+            }
+            Ok((input.path, p.spec))
+        })
+        .collect::<Result<_>>()?;
+    let (spec, renames) = timings.time("merge", || merge_inputs(specs, on_duplicate))?;
+    timings.set_nodes(spec.root.iter().count());
+    // 改名报告写到标准错误，不混进 --dry-run 的预览与其他标准输出
+    for r in &renames {
+        eprintln!(
+            "Warning: Renamed duplicate '{}' from '{}' to '{}'",
+            r.original.display(),
+            r.input.display(),
+            r.renamed.display()
+        );
+    }
+    Ok(spec)
+}
+
+/// 顶层命令的解析选项
+fn parse_options(args: &Args) -> ParseOptions {
+    ParseOptions {
+        schema_validate: args.schema_validate,
+        no_brace_expansion: args.no_brace_expansion,
+        keep_ellipsis: args.keep_ellipsis,
+        comment_prefixes: args.comment_prefix.clone(),
+        normalize: args.normalize.into(),
+        infer_dirs: args.infer_dirs,
+        md_style: args.md_style.into(),
+        sections: args.section.clone(),
+    }
+}
+
+/// treegen lint：打印所有警告，被 --deny 的检查项打印为错误并使命令失败
+fn run_lint(lint: &LintArgs, matches: &ArgMatches) -> Result<()> {
+    let Spec { root, .. } = load_inputs(
+        &lint.input,
+        lint.format,
+        &ParseOptions::default(),
+        OnDuplicate::Last,
+        false,
+    )?;
+    let vars = collect_vars(&lint.vars, matches);
+
+    let warnings = treegen::lint::lint(&root, &vars);
+    let mut denied = 0;
+    for warning in &warnings {
+        let level = if lint.deny.contains(&warning.lint) {
+            denied += 1;
+            "error"
+        } else {
+            "warning"
+        };
+        if warning.path.as_os_str().is_empty() {
+            println!("{}[{}]: {}", level, warning.lint, warning.message);
+        } else {
+            println!(
+                "{}[{}]: {}: {}",
+                level,
+                warning.lint,
+                warning.path.display(),
+                warning.message
+            );
+        }
+    }
+
+    if denied > 0 {
+        bail!("{} denied lint(s) found", denied);
+    }
+    if warnings.is_empty() {
+        println!("{}", Msg::LintClean);
+    } else {
+        println!(
+            "{}",
+            Msg::LintWarnings {
+                count: warnings.len()
+            }
+        );
+    }
+    Ok(())
+}
+
+/// 分组用的输出目录：SPEC=OUTDIR 映射 > --out > 当前工作目录（描述自带的 out 要解析后才知道）
+fn out_dir(mapped: Option<&Path>, gen: &GenerateArgs) -> Result<PathBuf> {
+    match mapped.map(Path::to_path_buf).or_else(|| gen.out.clone()) {
+        Some(dir) => Ok(dir),
+        None => env::current_dir().with_context(|| Msg::NoCurrentDir.to_string()),
+    }
+}
+
+/// --root-from-spec：输入文件名（去掉扩展名与压缩扩展名）作为 --out 下的根目录名，
+/// 与描述中的名称做同样的检查
+fn root_from_spec(mapped: &MappedInput, gen: &GenerateArgs) -> Result<PathBuf> {
+    let path = &mapped.input.path;
+    if let Some(out) = &mapped.out {
+        bail!(
+            "--root-from-spec and SPEC=OUTDIR ('{}={}') are mutually exclusive; \
+             each picks the directory the spec generates into",
+            path.display(),
+            out.display()
+        );
+    }
+    if path == Path::new("-") {
+        bail!("--root-from-spec needs a spec file; the input from stdin has no filename");
+    }
+    let path = match treegen::Compression::from_path(path) {
+        Some(_) => Path::new(path.file_stem().unwrap_or_default()),
+        None => path.as_path(),
+    };
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|stem| !stem.is_empty() && *stem != ".")
+        .with_context(|| {
+            format!(
+                "Cannot derive a root directory name from '{}'",
+                mapped.input.path.display()
+            )
+        })?;
+    let root = Node::root().child(Node::dir(stem)).build()?;
+    treegen::check_names(&root)?;
+    let base = gen.out.clone().unwrap_or_default();
+    Ok(base.join(stem))
+}
+
+/// 展开 glob 形式的输入并加入 --recursive 目录下的描述文件；没有匹配时报错
+fn expand_inputs(args: &Args) -> Result<Vec<MappedInput>> {
+    let mut expanded = Vec::new();
+    for mapped in &args.input {
+        if let Some(remote) = mapped.input.path.to_str().and_then(Remote::parse) {
+            let fetched = remote.fetch(&fetch_options(args)?)?;
+            if let Some(warning) = &fetched.warning {
+                eprintln!("Warning: {}", warning);
+            }
+            if args.gen.verbose {
+                log!(
+                    "Fetched spec: {} (from '{}')",
+                    fetched.path.display(),
+                    remote.input
+                );
+            }
+            expanded.push(MappedInput {
+                input: Input {
+                    path: fetched.path,
+                    format: mapped.input.format,
+                },
+                out: mapped.out.clone(),
+                remote: Some((remote.input, fetched.checkout)),
+            });
+            continue;
+        }
+        let pattern = mapped.input.path.to_string_lossy();
+        if mapped.input.path.exists() || !pattern.contains(['*', '?', '[']) {
+            expanded.push(mapped.clone());
+            continue;
+        }
+        let mut paths = glob::glob(&pattern)
+            .with_context(|| format!("Invalid glob pattern '{}'", pattern))?
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("Failed to expand '{}'", pattern))?;
+        paths.retain(|path| path.is_file());
+        if paths.is_empty() {
+            bail!("No spec files match '{}'", pattern);
+        }
+        paths.sort();
+        for path in paths {
+            if args.gen.verbose {
+                log!("Found spec: {} (from '{}')", path.display(), pattern);
+            }
+            expanded.push(MappedInput {
+                input: Input {
+                    path,
+                    format: mapped.input.format,
+                },
+                out: mapped.out.clone(),
+                remote: None,
+            });
+        }
+    }
+    for dir in &args.recursive {
+        let mut paths = Vec::new();
+        find_specs(dir, &mut paths)?;
+        if paths.is_empty() {
+            bail!("No spec files found under '{}'", dir.display());
+        }
+        paths.sort();
+        for path in paths {
+            if args.gen.verbose {
+                log!("Found spec: {}", path.display());
+            }
+            expanded.push(MappedInput {
+                input: Input::from(path),
+                out: None,
+                remote: None,
+            });
+        }
+    }
+    Ok(expanded)
+}
+
+/// 获取 git 仓库中的描述时的选项；缓存目录：--cache-dir/TREEGEN_CACHE_DIR > $XDG_CACHE_HOME/treegen > ~/.cache/treegen
+fn fetch_options(args: &Args) -> Result<FetchOptions> {
+    let cache_dir = match &args.cache_dir {
+        Some(dir) => dir.clone(),
+        None => env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".cache")))
+            .context("Cannot determine the cache directory; pass --cache-dir")?
+            .join("treegen"),
+    };
+    Ok(FetchOptions {
+        git_ref: args.git_ref.clone(),
+        offline: args.offline,
+        cache_dir,
+    })
+}
+
+/// 递归收集 `dir` 下扩展名受支持的文件
+fn find_specs(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory '{}'", dir.display()))?;
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("Failed to read directory '{}'", dir.display()))?
+            .path();
+        if path.is_dir() {
+            find_specs(&path, found)?;
+        } else if Format::from_path(&path).is_some() {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 按输出目录分组生成：映射到同一目录的输入合并后生成一次，各组分别打印结果
+fn run_inputs(args: &Args, matches: &ArgMatches) -> Result<()> {
+    // (输出目录, 规范化后的绝对路径, 输入)，按首次出现的顺序
+    let mut groups: Vec<(Option<PathBuf>, PathBuf, Vec<MappedInput>)> = Vec::new();
+    for mapped in expand_inputs(args)? {
+        let out = match args.root_from_spec {
+            true => Some(root_from_spec(&mapped, &args.gen)?),
+            false => mapped.out.clone(),
+        };
+        let dir = absolute(&out_dir(out.as_deref(), &args.gen)?)?;
+        match groups.iter_mut().find(|(_, key, _)| *key == dir) {
+            Some((_, _, inputs)) => inputs.push(mapped),
+            None => groups.push((out, dir, vec![mapped])),
+        }
+    }
+    if !args.allow_nested_out {
+        for (_, outer, _) in &groups {
+            if let Some((_, inner, _)) = groups
+                .iter()
+                .find(|(_, inner, _)| inner != outer && inner.starts_with(outer))
+            {
+                bail!(
+                    "Output directory '{}' is inside '{}'; pass --allow-nested-out to allow nested output directories",
+                    inner.display(),
+                    outer.display()
+                );
+            }
+        }
+    }
+    // 标准输入只能读一次
+    if groups.len() > 1 && args.gen.stdin_content.is_some() {
+        bail!(
+            "--stdin-content cannot be used when inputs generate into several output directories"
+        );
+    }
+    for (out, _, mapped) in &groups {
+        let inputs: Vec<Input> = mapped.iter().map(|m| m.input.clone()).collect();
+        let checkouts: Vec<(PathBuf, PathBuf)> = mapped
+            .iter()
+            .filter_map(|m| Some((m.input.path.clone(), m.remote.as_ref()?.1.clone())))
+            .collect();
+        let mut timings = Timings::default();
+        let mut spec = load_inputs_timed(
+            &inputs,
+            args.format,
+            &parse_options(args),
+            args.on_duplicate.into(),
+            args.gen.verbose,
+            &checkouts,
+            &mut timings,
+        )?;
+        let remote: Vec<&str> = mapped
+            .iter()
+            .filter_map(|m| m.remote.as_ref().map(|(input, _)| input.as_str()))
+            .collect();
+        if !remote.is_empty() && !spec.hooks.is_empty() {
+            spec.hooks = remote_hooks(spec.hooks, &remote.join("', '"), &args.gen)?;
+        }
+        run_generate(
+            &args.gen,
+            spec,
+            out.as_deref(),
+            Some(&args.list),
+            matches,
+            timings,
+        )?;
+    }
+    Ok(())
+}
+
+/// 相对当前目录的绝对路径，按字面消去 `.` 与 `..`（目录可能还不存在）
+fn absolute(path: &Path) -> Result<PathBuf> {
+    let cwd = env::current_dir().with_context(|| Msg::NoCurrentDir.to_string())?;
+    let mut out = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                out.pop();
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// 逐行打印差异（`+` 新增、`-` 删除、`~` 修改），有差异时返回错误
+fn run_diff(diff: &DiffArgs) -> Result<()> {
+    let load = |input: &Input| {
+        load_inputs(
+            std::slice::from_ref(input),
+            None,
+            &ParseOptions::default(),
+            OnDuplicate::Last,
+            false,
+        )
+    };
+    let old = load(&diff.old)?;
+    let new = load(&diff.new)?;
+
+    let differences = treegen::diff::diff(&old.root, &new.root);
+    for d in &differences {
+        println!("{} {}: {}", d.change.symbol(), d.path.display(), d.message);
+        if let (true, Some((a, b))) = (diff.unified, &d.contents) {
+            print!("{}", treegen::diff::unified(&d.path, a, b));
+        }
+    }
+
+    if !differences.is_empty() {
+        bail!("{} difference(s) found", differences.len());
+    }
+    println!("{}", Msg::NoDifferences);
+    Ok(())
+}
+
+fn run_verify(args: &VerifyArgs) -> Result<()> {
+    let text = fs::read_to_string(&args.checksums)
+        .with_context(|| format!("Failed to read '{}'", args.checksums.display()))?;
+    let entries = treegen::checksum::parse_manifest(&text)
+        .with_context(|| format!("Invalid checksum file '{}'", args.checksums.display()))?;
+    let out = match &args.out {
+        Some(out) => out.clone(),
+        None => env::current_dir()?,
+    };
+    let problems = treegen::checksum::verify(&entries, &out, Some(&args.checksums))?;
+    for p in &problems {
+        match p.change {
+            Change::Removed => println!("missing: {}", p.path.display()),
+            Change::Added => println!("extra: {}", p.path.display()),
+            Change::Changed => println!("mismatch: {} ({})", p.path.display(), p.message),
+        }
+    }
+    if !problems.is_empty() {
+        bail!("{} file(s) failed verification", problems.len());
+    }
+    println!(
+        "{}",
+        Msg::ChecksumsMatch {
+            count: entries.len()
+        }
+    );
+    Ok(())
+}
+
+fn run_vars(args: &VarsArgs) -> Result<()> {
+    let Spec { root, vars, .. } = load_inputs(
+        &args.input,
+        args.format,
+        &ParseOptions::default(),
+        OnDuplicate::Last,
+        false,
+    )?;
+    let undeclared: Vec<String> = treegen::referenced_vars(&root)
+        .into_iter()
+        .filter(|name| !vars.contains_key(name) && !name.starts_with("__"))
+        .collect();
+    if vars.is_empty() && undeclared.is_empty() {
+        println!("{}", Msg::NoVars);
+        return Ok(());
+    }
+    let width = vars
+        .keys()
+        .chain(&undeclared)
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, decl) in &vars {
+        let mut line = format!("{:<width$}  {}", name, decl.kind.name(), width = width);
+        if let Some(default) = &decl.default {
+            line.push_str(&format!(" = {}", default));
+        }
+        if let Some(description) = &decl.description {
+            line.push_str(&format!("  # {}", description));
+        }
+        println!("{}", line);
+    }
+    for name in &undeclared {
+        println!(
+            "{:<width$}  (referenced but not declared)",
+            name,
+            width = width
+        );
+    }
+    Ok(())
+}
+
+fn run_profiles(args: &ProfilesArgs) -> Result<()> {
+    let Spec { root, .. } = load_inputs(
+        &args.input,
+        args.format,
+        &ParseOptions::default(),
+        OnDuplicate::Last,
+        false,
+    )?;
+    let profiles = treegen::profiles(&root);
+    if profiles.is_empty() {
+        println!("{}", Msg::NoProfiles);
+        return Ok(());
+    }
+    let width = profiles.keys().map(|name| name.len()).max().unwrap_or(0);
+    for (name, count) in &profiles {
+        println!("{:<width$}  {} node(s)", name, count, width = width);
+    }
+    Ok(())
+}
+
+/// treegen merge：按 --on-duplicate 合并输入，写出一个描述文件
+fn run_merge(args: &MergeArgs) -> Result<()> {
+    let format = args
+        .to
+        .map(Format::from)
+        .or_else(|| args.output.as_deref().and_then(Format::from_path))
+        .unwrap_or(Format::Yaml);
+    if let Some(output) = &args.output {
+        if Compression::from_path(output).is_some() {
+            bail!(
+                "'{}' is a compressed file name; merge writes plain text specs",
+                output.display()
+            );
+        }
+        if args
+            .input
+            .iter()
+            .any(|input| same_file(&input.path, output))
+        {
+            bail!(
+                "'{}' is one of the inputs; write the merged spec to another file",
+                output.display()
+            );
+        }
+    }
+    let spec = load_inputs(
+        &args.input,
+        args.format,
+        &ParseOptions::default(),
+        args.on_duplicate.into(),
+        false,
+    )?;
+    let text = spec
+        .write(format)
+        .with_context(|| format!("Failed to write the merged spec as {}", format.name()))?;
+    if matches!(format, Format::Markdown | Format::Paths)
+        && spec
+            .root
+            .iter_files()
+            .any(|(_, node)| node.content.is_some())
+    {
+        eprintln!(
+            "Warning: File contents are not written to {}; only the structure is kept",
+            format.name()
+        );
+    }
+    match &args.output {
+        Some(path) => {
+            fs::write(path, &text)
+                .with_context(|| format!("Failed to write merged spec '{}'", path.display()))?;
+            println!(
+                "{}",
+                Msg::Merged {
+                    count: args.input.len(),
+                    path
+                }
+            );
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// treegen render：把合并后的树渲染成 HTML 或 SVG
+fn run_render(args: &RenderArgs) -> Result<()> {
+    let format = args.to.unwrap_or_else(|| {
+        let ext = args
+            .output
+            .as_deref()
+            .and_then(Path::extension)
+            .map(|ext| ext.to_string_lossy().to_lowercase());
+        match ext.as_deref() {
+            Some("svg") => RenderFormat::Svg,
+            _ => RenderFormat::Html,
+        }
+    });
+    if let Some(output) = &args.output {
+        if args
+            .input
+            .iter()
+            .any(|input| same_file(&input.path, output))
+        {
+            bail!(
+                "'{}' is one of the inputs; render to another file",
+                output.display()
+            );
+        }
+    }
+    let spec = load_inputs(
+        &args.input,
+        args.format,
+        &ParseOptions::default(),
+        OnDuplicate::Last,
+        false,
+    )?;
+    let text = match format {
+        RenderFormat::Html => {
+            let title = args
+                .input
+                .iter()
+                .map(|input| input.path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            treegen::render::html(&spec.root, &title)
+        }
+        RenderFormat::Svg => treegen::render::svg(&spec.root),
+    };
+    match &args.output {
+        Some(path) => {
+            fs::write(path, &text)
+                .with_context(|| format!("Failed to write '{}'", path.display()))?;
+            println!("{}", Msg::Rendered { path });
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// 两个路径指向同一个文件（不存在的路径按字面比较）
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 按 --profile 剪掉其他 profile 的节点；dry-run（或 --verbose）时列出被剪掉的路径
+fn apply_profiles(root: &mut Node, gen: &GenerateArgs) {
+    if gen.profile.is_empty() {
+        return;
+    }
+    let available = treegen::profiles(root);
+    for name in gen
+        .profile
+        .iter()
+        .filter(|name| !available.contains_key(*name))
+    {
+        eprintln!("Warning: Profile '{}' is not used by any node", name);
+    }
+    let selected: BTreeSet<String> = gen.profile.iter().cloned().collect();
+    let excluded = treegen::filter_profiles(root, &selected);
+    if gen.dry_run || gen.verbose {
+        let prefix = if gen.dry_run { "[Dry-Run] " } else { "" };
+        for path in &excluded {
+            log!("{}Excluded by profile: {}", prefix, path.display());
+        }
+    }
+}
+
+/// 应用 `$rules`；dry-run 或 --verbose 时列出每个文件匹配的规则
+fn apply_rules(root: &mut Node, rules: &[Rule], gen: &GenerateArgs) -> Result<()> {
+    let touched = treegen::apply_rules(root, rules)?;
+    if gen.dry_run || gen.verbose {
+        let prefix = if gen.dry_run { "[Dry-Run] " } else { "" };
+        for (path, matched) in &touched {
+            let names: Vec<String> = matched
+                .iter()
+                .map(|&i| format!("#{} ({})", i + 1, rules[i].pattern))
+                .collect();
+            log!(
+                "{}Rules applied to {}: {}",
+                prefix,
+                path.display(),
+                names.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// --list：按创建顺序打印相对输出目录的路径，目录（及复制目录的节点）以 / 结尾；
+/// 匹配 --exclude 的路径及其下的路径、--no-touch 时不会创建的文件都不列出
+fn print_list(root: &Node, kind: ListKind, print0: bool, gen: &GenerateArgs) -> Result<()> {
+    let mut exclude = GlobSetBuilder::new();
+    for pattern in &gen.exclude {
+        exclude.add(
+            Glob::new(pattern).with_context(|| format!("Invalid --exclude glob '{}'", pattern))?,
+        );
+    }
+    let exclude = exclude.build()?;
+    let terminator = if print0 { '\0' } else { '\n' };
+    let mut out = String::new();
+    root.walk(&mut |path, node| {
+        if path.as_os_str().is_empty() {
+            return WalkControl::Continue;
+        }
+        let rel = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if exclude.is_match(&rel) {
+            return WalkControl::SkipChildren;
+        }
+        let is_dir = match node.node_type {
+            NodeType::Dir => true,
+            NodeType::Copy => node.copy.as_ref().is_some_and(|source| source.is_dir()),
+            NodeType::File => {
+                if gen.no_touch && node.content.is_none() {
+                    return WalkControl::Continue;
+                }
+                false
+            }
+            NodeType::Hardlink => false,
+        };
+        let wanted = match kind {
+            ListKind::All => true,
+            ListKind::Files => !is_dir,
+            ListKind::Dirs => is_dir,
+        };
+        if wanted {
+            out.push_str(&rel);
+            if is_dir {
+                out.push('/');
+            }
+            out.push(terminator);
+        }
+        WalkControl::Continue
+    });
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(out.as_bytes())
+        .and_then(|()| stdout.flush())
+        .context("Failed to write the path list")
+}
 
-    /// 输出根目录（可选，默认是当前工作目录）
-    #[arg(short, long)]
-    out: Option<PathBuf>,
+/// 替换模板变量后，在输出目录下生成文件树，成功后执行钩子
+/// --report：运行 `f` 并写出运行报告，失败时也写出；写报告失败时运行本身的错误优先
+fn with_report(gen: &GenerateArgs, f: impl FnOnce() -> Result<()>) -> Result<()> {
+    let Some(path) = &gen.report else {
+        return f();
+    };
+    report::start(path, &gen.redact_var);
+    let result = f();
+    let (status, error) = match &result {
+        Ok(()) => (report::Status::Success, None),
+        Err(e) => (report::Status::Failed, Some(format!("{:#}", e))),
+    };
+    let written = report::finish(status, error);
+    match result {
+        Ok(()) => written,
+        Err(e) => {
+            if let Err(report_error) = written {
+                eprintln!("Warning: {:#}", report_error);
+            }
+            Err(e)
+        }
+    }
+}
 
-    /// 仅预览将要创建的文件/目录，不写入磁盘
-    #[arg(long)]
-    dry_run: bool,
+fn run_generate(
+    gen: &GenerateArgs,
+    spec: Spec,
+    out: Option<&Path>,
+    list: Option<&ListArgs>,
+    matches: &ArgMatches,
+    mut timings: Timings,
+) -> Result<()> {
+    // 从这里到预检结束记为 validate
+    let started = Instant::now();
+    let Spec {
+        mut root,
+        hooks,
+        vars: declared,
+        rules,
+        config,
+    } = spec;
+    // 之后的处理都是递归的，先拒绝过深的树
+    treegen::check_depth(&root, gen.max_tree_depth)?;
 
-    /// 打印详细日志（每个文件/目录创建情况）
-    #[arg(short, long)]
-    verbose: bool,
+    // 解析 mode，如 "0o644" -> 0o644；预检时与其他问题一起报告
+    let preflight = gen.dry_run || gen.preflight;
+    let mode_text = match &config.mode {
+        Some(mode) if !given(matches, "mode") => mode,
+        _ => &gen.mode,
+    };
+    let (mode, mode_problem) = match treegen::parse_mode(mode_text) {
+        Ok(mode) => (mode, None),
+        Err(e) if preflight => (0o644, Some(e.to_string())),
+        Err(e) => return Err(e),
+    };
 
-    /// 如果输出目录已存在同名路径，先删除再创建（谨慎使用）
-    #[arg(long)]
-    clean: bool,
+    // 命名片段最先展开，规则与变量替换作用于片段的内容
+    expand_snippets(&mut root, gen.verbose)?;
 
-    /// 新建文件的权限（八进制，如 0o644，仅类 Unix 平台生效）
-    #[arg(long, default_value = "0o644")]
-    mode: String,
-}
+    // 规则先于变量替换，它加入的文本同样可以引用变量
+    apply_rules(&mut root, &rules, gen)?;
 
-/// 节点类型：目录或文件
-#[derive(Debug)]
-enum NodeType {
-    Dir,
-    File,
-}
+    // 替换模板变量
+    let mut vars = read_var_files(&gen.var_files)?;
+    vars.extend(collect_vars(&gen.vars, matches));
+    for (name, value) in treegen::builtin_vars() {
+        vars.entry(name).or_insert(value);
+    }
+    // 确定输出目录：SPEC=OUTDIR 映射 > --out > TREEGEN_OUT > 描述自带的 out > 配置文件 > 当前工作目录。
+    // 无论来自哪里都可以引用模板变量，与描述中的变量一起解析
+    let (out_template, out_source) = match (out, &config.out) {
+        (Some(dir), _) => (Some(dir.to_path_buf()), "SPEC=OUTDIR"),
+        (None, _) if given(matches, "out") => match matches.value_source("out") {
+            Some(ValueSource::EnvVariable) => (gen.out.clone(), "TREEGEN_OUT"),
+            _ => (gen.out.clone(), "--out"),
+        },
+        (None, Some(dir)) => (Some(dir.clone()), "the spec"),
+        (None, None) => match &gen.out {
+            Some(dir) => (Some(dir.clone()), "config file"),
+            None => (None, "current directory"),
+        },
+    };
+    let mut referenced = treegen::referenced_vars(&root);
+    referenced.extend(
+        out_template
+            .iter()
+            .filter_map(|dir| dir.to_str())
+            .flat_map(treegen::vars_in),
+    );
+    resolve_vars(&referenced, &declared, &mut vars, gen)?;
+    report::record_vars(&vars);
+    // 替换之后就看不出哪些文件用到了要隐去的变量
+    treegen::mark_redacted(&mut root, &gen.redact_var);
+    treegen::substitute_vars(&mut root, &vars)?;
 
-/// 树节点结构
-#[derive(Debug)]
-struct Node {
-    name: String,
-    node_type: NodeType,
-    children: Vec<Node>,
-    content: Option<String>, // 用于 YAML/JSON/TOML/JSON5 中指定文件内容
-}
+    // 替换进来的值同样去掉行尾空白
+    let trimmed = treegen::trim_trailing_whitespace(&mut root, gen.trim_trailing_whitespace);
+    if gen.verbose && trimmed > 0 {
+        log!("Trimmed trailing whitespace in {} file(s)", trimmed);
+    }
 
-impl Node {
-    /// 构造一个文件节点（可携带内容）
-    fn new_file(name: String, content: Option<String>) -> Self {
-        Node {
-            name,
-            node_type: NodeType::File,
-            children: Vec::new(),
-            content,
+    // 标准输入的内容在变量替换之后挂上，原样写入
+    if let Some(path) = &gen.stdin_content {
+        let mut content = Vec::new();
+        io::stdin()
+            .read_to_end(&mut content)
+            .context("Failed to read file content from stdin")?;
+        let len = content.len();
+        if treegen::attach_content(&mut root, path, content)?.is_some() {
+            eprintln!(
+                "Warning: Content of '{}' in the spec is replaced by stdin",
+                path.display()
+            );
+        }
+        if gen.dry_run {
+            log!(
+                "[Dry-Run] Read {} bytes from stdin for: {}",
+                len,
+                path.display()
+            );
+        } else if gen.verbose {
+            log!("Read {} bytes from stdin for: {}", len, path.display());
         }
     }
-    /// 构造一个空目录节点
-    fn new_dir(name: String) -> Self {
-        Node {
-            name,
-            node_type: NodeType::Dir,
-            children: Vec::new(),
-            content: None,
+
+    // 替换输出目录中的变量；未定义的变量在创建任何目录之前报错
+    let out_dir = match out_template {
+        Some(dir) => match dir.to_str() {
+            Some(text) => {
+                let label = match out_source {
+                    "the spec" => "$out",
+                    source => source,
+                };
+                PathBuf::from(treegen::render_vars(text, &vars, Path::new(label))?)
+            }
+            // 不是 UTF-8 的路径中不会有变量
+            None => dir,
+        },
+        None => env::current_dir().with_context(|| Msg::NoCurrentDir.to_string())?,
+    };
+    if gen.verbose {
+        log!(
+            "Output directory: {} (from {})",
+            out_dir.display(),
+            out_source
+        );
+    }
+
+    // 按 --profile 剪枝，之后的检查与生成都只看剩下的节点
+    apply_profiles(&mut root, gen);
+    treegen::sort_tree(&mut root, gen.sort.into());
+
+    // 为没有内容的文件填入默认内容
+    if gen.default_content {
+        let project = out_dir
+            .canonicalize()
+            .unwrap_or_else(|_| out_dir.clone())
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "project".to_string());
+        fill_default_content(
+            &mut root,
+            &DefaultContent::load()?,
+            &vars,
+            &project,
+            gen.verbose,
+        )?;
+    }
+
+    // --flatten 在默认内容之后进行，按文件名选片段时用的还是原来的名称
+    let mut dropped_dirs = Vec::new();
+    if gen.flatten {
+        dropped_dirs = treegen::flatten(&mut root, &gen.flatten_sep)?;
+        if gen.verbose {
+            for dir in &dropped_dirs {
+                log!("Skip empty directory (flatten): {}", dir.display());
+            }
         }
     }
-}
 
-/// === Markdown 树状目录解析 ===
-/// 示例：
-/// project/
-/// ├── src/
-/// │   ├── main.rs
-/// │   └── lib.rs
-/// ├── Cargo.toml
-/// └── README.md
-fn parse_md_tree(lines: &[String]) -> Result<Node> {
-    // 根节点（"" 表示从指定输出目录开始，不创建额外文件夹）
-    let mut root = Node::new_dir("".to_string());
+    // --list 只打印计划生成的路径
+    if let Some(ListArgs {
+        list: Some(kind),
+        print0,
+    }) = list
+    {
+        return print_list(&root, *kind, *print0, gen);
+    }
+
+    // 写入期间持有输出目录的锁，同时往这里生成的其他 treegen 等待；dry-run 与 --check 不加锁
+    let mut lock = None;
+    if gen.fix && !gen.dry_run && out_dir.is_dir() {
+        lock = Some(lock_out_dir(&out_dir, gen)?);
+    }
+
+    // --check 到此为止，不碰输出目录；--fix 先删掉类型不符（及 --sync 时多出）的路径
+    if gen.check || gen.fix {
+        let differences = out_of_sync(&root, &out_dir, gen)?;
+        if gen.check {
+            return report_out_of_sync(&differences);
+        }
+        remove_out_of_sync(&differences, &out_dir, gen)?;
+    }
 
-    // 栈：维护 (level, *mut Node) 以便附加子节点
-    let mut stack: Vec<(usize, *mut Node)> = Vec::new();
-    let root_ptr: *mut Node = &mut root as *mut Node;
-    stack.push((0, root_ptr));
+    let opts = GenerateOptions {
+        dry_run: gen.dry_run,
+        verbose: gen.verbose,
+        mode,
+        exec_ext: gen
+            .exec_ext
+            .iter()
+            .map(|ext| ext.trim_start_matches('.').to_string())
+            .filter(|ext| !ext.is_empty())
+            .collect(),
+        auto_exec: gen.auto_exec,
+        no_touch: gen.no_touch,
+        skip_existing: gen.skip_existing,
+        fix_modes: gen.fix_modes,
+        max_depth: gen.max_tree_depth,
+        max_name_bytes: gen.max_name_bytes,
+        strict: gen.strict,
+        chown: gen.chown.clone(),
+        durable: gen.durable,
+        windows_hidden_dotfiles: gen.windows_hidden_dotfiles,
+        keep_going: gen.keep_going,
+        content_only: gen.content_only,
+        no_default_ignores: gen.no_default_ignores,
+        verify_writes: gen.verify_writes,
+        seed: gen.seed,
+        timestamp: None,
+        max_bytes: gen.max_bytes,
+        cancel: CancelToken::new(),
+    };
 
-    // 正则匹配：捕获缩进(indent)、可选前缀(prefix)、以及名称(name)
-    let re = Regex::new(r"^(?P<indent>(│   |    )*)(?P<prefix>├── |└── )?(?P<name>.+)$")?;
+    // dry-run 时对照已有的输出目录标注每个计划路径（与 --check 相同的比较），
+    // 在预检报错之前打印，类型冲突也能在树中看到；比较失败（如复制来源不存在）时交给预检报告
+    let status_tree = gen.dry_run && !gen.json && !gen.clean && out_dir.is_dir();
+    if status_tree {
+        if let Ok(status) = treegen::diff::status_dir(&root, &out_dir) {
+            print!("{}", status_tree_text(&root, &status));
+        }
+    }
 
-    for line in lines {
-        if line.trim().is_empty() {
-            continue;
+    // 预检一次列出所有会让生成失败的问题；--clean/--fix 会先清掉类型不符的已有路径
+    if preflight {
+        let problems: Vec<String> = mode_problem
+            .into_iter()
+            .chain(treegen::preflight::preflight(
+                &root,
+                &out_dir,
+                &opts,
+                !gen.clean && !gen.fix,
+            ))
+            .collect();
+        if !problems.is_empty() {
+            bail!(
+                "Pre-flight checks failed ({} problem(s)):\n{}",
+                problems.len(),
+                problems
+                    .iter()
+                    .map(|problem| format!("  - {}", problem))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
         }
-        let caps = re
-            .captures(line)
-            .with_context(|| format!("Line '{}' does not match Markdown tree format", line))?;
+        if gen.verbose {
+            let prefix = if gen.dry_run { "[Dry-Run] " } else { "" };
+            println!("{}Pre-flight checks passed", prefix);
+        }
+    }
 
-        // 计算 indent_blocks = 每 4 字符算一级
-        let indent_str = caps.name("indent").map_or("", |m| m.as_str());
-        let indent_blocks = indent_str.chars().count() / 4;
+    // 写入量超限时在清空或创建输出目录之前拒绝（预检已经报告过）
+    if !preflight {
+        treegen::check_max_bytes(&root, gen.max_bytes)?;
+        treegen::check_disk_space(&root, &out_dir)?;
+        treegen::check_ownership(&root, &opts)?;
+    }
+    timings.record(
+        "validate",
+        Timing {
+            elapsed: started.elapsed(),
+            nodes: root.iter().count(),
+            bytes: 0,
+        },
+    );
 
-        // 如果有 prefix (“├── ” 或 “└── ”)，层级 = indent_blocks + 2；否则 = indent_blocks + 1
-        let level = if caps.name("prefix").is_some() {
-            indent_blocks + 2
-        } else {
-            indent_blocks + 1
-        };
+    // --dry-run --json 只打印计划
+    if gen.json {
+        let plan = treegen::plan(&root, &out_dir, &opts)?;
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
 
-        let name = caps.name("name").unwrap().as_str().trim().to_string();
-        let node_type = if name.ends_with('/') {
-            NodeType::Dir
-        } else {
-            NodeType::File
-        };
+    if gen.content_only && !out_dir.is_dir() {
+        bail!(
+            "Output directory '{}' does not exist; --content-only only updates existing paths",
+            out_dir.display()
+        );
+    }
 
-        let child = Node {
-            name: name.clone(),
-            node_type,
-            children: Vec::new(),
-            content: None, // 移除内容填充功能
-        };
+    // 锁文件放在输出目录中，加锁前先创建它
+    let out_existed = out_dir.exists();
+    if lock.is_none() && !gen.dry_run {
+        create_out_dir(&out_dir, gen)?;
+        lock = Some(lock_out_dir(&out_dir, gen)?);
+    }
+
+    // 如果 --clean 并且 out_dir 存在，检查并确认后先清空
+    if gen.clean && out_dir.exists() && confirm_clean(&out_dir, gen)? {
+        if gen.verbose {
+            println!("Cleaning existing directory: {}", out_dir.display());
+        }
+        // 只清空内容，保留目录本身：其中的锁文件正被持有，当前目录（--allow-cwd）删掉后相对路径都无法再用
+        for entry in fs::read_dir(&out_dir)? {
+            let entry = entry?;
+            if entry.file_name() == LOCK_FILE {
+                continue;
+            }
+            let path = entry.path();
+            if path.is_dir() && !path.is_symlink() {
+                fs::remove_dir_all(&path)
+                    .with_context(|| format!("Failed to remove directory '{}'", path.display()))?;
+            } else {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove '{}'", path.display()))?;
+            }
+        }
+        // 与重新创建的目录一样设置 --out-mode
+        #[cfg(unix)]
+        if let Some(mode) = gen.out_mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&out_dir, fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions on '{}'", out_dir.display()))?;
+        }
+    }
+
+    // 确保输出目录存在（dry-run 时打印将要创建的目录）；--clean 时由生成过程创建，除非要设置 --out-mode
+    if !gen.clean || gen.out_mode.is_some() {
+        create_out_dir(&out_dir, gen)?;
+    }
 
-        // 弹出直到栈顶的 level < 当前 level
-        while stack.last().unwrap().0 >= level {
-            stack.pop();
+    // 在 out_dir 下创建目录/文件；生成期间 Ctrl-C 只设置取消标志，写完当前路径后停下
+    install_interrupt_handler()?;
+    let plan = timings.time("plan", || treegen::plan(&root, &out_dir, &opts))?;
+    timings.set_nodes(plan.actions.len());
+    *active_generation() = Some(opts.cancel.clone());
+    let result = treegen::execute(&plan, &opts);
+    *active_generation() = None;
+    let stats = match result.map_err(|e| e.downcast::<Interrupted>()) {
+        Ok(stats) => stats,
+        Err(Ok(interrupted)) => {
+            report::record_run(&plan, &interrupted.stats, gen.dry_run);
+            report::record_timings(timings.json());
+            exit_interrupted(interrupted, &out_dir, out_existed, lock, gen)
         }
-        // 此时栈顶即为父节点
-        let parent_ptr = stack.last().unwrap().1;
-        unsafe {
-            let parent_ref: &mut Node = &mut *parent_ptr;
-            parent_ref.children.push(child);
-            let last_idx = parent_ref.children.len() - 1;
-            if let NodeType::Dir = parent_ref.children[last_idx].node_type {
-                // 如果新节点是目录，把它压入栈
-                let child_ptr: *mut Node = &mut parent_ref.children[last_idx] as *mut Node;
-                stack.push((level, child_ptr));
+        Err(Err(e)) => {
+            // 没有 --keep-going 时停在第一个失败处，报告中仍记下已完成的路径
+            if let Some(aborted) = e.downcast_ref::<Aborted>() {
+                report::record_run(&plan, &aborted.stats, gen.dry_run);
+                timings.record_execute(&aborted.stats.timings, gen.durable && !gen.dry_run);
+                report::record_timings(timings.json());
             }
+            return Err(e);
+        }
+    };
+    report::record_run(&plan, &stats, gen.dry_run);
+    timings.record_execute(&stats.timings, gen.durable && !gen.dry_run);
+    for warning in &stats.warnings {
+        eprintln!(
+            "Warning: optional '{}' was not generated: {}",
+            warning.path.display(),
+            warning.error
+        );
+    }
+
+    let result = if gen.dry_run {
+        Msg::DryRunDone
+    } else if !stats.failures.is_empty() {
+        Msg::PartlyGenerated {
+            out: &out_dir,
+            failed: stats.failures.len(),
         }
+    } else {
+        Msg::Generated { out: &out_dir }
+    };
+    println!("{}", result);
+    let notes = [
+        (!dropped_dirs.is_empty()).then_some(Msg::FlattenDropped {
+            count: dropped_dirs.len(),
+        }),
+        (stats.skipped > 0).then_some(Msg::NoTouchSkipped {
+            count: stats.skipped,
+        }),
+        (stats.missing > 0).then_some(Msg::ContentOnlyMissing {
+            count: stats.missing,
+        }),
+        (gen.verify_writes && !gen.dry_run).then_some(Msg::WritesVerified {
+            count: stats.verified.len(),
+        }),
+        (!stats.warnings.is_empty()).then_some(Msg::OptionalSkipped {
+            count: stats.warnings.len(),
+        }),
+    ];
+    for note in notes.into_iter().flatten() {
+        println!("{}", note);
+    }
+    // dry-run 时已经打印过对照输出目录标注的树
+    if gen.print_tree && !status_tree {
+        print!("{}", annotated_tree(&root, &stats.outcomes));
+    }
+    if let Some(path) = &gen.summary_json {
+        write_summary(&root, &stats, &out_dir, path, &timings, gen)?;
+    }
+    // --keep-going：其余路径已生成，按类型列出失败的路径后以非零状态退出，不再执行钩子
+    if !stats.failures.is_empty() {
+        if gen.timings {
+            print!("{}", timings.table());
+        }
+        report::record_timings(timings.json());
+        return Err(FailureReport(stats.failures).into());
+    }
+    if let Some(path) = &gen.checksums {
+        write_checksums(&root, &out_dir, path, &stats, gen)?;
     }
 
-    Ok(root)
+    // 描述文件中的 $hooks 先执行，然后是 --run
+    let mut hooks_result = Ok(());
+    if !gen.no_hooks {
+        let commands: Vec<String> = hooks.into_iter().chain(gen.run.iter().cloned()).collect();
+        hooks_result = timings.time("hooks", || run_hooks(&commands, &out_dir, gen));
+        timings.set_nodes(commands.len());
+    }
+    report::record_timings(timings.json());
+    hooks_result?;
+    if gen.timings {
+        print!("{}", timings.table());
+    }
+    Ok(())
 }
 
-/// === YAML/JSON/TOML 解析 ===
-/// SerdeNode 用于反序列化：
-/// - Str(String)：代表文件内容
-/// - Map(BTreeMap<_, _>)：代表目录及其子结构
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum SerdeNode {
-    Str(String),
-    Map(BTreeMap<String, SerdeNode>),
+/// 一次生成中各阶段的耗时与处理量：总是记录，--timings 时打印成表格，也写进 --summary-json
+#[derive(Debug, Default)]
+struct Timings(Vec<(String, Timing)>);
+
+impl Timings {
+    fn record(&mut self, phase: impl Into<String>, timing: Timing) {
+        self.0.push((phase.into(), timing));
+    }
+
+    /// 计时运行 `f`；处理的节点数之后用 [`Timings::set_nodes`] 补上
+    fn time<T>(&mut self, phase: &str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.record(
+            phase,
+            Timing {
+                elapsed: started.elapsed(),
+                ..Timing::default()
+            },
+        );
+        result
+    }
+
+    /// 最近记录的阶段处理的节点数
+    fn set_nodes(&mut self, nodes: usize) {
+        if let Some((_, timing)) = self.0.last_mut() {
+            timing.nodes = nodes;
+        }
+    }
+
+    /// 生成过程按动作类型分开记录；没有 --durable 时没有同步目录这一步
+    fn record_execute(&mut self, execute: &ExecuteTimings, durable: bool) {
+        self.record("execute: dirs", execute.dirs);
+        self.record("execute: files", execute.files);
+        self.record("execute: modes", execute.modes);
+        if durable {
+            self.record("execute: sync", execute.sync);
+        }
+    }
+
+    fn table(&self) -> String {
+        let width = self
+            .0
+            .iter()
+            .map(|(phase, _)| phase.chars().count())
+            .chain([5])
+            .max()
+            .unwrap_or(0);
+        let mut out = format!(
+            "{:<width$}  {:>10}  {:>8}  {:>10}  {:>10}  {:>8}\n",
+            "phase", "time", "nodes", "bytes", "nodes/s", "MB/s"
+        );
+        let mut total = Duration::ZERO;
+        for (phase, timing) in &self.0 {
+            let secs = timing.elapsed.as_secs_f64();
+            let rate = |amount: f64| match secs > 0.0 {
+                true => format!("{:.0}", amount / secs),
+                false => "-".to_string(),
+            };
+            let mb = match (timing.bytes, secs > 0.0) {
+                (0, _) | (_, false) => "-".to_string(),
+                (bytes, true) => format!("{:.2}", bytes as f64 / 1e6 / secs),
+            };
+            out.push_str(&format!(
+                "{:<width$}  {:>10}  {:>8}  {:>10}  {:>10}  {:>8}\n",
+                phase,
+                format!("{:.2?}", timing.elapsed),
+                timing.nodes,
+                match timing.bytes {
+                    0 => "-".to_string(),
+                    bytes => treegen::preflight::human_bytes(bytes),
+                },
+                rate(timing.nodes as f64),
+                mb
+            ));
+            total += timing.elapsed;
+        }
+        out.push_str(&format!(
+            "{:<width$}  {:>10}\n",
+            "total",
+            format!("{:.2?}", total)
+        ));
+        out
+    }
+
+    /// `[{"phase", "seconds", "nodes", "bytes"}]`，按记录顺序
+    fn json(&self) -> serde_json::Value {
+        self.0
+            .iter()
+            .map(|(phase, timing)| {
+                serde_json::json!({
+                    "phase": phase,
+                    "seconds": timing.elapsed.as_secs_f64(),
+                    "nodes": timing.nodes,
+                    "bytes": timing.bytes,
+                })
+            })
+            .collect()
+    }
 }
 
-/// 将 SerdeNode 转为我们自己的 Node 结构
-fn serde_to_node(name: String, snode: &SerdeNode) -> Node {
-    match snode {
-        SerdeNode::Str(content) => Node::new_file(name, Some(content.clone())),
-        SerdeNode::Map(map) => {
-            let mut dir = Node::new_dir(name);
-            for (k, v) in map {
-                dir.children.push(serde_to_node(k.clone(), v));
+/// --clean 的保护：拒绝删除根目录、用户主目录、当前工作目录（或其上级，除非 --allow-cwd）
+/// 与含有 .git 的目录（除非 --allow-git）。按规范化后的路径检查，`--out ./..` 这样的写法绕不过去。
+/// 通过后 dry-run 只列出，否则需要 --yes 或在终端中确认；返回是否删除
+fn confirm_clean(out_dir: &Path, gen: &GenerateArgs) -> Result<bool> {
+    let target = fs::canonicalize(out_dir)
+        .with_context(|| format!("Failed to resolve output directory '{}'", out_dir.display()))?;
+    let refuse = |reason| {
+        anyhow::anyhow!(
+            "{}",
+            Msg::RefuseClean {
+                dir: &target,
+                reason
             }
-            dir
+        )
+    };
+    if target.parent().is_none() {
+        return Err(refuse(CleanRefusal::FilesystemRoot));
+    }
+    if let Some(home) = home_dir().and_then(|home| fs::canonicalize(home).ok()) {
+        if home.starts_with(&target) {
+            return Err(refuse(CleanRefusal::Home));
         }
     }
-}
+    if !gen.allow_cwd {
+        let cwd = env::current_dir().and_then(fs::canonicalize);
+        if cwd.is_ok_and(|cwd| cwd.starts_with(&target)) {
+            return Err(refuse(CleanRefusal::Cwd));
+        }
+    }
+    if !gen.allow_git && target.join(".git").exists() {
+        return Err(refuse(CleanRefusal::Git));
+    }
 
-/// 从 YAML 文件中解析出 Node 树
-fn parse_yaml_file(path: &PathBuf) -> Result<Node> {
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read YAML file '{}'", path.display()))?;
-    let data: BTreeMap<String, SerdeNode> = serde_yaml::from_str(&content)
-        .with_context(|| format!("Failed to parse YAML in '{}'", path.display()))?;
-    let mut root = Node::new_dir("".to_string());
-    for (k, v) in data {
-        root.children.push(serde_to_node(k, &v));
+    let entries = count_entries(&target);
+    if gen.dry_run {
+        println!(
+            "{}",
+            Msg::DryRunClean {
+                dir: &target,
+                entries
+            }
+        );
+        return Ok(false);
     }
-    Ok(root)
+    if gen.yes {
+        return Ok(true);
+    }
+    if gen.no_input || !io::stdin().is_terminal() {
+        bail!(
+            "{}",
+            Msg::CleanNeedsYes {
+                dir: &target,
+                entries
+            }
+        );
+    }
+    eprint!(
+        "{}",
+        Msg::ConfirmClean {
+            dir: &target,
+            entries
+        }
+    );
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    // 中文界面也接受“是”
+    if !matches!(answer.trim(), "y" | "Y" | "yes" | "YES" | "Yes" | "是") {
+        bail!("{}", Msg::CleanAborted { dir: &target });
+    }
+    Ok(true)
+}
+
+/// 目录下所有条目（不跟随符号链接）的数量
+fn count_entries(dir: &Path) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        // 持有中的锁文件不会被删除
+        .filter(|entry| entry.file_name() != LOCK_FILE)
+        .map(|entry| match entry.file_type() {
+            Ok(kind) if kind.is_dir() => 1 + count_entries(&entry.path()),
+            _ => 1,
+        })
+        .sum()
 }
 
-/// 从 JSON 文件中解析出 Node 树
-fn parse_json_file(path: &PathBuf) -> Result<Node> {
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read JSON file '{}'", path.display()))?;
-    let data: BTreeMap<String, SerdeNode> = serde_json::from_str(&content)
-        .with_context(|| format!("Failed to parse JSON in '{}'", path.display()))?;
-    let mut root = Node::new_dir("".to_string());
-    for (k, v) in data {
-        root.children.push(serde_to_node(k, &v));
+/// 锁住输出目录（见 [`treegen::lock`]），抢占了陈旧的锁时打印警告
+fn lock_out_dir(out_dir: &Path, gen: &GenerateArgs) -> Result<OutputLock> {
+    let lock = OutputLock::acquire(out_dir, Duration::from_secs(gen.lock_timeout))?;
+    if let Some(holder) = lock.stolen() {
+        eprintln!(
+            "Warning: removed a stale lock on '{}' left by {}, which is no longer running",
+            out_dir.display(),
+            holder
+        );
     }
-    Ok(root)
+    Ok(lock)
 }
 
-/// 从 TOML 文件中解析出 Node 树
-fn parse_toml_file(path: &PathBuf) -> Result<Node> {
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read TOML file '{}'", path.display()))?;
-    let data: BTreeMap<String, SerdeNode> = toml::from_str(&content)
-        .with_context(|| format!("Failed to parse TOML in '{}'", path.display()))?;
+/// 正在进行的生成的取消标志，没有生成在进行时为 None
+fn active_generation() -> MutexGuard<'static, Option<CancelToken>> {
+    static ACTIVE: Mutex<Option<CancelToken>> = Mutex::new(None);
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
 
-    let mut root = Node::new_dir("".to_string());
-    for (key, value) in data {
-        root.children.push(parse_toml_node(key, &value));
+/// 安装 Ctrl-C 处理（只安装一次）：生成进行中时第一次按下取消生成，
+/// 其余情况（没有生成在进行、再次按下）立即以 130 退出，与默认行为相同
+fn install_interrupt_handler() -> Result<()> {
+    static INSTALLED: AtomicBool = AtomicBool::new(false);
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
     }
-    Ok(root)
+    ctrlc::set_handler(|| match active_generation().as_ref() {
+        Some(token) if !token.is_cancelled() => {
+            eprintln!("{}", Msg::Interrupting);
+            token.cancel();
+        }
+        _ => std::process::exit(130),
+    })
+    .context("Failed to install the Ctrl-C handler")
 }
 
-fn parse_toml_node(name: String, snode: &SerdeNode) -> Node {
-    match snode {
-        SerdeNode::Str(content) => Node::new_file(name, Some(content.clone())),
-        SerdeNode::Map(map) => {
-            let mut dir = Node::new_dir(name);
-            for (key, value) in map {
-                dir.children.push(parse_toml_node(key.clone(), value));
+/// 生成被 Ctrl-C 中断：报告已完成的部分，--clean-on-interrupt 时删除本次新建的路径，以 130 退出
+fn exit_interrupted(
+    interrupted: Interrupted,
+    out_dir: &Path,
+    out_existed: bool,
+    lock: Option<OutputLock>,
+    gen: &GenerateArgs,
+) -> ! {
+    // process::exit 不会运行析构，先释放锁（删除锁文件）
+    drop(lock);
+    if let Err(e) = report::finish(report::Status::Interrupted, Some(interrupted.to_string())) {
+        eprintln!("Warning: {:#}", e);
+    }
+    let Interrupted { stats, remaining } = interrupted;
+    let count = |outcome: Outcome| stats.outcomes.values().filter(|o| **o == outcome).count();
+    println!(
+        "{}",
+        Msg::Interrupted {
+            out: out_dir,
+            created: count(Outcome::Created),
+            rewritten: count(Outcome::Overwritten),
+            remaining
+        }
+    );
+    if gen.clean_on_interrupt && !gen.dry_run {
+        match treegen::remove_created(out_dir, &stats) {
+            Ok(removed) => {
+                // 输出目录是本次新建的，清理后为空时一并删除（非空时 remove_dir 失败，保留它）
+                if !out_existed {
+                    let _ = fs::remove_dir(out_dir);
+                }
+                println!(
+                    "{}",
+                    Msg::CleanedOnInterrupt {
+                        count: removed.len()
+                    }
+                );
             }
-            dir
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+    }
+    std::process::exit(130)
+}
+
+/// 逐级创建输出目录中不存在的部分，只有这些新建的目录设置 --out-mode；
+/// dry-run 时只列出将要创建的目录
+fn create_out_dir(out_dir: &Path, gen: &GenerateArgs) -> Result<()> {
+    let mut missing: Vec<&Path> = out_dir
+        .ancestors()
+        .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+        .collect();
+    missing.reverse();
+    let describe = |dir: &Path| match gen.out_mode {
+        Some(mode) => format!("{} (mode 0o{:o})", dir.display(), mode),
+        None => dir.display().to_string(),
+    };
+    for dir in missing {
+        if gen.dry_run {
+            println!("[Dry-Run] Create output directory: {}", describe(dir));
+            continue;
+        }
+        match fs::create_dir(dir) {
+            // 同时被别的进程创建了，不算 treegen 新建的
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => continue,
+            result => result.with_context(|| Msg::CreateOutDirFailed { dir }.to_string())?,
+        }
+        #[cfg(unix)]
+        if let Some(mode) = gen.out_mode {
+            use std::os::unix::fs::PermissionsExt;
+            // 显式设置，不受 umask 影响
+            fs::set_permissions(dir, fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions on '{}'", dir.display()))?;
+        }
+        if gen.verbose {
+            println!("Create output directory: {}", describe(dir));
         }
     }
+    Ok(())
+}
+
+/// --summary-json：生成结果的各类数量、每个文件生效的 if_exists 策略与失败的路径
+fn write_summary(
+    root: &Node,
+    stats: &Stats,
+    out_dir: &Path,
+    path: &Path,
+    timings: &Timings,
+    gen: &GenerateArgs,
+) -> Result<()> {
+    let default = if gen.skip_existing {
+        IfExists::Skip
+    } else {
+        IfExists::Overwrite
+    };
+    let policies: BTreeMap<String, IfExists> = root
+        .iter()
+        .filter(|(_, node)| node.node_type == NodeType::File)
+        .map(|(rel, node)| {
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            (rel, node.attrs.if_exists.unwrap_or(default))
+        })
+        .collect();
+    let summary = serde_json::json!({
+        "out": out_dir,
+        "dry_run": gen.dry_run,
+        "dirs": stats.dirs,
+        "files": stats.files,
+        "unchanged": stats.unchanged,
+        "links": stats.links,
+        "skipped": stats.skipped,
+        "missing": stats.missing,
+        "if_exists": policies,
+        "failures": stats.failures,
+        "warnings": stats.warnings,
+        "verified": stats.verified.len(),
+        "timings": timings.json(),
+    });
+    let text = serde_json::to_string_pretty(&summary)?;
+    fs::write(path, text + "\n")
+        .with_context(|| format!("Failed to write summary file '{}'", path.display()))?;
+    Ok(())
 }
 
-/// dedent(): 去除多行字符串的首尾空行 + 公共缩进，保持内容整体对齐
-fn dedent(s: &str) -> String {
-    // 1. 按行拆分，去掉首尾纯空行
-    let mut lines: Vec<&str> = s.lines().collect();
-    // 去掉前导空行
-    while !lines.is_empty() && lines.first().unwrap().trim().is_empty() {
-        lines.remove(0);
+/// --checksums：哈希生成后磁盘上的文件（dry-run 时没有文件，不写清单）；
+/// --verify-writes 回读过的文件直接使用回读时的哈希
+fn write_checksums(
+    root: &Node,
+    out_dir: &Path,
+    path: &Path,
+    stats: &Stats,
+    gen: &GenerateArgs,
+) -> Result<()> {
+    if gen.dry_run {
+        if gen.verbose {
+            println!("[Dry-Run] Write checksums: {}", path.display());
+        }
+        return Ok(());
     }
-    // 去掉末尾空行
-    while !lines.is_empty() && lines.last().unwrap().trim().is_empty() {
-        lines.pop();
+    let files = treegen::checksum::generated_files(root, out_dir)?;
+    let manifest = treegen::checksum::manifest_with(out_dir, &files, &stats.verified)?;
+    fs::write(path, manifest)
+        .with_context(|| format!("Failed to write checksum file '{}'", path.display()))?;
+    if gen.verbose {
+        println!("Wrote {} checksum(s) to {}", files.len(), path.display());
     }
-    if lines.is_empty() {
-        return String::new();
+    Ok(())
+}
+
+/// 输出目录与描述不一致的路径（--check/--fix）：未要求 --sync 时不算多出的路径；
+/// 缺失或多出的目录只列出目录本身
+fn out_of_sync(root: &Node, out_dir: &Path, gen: &GenerateArgs) -> Result<Vec<Difference>> {
+    let mut exclude = GlobSetBuilder::new();
+    // 正在持有的锁文件不算多出的路径
+    exclude.add(Glob::new(LOCK_FILE)?);
+    for pattern in &gen.exclude {
+        exclude.add(
+            Glob::new(pattern).with_context(|| format!("Invalid --exclude glob '{}'", pattern))?,
+        );
     }
-    // 2. 找到所有非空行的最小缩进数（以空格计）
-    let mut min_indent = usize::MAX;
-    for &line in &lines {
-        if line.trim().is_empty() {
+    let differences = treegen::diff::diff_dir(root, out_dir, &exclude.build()?)?;
+    let mut kept: Vec<Difference> = Vec::new();
+    for d in differences {
+        if d.change == Change::Added && !gen.sync {
+            continue;
+        }
+        // --no-touch 不生成没有内容的文件：缺失或内容不同都不算不一致，类型不符仍然算
+        let untouched = || {
+            root.find(&d.path)
+                .is_some_and(|n| n.node_type == NodeType::File && n.content.is_none())
+        };
+        if gen.no_touch && (d.change == Change::Removed || d.contents.is_some()) && untouched() {
             continue;
         }
-        let count = line.chars().take_while(|c| *c == ' ').count();
-        if count < min_indent {
-            min_indent = count;
+        let covered = kept.iter().any(|k| {
+            k.change == d.change && k.change != Change::Changed && d.path.starts_with(&k.path)
+        });
+        if !covered {
+            kept.push(d);
         }
     }
-    if min_indent == usize::MAX {
-        min_indent = 0;
+    Ok(kept)
+}
+
+/// --check 的输出：每行一个不一致的路径，有不一致时返回错误
+fn report_out_of_sync(differences: &[Difference]) -> Result<()> {
+    for d in differences {
+        match d.change {
+            Change::Removed => println!("missing: {}", d.path.display()),
+            Change::Added => println!("extra: {}", d.path.display()),
+            Change::Changed => println!("mismatch: {} ({})", d.path.display(), d.message),
+        }
     }
-    // 3. 对每行去除前 min_indent 个空格
-    let dedented: Vec<String> = lines
-        .into_iter()
-        .map(|line| {
-            if line.len() >= min_indent {
-                line[min_indent..].to_string()
-            } else {
-                line.trim_start().to_string()
-            }
-        })
-        .collect();
-    dedented.join("\n")
-}
-
-/// === JSON5 格式解析 ===
-/// 支持：
-///  - 反引号（`…`）包裹多行字符串
-///  - 单/双引号字符串、无引号键、注释、末尾逗号等 JSON5 特性
-///  - 写法示例 (structure.json5)：
-///    ```json5
-///    // 顶层就是一个对象
-///    {
-///      my_project: {
-///        src: {
-///          "main.rs": `
-///            fn main() {
-///                println!("Hello from JSON5!");
-///            }
-///          `,
-///          "lib.rs": ""
-///        },
-///        "Cargo.toml": `
-///    [package]
-///    name = "my_project"
-///    version = "0.1.0"
-///    `,
-///        "README.md": `
-///    # My Project
-///
-///    这是示例项目，通过 JSON5 定义生成。
-///    `
-///      }
-///    }
-///    ```
-///  直接用 `json5::from_str` 解析时，内部会保留原样的多行文本，我们再对其 dedent 后输出。
-fn parse_json5_file(path: &PathBuf) -> Result<Node> {
-    // 1. 读取整个 .json5 文件内容
-    let raw = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read JSON5 file '{}'", path.display()))?;
-
-    // 2. 我们需要先把所有反引号包裹的多行内容 dedent 后再交给 json5 解析。
-    //    简单思路：扫描整个 raw，将 `…` 之间的内容先提取、dedent、再放回 raw 中。
-    let mut output = String::new();
-    let mut chars = raw.chars().peekable();
-    while let Some(ch) = chars.next() {
-        if ch == '`' {
-            // 收集反引号内的内容
-            let mut content = String::new();
-            while let Some(&next_ch) = chars.peek() {
-                chars.next();
-                if next_ch == '`' {
-                    break;
-                } else {
-                    content.push(next_ch);
-                }
+    if !differences.is_empty() {
+        bail!("{} path(s) out of sync with the spec", differences.len());
+    }
+    println!("{}", Msg::InSync);
+    Ok(())
+}
+
+/// --fix：删除类型不符的路径，--sync 时还删除多出的路径；内容不符的文件留给生成时重写。
+/// diff_dir 不比较元数据，因此不带内容的 Changed 就是类型不符
+fn remove_out_of_sync(
+    differences: &[Difference],
+    out_dir: &Path,
+    gen: &GenerateArgs,
+) -> Result<()> {
+    let stale = |d: &&Difference| match d.change {
+        Change::Added => true,
+        Change::Changed => d.contents.is_none(),
+        Change::Removed => false,
+    };
+    for d in differences.iter().filter(stale) {
+        let path = out_dir.join(&d.path);
+        if gen.dry_run {
+            if gen.verbose {
+                println!("[Dry-Run] Remove: {}", path.display());
             }
-            // dedent 之后再放到 output：用三引号包裹以便 JSON5 理解多行？
-            // 但 json5 本身也支持反引号，此处只要保证「缩进对齐」，让 JSON5 解析时拿到干净的多行文本即可。
-            let dedented = dedent(&content); // 修复反引号包裹内容的缩进问题
-            output.push('`');
-            output.push_str(&dedented);
-            output.push('`');
-        } else {
-            output.push(ch);
+            continue;
+        }
+        if gen.verbose {
+            println!("Remove: {}", path.display());
         }
+        let removed = if fs::symlink_metadata(&path).is_ok_and(|m| m.is_dir()) {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        removed.with_context(|| format!("Failed to remove '{}'", path.display()))?;
     }
+    Ok(())
+}
 
-    // 3. 用 json5 解析成 BTreeMap<String, SerdeNode>
-    let data: BTreeMap<String, SerdeNode> = json5::from_str(&output)
-        .with_context(|| format!("Failed to parse JSON5 in '{}'", path.display()))?;
-
-    // 4. 转为 Node 树
-    let mut root = Node::new_dir("".to_string());
-    for (k, v) in data {
-        root.children.push(serde_to_node(k, &v));
-    }
-    Ok(root)
+/// 带生成结果的目录树；输出到终端且未设置 NO_COLOR 时按结果着色
+fn annotated_tree(root: &Node, outcomes: &BTreeMap<PathBuf, Outcome>) -> String {
+    let color = std::io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    root.render_tree(&|path, _| {
+        let Some(outcome) = outcomes.get(path) else {
+            return String::new();
+        };
+        if !color {
+            return format!("  ({})", outcome.name());
+        }
+        let code = match outcome {
+            Outcome::Created => "32",
+            Outcome::Overwritten => "33",
+            Outcome::Unchanged | Outcome::Skipped | Outcome::Missing => "2",
+        };
+        format!("  \x1b[{}m({})\x1b[0m", code, outcome.name())
+    })
 }
 
-/// 从 Markdown 文件中解析 Node 树
-fn parse_md_file(path: &PathBuf) -> Result<Node> {
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read Markdown file '{}'", path.display()))?;
-    let lines: Vec<String> = content.lines().map(|l| l.to_string()).collect();
-    let sanitized_lines: Vec<String> = lines
-        .iter()
-        .map(|line| line.replace(":", "_")) // 修复文件名语法问题
-        .collect();
-    parse_md_tree(&sanitized_lines)
+/// dry-run 的目录树：绿色 `+` 新建、暗色 `=` 内容相同、黄色 `~` 内容不同、红色 `!` 类型冲突
+fn status_tree_text(root: &Node, status: &BTreeMap<PathBuf, DiffStatus>) -> String {
+    let color = std::io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    root.render_tree(&|path, _| {
+        let Some(status) = status.get(path) else {
+            return String::new();
+        };
+        let text = match status {
+            DiffStatus::New => format!("{} new", status.symbol()),
+            DiffStatus::Identical => format!("{} identical", status.symbol()),
+            DiffStatus::Differs => format!("{} differs", status.symbol()),
+            DiffStatus::Conflict(message) => format!("{} conflict: {}", status.symbol(), message),
+        };
+        if !color {
+            return format!("  {}", text);
+        }
+        let code = match status {
+            DiffStatus::New => "32",
+            DiffStatus::Identical => "2",
+            DiffStatus::Differs => "33",
+            DiffStatus::Conflict(_) => "31",
+        };
+        format!("  \x1b[{}m{}\x1b[0m", code, text)
+    })
 }
 
-/// === 递归在磁盘上创建目录和文件 ===
-fn create_fs(base: &PathBuf, node: &Node, dry_run: bool, verbose: bool, _mode: u32) -> Result<()> {
-    // 如果 name 为空，则 base 本身；否则 base/<name>
-    let path = if node.name.is_empty() {
-        base.clone()
-    } else {
-        base.join(&node.name)
-    };
+/// 从 git 仓库获取的描述中的 $hooks 等于执行仓库中的代码：默认不执行，--allow-remote-hooks
+/// 或在终端中确认后才执行（与远程描述合并的本地描述的 $hooks 一并处理）；--run 给出的命令不受影响
+fn remote_hooks(hooks: Vec<String>, source: &str, gen: &GenerateArgs) -> Result<Vec<String>> {
+    if gen.allow_remote_hooks || gen.no_hooks {
+        return Ok(hooks);
+    }
+    let count = hooks.len();
+    if !gen.dry_run && !gen.no_input && io::stdin().is_terminal() {
+        for command in &hooks {
+            eprintln!("  {}", command);
+        }
+        eprint!("{}", Msg::ConfirmRemoteHooks { source, count });
+        io::stderr().flush().ok();
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if matches!(answer.trim(), "y" | "Y" | "yes" | "YES" | "Yes" | "是") {
+            return Ok(hooks);
+        }
+    }
+    eprintln!("{}", Msg::RemoteHooksSkipped { source, count });
+    Ok(Vec::new())
+}
 
-    match node.node_type {
-        NodeType::Dir => {
-            if dry_run {
-                if verbose {
-                    println!("[Dry-Run] Create directory: {}", path.display());
-                }
-            } else {
-                if verbose {
-                    println!("Create directory: {}", path.display());
-                }
-                fs::create_dir_all(&path)
-                    .with_context(|| format!("Failed to create directory '{}'", path.display()))?;
-            }
-            for child in node.children.iter() {
-                create_fs(&path, child, dry_run, verbose, _mode)
-                    .with_context(|| format!("Failed under directory '{}'", path.display()))?;
-            }
+/// 在输出目录中依次执行钩子命令，标准输出/错误直接透传；
+/// dry-run 时只打印将要执行的命令
+fn run_hooks(commands: &[String], out_dir: &Path, gen: &GenerateArgs) -> Result<()> {
+    for command in commands {
+        let _span = tracing::info_span!("hook", command = %command).entered();
+        if gen.dry_run {
+            println!("[Dry-Run] Run hook: {}", command);
+            continue;
         }
-        NodeType::File => {
-            if let Some(parent) = path.parent() {
-                if !dry_run {
-                    fs::create_dir_all(parent).ok();
-                } else if verbose {
-                    println!("[Dry-Run] Ensure parent dirs for: {}", path.display());
-                }
-            }
-            if dry_run {
-                if verbose {
-                    println!("[Dry-Run] Create file: {}", path.display());
-                }
+        println!("▶ {}", command);
+        #[cfg(windows)]
+        let mut process = std::process::Command::new("cmd");
+        #[cfg(windows)]
+        process.arg("/C");
+        #[cfg(not(windows))]
+        let mut process = std::process::Command::new("sh");
+        #[cfg(not(windows))]
+        process.arg("-c");
+        let status = process
+            .arg(command)
+            .current_dir(out_dir)
+            .status()
+            .with_context(|| format!("Failed to start hook '{}'", command))?;
+        tracing::info!(%status, "hook finished");
+        if !status.success() {
+            if gen.hooks_keep_going {
+                eprintln!("Warning: hook '{}' failed with {}", command, status);
             } else {
-                if verbose {
-                    println!("Create file: {}", path.display());
-                }
-                if let Some(content) = &node.content {
-                    fs::write(&path, content)
-                        .with_context(|| format!("Failed to write file '{}'", path.display()))?;
-                } else {
-                    fs::write(&path, "").with_context(|| {
-                        format!("Failed to create empty file '{}'", path.display())
-                    })?;
-                }
-                #[cfg(unix)]
-                {
-                    fs::set_permissions(&path, fs::Permissions::from_mode(_mode)).with_context(
-                        || format!("Failed to set permissions for '{}'", path.display()),
-                    )?;
-                }
+                bail!("Hook '{}' failed with {}", command, status);
             }
         }
     }
     Ok(())
 }
 
-fn main() -> Result<()> {
-    // 解析命令行参数
-    let args = Args::parse();
+// === --watch ===
 
-    // 确定输出目录：如果指定了 --out，就用它；否则用当前工作目录
-    let out_dir = if let Some(dir) = args.out.clone() {
-        dir
-    } else {
-        env::current_dir().context("Failed to get current working directory")?
+/// 文件变化后等待这么久没有新事件才重新生成（编辑器保存时往往触发多个事件）
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 当前本地时间，用于 --watch 的日志行
+fn timestamp() -> String {
+    chrono::Local::now().format("%H:%M:%S").to_string()
+}
+
+/// 初次生成后监视输入文件，变化时（防抖后）重新生成；单次生成失败只报告、不退出
+fn watch(args: &Args, matches: &ArgMatches) -> Result<()> {
+    let inputs = expand_inputs(args)?
+        .iter()
+        .map(
+            |MappedInput {
+                 input: Input { path: p, .. },
+                 ..
+             }| {
+                // 压缩包中的描述监视压缩包本身
+                let p = treegen::archive::split(p).map_or_else(|| p.clone(), |(zip, _)| zip);
+                fs::canonicalize(&p)
+                    .with_context(|| format!("Input file '{}' does not exist", p.display()))
+            },
+        )
+        .collect::<Result<Vec<_>>>()?;
+
+    // 监视所在目录而不是文件本身：编辑器常以"写临时文件再重命名"的方式保存
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+    let dirs: BTreeSet<&Path> = inputs.iter().filter_map(|p| p.parent()).collect();
+    for dir in dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch '{}'", dir.display()))?;
+    }
+
+    let run = || {
+        if let Err(e) = run_inputs(args, matches) {
+            eprintln!("[{}] Error: {:#}", timestamp(), e);
+        }
     };
 
-    // 如果 --clean 并且 out_dir 存在，则先删除
-    if args.clean && out_dir.exists() {
-        if args.verbose {
-            println!("Cleaning existing directory: {}", out_dir.display());
+    println!("[{}] Generating", timestamp());
+    run();
+    println!(
+        "Watching {} input file(s) for changes, press Ctrl-C to stop",
+        inputs.len()
+    );
+
+    let relevant = |event: &notify::Result<notify::Event>| match event {
+        Ok(event) => !event.kind.is_access() && event.paths.iter().any(|p| inputs.contains(p)),
+        Err(_) => false,
+    };
+    loop {
+        let event = rx.recv().context("File watcher stopped unexpectedly")?;
+        if !relevant(&event) {
+            continue;
         }
-        fs::remove_dir_all(&out_dir)
-            .with_context(|| format!("Failed to remove directory '{}'", out_dir.display()))?;
+        // 防抖：吸收紧随其后的事件
+        while rx.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+        println!("[{}] Change detected, regenerating", timestamp());
+        run();
     }
+}
 
-    // 确保输出目录存在
-    if !args.clean {
-        fs::create_dir_all(&out_dir).with_context(|| {
-            format!("Failed to create output directory '{}'", out_dir.display())
-        })?;
+/// 按 --log-level（优先）或 RUST_LOG 安装 tracing 的输出，写到标准错误；都没有时不安装，
+/// 事件直接丢弃。--verbose 的逐个路径的输出不受影响
+fn init_tracing(level: Option<LogLevel>, format: LogFormat) -> Result<()> {
+    use tracing_subscriber::EnvFilter;
+    let filter = match level {
+        Some(level) => EnvFilter::default().add_directive(tracing::Level::from(level).into()),
+        None => match env::var("RUST_LOG") {
+            Ok(directives) if !directives.trim().is_empty() => EnvFilter::try_new(&directives)
+                .with_context(|| format!("Invalid RUST_LOG '{}'", directives))?,
+            _ => return Ok(()),
+        },
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none())
+        .with_writer(io::stderr);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
     }
+    Ok(())
+}
 
-    // 解析 mode，如 "0o644" -> 0o644
-    let mode = u32::from_str_radix(args.mode.trim_start_matches("0o"), 8)
-        .context("Invalid mode format; use octal like 0o644")?;
-
-    // 根节点：合并所有输入文件解析结果
-    let mut root = Node::new_dir("".to_string());
-
-    for input_path in &args.input {
-        if !input_path.exists() {
-            bail!("Input file '{}' does not exist", input_path.display());
-        }
-        let ext = input_path
-            .extension()
-            .map(|e| e.to_string_lossy().to_lowercase())
-            .unwrap_or_default();
-        let parsed = match ext.as_str() {
-            "md" => parse_md_file(input_path)?,
-            "yaml" | "yml" => parse_yaml_file(input_path)?,
-            "json" => parse_json_file(input_path)?,
-            "toml" => parse_toml_file(input_path)?,
-            "json5" => parse_json5_file(input_path)?,
-            _ => bail!("Unsupported file extension '{}'", input_path.display()),
+fn main() -> Result<()> {
+    // 解析命令行参数（已合并配置文件中的默认值）
+    let (args, matches, config) = load_args()?;
+    init_tracing(args.log_level, args.log_format)?;
+    messages::init(args.lang);
+
+    if let Some(command) = &args.command {
+        return match command {
+            Command::Init(init) => run_init(init),
+            Command::Completions { shell } => run_completions(*shell),
+            Command::Config(cfg) => run_config(cfg, &matches, config.as_ref()),
+            Command::New(new) => {
+                let sub = matches.subcommand_matches("new").unwrap();
+                with_report(&new.gen, || run_new(new, sub))
+            }
+            Command::Template(template) => run_template(template),
+            Command::Lint(lint) => {
+                let sub = matches.subcommand_matches("lint").unwrap();
+                run_lint(lint, sub)
+            }
+            Command::Schema => {
+                println!("{}", serde_json::to_string_pretty(&treegen::schema())?);
+                Ok(())
+            }
+            Command::Diff(diff) => run_diff(diff),
+            Command::Verify(verify) => run_verify(verify),
+            Command::Vars(vars) => run_vars(vars),
+            Command::Profiles(profiles) => run_profiles(profiles),
+            Command::Merge(merge) => run_merge(merge),
+            Command::Snippets(snippets) => run_snippets(snippets),
+            Command::Render(render) => run_render(render),
         };
-        // 合并子节点
-        root.children.extend(parsed.children);
     }
 
-    // 递归在 out_dir 下创建目录/文件
-    create_fs(&out_dir, &root, args.dry_run, args.verbose, mode)?;
+    // --watch 每次变化都重新生成，没有单独的一次运行可以报告
+    if args.watch && args.gen.report.is_some() {
+        bail!("--report cannot be used with --watch");
+    }
+    with_report(&args.gen, || generate_from_args(&args, &matches))
+}
+
+/// 不带子命令时：按输入（文件、标准输入或剪贴板）生成
+fn generate_from_args(args: &Args, matches: &ArgMatches) -> Result<()> {
+    let stdin = stdin_input(args)?;
+    if stdin.is_none() && args.compressed.is_some() {
+        bail!("--compressed only applies to input from stdin ('-'); .gz/.zst files are decompressed automatically");
+    }
+    if stdin.is_some() && args.gen.stdin_content.is_some() {
+        bail!("--stdin-content cannot be used with input from stdin ('-')");
+    }
+    if args.watch && args.gen.stdin_content.is_some() {
+        bail!("--stdin-content cannot be used with --watch");
+    }
+    if args.watch && args.list.list.is_some() {
+        bail!("--list cannot be used with --watch");
+    }
+    LOG_TO_STDERR.store(args.list.list.is_some() || args.gen.json, Ordering::Relaxed);
+    if args.watch {
+        if stdin.is_some() {
+            bail!("--watch cannot be used with input from stdin ('-')");
+        }
+        return watch(args, matches);
+    }
 
-    if args.dry_run {
-        println!("✅ Dry‐Run 完成，没有写入磁盘。");
+    // 剪贴板或标准输入中只有一份描述，生成到 --out（或 -=OUTDIR 指定的目录）
+    let (source, content, format, out) = if args.clipboard {
+        let format = args.format.map(Format::from);
+        ("clipboard", read_clipboard()?, format, None)
+    } else if let Some(mapped) = stdin {
+        let content = match args.compressed.map(Compression::from) {
+            Some(compression) => {
+                let mut data = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut data)
+                    .context("Failed to read the spec from stdin")?;
+                compression.decompress(&data).with_context(|| {
+                    format!(
+                        "Failed to decompress {} spec from stdin",
+                        compression.name()
+                    )
+                })?
+            }
+            None => {
+                let mut content = String::new();
+                io::stdin()
+                    .read_to_string(&mut content)
+                    .context("Failed to read the spec from stdin")?;
+                content
+            }
+        };
+        let format = mapped.input.format.or(args.format.map(Format::from));
+        ("stdin", content, format, mapped.out)
     } else {
-        println!("✅ 成功在 '{}' 生成文件树！", out_dir.display());
+        return run_inputs(args, matches);
+    };
+    report::record_spec_text(source, &content);
+    let parsed = treegen::parse_str(source, &content, format, &parse_options(args))?;
+    if args.gen.verbose && parsed.detection == Detection::Sniffed {
+        println!(
+            "Detected {} format for the {} content",
+            parsed.format.name(),
+            source
+        );
     }
-    Ok(())
+    if args.gen.verbose {
+        for note in &parsed.notes {
+            println!("{} ({})", note, source);
+        }
+    }
+    let mut timings = Timings::default();
+    timings.record(format!("parse {}", source), parsed.timing);
+    run_generate(
+        &args.gen,
+        parsed.spec,
+        out.as_deref(),
+        Some(&args.list),
+        matches,
+        timings,
+    )
+}
+
+/// 输入 `-`（可写成 FORMAT:- 或 -=OUTDIR）表示从标准输入读取描述，此时它必须是唯一的输入
+fn stdin_input(args: &Args) -> Result<Option<MappedInput>> {
+    let is_stdin = |mapped: &&MappedInput| mapped.input.path.as_os_str() == "-";
+    let Some(mapped) = args.input.iter().find(is_stdin) else {
+        return Ok(None);
+    };
+    if args.input.len() > 1 || !args.recursive.is_empty() {
+        bail!("Input from stdin ('-') cannot be combined with other inputs");
+    }
+    Ok(Some(mapped.clone()))
+}
+
+/// 读取剪贴板中的文本；没有图形环境（如无 DISPLAY 的 Linux）或剪贴板为空时报错
+#[cfg(feature = "clipboard")]
+fn read_clipboard() -> Result<String> {
+    let mut clipboard = arboard::Clipboard::new()
+        .context("The clipboard is unavailable (no display or clipboard service?)")?;
+    let text = match clipboard.get_text() {
+        Ok(text) => text,
+        Err(arboard::Error::ContentNotAvailable) => {
+            bail!("The clipboard is empty or does not contain text")
+        }
+        Err(e) => return Err(e).context("Failed to read the clipboard"),
+    };
+    if text.trim().is_empty() {
+        bail!("The clipboard is empty");
+    }
+    Ok(text)
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn read_clipboard() -> Result<String> {
+    bail!("This treegen was built without clipboard support; rebuild with the 'clipboard' feature")
 }
@@ -0,0 +1,370 @@
+//! 命令行面向用户的消息（结果提示、dry-run 提示、确认提示与常见错误）的英文与中文文本。
+//! 语言由 `--lang` 指定，否则按 `LC_ALL`/`LC_MESSAGES`/`LANG` 推断，都没有时为英文。
+//! 逐个路径的日志（`--verbose`、`[Dry-Run] Create file: ...`）与机器可读的输出（`--list`、
+//! `--check`、JSON）不翻译。
+
+use clap::ValueEnum;
+use std::{env, fmt, path::Path, sync::OnceLock};
+
+/// 消息语言
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Zh,
+}
+
+impl Lang {
+    /// 第一个非空的 `LC_ALL`、`LC_MESSAGES`、`LANG` 以 `zh` 开头时为中文，否则为英文
+    fn from_env() -> Lang {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        if locale.to_ascii_lowercase().starts_with("zh") {
+            Lang::Zh
+        } else {
+            Lang::En
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// 设置消息语言（`--lang`），没有指定时按环境变量
+pub fn init(lang: Option<Lang>) {
+    let _ = LANG.set(lang.unwrap_or_else(Lang::from_env));
+}
+
+fn lang() -> Lang {
+    *LANG.get_or_init(Lang::from_env)
+}
+
+/// 按当前语言写出两种文本之一
+macro_rules! say {
+    ($f:expr, $en:literal, $zh:literal $(, $arg:expr)* $(,)?) => {
+        match lang() {
+            Lang::En => write!($f, $en $(, $arg)*),
+            Lang::Zh => write!($f, $zh $(, $arg)*),
+        }
+    };
+}
+
+/// `--clean` 拒绝删除的原因
+#[derive(Debug, Clone, Copy)]
+pub enum CleanRefusal {
+    FilesystemRoot,
+    Home,
+    Cwd,
+    Git,
+}
+
+/// 一条消息及其参数，按当前语言显示
+#[derive(Debug, Clone, Copy)]
+pub enum Msg<'a> {
+    // 各子命令的结果
+    ExampleWritten {
+        path: &'a Path,
+    },
+    TemplateAdded {
+        name: &'a str,
+        dest: &'a Path,
+    },
+    TemplateRemoved {
+        name: &'a str,
+    },
+    LintClean,
+    LintWarnings {
+        count: usize,
+    },
+    NoDifferences,
+    ChecksumsMatch {
+        count: usize,
+    },
+    NoVars,
+    NoProfiles,
+    Merged {
+        count: usize,
+        path: &'a Path,
+    },
+    Rendered {
+        path: &'a Path,
+    },
+    // 生成的结果
+    Generated {
+        out: &'a Path,
+    },
+    DryRunDone,
+    PartlyGenerated {
+        out: &'a Path,
+        failed: usize,
+    },
+    FlattenDropped {
+        count: usize,
+    },
+    NoTouchSkipped {
+        count: usize,
+    },
+    ContentOnlyMissing {
+        count: usize,
+    },
+    WritesVerified {
+        count: usize,
+    },
+    OptionalSkipped {
+        count: usize,
+    },
+    InSync,
+    // Ctrl-C 中断
+    Interrupting,
+    Interrupted {
+        out: &'a Path,
+        created: usize,
+        rewritten: usize,
+        remaining: usize,
+    },
+    CleanedOnInterrupt {
+        count: usize,
+    },
+    // --clean 的提示与错误
+    DryRunClean {
+        dir: &'a Path,
+        entries: usize,
+    },
+    ConfirmClean {
+        dir: &'a Path,
+        entries: usize,
+    },
+    CleanNeedsYes {
+        dir: &'a Path,
+        entries: usize,
+    },
+    CleanAborted {
+        dir: &'a Path,
+    },
+    RefuseClean {
+        dir: &'a Path,
+        reason: CleanRefusal,
+    },
+    // 远程描述中的钩子
+    ConfirmRemoteHooks {
+        source: &'a str,
+        count: usize,
+    },
+    RemoteHooksSkipped {
+        source: &'a str,
+        count: usize,
+    },
+    // 常见错误
+    NoCurrentDir,
+    CreateOutDirFailed {
+        dir: &'a Path,
+    },
+}
+
+impl fmt::Display for Msg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Msg::ExampleWritten { path } => say!(
+                f,
+                "✅ Wrote the example spec '{}'",
+                "✅ 已写入示例描述文件 '{}'",
+                path.display()
+            ),
+            Msg::TemplateAdded { name, dest } => say!(
+                f,
+                "✅ Added template '{}': {}",
+                "✅ 已添加模板 '{}'：{}",
+                name,
+                dest.display()
+            ),
+            Msg::TemplateRemoved { name } => {
+                say!(f, "✅ Removed template '{}'", "✅ 已删除模板 '{}'", name)
+            }
+            Msg::LintClean => say!(f, "✅ No problems found", "✅ 没有发现问题"),
+            Msg::LintWarnings { count } => {
+                say!(f, "⚠️ {} warning(s)", "⚠️ 共 {} 条警告", count)
+            }
+            Msg::NoDifferences => say!(f, "✅ The two specs are identical", "✅ 两个描述文件没有差异"),
+            Msg::ChecksumsMatch { count } => say!(
+                f,
+                "✅ All {} checksum(s) match",
+                "✅ {} 个文件的校验和全部一致",
+                count
+            ),
+            Msg::NoVars => say!(
+                f,
+                "✅ The spec uses no template variables",
+                "✅ 描述文件没有使用模板变量"
+            ),
+            Msg::NoProfiles => say!(
+                f,
+                "✅ The spec has no profiles; every node is generated",
+                "✅ 描述文件中没有 profile，所有节点都会生成"
+            ),
+            Msg::Merged { count, path } => say!(
+                f,
+                "✅ Merged {} spec(s) into '{}'",
+                "✅ 已把 {} 个描述文件合并写入 '{}'",
+                count,
+                path.display()
+            ),
+            Msg::Rendered { path } => {
+                say!(f, "✅ Rendered to '{}'", "✅ 已渲染到 '{}'", path.display())
+            }
+            Msg::Generated { out } => say!(
+                f,
+                "✅ Generated the file tree in '{}'",
+                "✅ 成功在 '{}' 生成文件树！",
+                out.display()
+            ),
+            Msg::DryRunDone => say!(
+                f,
+                "✅ Dry-run complete, nothing was written to disk.",
+                "✅ Dry‐Run 完成，没有写入磁盘。"
+            ),
+            Msg::PartlyGenerated { out, failed } => say!(
+                f,
+                "⚠️ Generated the other paths in '{}'; {} path(s) failed",
+                "⚠️ 已在 '{}' 生成其余路径，{} 个路径失败",
+                out.display(),
+                failed
+            ),
+            Msg::FlattenDropped { count } => say!(
+                f,
+                "(--flatten: {} empty directory(ies) not generated)",
+                "（--flatten：{} 个空目录没有生成）",
+                count
+            ),
+            Msg::NoTouchSkipped { count } => say!(
+                f,
+                "(--no-touch: skipped {} file(s) without content)",
+                "（--no-touch：{} 个没有内容的文件已跳过）",
+                count
+            ),
+            Msg::ContentOnlyMissing { count } => say!(
+                f,
+                "(--content-only: {} missing path(s) not created)",
+                "（--content-only：{} 个不存在的路径没有创建）",
+                count
+            ),
+            Msg::WritesVerified { count } => say!(
+                f,
+                "(--verify-writes: read back and verified {} file(s))",
+                "（--verify-writes：{} 个文件已回读校验）",
+                count
+            ),
+            Msg::OptionalSkipped { count } => say!(
+                f,
+                "(optional: skipped {} optional path(s) that could not be generated)",
+                "（optional：{} 个可选路径没能生成，已跳过）",
+                count
+            ),
+            Msg::InSync => say!(
+                f,
+                "✅ The output directory matches the spec",
+                "✅ 输出目录与描述一致"
+            ),
+            Msg::Interrupting => say!(
+                f,
+                "\nInterrupted, stopping after the current path (press Ctrl-C again to exit now)",
+                "\n已中断，写完当前路径后停止（再按一次 Ctrl-C 立即退出）"
+            ),
+            Msg::Interrupted {
+                out,
+                created,
+                rewritten,
+                remaining,
+            } => say!(
+                f,
+                "⚠️ Interrupted: created {} and rewrote {} path(s) in '{}'; {} planned path(s) were not generated",
+                "⚠️ 已中断：在 '{2}' 新建了 {0} 个路径、重写了 {1} 个路径，{3} 个计划中的路径没有生成",
+                created,
+                rewritten,
+                out.display(),
+                remaining
+            ),
+            Msg::CleanedOnInterrupt { count } => say!(
+                f,
+                "(--clean-on-interrupt: removed {} created path(s); rewritten files cannot be restored)",
+                "（--clean-on-interrupt：已删除 {} 个新建的路径，重写过的已有文件无法恢复）",
+                count
+            ),
+            Msg::DryRunClean { dir, entries } => say!(
+                f,
+                "[Dry-Run] Remove output directory: {} ({} entries)",
+                "[Dry-Run] 清空输出目录：{}（{} 个条目）",
+                dir.display(),
+                entries
+            ),
+            Msg::ConfirmClean { dir, entries } => say!(
+                f,
+                "Remove '{}' and its {} entries? [y/N] ",
+                "删除 '{}' 及其中的 {} 个条目？[y/N] ",
+                dir.display(),
+                entries
+            ),
+            Msg::CleanNeedsYes { dir, entries } => say!(
+                f,
+                "--clean would remove '{}' ({} entries); pass --yes to confirm",
+                "--clean 将删除 '{}'（{} 个条目）；加上 --yes 确认",
+                dir.display(),
+                entries
+            ),
+            Msg::ConfirmRemoteHooks { source, count } => say!(
+                f,
+                "Run the {} $hooks command(s) above from '{}'? [y/N] ",
+                "执行上面来自 '{}' 的 {} 个 $hooks 命令？[y/N] ",
+                count,
+                source
+            ),
+            Msg::RemoteHooksSkipped { source, count } => say!(
+                f,
+                "Warning: Skipped {} $hooks command(s) from '{}'; pass --allow-remote-hooks to run them",
+                "警告：没有执行来自 '{}' 的 {} 个 $hooks 命令；加上 --allow-remote-hooks 执行它们",
+                count,
+                source
+            ),
+            Msg::CleanAborted { dir } => say!(
+                f,
+                "Aborted: '{}' was not removed",
+                "已取消：没有删除 '{}'",
+                dir.display()
+            ),
+            Msg::RefuseClean { dir, reason } => {
+                say!(f, "Refusing to --clean '{}': ", "拒绝 --clean '{}'：", dir.display())?;
+                match reason {
+                    CleanRefusal::FilesystemRoot => {
+                        say!(f, "it is the filesystem root", "它是文件系统的根目录")
+                    }
+                    CleanRefusal::Home => say!(
+                        f,
+                        "it is or contains your home directory",
+                        "它是或包含用户主目录"
+                    ),
+                    CleanRefusal::Cwd => say!(
+                        f,
+                        "it is or contains the current directory (pass --allow-cwd to clean it anyway)",
+                        "它是或包含当前目录（加上 --allow-cwd 仍然清空）"
+                    ),
+                    CleanRefusal::Git => say!(
+                        f,
+                        "it contains a .git folder (pass --allow-git to clean it anyway)",
+                        "其中有 .git 目录（加上 --allow-git 仍然清空）"
+                    ),
+                }
+            }
+            Msg::NoCurrentDir => say!(
+                f,
+                "Failed to get current working directory",
+                "无法获取当前工作目录"
+            ),
+            Msg::CreateOutDirFailed { dir } => say!(
+                f,
+                "Failed to create output directory '{}'",
+                "无法创建输出目录 '{}'",
+                dir.display()
+            ),
+        }
+    }
+}
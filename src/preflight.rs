@@ -0,0 +1,376 @@
+//! 生成前的预检（dry-run 与 `--preflight`）：不写入任何东西，检查实际生成能否成功，
+//! 并一次列出所有问题，而不是停在第一个错误上。
+//!
+//! ```
+//! use treegen::{preflight::preflight, GenerateOptions, Node};
+//!
+//! let out = std::env::temp_dir();
+//! let mut root = Node::root().file("a.txt", "hi").build()?;
+//! root.children[0].attrs.mode = Some(0o17777);
+//! let problems = preflight(&root, &out, &GenerateOptions::default(), true);
+//! assert_eq!(problems, ["Invalid mode 0o17777 of 'a.txt'; modes must be at most 0o7777"]);
+//! # Ok::<(), treegen::NodeError>(())
+//! ```
+
+use crate::{
+    archive::{Archive, Member},
+    check_lengths, check_names, content_generator, ownership_problems, plan_hardlinks, CopySource,
+    Fill, GenerateOptions, IfExists, Node, NodeType,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// 预检发现的问题，每条是一行说明；为空表示可以生成
+///
+/// 依次检查：节点名称与硬链接目标、`--mode` 与节点的 mode、属主与属组能否解析、复制来源、
+/// 输出目录是否可写、已有路径的类型是否与描述相符、`if_exists: error` 的文件是否已有不同的内容
+/// （生成前会清空冲突路径时，如 `--clean`，传 `check_existing = false` 跳过这两项），
+/// 以及估计的写入量是否超过可用磁盘空间。
+/// 可选节点（及其下的路径）的复制来源与类型冲突不算问题，生成时只会警告
+pub fn preflight(
+    root: &Node,
+    out: &Path,
+    opts: &GenerateOptions,
+    check_existing: bool,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Err(e) = check_names(root) {
+        problems.push(e.to_string());
+    }
+    if let Err(e) = check_lengths(root, out, opts.max_name_bytes) {
+        problems.push(e.to_string());
+    }
+    if let Err(e) = plan_hardlinks(root, out) {
+        problems.push(e.to_string());
+    }
+    if opts.mode > 0o7777 {
+        problems.push(format!(
+            "Invalid --mode 0o{:o}; modes must be at most 0o7777",
+            opts.mode
+        ));
+    }
+    problems.extend(ownership_problems(root, opts));
+
+    let existing = existing_ancestor(out);
+    let out_exists = existing.as_deref() == Some(out);
+    // 占用磁盘的字节数（稀疏文件不计）与 --max-bytes 比较的声明字节数
+    let (mut bytes, mut declared) = (0, 0);
+    let mut optional: Vec<PathBuf> = Vec::new();
+    for (rel, node) in root.iter() {
+        if node.attrs.optional {
+            optional.push(rel.clone());
+        }
+        let is_optional = optional.iter().any(|dir| rel.starts_with(dir));
+        if let Some(mode) = node.attrs.mode.filter(|mode| *mode > 0o7777) {
+            problems.push(format!(
+                "Invalid mode 0o{:o} of '{}'; modes must be at most 0o7777",
+                mode,
+                rel.display()
+            ));
+        }
+        match node.node_type {
+            NodeType::File => {
+                let size = file_bytes(node);
+                declared += size;
+                if !node
+                    .attrs
+                    .placeholder
+                    .is_some_and(|p| p.fill == Fill::Sparse)
+                {
+                    bytes += size;
+                }
+            }
+            NodeType::Copy => {
+                let source = node.copy.as_ref().expect("copy node has a source");
+                match check_copy_source(source) {
+                    Ok(size) => {
+                        bytes += size;
+                        declared += size;
+                    }
+                    Err(_) if is_optional => {}
+                    Err(problem) => problems.push(format!("{} (for '{}')", problem, rel.display())),
+                }
+            }
+            NodeType::Dir | NodeType::Hardlink => {}
+        }
+        // 输出目录不存在时其中也不会有冲突的路径
+        if check_existing && out_exists && !rel.as_os_str().is_empty() && !is_optional {
+            if let Some(problem) = type_conflict(&out.join(&rel), node) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    if check_existing && out_exists {
+        problems.extend(if_exists_conflicts(root, out, opts));
+    }
+    problems.extend(max_bytes_problem(declared, opts.max_bytes));
+
+    match &existing {
+        Some(dir) if !dir.is_dir() => problems.push(format!(
+            "Output path '{}' is not a directory",
+            dir.display()
+        )),
+        Some(dir) if !writable(dir) => problems.push(format!(
+            "Output directory '{}' is not writable",
+            dir.display()
+        )),
+        Some(dir) => problems.extend(space_problem(dir, bytes)),
+        None => problems.push(format!(
+            "No existing parent directory for '{}'",
+            out.display()
+        )),
+    }
+    problems
+}
+
+/// `path` 本身或离它最近的已有上级；不存在的输出目录会在那里创建
+pub(crate) fn existing_ancestor(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .map(|dir| {
+            if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            }
+        })
+        .find(|dir| dir.exists())
+        .map(Path::to_path_buf)
+}
+
+/// 文件节点要写入的字节数：内容的长度、占位文件声明的大小，或内容生成器生成的大小
+pub(crate) fn file_bytes(node: &Node) -> u64 {
+    if let Some(Ok(generator)) = content_generator(node) {
+        return generator.len_hint();
+    }
+    match (&node.attrs.placeholder, &node.attrs.bytes) {
+        (Some(placeholder), _) => placeholder.size,
+        (None, Some(raw)) => raw.len() as u64,
+        (None, None) => node.content.as_ref().map_or(0, |c| c.len() as u64),
+    }
+}
+
+/// 估计生成会写入的字节数：文件内容、占位文件声明的大小（稀疏文件也算）与复制来源的大小，
+/// 读不到的复制来源不计
+pub(crate) fn estimated_bytes(root: &Node) -> u64 {
+    root.iter()
+        .map(|(_, node)| match node.node_type {
+            NodeType::File => file_bytes(node),
+            NodeType::Copy => {
+                check_copy_source(node.copy.as_ref().expect("copy node has a source")).unwrap_or(0)
+            }
+            NodeType::Dir | NodeType::Hardlink => 0,
+        })
+        .sum()
+}
+
+/// 占用磁盘的字节数：文件内容与复制来源，稀疏的占位文件不计
+pub(crate) fn disk_bytes(root: &Node) -> u64 {
+    root.iter()
+        .map(|(_, node)| match node.node_type {
+            NodeType::File
+                if node
+                    .attrs
+                    .placeholder
+                    .is_some_and(|p| p.fill == Fill::Sparse) =>
+            {
+                0
+            }
+            NodeType::File => file_bytes(node),
+            NodeType::Copy => {
+                check_copy_source(node.copy.as_ref().expect("copy node has a source")).unwrap_or(0)
+            }
+            NodeType::Dir | NodeType::Hardlink => 0,
+        })
+        .sum()
+}
+
+/// 要写入的 `bytes` 超过 `dir` 所在文件系统的可用空间时的说明
+pub(crate) fn space_problem(dir: &Path, bytes: u64) -> Option<String> {
+    let available = available_space(dir).filter(|available| bytes > *available)?;
+    Some(format!(
+        "Not enough disk space on '{}': about {} needed, {} available",
+        dir.display(),
+        human_bytes(bytes),
+        human_bytes(available)
+    ))
+}
+
+/// 估计的写入量超过 `--max-bytes`（[`GenerateOptions::max_bytes`]）时的说明
+pub(crate) fn max_bytes_problem(bytes: u64, max: Option<u64>) -> Option<String> {
+    max.filter(|max| bytes > *max).map(|max| {
+        format!(
+            "Generation would write about {} ({} bytes), more than --max-bytes {} ({} bytes)",
+            human_bytes(bytes),
+            bytes,
+            human_bytes(max),
+            max
+        )
+    })
+}
+
+/// 复制来源要存在，目录必须是递归复制；返回要复制的字节数
+fn check_copy_source(source: &CopySource) -> Result<u64, String> {
+    if let Some(zip) = &source.archive {
+        let archive = Archive::open(zip).map_err(|e| format!("{:#}", e))?;
+        return match archive.member(&source.from) {
+            None => Err(format!("Copy source '{}' does not exist", source.display())),
+            Some(Member::File(size)) => Ok(size),
+            Some(_) if !source.recursive => Err(format!(
+                "Copy source '{}' is a directory; set \"recursive\": true to copy it",
+                source.display()
+            )),
+            Some(Member::Dir { files, .. }) => Ok(files.iter().map(|(_, size)| size).sum()),
+        };
+    }
+    let from = &source.from;
+    let meta = fs::metadata(from)
+        .map_err(|_| format!("Copy source '{}' does not exist", from.display()))?;
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    if !source.recursive {
+        return Err(format!(
+            "Copy source '{}' is a directory; set \"recursive\": true to copy it",
+            from.display()
+        ));
+    }
+    Ok(dir_size(from))
+}
+
+/// 目录下所有文件的总大小（读不了的部分不计）
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// `if_exists: error` 的文件已存在且内容不同时生成会失败；在写入任何东西之前找出来，
+/// 不至于之前的节点已经备份、追加或覆盖之后才停下。可选节点（及其下的路径）不算；
+/// 内容生成器的值在写入时才算出，留到写入时检查
+pub(crate) fn if_exists_conflicts(root: &Node, out: &Path, opts: &GenerateOptions) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut optional: Vec<PathBuf> = Vec::new();
+    for (rel, node) in root.iter() {
+        if node.attrs.optional {
+            optional.push(rel.clone());
+        }
+        if node.node_type != NodeType::File
+            || node.attrs.if_exists != Some(IfExists::Error)
+            || opts.no_touch && node.content.is_none()
+            || optional.iter().any(|dir| rel.starts_with(dir))
+            || content_generator(node).is_some()
+        {
+            continue;
+        }
+        let path = out.join(&rel);
+        if !path.is_file() {
+            continue;
+        }
+        let unchanged = match (node.attrs.placeholder, &node.attrs.bytes) {
+            (Some(p), _) => fs::metadata(&path).is_ok_and(|m| m.len() == p.size),
+            (None, Some(bytes)) => fs::read(&path).is_ok_and(|old| old == *bytes),
+            (None, None) => fs::read(&path)
+                .is_ok_and(|old| old == node.content.as_deref().unwrap_or("").as_bytes()),
+        };
+        if !unchanged {
+            problems.push(format!(
+                "'{}' already exists with different content (if_exists: error)",
+                path.display()
+            ));
+        }
+    }
+    problems
+}
+
+/// 已有路径的类型与节点不符时生成会失败（目录不会被文件覆盖，反之亦然）
+fn type_conflict(path: &Path, node: &Node) -> Option<String> {
+    let meta = fs::symlink_metadata(path).ok()?;
+    let want_dir = match node.node_type {
+        NodeType::Dir => true,
+        NodeType::File | NodeType::Hardlink => false,
+        NodeType::Copy => return None,
+    };
+    if meta.is_dir() == want_dir {
+        return None;
+    }
+    Some(if want_dir {
+        format!(
+            "'{}' exists as a file but the spec has a directory",
+            path.display()
+        )
+    } else {
+        format!(
+            "'{}' exists as a directory but the spec has a file",
+            path.display()
+        )
+    })
+}
+
+#[cfg(unix)]
+fn writable(dir: &Path) -> bool {
+    use nix::unistd::{access, AccessFlags};
+    access(dir, AccessFlags::W_OK | AccessFlags::X_OK).is_ok()
+}
+
+#[cfg(not(unix))]
+fn writable(dir: &Path) -> bool {
+    fs::metadata(dir).is_ok_and(|meta| !meta.permissions().readonly())
+}
+
+/// 当前用户在 `dir` 所在文件系统上可用的字节数，查询失败时为 `None`（不检查）
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+    let stat = nix::sys::statvfs::statvfs(dir).ok()?;
+    #[allow(clippy::useless_conversion)]
+    let available = u64::from(stat.blocks_available()) * u64::from(stat.fragment_size());
+    Some(available)
+}
+
+#[cfg(windows)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: wide 是以 0 结尾的 UTF-16 路径，不需要的输出参数传空指针
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    (ok != 0).then_some(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// 1536 -> "1.5 KiB"
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
@@ -0,0 +1,286 @@
+//! 从 git 仓库获取描述文件：`gh:OWNER/REPO//PATH` 是 GitHub 的简写，
+//! `git+URL//PATH`（如 `git+https://example.com/templates.git//rust-cli.yaml`）适用于任意 git 地址。
+//! 仓库浅克隆到缓存目录（按仓库地址与 ref 区分），之后的更新只取最新的一个提交；
+//! 获取失败（或 `offline`）时使用缓存中已有的副本。描述在检出的仓库中解析，
+//! 相对路径的复制来源因此指向同一个仓库中的文件。
+//!
+//! ```
+//! use std::path::Path;
+//! use treegen::remote::Remote;
+//!
+//! let remote = Remote::parse("gh:myorg/templates//rust/cli.yaml").unwrap();
+//! assert_eq!(remote.url, "https://github.com/myorg/templates.git");
+//! assert_eq!(remote.path, Path::new("rust/cli.yaml"));
+//! let remote = Remote::parse("git+ssh://git@example.com/t.git//app.yaml").unwrap();
+//! assert_eq!(remote.url, "ssh://git@example.com/t.git");
+//! assert!(Remote::parse("specs/app.yaml").is_none());
+//! ```
+
+use crate::Spec;
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
+
+/// git 仓库中的一个描述文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Remote {
+    /// 命令行中的原始写法（用于日志与错误信息）
+    pub input: String,
+    /// 传给 `git clone` 的仓库地址
+    pub url: String,
+    /// 描述文件在仓库中的路径
+    pub path: PathBuf,
+}
+
+/// 获取仓库的选项
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// 分支或标签；`None` 为远端的默认分支
+    pub git_ref: Option<String>,
+    /// 不访问网络，只使用缓存
+    pub offline: bool,
+    /// 缓存目录，检出的仓库放在其下的 `git/` 中
+    pub cache_dir: PathBuf,
+}
+
+/// 获取的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fetched {
+    /// 检出的仓库中描述文件的路径
+    pub path: PathBuf,
+    /// 检出的仓库，描述引用的文件都必须在其中（见 [`confine`]）
+    pub checkout: PathBuf,
+    /// 没能更新、改用缓存时的说明
+    pub warning: Option<String>,
+}
+
+impl Remote {
+    /// 识别 `gh:OWNER/REPO//PATH` 与 `git+URL//PATH`；不是这两种写法时为 `None`
+    pub fn parse(input: &str) -> Option<Self> {
+        let (url, path) = if let Some(rest) = input.strip_prefix("gh:") {
+            let (repo, path) = rest.split_once("//")?;
+            (format!("https://github.com/{}.git", repo), path)
+        } else {
+            let rest = input.strip_prefix("git+")?;
+            // 跳过协议后的 "//"（本地路径的 file:/// 也一样）
+            let start = rest.find("://").map_or(0, |i| i + 3);
+            let split = rest[start..].find("//")? + start;
+            (rest[..split].to_string(), &rest[split + 2..])
+        };
+        Some(Remote {
+            input: input.to_string(),
+            url,
+            path: PathBuf::from(path),
+        })
+    }
+
+    /// 克隆或更新缓存中的仓库，返回描述文件的本地路径
+    pub fn fetch(&self, opts: &FetchOptions) -> Result<Fetched> {
+        let _span = tracing::info_span!("fetch", input = %self.input).entered();
+        if self.path.as_os_str().is_empty() {
+            bail!("'{}' does not name a spec file after '//'", self.input);
+        }
+        if !is_contained(&self.path) {
+            bail!(
+                "'{}' must name a file inside the repository after '//' (a relative path that does not leave it)",
+                self.input
+            );
+        }
+        let checkout = self.checkout_dir(opts);
+        let cached = checkout.join(".git").is_dir();
+        let warning = if opts.offline {
+            if !cached {
+                bail!(
+                    "'{}' is not in the cache ('{}'); run without --offline to fetch it",
+                    self.input,
+                    checkout.display()
+                );
+            }
+            None
+        } else {
+            match update(&self.url, opts.git_ref.as_deref(), &checkout) {
+                Ok(()) => None,
+                Err(e) if cached => Some(format!(
+                    "Failed to fetch '{}' ({:#}); using the cached copy in '{}'",
+                    self.input,
+                    e,
+                    checkout.display()
+                )),
+                Err(e) => {
+                    return Err(e.context(format!("Failed to fetch '{}'", self.input)));
+                }
+            }
+        };
+        let path = checkout.join(&self.path);
+        // 仓库中的符号链接同样不能指到仓库之外
+        if path.is_file() && !inside(&path, &checkout) {
+            bail!("'{}' resolves to a file outside the repository", self.input);
+        }
+        if !path.is_file() {
+            bail!(
+                "'{}' not found in {}{}",
+                self.path.display(),
+                self.url,
+                opts.git_ref
+                    .as_ref()
+                    .map(|r| format!(" (ref {})", r))
+                    .unwrap_or_default()
+            );
+        }
+        Ok(Fetched {
+            path,
+            checkout,
+            warning,
+        })
+    }
+
+    /// 缓存中的检出目录：可读的仓库名加上地址与 ref 的哈希
+    pub fn checkout_dir(&self, opts: &FetchOptions) -> PathBuf {
+        let git_ref = opts.git_ref.as_deref().unwrap_or("HEAD");
+        let hash = Sha256::digest(format!("{}#{}", self.url, git_ref));
+        let hash: String = hash[..6].iter().map(|b| format!("{:02x}", b)).collect();
+        let name = self
+            .url
+            .trim_end_matches('/')
+            .trim_end_matches(".git")
+            .rsplit(['/', ':'])
+            .next()
+            .unwrap_or("repo");
+        opts.cache_dir
+            .join("git")
+            .join(format!("{}-{}-{}", name, slug(git_ref), hash))
+    }
+}
+
+/// 文件名中只保留字母数字、`.`、`-` 与 `_`
+fn slug(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// 已有检出时只取 `git_ref` 的最新提交，否则浅克隆到临时目录再改名（中断时不留下半个仓库）
+fn update(url: &str, git_ref: Option<&str>, checkout: &Path) -> Result<()> {
+    if checkout.join(".git").is_dir() {
+        git(
+            Some(checkout),
+            &[
+                "fetch",
+                "--quiet",
+                "--depth",
+                "1",
+                "--",
+                "origin",
+                git_ref.unwrap_or("HEAD"),
+            ],
+        )?;
+        return git(
+            Some(checkout),
+            &["checkout", "--quiet", "--force", "--detach", "FETCH_HEAD"],
+        );
+    }
+    let parent = checkout.parent().expect("checkout has a parent");
+    fs::create_dir_all(parent)
+        .with_context(|| format!("Failed to create cache directory '{}'", parent.display()))?;
+    let mut tmp = checkout.as_os_str().to_owned();
+    tmp.push(format!(".tmp-{}", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    let _ = fs::remove_dir_all(&tmp);
+    let tmp_str = tmp.to_string_lossy();
+    let mut args = vec!["clone", "--quiet", "--depth", "1"];
+    if let Some(git_ref) = git_ref {
+        args.extend(["--branch", git_ref]);
+    }
+    // `--` 之后的地址与路径不会被当作选项，即使以 `-` 开头
+    args.extend(["--", url, &tmp_str]);
+    if let Err(e) = git(None, &args) {
+        let _ = fs::remove_dir_all(&tmp);
+        return Err(e);
+    }
+    fs::rename(&tmp, checkout)
+        .with_context(|| format!("Failed to move clone to '{}'", checkout.display()))
+}
+
+/// 运行 git（不询问凭据），失败时错误中带上它的输出
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<()> {
+    let mut cmd = Command::new("git");
+    if let Some(dir) = dir {
+        cmd.arg("-C").arg(dir);
+    }
+    let output = cmd
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git; is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// 远程描述只能引用检出的仓库 `checkout` 中的路径：复制来源与 `$out`（解析时已按描述所在目录
+/// 变成完整路径）不能是绝对路径，也不能经 `..` 或符号链接离开仓库
+pub fn confine(spec: &Spec, checkout: &Path) -> Result<()> {
+    if let Some(out) = &spec.config.out {
+        if !within(out, checkout) {
+            bail!(
+                "$out '{}' is outside the repository the spec was fetched from",
+                out.display()
+            );
+        }
+    }
+    for (rel, node) in spec.root.iter() {
+        let Some(source) = &node.copy else {
+            continue;
+        };
+        if !within(source.archive.as_ref().unwrap_or(&source.from), checkout) {
+            bail!(
+                "Copy source '{}' of '{}' is outside the repository the spec was fetched from",
+                source.display(),
+                rel.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `path` 按字面在 `dir` 之中，已存在时解析符号链接后也是
+fn within(path: &Path, dir: &Path) -> bool {
+    match path.strip_prefix(dir) {
+        Ok(rest) => is_contained(rest) && (!path.exists() || inside(path, dir)),
+        Err(_) => false,
+    }
+}
+
+/// 相对路径，且按字面处理 `..` 后不会离开所在的目录（`a/../b` 可以，`../b` 不行）
+fn is_contained(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// 解析符号链接后 `path` 仍在 `dir` 之中
+fn inside(path: &Path, dir: &Path) -> bool {
+    match (fs::canonicalize(path), fs::canonicalize(dir)) {
+        (Ok(path), Ok(dir)) => path.starts_with(dir),
+        _ => false,
+    }
+}
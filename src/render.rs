@@ -0,0 +1,205 @@
+//! 把解析后的 [`Node`] 树渲染成可视化文档（`treegen render`）：可折叠的独立 HTML 页面，
+//! 或静态的方框树 SVG 图。只读取树，不访问文件系统；输出不引用任何外部资源。
+//!
+//! ```
+//! use treegen::render::{html, svg};
+//!
+//! let spec = treegen::parse_yaml("src:\n  main.rs: \"fn main() { 1 < 2; }\\n\"\n")?;
+//! let page = html(&spec.root, "spec.yaml");
+//! assert!(page.contains("<title>spec.yaml</title>"));
+//! assert!(!page.contains("1 < 2"));
+//! assert!(svg(&spec.root).contains(">main.rs</text>"));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::{Node, NodeType};
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+
+/// HTML 中每个文件预览的最大字节数，超出的部分截断
+pub const PREVIEW_BYTES: usize = 64 * 1024;
+
+/// SVG 中每行的高度与每层的缩进（像素）
+const ROW: usize = 26;
+const INDENT: usize = 20;
+/// 12px 等宽字体中一个 ASCII 字符的大致宽度
+const CHAR_WIDTH: f64 = 7.2;
+
+/// HTML 页面模板（样式与脚本都内嵌）
+const PAGE: &str = include_str!("render/page.html");
+
+/// 独立的 HTML 页面：目录点击展开/折叠（首次展开时才创建其子节点，大树也能很快打开），
+/// 文件点击后在 `<pre>` 中显示内容预览。树以 JSON 嵌入页面，`<`、`>`、`&` 都已转义
+pub fn html(root: &Node, title: &str) -> String {
+    let nodes: Vec<Value> = top_level(root).iter().map(node_json).collect();
+    let data = serde_json::to_string(&nodes)
+        .expect("tree JSON is always serializable")
+        // JSON 中只有字符串里会出现这些字符，换成 \uXXXX 后不会结束 <script>
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026");
+    // 模板中的 @TITLE@ 与 @DATA@ 各出现一次，按位置拼接，替换进去的文本不会再被替换
+    let (head, rest) = PAGE
+        .split_once("@TITLE@")
+        .expect("page template has a title");
+    let (middle, tail) = rest.split_once("@DATA@").expect("page template has data");
+    [head, &escape(title), middle, &data, tail].concat()
+}
+
+/// 静态的方框树 SVG：每个节点一行，按层缩进，用折线连接到上级目录
+pub fn svg(root: &Node) -> String {
+    // (层数, 标签, 类型)，按先序
+    let rows: Vec<(usize, String, &'static str)> = top_level(root)
+        .iter()
+        .flat_map(|top| top.iter())
+        .map(|(rel, node)| (rel.components().count() - 1, label(node), class(node)))
+        .collect();
+    let box_width = |text: &str| {
+        let units: usize = text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum();
+        (units as f64 * CHAR_WIDTH).ceil() as usize + 16
+    };
+    let width = rows
+        .iter()
+        .map(|(depth, text, _)| 10 + depth * INDENT + box_width(text))
+        .max()
+        .unwrap_or(0)
+        + 10;
+    let height = rows.len() * ROW + 14;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = width,
+        h = height
+    );
+    out.push_str(concat!(
+        "<style>",
+        "text{font:12px ui-monospace,SFMono-Regular,Menlo,Consolas,monospace;fill:#1f2328}",
+        "rect{stroke-width:1;rx:3}",
+        ".dir rect{fill:#ddf4ff;stroke:#54aeff}",
+        ".file rect{fill:#f6f8fa;stroke:#afb8c1}",
+        ".copy rect{fill:#fff8c5;stroke:#d4a72c}",
+        ".hardlink rect{fill:#fbefff;stroke:#c297ff}",
+        "path{fill:none;stroke:#8c959f}",
+        "</style>\n"
+    ));
+    // 每层最近一个目录所在的行，子节点的连线从那里引出
+    let mut parents: Vec<usize> = Vec::new();
+    for (row, (depth, text, class)) in rows.iter().enumerate() {
+        let x = 10 + depth * INDENT;
+        let y = 10 + row * ROW;
+        parents.truncate(*depth);
+        if let Some(&parent) = parents.last() {
+            let px = 10 + (depth - 1) * INDENT + 8;
+            let py = 10 + parent * ROW + 20;
+            let _ = writeln!(out, r#"<path d="M{} {} V{} H{}"/>"#, px, py, y + 10, x);
+        }
+        let _ = writeln!(
+            out,
+            r#"<g class="{}"><rect x="{}" y="{}" width="{}" height="20"/><text x="{}" y="{}">{}</text></g>"#,
+            class,
+            x,
+            y,
+            box_width(text),
+            x + 8,
+            y + 14,
+            escape(text)
+        );
+        parents.push(row);
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+/// 未命名的根节点只渲染其子节点
+fn top_level(root: &Node) -> &[Node] {
+    if root.name.is_empty() {
+        &root.children
+    } else {
+        std::slice::from_ref(root)
+    }
+}
+
+fn node_json(node: &Node) -> Value {
+    let mut value = json!({ "n": node.name, "t": class(node) });
+    if let Some(detail) = detail(node) {
+        value["d"] = json!(detail);
+    }
+    if let Some(content) = &node.content {
+        value["s"] = json!(content.len());
+        let shown = preview(content);
+        value["c"] = json!(shown);
+        if shown.len() < content.len() {
+            value["x"] = json!(true);
+        }
+    }
+    if node.node_type == NodeType::Dir {
+        value["k"] = Value::Array(node.children.iter().map(node_json).collect());
+    }
+    value
+}
+
+/// 内容的前 [`PREVIEW_BYTES`] 字节（在字符边界截断）
+fn preview(content: &str) -> &str {
+    if content.len() <= PREVIEW_BYTES {
+        return content;
+    }
+    let mut end = PREVIEW_BYTES;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    &content[..end]
+}
+
+fn class(node: &Node) -> &'static str {
+    match node.node_type {
+        NodeType::Dir => "dir",
+        NodeType::File => "file",
+        NodeType::Copy => "copy",
+        NodeType::Hardlink => "hardlink",
+    }
+}
+
+/// 复制来源或链接目标
+fn detail(node: &Node) -> Option<String> {
+    match node.node_type {
+        NodeType::Copy => node
+            .copy
+            .as_ref()
+            .map(|copy| format!("← {}", copy.from.display())),
+        NodeType::Hardlink => node
+            .target
+            .as_ref()
+            .map(|target| format!("⇒ {}", target.display())),
+        _ => None,
+    }
+}
+
+fn label(node: &Node) -> String {
+    let mut text = node.name.clone();
+    if node.node_type == NodeType::Dir {
+        text.push('/');
+    }
+    if let Some(detail) = detail(node) {
+        text.push(' ');
+        text.push_str(&detail);
+    }
+    text
+}
+
+/// 转义 HTML/XML 文本与属性中的特殊字符
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
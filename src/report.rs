@@ -0,0 +1,291 @@
+//! `--report`：把一次运行写成 JSON 文档留档，内容包括调用的参数、描述文件的 SHA-256、模板变量
+//! （`--redact-var` 指定的值隐去）、每个路径的动作与原因、写入内容的 SHA-256、各阶段耗时与最终状态。
+//! 运行中途失败或被 Ctrl-C 中断时同样写出到那时为止记录的部分。
+//!
+//! 记录器是全局的，运行过程中各处按需记录；没有 `--report` 时什么也不做。文档结构不兼容地改变时
+//! 递增 [`VERSION`]，只增加字段不改变它。
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+use treegen::{checksum, ActionKind, ContentRef, Outcome, Plan, Stats};
+
+/// 报告格式的版本
+pub const VERSION: u32 = 1;
+
+/// 隐去的变量值与参数
+const REDACTED: &str = "<redacted>";
+
+/// 运行的最终状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Success,
+    Failed,
+    Interrupted,
+}
+
+/// 写出的文档
+#[derive(Debug, Serialize)]
+struct Document {
+    version: u32,
+    treegen: &'static str,
+    started: String,
+    finished: String,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// 命令行参数（不含程序名）
+    args: Vec<String>,
+    cwd: PathBuf,
+    specs: Vec<SpecHash>,
+    vars: BTreeMap<String, String>,
+    /// 每个输出目录一次生成
+    runs: Vec<Run>,
+}
+
+/// 一份描述及其内容的 SHA-256（读不到时为 null，如 git 仓库中的描述）
+#[derive(Debug, Serialize)]
+struct SpecHash {
+    path: String,
+    sha256: Option<String>,
+}
+
+/// 一次生成
+#[derive(Debug, Serialize)]
+struct Run {
+    out: PathBuf,
+    dry_run: bool,
+    paths: Vec<PathReport>,
+    timings: serde_json::Value,
+}
+
+/// 计划中一个路径的结果
+#[derive(Debug, Serialize)]
+struct PathReport {
+    path: String,
+    kind: ActionKind,
+    /// created/overwritten/unchanged/skipped/missing/failed，没有执行到的为 not-run
+    action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+    /// 生成后磁盘上文件的 SHA-256（dry-run 与目录没有）；内容用到了要隐去的变量时
+    /// 为 `<redacted>`，否则短的值可以由哈希穷举出来
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+/// 正在记录的报告：写到哪里、要隐去的变量与已记录的内容
+struct Recorder {
+    path: PathBuf,
+    redact: Vec<String>,
+    doc: Document,
+}
+
+fn active() -> MutexGuard<'static, Option<Recorder>> {
+    static ACTIVE: Mutex<Option<Recorder>> = Mutex::new(None);
+    ACTIVE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 开始记录，报告之后由 [`finish`] 写到 `path`
+pub fn start(path: &Path, redact: &[String]) {
+    let args = redact_args(
+        env::args_os()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy().into_owned()),
+        redact,
+    );
+    *active() = Some(Recorder {
+        path: path.to_path_buf(),
+        redact: redact.to_vec(),
+        doc: Document {
+            version: VERSION,
+            treegen: env!("CARGO_PKG_VERSION"),
+            started: chrono::Local::now().to_rfc3339(),
+            finished: String::new(),
+            status: Status::Success,
+            error: None,
+            args,
+            cwd: env::current_dir().unwrap_or_default(),
+            specs: Vec::new(),
+            vars: BTreeMap::new(),
+            runs: Vec::new(),
+        },
+    });
+}
+
+/// 是否在记录（没有时不必为报告计算哈希）
+pub fn is_active() -> bool {
+    active().is_some()
+}
+
+/// 记录描述文件：哈希磁盘上的文件，不是文件时哈希为 null
+pub fn record_spec_file(path: &Path) {
+    if !is_active() {
+        return;
+    }
+    let sha256 = path
+        .is_file()
+        .then(|| checksum::sha256_file(path).ok())
+        .flatten();
+    record_spec(path.display().to_string(), sha256);
+}
+
+/// 记录来自标准输入、剪贴板或内置预设的描述
+pub fn record_spec_text(source: &str, content: &str) {
+    if is_active() {
+        record_spec(
+            source.to_string(),
+            Some(checksum::sha256_bytes(content.as_bytes())),
+        );
+    }
+}
+
+fn record_spec(path: String, sha256: Option<String>) {
+    if let Some(recorder) = active().as_mut() {
+        recorder.doc.specs.push(SpecHash { path, sha256 });
+    }
+}
+
+/// 记录替换时使用的模板变量，`--redact-var` 指定的值隐去
+pub fn record_vars(vars: &BTreeMap<String, String>) {
+    if let Some(recorder) = active().as_mut() {
+        for (name, value) in vars {
+            let value = match recorder.redact.contains(name) {
+                true => REDACTED.to_string(),
+                false => value.clone(),
+            };
+            recorder.doc.vars.insert(name.clone(), value);
+        }
+    }
+}
+
+/// 记录一次生成的结果：计划中每个路径的动作，以及生成后文件的哈希
+pub fn record_run(plan: &Plan, stats: &Stats, dry_run: bool) {
+    if !is_active() {
+        return;
+    }
+    let failed = |rel: &Path| stats.failures.iter().find(|f| rel.starts_with(&f.path));
+    // 指向隐去内容的硬链接是同一份内容；硬链接排在它们的目标之后
+    let mut redacted: Vec<&Path> = Vec::new();
+    for action in &plan.actions {
+        let hidden = match &action.content_ref {
+            Some(ContentRef::Link { target }) => redacted.contains(&target.as_path()),
+            _ => action.node.attrs.redacted,
+        };
+        if hidden {
+            redacted.push(&action.path);
+        }
+    }
+    let paths = plan
+        .actions
+        .iter()
+        .filter(|action| !action.path.as_os_str().is_empty())
+        .map(|action| {
+            let rel = &action.path;
+            let (name, reason) = match stats.outcomes.get(rel) {
+                Some(outcome) => (outcome.name(), outcome_reason(*outcome)),
+                None => match stats.warnings.iter().find(|w| rel.starts_with(&w.path)) {
+                    Some(warning) => ("skipped", Some(format!("optional: {}", warning.error))),
+                    None => match failed(rel) {
+                        Some(failure) if failure.path == *rel => {
+                            ("failed", Some(failure.error.clone()))
+                        }
+                        Some(failure) => (
+                            "skipped",
+                            Some(format!(
+                                "'{}' failed",
+                                failure.path.to_string_lossy().replace('\\', "/")
+                            )),
+                        ),
+                        None => ("not-run", None),
+                    },
+                },
+            };
+            let written = matches!(
+                stats.outcomes.get(rel),
+                Some(Outcome::Created | Outcome::Overwritten | Outcome::Unchanged)
+            );
+            let file = plan.out.join(rel);
+            let sha256 = (written && !dry_run && file.is_file())
+                .then(|| match stats.verified.get(&file) {
+                    _ if redacted.contains(&rel.as_path()) => Some(REDACTED.to_string()),
+                    Some(hash) => Some(hash.clone()),
+                    None => checksum::sha256_file(&file).ok(),
+                })
+                .flatten();
+            PathReport {
+                path: rel.to_string_lossy().replace('\\', "/"),
+                kind: action.kind,
+                action: name,
+                reason,
+                sha256,
+            }
+        })
+        .collect();
+    if let Some(recorder) = active().as_mut() {
+        recorder.doc.runs.push(Run {
+            out: plan.out.clone(),
+            dry_run,
+            paths,
+            timings: serde_json::Value::Array(Vec::new()),
+        });
+    }
+}
+
+/// 没有生成的路径的原因
+fn outcome_reason(outcome: Outcome) -> Option<String> {
+    let reason = match outcome {
+        Outcome::Skipped => "no content (--no-touch)",
+        Outcome::Missing => "does not exist (--content-only)",
+        _ => return None,
+    };
+    Some(reason.to_string())
+}
+
+/// 更新最近一次生成的各阶段耗时（生成之后还有钩子等阶段）
+pub fn record_timings(timings: serde_json::Value) {
+    if let Some(run) = active().as_mut().and_then(|r| r.doc.runs.last_mut()) {
+        run.timings = timings;
+    }
+}
+
+/// 写出报告并停止记录；没有在记录时什么也不做
+pub fn finish(status: Status, error: Option<String>) -> Result<()> {
+    let Some(Recorder { path, mut doc, .. }) = active().take() else {
+        return Ok(());
+    };
+    doc.finished = chrono::Local::now().to_rfc3339();
+    doc.status = status;
+    doc.error = error;
+    let text = serde_json::to_string_pretty(&doc)?;
+    fs::write(&path, text + "\n")
+        .with_context(|| format!("Failed to write report file '{}'", path.display()))
+}
+
+/// 隐去 `--var NAME=VALUE` 与 `--var=NAME=VALUE` 中要隐去的变量的值
+fn redact_args(args: impl Iterator<Item = String>, redact: &[String]) -> Vec<String> {
+    let hide = |assignment: &str| match assignment.split_once('=') {
+        Some((name, _)) if redact.iter().any(|r| r == name) => {
+            Some(format!("{}={}", name, REDACTED))
+        }
+        _ => None,
+    };
+    let mut out: Vec<String> = Vec::new();
+    for arg in args {
+        let redacted = match out.last().map(String::as_str) {
+            Some("--var") => hide(&arg),
+            _ => arg
+                .strip_prefix("--var=")
+                .and_then(hide)
+                .map(|value| format!("--var={}", value)),
+        };
+        out.push(redacted.unwrap_or(arg));
+    }
+    out
+}
@@ -0,0 +1,103 @@
+//! 盘符、UNC 前缀、绝对路径与 `..` 作为节点名称时报错，而不是生成到输出目录之外
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{
+    generate, parse_json, parse_markdown, parse_paths, parse_yaml, GenerateOptions, Node,
+};
+
+#[test]
+fn spec_keys_must_be_relative() {
+    for (key, reason) in [
+        ("C:\\\\Users\\\\me\\\\project", "starts with a drive letter"),
+        ("C:/tmp/x", "starts with a drive letter"),
+        ("d:", "starts with a drive letter"),
+        ("\\\\\\\\server\\\\share", "is a UNC path"),
+        ("//server/share", "is a UNC path"),
+        ("/etc", "is an absolute path"),
+        ("src/../..", "contains '..'"),
+    ] {
+        let err = parse_json(&format!(r#"{{ "app": {{ "{}": {{}} }} }}"#, key)).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains(reason), "{key}: {message}");
+        assert!(message.contains("must be relative"), "{key}: {message}");
+    }
+    let err = parse_yaml("'C:\\Users': {}\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Key 'C:\\Users' starts with a drive letter; spec paths must be relative to the output directory"
+    );
+    // 冒号不在第二个字符的名称照常使用
+    assert!(parse_yaml("'ab:c': ''\n'c': ''\n").is_ok());
+}
+
+#[test]
+fn markdown_and_path_list_names_must_be_relative() {
+    let err = parse_markdown("app/\n├── ok.txt\n└── C:\\temp\\\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Line 3, column 5: 'C:\\temp\\' starts with a drive letter; spec paths must be relative to the output directory"
+    );
+    let err = parse_markdown("\\\\server\\share/\n└── a\n").unwrap_err();
+    assert!(err.to_string().contains("Line 1"), "{err}");
+    assert!(err.to_string().contains("is a UNC path"), "{err}");
+    let err = parse_paths("a.txt\nC:/Windows/win.ini\n").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Line 2: 'C:/Windows/win.ini' must be a relative path"),
+        "{err}"
+    );
+}
+
+#[test]
+fn generate_rejects_built_trees_with_absolute_names() {
+    let out = tempfile::tempdir().unwrap();
+    let root = Node::root()
+        .child(Node::dir("app").child(Node::dir("C:\\Windows").file("x", "")))
+        .build()
+        .unwrap();
+    let err = generate(&root, out.path(), &GenerateOptions::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Node 'C:\\Windows' in 'app' starts with a drive letter; spec paths must be relative to the output directory"
+    );
+    // 在写入任何东西之前就报错
+    assert!(!out.path().join("app").exists());
+}
+
+#[test]
+fn cli_names_the_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.json", r#"{ "C:\\tmp\\x": "" }"#)]);
+    let output = treegen(dir)
+        .args(["app.json", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Key 'C:\\tmp\\x' starts with a drive letter"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+}
+
+/// 在 Windows 上 `Path::join` 会用带盘符或 UNC 前缀的名称整个换掉输出目录
+#[cfg(windows)]
+#[test]
+fn windows_join_cannot_escape_out() {
+    let out = tempfile::tempdir().unwrap();
+    let elsewhere = tempfile::tempdir().unwrap();
+    let escaped = elsewhere.path().join("escaped.txt");
+    assert_eq!(out.path().join(&escaped), escaped);
+    let root = Node::root()
+        .file(escaped.to_str().unwrap(), "oops")
+        .build()
+        .unwrap();
+    assert!(generate(&root, out.path(), &GenerateOptions::default()).is_err());
+    assert!(!escaped.exists());
+    let err = parse_yaml("'\\\\?\\C:\\x': ''\n").unwrap_err();
+    assert!(err.to_string().contains("is a UNC path"), "{err}");
+}
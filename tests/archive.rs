@@ -0,0 +1,203 @@
+//! zip 模板包：解析包中的描述，相对的复制来源从同一个包中复制
+
+mod common;
+
+use common::{read, treegen};
+use std::io::Write;
+use zip::write::SimpleFileOptions;
+
+const SPEC: &str = r##"
+"{{name}}":
+  README.md: "# {{name}}\n"
+  logo.png: { type: copy, from: assets/logo.png }
+  templates: { type: copy, from: ../shared, recursive: true, exclude: ["*.tmp"] }
+"##;
+
+/// 把（成员, 内容）写成 `dir/name` 压缩包；以 `/` 结尾的成员是目录
+fn zip(dir: &std::path::Path, name: &str, members: &[(&str, &[u8])]) {
+    let file = std::fs::File::create(dir.join(name)).unwrap();
+    let mut zip = zip::ZipWriter::new(file);
+    for (member, content) in members {
+        if member.ends_with('/') {
+            zip.add_directory(*member, SimpleFileOptions::default())
+                .unwrap();
+        } else {
+            let options = SimpleFileOptions::default().unix_permissions(0o640);
+            zip.start_file(*member, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+    }
+    zip.finish().unwrap();
+}
+
+fn bundle(dir: &std::path::Path) {
+    zip(
+        dir,
+        "bundle.zip",
+        &[
+            ("spec/app.yaml", SPEC.as_bytes()),
+            ("spec/assets/logo.png", &[0x89, b'P', b'N', b'G', 0, 0xff]),
+            ("shared/", b""),
+            ("shared/ci.yml", b"on: push\n"),
+            ("shared/deep/a.txt", b"a"),
+            ("shared/cache.tmp", b"x"),
+        ],
+    );
+}
+
+#[test]
+fn generates_from_a_bundle() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    bundle(dir);
+    let output = treegen(dir)
+        .args([
+            "bundle.zip!spec/app.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=demo",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/demo/README.md"), "# demo\n");
+    assert_eq!(
+        std::fs::read(dir.join("out/demo/logo.png")).unwrap(),
+        [0x89, b'P', b'N', b'G', 0, 0xff]
+    );
+    assert_eq!(read(dir, "out/demo/templates/ci.yml"), "on: push\n");
+    assert_eq!(read(dir, "out/demo/templates/deep/a.txt"), "a");
+    assert!(!dir.join("out/demo/templates/cache.tmp").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.join("out/demo/logo.png"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    // 生成的结果与描述一致
+    let output = treegen(dir)
+        .args([
+            "bundle.zip!spec/app.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=demo",
+        ])
+        .arg("--check")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn missing_members_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    bundle(dir);
+    let output = treegen(dir)
+        .args(["bundle.zip!spec/missing.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("'spec/missing.yaml' not found in archive 'bundle.zip'"),
+        "{}",
+        stderr
+    );
+
+    zip(
+        dir,
+        "broken.zip",
+        &[("app.yaml", b"logo.png: { type: copy, from: logo.png }\n")],
+    );
+    let output = treegen(dir)
+        .args(["broken.zip", "--out", "out", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Copy source 'broken.zip!logo.png' does not exist"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn several_specs_need_a_member() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    zip(
+        dir,
+        "bundle.zip",
+        &[
+            ("api.yaml", b"api.txt: a\n"),
+            ("web.json", b"{\"web.txt\": \"w\"}"),
+            ("docs/guide.md", b"# guide\n"),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["bundle.zip", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(
+            "Archive 'bundle.zip' contains several spec files (api.yaml, web.json); \
+             pick one with 'bundle.zip!api.yaml'"
+        ),
+        "{}",
+        stderr
+    );
+
+    let output = treegen(dir)
+        .args(["bundle.zip!web.json", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/web.txt"), "w");
+}
+
+#[test]
+fn entries_escaping_the_archive_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let spec = "assets: { type: copy, from: assets, recursive: true }\n";
+    for (name, entry) in [
+        ("parent.zip", "assets/../../../../outside.txt"),
+        ("inside.zip", "assets/../outside.txt"),
+        // Windows 上 `\` 同样是分隔符
+        ("backslash.zip", "assets/..\\..\\outside.txt"),
+    ] {
+        zip(
+            dir,
+            name,
+            &[
+                ("spec.yaml", spec.as_bytes()),
+                ("assets/ok.txt", b"ok"),
+                (entry, b"pwned"),
+            ],
+        );
+        let output = treegen(dir)
+            .args([name, "--out", "out/inner"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{name}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!(
+                "Archive '{name}' contains an unsafe entry '{entry}' (absolute or with '..')"
+            )),
+            "{stderr}"
+        );
+        assert!(!dir.join("out").exists(), "{name}");
+        assert!(!dir.join("outside.txt").exists(), "{name}");
+    }
+}
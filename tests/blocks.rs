@@ -0,0 +1,55 @@
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r#"$blocks:
+  pyinit: "\"\"\"{{name}} package.\"\"\"\n"
+  license: "@snippet:mit-license"
+src:
+  app/__init__.py: "@block:pyinit"
+  app/models/__init__.py: "@block:pyinit"
+  raw.txt: { type: file, content: "@block:pyinit", raw: true }
+LICENSE: "@block:license"
+"#;
+
+#[test]
+fn blocks_are_shared_and_substituted() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .args(["--var", "name=demo", "--var", "author=Jane"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let expected = "\"\"\"demo package.\"\"\"\n";
+    assert_eq!(read(dir, "out/src/app/__init__.py"), expected);
+    assert_eq!(read(dir, "out/src/app/models/__init__.py"), expected);
+    assert_eq!(read(dir, "out/src/raw.txt"), "@block:pyinit");
+    assert!(read(dir, "out/LICENSE").contains(" Jane\n"));
+}
+
+#[test]
+fn undefined_block_names_node_and_block() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "spec.json",
+            r#"{ "$blocks": { "header": "// x\n" }, "src": { "main.rs": "@block:heder" } }"#,
+        )],
+    );
+    let output = treegen(dir)
+        .args(["spec.json", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Undefined block 'heder' referenced by 'src/main.rs'"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("header"), "{stderr}");
+}
@@ -0,0 +1,134 @@
+//! 名称中的花括号展开：{a,b}、数字范围 {1..3}/{01..10}，可以嵌套
+mod common;
+
+use common::{treegen, write_files};
+use std::path::Path;
+
+/// 生成后输出目录中的所有路径（相对路径，排序）
+fn generate(dir: &Path, spec: &str, args: &[&str]) -> Vec<String> {
+    let _ = std::fs::remove_dir_all(dir.join("out"));
+    write_files(dir, &[("spec.yaml", spec)]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .args(args)
+        .assert()
+        .success();
+    let mut paths = Vec::new();
+    let mut pending = vec![dir.join("out")];
+    while let Some(path) = pending.pop() {
+        for entry in std::fs::read_dir(path).unwrap() {
+            let path = entry.unwrap().path();
+            let rel = path.strip_prefix(dir.join("out")).unwrap();
+            paths.push(rel.to_string_lossy().replace('\\', "/"));
+            if path.is_dir() {
+                pending.push(path);
+            }
+        }
+    }
+    paths.sort();
+    paths
+}
+
+fn fails(dir: &Path, spec: &str, args: &[&str]) -> String {
+    write_files(dir, &[("spec.yaml", spec)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn ranges_keep_zero_padding_and_may_run_backwards() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    assert_eq!(
+        generate(dir, "'f{08..11}.txt': ''\n", &[]),
+        ["f08.txt", "f09.txt", "f10.txt", "f11.txt"]
+    );
+    assert_eq!(
+        generate(dir, "'r{3..1}': ''\n'n{-1..1}': ''\n", &[]),
+        ["n-1", "n0", "n1", "r1", "r2", "r3"]
+    );
+}
+
+#[test]
+fn braces_nest_and_multiply() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    assert_eq!(
+        generate(dir, "'{src,test}/{a,b{1..2}}.rs': ''\n", &[]),
+        [
+            "src",
+            "src/a.rs",
+            "src/b1.rs",
+            "src/b2.rs",
+            "test",
+            "test/a.rs",
+            "test/b1.rs",
+            "test/b2.rs"
+        ]
+    );
+    // 模板变量的 {{ }} 不被当作花括号展开
+    assert_eq!(
+        generate(dir, "'{{name}}-{a,b}': ''\n", &["--var", "name=x"]),
+        ["x-a", "x-b"]
+    );
+}
+
+#[test]
+fn expansions_are_capped() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let stderr = fails(dir, "'f{1..10001}': ''\n", &[]);
+    assert!(
+        stderr.contains("The range {1..10001} has more than 10000 values"),
+        "{stderr}"
+    );
+    // 每个名称都不超过上限，但合起来产生的节点超过了
+    let stderr = fails(dir, "'d{1..100}':\n  'f{1..101}': ''\n", &[]);
+    assert!(
+        stderr.contains(
+            "Brace expansion of 'd{1..100}' produces more than 10000 nodes (pass --no-brace-expansion if the braces are part of the name)"
+        ),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn duplicates_after_expansion_follow_on_duplicate() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let stderr = fails(dir, "'{a,a}.txt': x\n", &["--on-duplicate", "error"]);
+    assert!(
+        stderr.contains("'a.txt' is defined more than once in 'spec.yaml'"),
+        "{stderr}"
+    );
+    let stderr = fails(
+        dir,
+        "'{a,b}.txt': x\nb.txt: y\n",
+        &["--on-duplicate", "error"],
+    );
+    assert!(
+        stderr.contains("'b.txt' is defined more than once"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn no_brace_expansion_keeps_names_literal() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    assert_eq!(
+        generate(
+            dir,
+            "'{a,b}.txt': x\n'{1..2}': ''\n",
+            &["--no-brace-expansion"]
+        ),
+        ["{1..2}", "{a,b}.txt"]
+    );
+}
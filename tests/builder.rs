@@ -0,0 +1,43 @@
+//! 构建器：构建的树能直接生成，违反约束时 `build` 返回第一个错误
+
+use std::fs;
+use treegen::{generate, GenerateOptions, Node, NodeError};
+
+#[test]
+fn built_tree_is_generated() {
+    let out = tempfile::tempdir().unwrap();
+    let root = Node::root()
+        .child(
+            Node::dir("demo")
+                .file("Cargo.toml", "[package]\nname = \"demo\"\n")
+                .child(
+                    Node::dir("src")
+                        .file("main.rs", "fn main() {}\n")
+                        .file_empty("lib.rs"),
+                ),
+        )
+        .build()
+        .unwrap();
+    let stats = generate(&root, out.path(), &GenerateOptions::default()).unwrap();
+    assert_eq!((stats.dirs, stats.files), (2, 3));
+    assert_eq!(
+        fs::read_to_string(out.path().join("demo/src/main.rs")).unwrap(),
+        "fn main() {}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(out.path().join("demo/src/lib.rs")).unwrap(),
+        ""
+    );
+}
+
+#[test]
+fn empty_names_are_rejected() {
+    let err = Node::root().child(Node::dir("")).build().unwrap_err();
+    assert_eq!(err, NodeError::EmptyName);
+    let err = Node::root()
+        .file("", "x")
+        .file("ok.txt", "")
+        .build()
+        .unwrap_err();
+    assert_eq!(err, NodeError::EmptyName);
+}
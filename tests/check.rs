@@ -0,0 +1,107 @@
+//! --check 与 --fix：与描述比较输出目录、忽略 --exclude 的路径，不一致时退出码为 1
+
+mod common;
+
+use common::{read, treegen, write_files};
+use globset::{Glob, GlobSetBuilder};
+use treegen::diff::{diff_dir, Change};
+
+/// 描述与不一致的输出目录：README.md 内容不同、extra.txt 多出、src 缺失
+fn setup() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    write_files(
+        dir.path(),
+        &[
+            ("app.yaml", "README.md: new\nsrc:\n  main.rs: ''\n"),
+            ("out/.git/HEAD", "ref: refs/heads/main\n"),
+            ("out/README.md", "old"),
+            ("out/extra.txt", ""),
+        ],
+    );
+    dir
+}
+
+#[test]
+fn diff_dir_reports_changes_outside_the_exclusions() {
+    let dir = setup();
+    let spec = treegen::parse_yaml("README.md: new\nsrc:\n  main.rs: ''\n").unwrap();
+    let mut exclude = GlobSetBuilder::new();
+    exclude.add(Glob::new(".git").unwrap());
+    let found: Vec<_> = diff_dir(
+        &spec.root,
+        &dir.path().join("out"),
+        &exclude.build().unwrap(),
+    )
+    .unwrap()
+    .into_iter()
+    .map(|d| (d.change, d.path.to_string_lossy().replace('\\', "/")))
+    .collect();
+    assert_eq!(
+        found,
+        [
+            (Change::Changed, "README.md".to_string()),
+            (Change::Added, "extra.txt".to_string()),
+            (Change::Removed, "src".to_string()),
+            (Change::Removed, "src/main.rs".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn check_lists_out_of_sync_paths_and_writes_nothing() {
+    let dir = setup();
+    let dir = dir.path();
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--check"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("mismatch: README.md"), "{stdout}");
+    assert!(stdout.contains("missing: src\n"), "{stdout}");
+    // 不加 --sync 时多出的路径不算不一致
+    assert!(!stdout.contains("extra"), "{stdout}");
+    assert_eq!(read(&dir.join("out"), "README.md"), "old");
+    assert!(!dir.join("out/src").exists());
+
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--check", "--sync"])
+        .args(["--exclude", ".git"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("extra: extra.txt"), "{stdout}");
+    assert!(!stdout.contains(".git"), "{stdout}");
+}
+
+#[test]
+fn fix_brings_the_directory_in_sync() {
+    let dir = setup();
+    let dir = dir.path();
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--fix"])
+        .assert()
+        .success();
+    assert_eq!(read(&dir.join("out"), "README.md"), "new");
+    assert!(dir.join("out/src/main.rs").is_file());
+    assert!(dir.join("out/extra.txt").exists());
+
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--check"])
+        .assert()
+        .success();
+    treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "out",
+            "--fix",
+            "--sync",
+            "--exclude",
+            ".git",
+        ])
+        .assert()
+        .success();
+    assert!(!dir.join("out/extra.txt").exists());
+    assert!(dir.join("out/.git/HEAD").exists());
+}
@@ -0,0 +1,98 @@
+//! --checksums 与 `treegen verify`：清单与 sha256sum 兼容，校验报告不符、缺失与多出的文件
+
+mod common;
+
+use common::{treegen, write_files};
+use std::path::Path;
+use treegen::checksum::{generated_files, manifest, parse_manifest, verify};
+use treegen::diff::Change;
+
+const EMPTY: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[test]
+fn manifest_hashes_the_written_bytes() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    let spec = treegen::parse_yaml("src:\n  main.rs: 'fn main() {}'\nempty.txt: ''\n").unwrap();
+    treegen::generate(&spec.root, out, &Default::default()).unwrap();
+    let text = manifest(out, &generated_files(&spec.root, out).unwrap()).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], format!("{}  empty.txt", EMPTY));
+    assert!(lines[1].ends_with("  src/main.rs"), "{text}");
+
+    let entries = parse_manifest(&text).unwrap();
+    assert!(verify(&entries, out, None).unwrap().is_empty());
+    std::fs::write(out.join("src/main.rs"), "changed").unwrap();
+    std::fs::remove_file(out.join("empty.txt")).unwrap();
+    std::fs::write(out.join("extra.txt"), "").unwrap();
+    let problems: Vec<_> = verify(&entries, out, None)
+        .unwrap()
+        .into_iter()
+        .map(|p| (p.change, p.path))
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            (Change::Removed, Path::new("empty.txt").to_path_buf()),
+            (Change::Added, Path::new("extra.txt").to_path_buf()),
+            (Change::Changed, Path::new("src").join("main.rs")),
+        ]
+    );
+}
+
+#[test]
+fn verify_subcommand_fails_on_drift() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "a.txt: hi\nb.txt: ''\n")]);
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--checksums", "sums.txt"])
+        .assert()
+        .success();
+    let sums = std::fs::read_to_string(dir.join("sums.txt")).unwrap();
+    assert!(sums.contains(&format!("{}  b.txt\n", EMPTY)), "{sums}");
+
+    let verify = || {
+        treegen(dir)
+            .args(["verify", "--checksums", "sums.txt", "--out", "out"])
+            .output()
+            .unwrap()
+    };
+    assert!(verify().status.success());
+
+    std::fs::write(dir.join("out/a.txt"), "changed").unwrap();
+    let output = verify();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("mismatch: a.txt"), "{stdout}");
+}
+
+/// 清单可以直接交给 coreutils 的 `sha256sum -c`
+#[cfg(unix)]
+#[test]
+fn manifest_is_accepted_by_sha256sum() {
+    let available = std::process::Command::new("sha256sum")
+        .arg("--version")
+        .output()
+        .is_ok_and(|o| o.status.success());
+    if !available {
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[("app.yaml", "src:\n  main.rs: 'fn main() {}'\nb.txt: ''\n")],
+    );
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--checksums", "out/SHA256SUMS"])
+        .assert()
+        .success();
+    let status = std::process::Command::new("sha256sum")
+        .args(["-c", "--quiet", "SHA256SUMS"])
+        .current_dir(dir.join("out"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
@@ -0,0 +1,111 @@
+//! --chown 与节点的 owner/group：名称在写入任何东西之前解析（测试环境以 root 运行，数字 id 可用）
+#![cfg(unix)]
+
+mod common;
+
+use common::{treegen, write_files};
+use std::os::unix::fs::MetadataExt;
+
+#[test]
+fn numeric_ids_are_applied_and_nodes_override_chown() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "app:\n  a.txt: x\n  b.txt: { type: file, content: y, group: \"2\" }\n",
+        )],
+    );
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--chown", "1:1"])
+        .assert()
+        .success();
+    let ids = |rel: &str| {
+        let meta = std::fs::metadata(dir.join("out").join(rel)).unwrap();
+        (meta.uid(), meta.gid())
+    };
+    assert_eq!(ids("app"), (1, 1));
+    assert_eq!(ids("app/a.txt"), (1, 1));
+    assert_eq!(ids("app/b.txt"), (1, 2));
+    // 输出目录本身不改属主
+    assert_eq!(ids(""), (0, 0));
+}
+
+#[test]
+fn unknown_chown_names_are_rejected_when_parsing_arguments() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "a.txt: x\n")]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--chown", ":nogroupxx"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unknown group 'nogroupxx'"), "{stderr}");
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn unknown_node_owners_fail_before_anything_is_written() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "app:\n  a.txt: x\n  z.txt: { type: file, content: y, group: nogroupxx }\n",
+        )],
+    );
+
+    // dry-run 的预检报告，而不是通过
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Pre-flight checks failed"), "{stderr}");
+    assert!(
+        stderr.contains("Unknown group 'nogroupxx' (for 'app/z.txt')"),
+        "{stderr}"
+    );
+
+    // 实际生成在创建任何路径之前失败，而不是写完 a.txt 才停下
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unknown group 'nogroupxx' (for 'app/z.txt')"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+
+    // 不存在的用户同样报告，预检一次列出所有问题
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "a.txt: { type: file, content: x, owner: nouserxx }\nb: { type: dir, group: nogroupxx }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--preflight"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("(2 problem(s))"), "{stderr}");
+    assert!(
+        stderr.contains("Unknown user 'nouserxx' (for 'a.txt')"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("Unknown group 'nogroupxx' (for 'b')"),
+        "{stderr}"
+    );
+}
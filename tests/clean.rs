@@ -0,0 +1,129 @@
+mod common;
+
+use common::{treegen, write_files};
+use std::path::Path;
+
+/// 在 `cwd` 中带 --clean 生成到 `out`，返回（是否成功, stderr）
+fn clean(cwd: &Path, home: &Path, out: &str, extra: &[&str]) -> (bool, String) {
+    let output = treegen(cwd)
+        .env("HOME", home)
+        .args(["app.yaml", "--out", out, "--clean"])
+        .args(extra)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn clean_requires_yes_without_terminal() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("app.yaml", "new.txt: ''\n"), ("out/old.txt", "old")],
+    );
+    let (ok, stderr) = clean(dir, dir, "out", &[]);
+    assert!(!ok);
+    assert!(stderr.contains("(1 entries); pass --yes"), "{stderr}");
+    assert!(dir.join("out/old.txt").exists());
+
+    let (ok, stderr) = clean(dir, dir, "out", &["--yes"]);
+    assert!(ok, "{stderr}");
+    assert!(!dir.join("out/old.txt").exists());
+    assert!(dir.join("out/new.txt").exists());
+}
+
+#[test]
+fn clean_dry_run_keeps_directory() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("app.yaml", "new.txt: ''\n"), ("out/a/old.txt", "old")],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--clean", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Remove output directory"), "{stdout}");
+    assert!(stdout.contains("(2 entries)"), "{stdout}");
+    assert!(dir.join("out/a/old.txt").exists());
+}
+
+#[test]
+fn clean_refuses_cwd_and_its_parents() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let home = tempfile::tempdir().unwrap();
+    write_files(dir, &[("work/app.yaml", "new.txt: ''\n")]);
+    let work = dir.join("work");
+    for out in [".", "./..", "../work"] {
+        let (ok, stderr) = clean(&work, home.path(), out, &["--yes"]);
+        assert!(!ok, "{out}");
+        assert!(stderr.contains("current directory"), "{out}: {stderr}");
+    }
+    assert!(work.join("app.yaml").exists());
+
+    let (ok, stderr) = clean(&work, home.path(), ".", &["--yes", "--allow-cwd"]);
+    assert!(ok, "{stderr}");
+    assert!(work.join("new.txt").exists());
+}
+
+#[test]
+fn clean_refuses_git_repository() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", "new.txt: ''\n"),
+            ("repo/.git/HEAD", "ref: refs/heads/main\n"),
+        ],
+    );
+    let (ok, stderr) = clean(dir, dir, "repo", &["--yes"]);
+    assert!(!ok);
+    assert!(stderr.contains(".git folder"), "{stderr}");
+    assert!(dir.join("repo/.git/HEAD").exists());
+
+    let (ok, stderr) = clean(dir, dir, "repo", &["--yes", "--allow-git"]);
+    assert!(ok, "{stderr}");
+    assert!(!dir.join("repo/.git").exists());
+}
+
+#[test]
+fn clean_refuses_home_and_root() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("app.yaml", "new.txt: ''\n"), ("home/me/.profile", "")],
+    );
+    let (ok, stderr) = clean(dir, &dir.join("home/me"), "home/me", &["--yes"]);
+    assert!(!ok);
+    assert!(stderr.contains("home directory"), "{stderr}");
+    // 主目录的上级同样拒绝
+    let (ok, stderr) = clean(dir, &dir.join("home/me"), "home/./me/..", &["--yes"]);
+    assert!(!ok);
+    assert!(stderr.contains("home directory"), "{stderr}");
+    assert!(dir.join("home/me/.profile").exists());
+
+    let (ok, stderr) = clean(dir, dir, "/", &["--yes", "--allow-cwd", "--allow-git"]);
+    assert!(!ok);
+    assert!(stderr.contains("filesystem root"), "{stderr}");
+}
+
+#[test]
+fn clean_flags_require_clean() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", "new.txt: ''\n")]);
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--yes"])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,46 @@
+//! --clipboard：从系统剪贴板读取描述内容，代替输入文件
+mod common;
+
+use common::{treegen, write_files};
+
+#[test]
+fn clipboard_conflicts_with_positional_inputs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "a.txt: a\n")]);
+    let output = treegen(dir)
+        .args(["--clipboard", "spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the argument '--clipboard' cannot be used with '[INPUT]...'"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+}
+
+/// 没有图形界面时给出错误而不是崩溃或挂起；不带 clipboard 特性的构建同样以错误退出
+#[cfg(target_os = "linux")]
+#[test]
+fn clipboard_without_a_display_fails_cleanly() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let output = treegen(dir)
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .env("RUST_BACKTRACE", "0")
+        .args(["--clipboard", "--out", "out"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.starts_with("Error: The clipboard is unavailable")
+            || stderr.starts_with("Error: This treegen was built without clipboard support"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+    assert!(!dir.join("out").exists());
+}
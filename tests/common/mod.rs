@@ -0,0 +1,47 @@
+//! 集成测试共用的辅助函数
+
+#![allow(dead_code)]
+
+use assert_cmd::{cargo::cargo_bin, Command};
+use std::path::Path;
+
+/// 在 `dir` 中运行 treegen：不读取用户的配置与模板库，不受外部 `TREEGEN_*`、`RUST_LOG` 与语言环境变量影响
+pub fn treegen(dir: &Path) -> Command {
+    Command::from_std(treegen_process(dir))
+}
+
+/// 同 [`treegen`]，用于需要在后台运行的进程（如 --watch）
+pub fn treegen_process(dir: &Path) -> std::process::Command {
+    let mut cmd = std::process::Command::new(cargo_bin!("treegen"));
+    cmd.current_dir(dir)
+        .env("HOME", dir)
+        .env("USERPROFILE", dir)
+        .env("XDG_CONFIG_HOME", dir.join(".config"))
+        .env("XDG_DATA_HOME", dir.join(".local/share"))
+        .env("XDG_CACHE_HOME", dir.join(".cache"))
+        .env_remove("RUST_LOG")
+        .env_remove("LC_ALL")
+        .env_remove("LC_MESSAGES")
+        .env_remove("LANG");
+    for (key, _) in std::env::vars_os() {
+        if key.to_string_lossy().starts_with("TREEGEN_") {
+            cmd.env_remove(key);
+        }
+    }
+    cmd
+}
+
+/// 把 `files` 中的（路径，内容）写到 `dir` 下
+pub fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+}
+
+/// `dir` 下的文件内容
+pub fn read(dir: &Path, path: &str) -> String {
+    std::fs::read_to_string(dir.join(path))
+        .unwrap_or_else(|e| panic!("read {}: {}", dir.join(path).display(), e))
+}
@@ -0,0 +1,78 @@
+//! `treegen completions`：每种 shell 都能生成补全脚本（同时检查 CLI 定义本身没有冲突）
+
+mod common;
+
+use common::treegen;
+
+/// 补全脚本中应出现的描述文件扩展名
+const SPEC_EXTENSIONS: &[&str] = &[
+    "md", "yaml", "yml", "json", "toml", "json5", "paths", "list",
+];
+
+fn completions(shell: &str) -> String {
+    let dir = tempfile::tempdir().unwrap();
+    let output = treegen(dir.path())
+        .args(["completions", shell])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}: {}",
+        shell,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn every_shell_gets_a_script() {
+    for shell in ["bash", "zsh", "fish", "powershell", "elvish"] {
+        let script = completions(shell);
+        assert!(!script.trim().is_empty(), "{shell}");
+        assert!(script.contains("treegen"), "{shell}");
+        assert!(script.contains("init"), "{shell}");
+    }
+}
+
+#[test]
+fn format_values_and_spec_extensions_are_completed() {
+    let bash = completions("bash");
+    assert!(
+        bash.contains(r#"compgen -W "md yaml json toml json5 paths""#),
+        "{bash}"
+    );
+
+    let zsh = completions("zsh");
+    let glob = format!("_files -g \"*.({})\"", SPEC_EXTENSIONS.join("|"));
+    // 位置参数 input 的每一行（顶层命令的在最前面，它可以省略，写成 '::input）都只补全描述文件
+    let inputs: Vec<&str> = zsh
+        .lines()
+        .filter(|line| {
+            line.strip_prefix('\'')
+                .is_some_and(|rest| rest.trim_start_matches(['*', ':']).starts_with("input -- "))
+        })
+        .collect();
+    assert!(inputs[0].starts_with("'::input -- "), "{zsh}");
+    assert!(inputs.len() > 1, "{zsh}");
+    for line in inputs {
+        assert!(line.contains(&glob), "{line}");
+        assert!(!line.ends_with(":_files' \\"), "{line}");
+    }
+
+    let fish = completions("fish");
+    for ext in SPEC_EXTENSIONS {
+        assert!(
+            fish.contains(&format!("(__fish_complete_suffix .{})", ext)),
+            "{ext}"
+        );
+    }
+}
+
+#[test]
+fn unknown_shell_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    treegen(dir.path())
+        .args(["completions", "tcsh"])
+        .assert()
+        .failure();
+}
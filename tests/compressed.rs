@@ -0,0 +1,124 @@
+//! 压缩的描述文件：.gz（及 zstd feature 下的 .zst）按内层扩展名解析；标准输入用 --compressed
+
+mod common;
+
+use common::{read, treegen};
+use std::io::Write;
+
+const SPEC: &str = r#"{ "app": { "src": { "main.rs": "fn main() {}\n" } } }"#;
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn decompresses_gzip_files_by_inner_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::write(dir.join("spec.json.gz"), gzip(SPEC.as_bytes())).unwrap();
+    let output = treegen(dir)
+        .args(["spec.json.gz", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/app/src/main.rs"), "fn main() {}\n");
+}
+
+#[test]
+fn recursive_finds_compressed_specs() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::create_dir(dir.join("specs")).unwrap();
+    std::fs::write(dir.join("specs/a.yaml.gz"), gzip(b"a.txt: a\n")).unwrap();
+    std::fs::write(dir.join("specs/notes.gz"), gzip(b"not a spec")).unwrap();
+    let output = treegen(dir)
+        .args(["--recursive", "specs", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/a.txt"), "a");
+}
+
+#[test]
+fn names_the_archive_and_the_failure() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::write(dir.join("spec.json.gz"), SPEC).unwrap();
+    let output = treegen(dir)
+        .args(["spec.json.gz", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Failed to decompress gzip spec file 'spec.json.gz'"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("invalid gzip header"), "{}", stderr);
+}
+
+#[test]
+fn decompresses_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let output = treegen(dir)
+        .args([
+            "-",
+            "--format",
+            "json",
+            "--compressed",
+            "gzip",
+            "--out",
+            "out",
+        ])
+        .write_stdin(gzip(SPEC.as_bytes()))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/app/src/main.rs"), "fn main() {}\n");
+
+    let output = treegen(dir)
+        .args(["spec.json", "--compressed", "gzip"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--compressed only applies to input from stdin ('-')"),
+        "{}",
+        stderr
+    );
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn decompresses_zstd_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let data = zstd::encode_all(SPEC.as_bytes(), 0).unwrap();
+    std::fs::write(dir.join("spec.json.zst"), data).unwrap();
+    let output = treegen(dir)
+        .args(["spec.json.zst", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/app/src/main.rs"), "fn main() {}\n");
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn zstd_needs_the_feature() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    std::fs::write(dir.join("spec.json.zst"), "").unwrap();
+    let output = treegen(dir)
+        .args(["spec.json.zst", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'zstd' feature"), "{}", stderr);
+}
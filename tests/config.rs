@@ -0,0 +1,157 @@
+//! 配置文件（$TREEGEN_CONFIG 或 ~/.config/treegen/config.toml）：命令行 > 环境变量 > 配置文件 > 内置默认值
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = "app:\n  a.txt: hi\n";
+const CONFIG: &str = "verbose = true\nout = \"from-config\"\nmode = \"0o600\"\n";
+
+/// `treegen config --show` 中某个参数的一行（值与来源）
+fn shown(stdout: &str, key: &str) -> String {
+    let line = stdout
+        .lines()
+        .find(|line| line.split_whitespace().next() == Some(key))
+        .unwrap_or_else(|| panic!("{key} missing in:\n{stdout}"));
+    line.split_once(" = ")
+        .unwrap()
+        .1
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn config_values_are_defaults() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC), ("cfg.toml", CONFIG)]);
+    let output = treegen(dir)
+        .arg("spec.yaml")
+        .env("TREEGEN_CONFIG", "cfg.toml")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Output directory: from-config (from config file)"),
+        "{stdout}"
+    );
+    assert_eq!(read(dir, "from-config/app/a.txt"), "hi");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.join("from-config/app/a.txt"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[test]
+fn cli_and_environment_win_over_the_config_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC), ("cfg.toml", CONFIG)]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "cli"])
+        .env("TREEGEN_CONFIG", "cfg.toml")
+        .env("TREEGEN_OUT", "env")
+        .assert()
+        .success();
+    assert!(dir.join("cli/app/a.txt").is_file());
+
+    treegen(dir)
+        .arg("spec.yaml")
+        .env("TREEGEN_CONFIG", "cfg.toml")
+        .env("TREEGEN_OUT", "env")
+        .assert()
+        .success();
+    assert!(dir.join("env/app/a.txt").is_file());
+    assert!(!dir.join("from-config").exists());
+}
+
+#[test]
+fn config_show_lists_values_and_origins() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("cfg.toml", CONFIG)]);
+    let output = treegen(dir)
+        .args(["config", "--show"])
+        .env("TREEGEN_CONFIG", "cfg.toml")
+        .env("TREEGEN_OUT", "env")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# config file: cfg.toml\n"), "{stdout}");
+    assert_eq!(shown(&stdout, "verbose"), "true # config");
+    assert_eq!(shown(&stdout, "mode"), "\"0o600\" # config");
+    assert_eq!(shown(&stdout, "out"), "env # env");
+    assert_eq!(shown(&stdout, "dry-run"), "false # default");
+
+    let output = treegen(dir)
+        .args(["config", "--path"])
+        .env("TREEGEN_CONFIG", "cfg.toml")
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "cfg.toml\n");
+}
+
+#[test]
+fn default_location_is_under_the_config_home() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[(".config/treegen/config.toml", "dry_run = true\n")]);
+    let output = treegen(dir).args(["config", "--show"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(".config/treegen/config.toml"), "{stdout}");
+    // 键名中的 `_` 与 `-` 等价
+    assert_eq!(shown(&stdout, "dry-run"), "true # config");
+
+    // 默认位置没有配置文件时没有配置
+    std::fs::remove_file(dir.join(".config/treegen/config.toml")).unwrap();
+    let output = treegen(dir).args(["config", "--show"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("# config file: (none)\n"), "{stdout}");
+}
+
+#[test]
+fn invalid_config_files_are_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("spec.yaml", SPEC),
+            ("unknown.toml", "bogus = 1\n"),
+            ("type.toml", "verbose = \"yes\"\n"),
+            ("syntax.toml", "verbose = \n"),
+        ],
+    );
+    for (config, error) in [
+        (
+            "missing.toml",
+            "Config file 'missing.toml' (from TREEGEN_CONFIG) does not exist",
+        ),
+        (
+            "unknown.toml",
+            "Unknown key 'bogus' in config file 'unknown.toml'",
+        ),
+        (
+            "type.toml",
+            "Invalid value for 'verbose' in config file 'type.toml'",
+        ),
+        ("syntax.toml", "Failed to parse config file 'syntax.toml'"),
+    ] {
+        let output = treegen(dir)
+            .args(["spec.yaml", "--out", "out"])
+            .env("TREEGEN_CONFIG", config)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{config}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(error), "{stderr}");
+        assert!(!dir.join("out").exists());
+    }
+}
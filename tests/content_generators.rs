@@ -0,0 +1,99 @@
+mod common;
+
+use common::{read, treegen, write_files};
+use std::fs;
+
+const SPEC: &str = r#"app:
+  id.txt: "@uuid"
+  other.txt: "@uuid"
+  a/tenant.txt: "@uuid:shared:tenant"
+  b/tenant.txt: "@uuid:shared:tenant"
+  seed.bin: "@random:256"
+  built_at.txt: "@timestamp"
+  unix.txt: "@timestamp:unix"
+  scope.txt: "@types/node"
+"#;
+
+fn is_uuid(text: &str) -> bool {
+    let parts: Vec<&str> = text.trim_end().split('-').collect();
+    parts.iter().map(|part| part.len()).eq([8, 4, 4, 4, 12])
+        && parts
+            .iter()
+            .all(|part| part.chars().all(|c| c.is_ascii_hexdigit()))
+        && parts[2].starts_with('4')
+}
+
+#[test]
+fn generators_fill_each_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--seed", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let id = read(dir, "out/app/id.txt");
+    assert!(is_uuid(&id), "{id}");
+    assert!(id.ends_with('\n'));
+    assert_ne!(id, read(dir, "out/app/other.txt"));
+    let tenant = read(dir, "out/app/a/tenant.txt");
+    assert!(is_uuid(&tenant), "{tenant}");
+    assert_eq!(tenant, read(dir, "out/app/b/tenant.txt"));
+    assert_eq!(fs::read(dir.join("out/app/seed.bin")).unwrap().len(), 256);
+    let built_at = read(dir, "out/app/built_at.txt");
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(built_at.trim_end()).is_ok(),
+        "{built_at}"
+    );
+    assert!(read(dir, "out/app/unix.txt")
+        .trim_end()
+        .parse::<u64>()
+        .is_ok());
+    assert_eq!(read(dir, "out/app/scope.txt"), "@types/node");
+
+    // 同一种子重新生成的随机值相同
+    fs::remove_dir_all(dir.join("out")).unwrap();
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--seed", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/app/id.txt"), id);
+    assert_eq!(read(dir, "out/app/a/tenant.txt"), tenant);
+}
+
+#[test]
+fn dry_run_shows_generator_names() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("id.txt (@uuid)"), "{stdout}");
+    assert!(stdout.contains("seed.bin (@random:256)"), "{stdout}");
+    assert!(stdout.contains("(@uuid:shared:tenant)"), "{stdout}");
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn invalid_generator_arguments_are_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "seed.bin: \"@random\"\n")]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Invalid content generator '@random'"),
+        "{stderr}"
+    );
+}
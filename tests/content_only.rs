@@ -0,0 +1,76 @@
+//! --content-only：只更新已存在的文件，不创建新的文件、目录或链接
+
+mod common;
+
+use common::{read, treegen, write_files};
+use std::path::Path;
+use treegen::{generate, parse_yaml, GenerateOptions, Outcome};
+
+#[test]
+fn only_existing_paths_are_written() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    write_files(out, &[("app/README.md", "old"), ("app/LICENSE", "MIT\n")]);
+    std::fs::create_dir(out.join("app/src")).unwrap();
+    let spec = parse_yaml(
+        "app:\n  README.md: new\n  LICENSE: \"MIT\\n\"\n  src:\n    main.rs: x\n  docs:\n    guide.md: y\n",
+    )
+    .unwrap();
+    let opts = GenerateOptions {
+        content_only: true,
+        ..Default::default()
+    };
+    let stats = generate(&spec.root, out, &opts).unwrap();
+    assert_eq!(read(out, "app/README.md"), "new");
+    assert!(!out.join("app/src/main.rs").exists());
+    assert!(!out.join("app/docs").exists());
+    assert_eq!((stats.files, stats.unchanged, stats.missing), (1, 1, 3));
+    let outcome = |path: &str| stats.outcomes[Path::new(path)];
+    assert_eq!(outcome("app/README.md"), Outcome::Overwritten);
+    assert_eq!(outcome("app/src"), Outcome::Unchanged);
+    assert_eq!(outcome("app/docs/guide.md"), Outcome::Missing);
+}
+
+#[test]
+fn copies_only_refresh_existing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("templates/ci/build.yml", "build: v2\n"),
+            ("templates/ci/extra/lint.yml", "lint\n"),
+            ("out/.github/build.yml", "build: v1\n"),
+            (
+                "spec.yaml",
+                ".github: { type: copy, from: templates/ci, recursive: true }\n",
+            ),
+        ],
+    );
+    let output = treegen(dir)
+        .args([
+            "spec.yaml",
+            "--out",
+            "out",
+            "--content-only",
+            "--summary-json",
+            "summary.json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/.github/build.yml"), "build: v2\n");
+    assert!(!dir.join("out/.github/extra").exists());
+    let summary: serde_json::Value = serde_json::from_str(&read(dir, "summary.json")).unwrap();
+    assert_eq!(summary["missing"], 2);
+
+    // 输出目录本身也不创建
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "nowhere", "--content-only"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Output directory 'nowhere' does not exist"));
+    assert!(!dir.join("nowhere").exists());
+}
@@ -0,0 +1,37 @@
+//! 结构化格式（YAML/JSON/TOML/JSON5）转换为 Node 树：同一描述得到同一棵树，大段内容原样保留
+
+use treegen::{parse_json, parse_json5, parse_toml, parse_yaml, Node, NodeType};
+
+#[test]
+fn every_structured_format_gives_the_same_tree() {
+    let big = "0123456789abcdef".repeat(64 * 1024);
+    let yaml = format!(
+        "src:\n  main.rs: 'fn main() {{}}'\n  data:\n    big.txt: '{big}'\n\
+         run.sh: 'echo hi'\nempty: {{}}\n"
+    );
+    let json = format!(
+        r#"{{"src": {{"main.rs": "fn main() {{}}", "data": {{"big.txt": "{big}"}}}},
+            "run.sh": "echo hi", "empty": {{}}}}"#
+    );
+    let toml = format!(
+        "\"run.sh\" = \"echo hi\"\n\
+         [src]\n\"main.rs\" = \"fn main() {{}}\"\n[src.data]\n\"big.txt\" = \"{big}\"\n[empty]\n"
+    );
+    let json5 = format!(
+        "{{ src: {{ 'main.rs': 'fn main() {{}}', data: {{ 'big.txt': '{big}' }} }},\n\
+         'run.sh': 'echo hi', empty: {{}}, }}\n"
+    );
+
+    let expected = parse_yaml(&yaml).unwrap().root;
+    assert_eq!(parse_json(&json).unwrap().root, expected);
+    assert_eq!(parse_toml(&toml).unwrap().root, expected);
+    assert_eq!(parse_json5(&json5).unwrap().root, expected);
+
+    let content = |path: &str| expected.find(path).unwrap().content.as_deref().unwrap();
+    assert_eq!(content("src/data/big.txt"), big);
+    assert_eq!(content("src/main.rs"), "fn main() {}");
+    assert_eq!(content("run.sh"), "echo hi");
+    let empty: &Node = expected.find("empty").unwrap();
+    assert_eq!(empty.node_type, NodeType::Dir);
+    assert!(empty.children.is_empty());
+}
@@ -0,0 +1,174 @@
+//! type: copy 节点：复制已有的文件或目录（recursive），exclude 跳过匹配的条目
+mod common;
+
+use common::{read, treegen, write_files};
+use std::path::Path;
+
+const SOURCES: &[(&str, &str)] = &[
+    ("skel/a.txt", "hello\n"),
+    ("skel/x.tmp", "tmp\n"),
+    ("skel/cache/c", "c\n"),
+    ("skel/src/main.rs", "fn main(){}\n"),
+    ("logo.png", "logo\n"),
+];
+
+const SPEC: &str = r#"skel:
+  type: copy
+  from: ./skel/
+  recursive: true
+  exclude: ["*.tmp", "cache"]
+logo.png: { type: copy, from: ./logo.png }
+"#;
+
+fn run(dir: &Path, spec: &str, args: &[&str]) -> (bool, String, String) {
+    write_files(dir, &[("spec.yaml", spec)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn copies_files_and_directories_without_excluded_entries() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SOURCES);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let main = dir.join("skel/src/main.rs");
+        std::fs::set_permissions(&main, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let (success, _, stderr) = run(dir, SPEC, &[]);
+    assert!(success, "{stderr}");
+    assert_eq!(read(dir, "out/logo.png"), "logo\n");
+    assert_eq!(read(dir, "out/skel/a.txt"), "hello\n");
+    assert_eq!(read(dir, "out/skel/src/main.rs"), "fn main(){}\n");
+    assert!(!dir.join("out/skel/x.tmp").exists());
+    assert!(!dir.join("out/skel/cache").exists());
+    // 复制保留权限
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.join("out/skel/src/main.rs"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+}
+
+#[test]
+fn dry_run_lists_copies_with_sizes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SOURCES);
+    let (success, stdout, stderr) = run(dir, SPEC, &["--dry-run", "--verbose"]);
+    assert!(success, "{stderr}");
+    let line = |dest: &str, size: &str, source: &str| {
+        let (dest, source) = (Path::new(dest), Path::new(source));
+        format!(
+            "[Dry-Run] Copy file: {} ({}) from {}",
+            Path::new("out").join(dest).display(),
+            size,
+            source.display()
+        )
+    };
+    assert!(
+        stdout.contains(&line("logo.png", "5 bytes", "logo.png")),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(&line("skel/a.txt", "6 bytes", "skel/a.txt")),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(&line("skel/src/main.rs", "12 bytes", "skel/src/main.rs")),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Skip excluded: "), "{stdout}");
+    assert!(!stdout.contains("Copy file: out/skel/x.tmp"), "{stdout}");
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn directories_need_recursive() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SOURCES);
+    let spec = "skel: { type: copy, from: ./skel }\n";
+    let expected =
+        "Copy source 'skel' is a directory; set \"recursive\": true to copy it (for 'skel')";
+
+    let (success, _, stderr) = run(dir, spec, &["--dry-run"]);
+    assert!(!success);
+    assert!(stderr.contains(expected), "{stderr}");
+
+    let (success, _, stderr) = run(dir, spec, &[]);
+    assert!(!success);
+    assert!(
+        stderr.contains("is a directory; set \"recursive\": true"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out/skel").exists());
+}
+
+#[test]
+fn missing_sources_are_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let spec = "x: { type: copy, from: ./nope.txt }\n";
+
+    let (success, _, stderr) = run(dir, spec, &["--dry-run"]);
+    assert!(!success);
+    assert!(
+        stderr.contains("Copy source 'nope.txt' does not exist (for 'x')"),
+        "{stderr}"
+    );
+
+    let (success, _, stderr) = run(dir, spec, &[]);
+    assert!(!success);
+    assert!(stderr.contains("Copy source 'nope.txt' for '"), "{stderr}");
+    assert!(stderr.contains("' does not exist"), "{stderr}");
+    assert!(!dir.join("out/x").exists());
+}
+
+/// 来源目录中的符号链接不跟随：循环链接与指向 `/` 的链接都报错并给出路径，被 exclude 的链接不影响
+#[cfg(unix)]
+#[test]
+fn symlinks_inside_sources_are_refused() {
+    use std::os::unix::fs::symlink;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SOURCES);
+    for (link, target) in [("loop", ".."), ("host", "/")] {
+        let path = dir.join("skel/src").join(link);
+        symlink(target, &path).unwrap();
+        let (ok, _, stderr) = run(dir, SPEC, &[]);
+        assert!(!ok, "{link}");
+        assert!(
+            stderr.contains(&format!(
+                "Copy source contains a symbolic link 'skel/src/{link}'"
+            )),
+            "{stderr}"
+        );
+        // 失败前已写出的内容之外没有跟随链接复制出任何东西
+        assert!(!dir.join("out/skel/src").join(link).exists());
+        let (ok, _, stderr) = run(dir, SPEC, &["--dry-run"]);
+        assert!(!ok, "{stderr}");
+
+        let excluded = SPEC.replace("\"cache\"]", &format!("\"cache\", \"src/{link}\"]"));
+        let (ok, _, stderr) = run(dir, &excluded, &[]);
+        assert!(ok, "{stderr}");
+        assert!(!dir.join("out/skel/src").join(link).exists());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_dir_all(dir.join("out")).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SKELETON: &[(&str, &str)] = &[
+    ("skeleton/Cargo.toml", "[package]\nname = \"{{name}}\"\n"),
+    (
+        "skeleton/src/main.rs",
+        "fn main() { println!(\"{{name}}\"); }\n",
+    ),
+    ("skeleton/src/tests/it.rs", "#[test] fn it() {}\n"),
+    ("skeleton/README.md", "# {{name}}\n"),
+    ("skeleton/docs/guide.md", "guide\n"),
+];
+
+const SPEC: &str = r#"crate:
+  type: copy
+  from: ./skeleton
+  include: ["**/*.rs", "Cargo.toml"]
+  exclude: ["**/tests/**"]
+  substitute: true
+"#;
+
+#[test]
+fn include_copies_matching_files_with_structure() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SKELETON);
+    write_files(dir, &[("app.yaml", SPEC)]);
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--var", "name=demo"])
+        .assert()
+        .success();
+    assert_eq!(
+        read(dir, "out/crate/Cargo.toml"),
+        "[package]\nname = \"demo\"\n"
+    );
+    assert_eq!(
+        read(dir, "out/crate/src/main.rs"),
+        "fn main() { println!(\"demo\"); }\n"
+    );
+    assert!(!dir.join("out/crate/src/tests").exists());
+    assert!(!dir.join("out/crate/README.md").exists());
+    // 没有选中文件的目录不创建
+    assert!(!dir.join("out/crate/docs").exists());
+}
+
+#[test]
+fn substitute_passes_binaries_through() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("skeleton/a.txt", "{{name}}\n"),
+            (
+                "app.yaml",
+                "t: { type: copy, from: ./skeleton, recursive: true, substitute: true }\n",
+            ),
+        ],
+    );
+    let binary = b"\x00{{name}}\xff";
+    std::fs::write(dir.join("skeleton/logo.bin"), binary).unwrap();
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--var", "name=x"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/t/a.txt"), "x\n");
+    assert_eq!(std::fs::read(dir.join("out/t/logo.bin")).unwrap(), binary);
+
+    // 不开启 substitute 时原样复制
+    write_files(
+        dir,
+        &[(
+            "plain.yaml",
+            "t: { type: copy, from: ./skeleton, recursive: true }\n",
+        )],
+    );
+    treegen(dir)
+        .args(["plain.yaml", "--out", "plain"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "plain/t/a.txt"), "{{name}}\n");
+}
+
+#[test]
+fn dry_run_lists_selected_files_with_sizes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SKELETON);
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=demo",
+            "--dry-run",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let cargo = format!(
+        "{}",
+        std::path::Path::new("crate").join("Cargo.toml").display()
+    );
+    assert!(
+        stdout.lines().any(|line| line.contains("Copy file")
+            && line.contains(&cargo)
+            && line.contains("(24 bytes)")),
+        "{stdout}"
+    );
+    assert!(stdout.contains("main.rs"), "{stdout}");
+    assert!(!stdout.contains("guide.md"), "{stdout}");
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn include_without_matches_is_an_error() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SKELETON);
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "c: { type: copy, from: ./skeleton, include: [\"**/*.go\"] }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("match include **/*.go"), "{stderr}");
+
+    write_files(
+        dir,
+        &[(
+            "missing.yaml",
+            "c: { type: copy, from: ./nope, include: [\"*.rs\"] }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["missing.yaml", "--out", "out2"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("does not exist"), "{stderr}");
+}
+
+#[test]
+fn check_expands_copies_like_generate() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SKELETON);
+    write_files(
+        dir,
+        &[
+            ("app.yaml", SPEC),
+            ("skeleton/.treegenignore", "docs/\n"),
+            ("skeleton/.git/HEAD", "ref: refs/heads/main\n"),
+        ],
+    );
+    let args = ["app.yaml", "--out", "out", "--var", "name=demo"];
+    treegen(dir).args(args).assert().success();
+    treegen(dir).args(args).arg("--check").assert().success();
+    // dry-run 的标注用同样的比较
+    let output = treegen(dir).args(args).arg("--dry-run").output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("crate/  = identical"), "{stdout}");
+}
@@ -0,0 +1,69 @@
+//! 很深的树：解析与生成要么成功，要么给出深度错误，不能栈溢出
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{check_depth, generate, parse_json, parse_json5, parse_markdown, parse_yaml, Node};
+
+const LEVELS: usize = 10_000;
+/// Markdown 的缩进随层数增长，文件大小是层数的平方，取一个足以撑爆栈的层数
+const MARKDOWN_LEVELS: usize = 3_000;
+
+fn deep_markdown() -> String {
+    let mut md = String::from("root/\n");
+    for i in 0..MARKDOWN_LEVELS {
+        md.push_str(&" ".repeat(i));
+        md.push_str(&format!("└── d{}/\n", i));
+    }
+    md
+}
+
+fn deep_json() -> String {
+    format!(
+        "{}{{\"f\": \"x\"}}{}",
+        "{\"d\": ".repeat(LEVELS),
+        "}".repeat(LEVELS)
+    )
+}
+
+#[test]
+fn deep_specs_fail_with_depth_error() {
+    let spec = parse_markdown(&deep_markdown()).unwrap();
+    let err = check_depth(&spec.root, treegen::DEFAULT_MAX_DEPTH).unwrap_err();
+    assert!(err.to_string().contains("nested deeper than"), "{err}");
+
+    let yaml: String = (0..LEVELS)
+        .map(|i| format!("{}d:\n", "  ".repeat(i)))
+        .collect();
+    // serde 的解析器自带递归上限，错误信息各不相同，只要求不崩溃
+    assert!(parse_yaml(&yaml).is_err());
+    assert!(parse_json(&deep_json()).is_err());
+    assert!(parse_json5(&deep_json()).is_err());
+}
+
+#[test]
+fn deep_tree_in_memory_fails_to_generate() {
+    let mut root = Node::new_dir(String::new());
+    let mut cursor = &mut root;
+    for _ in 0..LEVELS {
+        cursor.children.push(Node::new_dir("d".to_string()));
+        cursor = &mut cursor.children[0];
+    }
+    let out = tempfile::tempdir().unwrap();
+    let err = generate(&root, out.path(), &Default::default()).unwrap_err();
+    assert!(err.to_string().contains("nested deeper than 256"), "{err}");
+}
+
+#[test]
+fn cli_reports_depth_error_for_deep_specs() {
+    let dir = tempfile::tempdir().unwrap();
+    write_files(dir.path(), &[("deep.md", &deep_markdown())]);
+    let output = treegen(dir.path())
+        .args(["deep.md", "--out", "out"])
+        .output()
+        .unwrap();
+    // 栈溢出时进程被信号终止，没有退出码
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("nested deeper than 256"), "{stderr}");
+}
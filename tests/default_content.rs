@@ -0,0 +1,113 @@
+//! --default-content：为没有内容的文件按文件名或扩展名填入片段，用户片段覆盖内置片段
+mod common;
+
+use common::{read, treegen, write_files};
+
+const TREE: &str = "my-app/\n├── LICENSE\n├── README.md\n├── Cargo.toml\n├── run.sh\n└── web/\n    └── index.html\nNOTES.md\n";
+
+#[test]
+fn works_without_any_variables() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("tree.md", TREE)]);
+    let output = treegen(dir)
+        .args(["tree.md", "--out", "out", "--default-content"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // name 取所在目录的名称
+    assert_eq!(read(dir, "out/my-app/README.md"), "# my-app\n");
+    assert!(read(dir, "out/my-app/Cargo.toml").contains("name = \"my-app\""));
+    assert!(read(dir, "out/my-app/web/index.html").contains("<title>web</title>"));
+    assert!(read(dir, "out/my-app/run.sh").starts_with("#!/usr/bin/env bash\n"));
+    // 没有片段的文件保持为空
+    assert_eq!(read(dir, "out/NOTES.md"), "");
+    // LICENSE 需要 author，没有时不填入并警告
+    assert_eq!(read(dir, "out/my-app/LICENSE"), "");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: No default content for 'my-app/LICENSE': built-in by-name 'LICENSE' uses undefined variable 'author' (pass --var author=...)"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn variables_fill_the_snippets() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("tree.md", TREE)]);
+    let output = treegen(dir)
+        .args(["tree.md", "--out", "out", "--default-content"])
+        .args(["--var", "author=Ada", "--var", "name=demo"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+    let license = read(dir, "out/my-app/LICENSE");
+    assert!(license.starts_with("MIT License\n"), "{license}");
+    assert!(license.contains(" Ada\n"), "{license}");
+    assert!(!license.contains("{{"), "{license}");
+    assert_eq!(read(dir, "out/my-app/README.md"), "# demo\n");
+}
+
+#[test]
+fn top_level_files_use_the_output_directory_name() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("tree.md", "README.md\n")]);
+    treegen(dir)
+        .args(["tree.md", "--out", "shop", "--default-content"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "shop/README.md"), "# shop\n");
+}
+
+#[test]
+fn explicit_content_is_kept() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "README.md: ''\nrun.sh: 'echo hi'\n")]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--default-content"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/README.md"), "");
+    assert_eq!(read(dir, "out/run.sh"), "echo hi");
+}
+
+#[test]
+fn user_snippets_override_built_ins() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("tree.md", TREE),
+            (
+                ".config/treegen/snippets/by-name/LICENSE",
+                "Proprietary, {{name}}\n",
+            ),
+            (".config/treegen/snippets/by-ext/sh", "#!/bin/sh\n"),
+            (".config/treegen/snippets/by-ext/md", "notes\n"),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["tree.md", "--out", "out", "--default-content", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/my-app/LICENSE"), "Proprietary, my-app\n");
+    assert_eq!(read(dir, "out/my-app/run.sh"), "#!/bin/sh\n");
+    // 文件名精确匹配优先于扩展名
+    assert_eq!(read(dir, "out/my-app/README.md"), "# my-app\n");
+    assert_eq!(read(dir, "out/NOTES.md"), "notes\n");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Default content for my-app/LICENSE: '"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("Default content for my-app/README.md: built-in by-name 'README.md'"),
+        "{stdout}"
+    );
+}
@@ -0,0 +1,91 @@
+//! 描述文件的解析错误：给出文件、行列、摘录的行与 `^`，节点类型不对时报告其路径与提示
+mod common;
+
+use common::{treegen, write_files};
+
+/// 解析 `file` 失败时的标准错误
+fn parse_error(file: &str, content: &str) -> String {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[(file, content)]);
+    let output = treegen(dir)
+        .args([file, "--out", "out", "--dry-run"])
+        .env("RUST_LIB_BACKTRACE", "0")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn wrong_node_type_reports_its_key_path() {
+    let stderr = parse_error(
+        "app.yaml",
+        "app:\n  README.md: hello\n  src:\n    port: 8080\n",
+    );
+    assert!(
+        stderr.contains("'app/src/port' is neither file content nor a directory (integer)"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("--> app.yaml:4:11"), "{stderr}");
+    assert!(
+        stderr.contains("4 |     port: 8080\n      |           ^"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("hint: values must be strings or nested maps — found integer"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("untagged"), "{stderr}");
+
+    // 其他格式同样定位到节点
+    let stderr = parse_error("app.toml", "[app]\nREADME = \"hi\"\ndebug = true\n");
+    assert!(stderr.contains("'app/debug'"), "{stderr}");
+    assert!(stderr.contains("--> app.toml:3:9"), "{stderr}");
+    let stderr = parse_error("app.json5", "{\n  app: {\n    logs: null,\n  },\n}\n");
+    assert!(stderr.contains("--> app.json5:3:11"), "{stderr}");
+    assert!(stderr.contains("write \"\" for an empty file"), "{stderr}");
+}
+
+#[test]
+fn invalid_typed_node_reports_the_metadata_error() {
+    let stderr = parse_error(
+        "app.json",
+        r#"{ "app": { "big.bin": { "type": "file", "size": true } } }"#,
+    );
+    assert!(
+        stderr.contains("'app/big.bin' is not a valid 'file' node: "),
+        "{stderr}"
+    );
+    assert!(stderr.contains("--> app.json:1:23"), "{stderr}");
+}
+
+#[test]
+fn syntax_errors_show_the_line_and_column() {
+    let stderr = parse_error("app.json", "{\n  \"app\": {\n    \"a\": \"x\",\n  }\n}\n");
+    assert!(stderr.contains("trailing comma\n"), "{stderr}");
+    assert!(stderr.contains("--> app.json:4:3"), "{stderr}");
+    assert!(stderr.contains("4 |   }\n      |   ^"), "{stderr}");
+    assert!(
+        stderr.contains("hint: JSON does not allow trailing commas"),
+        "{stderr}"
+    );
+
+    let stderr = parse_error("app.yaml", "app:\n  src:\n\tmain.rs: ''\n");
+    assert!(stderr.contains("--> app.yaml:3:1"), "{stderr}");
+    assert!(
+        stderr.contains("hint: YAML indentation must use spaces"),
+        "{stderr}"
+    );
+
+    // TOML 与 JSON5 的位置原先在包装后丢失
+    let stderr = parse_error("app.toml", "[app\nREADME = \"hi\"\n");
+    assert!(
+        stderr.contains("expected a right bracket, found a newline\n"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("--> app.toml:1:5"), "{stderr}");
+    let stderr = parse_error("app.json5", "{ app: { README: } }\n");
+    assert!(stderr.contains("--> app.json5:1:18"), "{stderr}");
+    assert!(stderr.contains("1 | { app: { README: } }"), "{stderr}");
+}
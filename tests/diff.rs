@@ -0,0 +1,103 @@
+//! treegen diff：按路径比较两份描述（格式可以不同），有差异时退出码非零
+mod common;
+
+use common::{treegen, write_files};
+
+const OLD: &str = "app:\n  keep.txt: same\n  old.txt: gone\n  conf.txt: \"a\\nb\\n\"\n  x: { type: dir }\n  y.txt: file\n";
+const NEW: &str = "app:\n  keep.txt: same\n  new.txt: hi\n  conf.txt: \"a\\nc\\n\"\n  x: file\n  y.txt: { type: dir }\n";
+
+fn diff(dir: &std::path::Path, args: &[&str]) -> (bool, String, String) {
+    let output = treegen(dir).arg("diff").args(args).output().unwrap();
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn lists_added_removed_and_changed_paths() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("old.yaml", OLD), ("new.yaml", NEW)]);
+    let (success, stdout, stderr) = diff(dir, &["old.yaml", "new.yaml"]);
+    assert!(!success);
+    assert_eq!(
+        stdout,
+        "~ app/conf.txt: content differs\n\
+         + app/new.txt: file added\n\
+         - app/old.txt: file removed\n\
+         ~ app/x: directory -> file\n\
+         ~ app/y.txt: file -> directory\n"
+    );
+    assert!(stderr.contains("5 difference(s) found"), "{stderr}");
+    // 没有 --unified 时不打印内容的差异
+    assert!(!stdout.contains("@@"));
+}
+
+#[test]
+fn unified_shows_content_changes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("old.yaml", OLD), ("new.yaml", NEW)]);
+    let (success, stdout, _) = diff(dir, &["old.yaml", "new.yaml", "--unified"]);
+    assert!(!success);
+    assert!(
+        stdout.contains(
+            "~ app/conf.txt: content differs\n\
+             --- a/app/conf.txt\n\
+             +++ b/app/conf.txt\n\
+             @@ -1,2 +1,2 @@\n \
+             a\n\
+             -b\n\
+             +c\n\
+             + app/new.txt: file added\n"
+        ),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn identical_specs_succeed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("old.yaml", OLD)]);
+    let (success, stdout, _) = diff(dir, &["old.yaml", "old.yaml"]);
+    assert!(success);
+    assert!(stdout.contains("The two specs are identical"), "{stdout}");
+}
+
+#[test]
+fn compares_specs_in_different_formats() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            (
+                "tree.md",
+                "app/\n├── src/\n│   └── main.rs\n└── README.md\n",
+            ),
+            (
+                "same.json",
+                r#"{"app": {"src": {"main.rs": ""}, "README.md": ""}}"#,
+            ),
+            (
+                "other.toml",
+                "[app]\n\"README.md\" = \"# app\\n\"\n\"Cargo.toml\" = \"\"\n",
+            ),
+        ],
+    );
+    let (success, stdout, _) = diff(dir, &["tree.md", "same.json"]);
+    assert!(success, "{stdout}");
+
+    let (success, stdout, _) = diff(dir, &["tree.md", "other.toml"]);
+    assert!(!success);
+    assert_eq!(
+        stdout,
+        "+ app/Cargo.toml: file added\n\
+         ~ app/README.md: content differs\n\
+         - app/src: directory removed\n\
+         - app/src/main.rs: file removed\n"
+    );
+}
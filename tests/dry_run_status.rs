@@ -0,0 +1,75 @@
+mod common;
+
+use common::{treegen, write_files};
+
+const SPEC: &str =
+    "app:\n  new.txt: new\n  same.txt: same\n  changed.txt: after\n  conflict: file\n";
+
+fn dry_run(dir: &std::path::Path, extra: &[&str]) -> (bool, String, String) {
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--dry-run"])
+        .args(extra)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn dry_run_annotates_against_existing_output() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", SPEC),
+            ("out/app/same.txt", "same"),
+            ("out/app/changed.txt", "before"),
+            ("out/app/conflict/inner.txt", ""),
+        ],
+    );
+    let (ok, stdout, stderr) = dry_run(dir, &[]);
+    // 类型冲突由预检报告为失败，树在此之前已经打印
+    assert!(!ok);
+    assert!(stderr.contains("Pre-flight checks failed"), "{stderr}");
+    assert!(stdout.contains("app/  ~ differs"), "{stdout}");
+    assert!(stdout.contains("new.txt  + new"), "{stdout}");
+    assert!(stdout.contains("same.txt  = identical"), "{stdout}");
+    assert!(stdout.contains("changed.txt  ~ differs"), "{stdout}");
+    assert!(
+        stdout.contains("conflict  ! conflict: file -> directory"),
+        "{stdout}"
+    );
+    assert!(!dir.join("out/app/new.txt").exists());
+}
+
+#[test]
+fn dry_run_status_without_conflicts_succeeds() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", "app:\n  same.txt: same\n"),
+            ("out/app/same.txt", "same"),
+        ],
+    );
+    let (ok, stdout, stderr) = dry_run(dir, &["--print-tree"]);
+    assert!(ok, "{stderr}");
+    assert!(stdout.contains("app/  = identical"), "{stdout}");
+    // --print-tree 不再重复打印一棵树
+    assert_eq!(stdout.matches("same.txt").count(), 1, "{stdout}");
+}
+
+#[test]
+fn no_status_tree_without_existing_output() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let (ok, stdout, stderr) = dry_run(dir, &[]);
+    assert!(ok, "{stderr}");
+    assert!(!stdout.contains("+ new"), "{stdout}");
+}
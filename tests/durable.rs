@@ -0,0 +1,40 @@
+//! --durable：每个文件与涉及的目录都同步到磁盘，结果与普通生成相同
+
+mod common;
+
+use common::{read, treegen, write_files};
+use treegen::{generate, parse_yaml, GenerateOptions};
+
+#[test]
+fn durable_generation_writes_everything() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    let spec = parse_yaml("a:\n  b.txt: hi\n  c: {}\nd.txt: ''\n").unwrap();
+    let opts = GenerateOptions {
+        durable: true,
+        ..Default::default()
+    };
+    let stats = generate(&spec.root, out, &opts).unwrap();
+    assert_eq!((stats.dirs, stats.files), (2, 2));
+    assert_eq!(read(out, "a/b.txt"), "hi");
+    assert_eq!(read(out, "d.txt"), "");
+    assert!(out.join("a/c").is_dir());
+
+    // 覆盖已有文件时同样走原子写入
+    let spec = parse_yaml("a:\n  b.txt: again\n").unwrap();
+    let stats = generate(&spec.root, out, &opts).unwrap();
+    assert_eq!((stats.files, stats.unchanged), (1, 0));
+    assert_eq!(read(out, "a/b.txt"), "again");
+}
+
+#[test]
+fn durable_flag() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "src:\n  main.rs: 'fn main() {}'\n")]);
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--durable"])
+        .assert()
+        .success();
+    assert_eq!(read(&dir.join("out"), "src/main.rs"), "fn main() {}");
+}
@@ -0,0 +1,91 @@
+//! Markdown 树中的省略行（`...`、`… (20 more files)`）默认跳过，--keep-ellipsis 时按字面生成
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{parse_markdown, parse_str, Format, ParseOptions};
+
+const ELIDED: &str = "\
+app/
+├── src/
+│   ├── main.rs
+│   ├── …
+│   └── ... (20 more files)
+├── docs/
+│   ├── guide/
+│   │   ├── intro.md
+│   │   └── ...
+│   └── index.md
+├── ....
+└── README.md
+";
+
+#[test]
+fn ellipsis_lines_are_skipped() {
+    let spec = parse_markdown(ELIDED).unwrap();
+    let paths: Vec<String> = spec
+        .root
+        .iter()
+        .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+        .collect();
+    // 省略行之后的兄弟节点仍然挂在原来的父节点下
+    assert_eq!(
+        paths,
+        [
+            "",
+            "app/",
+            "app/src/",
+            "app/src/main.rs",
+            "app/docs/",
+            "app/docs/guide/",
+            "app/docs/guide/intro.md",
+            "app/docs/index.md",
+            "app/README.md",
+        ]
+    );
+    // 只有省略号（及括号说明）的名称才算
+    let spec = parse_markdown("app/\n├── ...rc\n└── … notes\n").unwrap();
+    assert_eq!(spec.root.children[0].children.len(), 2);
+}
+
+#[test]
+fn keep_ellipsis_generates_literal_names() {
+    let opts = ParseOptions {
+        keep_ellipsis: true,
+        ..Default::default()
+    };
+    let parsed = parse_str("tree.md", ELIDED, Some(Format::Markdown), &opts).unwrap();
+    assert!(parsed.notes.is_empty());
+    let app = &parsed.spec.root.children[0];
+    assert!(app.find("src/…").is_some());
+    assert!(app.find("src/... (20 more files)").is_some());
+    assert!(app.find("docs/guide/...").is_some());
+    assert!(app.find("README.md").is_some());
+    // 写回 Markdown 时这样的名称会被跳过，因此拒绝
+    assert!(parsed.spec.root.to_markdown().is_err());
+}
+
+#[test]
+fn skipped_lines_are_reported_in_verbose_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("tree.md", ELIDED)]);
+    let output = treegen(dir)
+        .args(["tree.md", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Skip placeholder line 5: '... (20 more files)' (tree.md)"),
+        "{stdout}"
+    );
+    assert!(dir.join("out/app/docs/index.md").is_file());
+    assert!(!dir.join("out/app/src/…").exists());
+
+    treegen(dir)
+        .args(["tree.md", "--out", "literal", "--keep-ellipsis"])
+        .assert()
+        .success();
+    assert!(dir.join("literal/app/src/…").is_file());
+}
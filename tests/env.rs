@@ -0,0 +1,86 @@
+//! `TREEGEN_*` 环境变量与命令行参数的优先级
+
+mod common;
+
+use common::{read, treegen, write_files};
+
+#[test]
+fn dry_run_from_env_writes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    write_files(dir.path(), &[("spec.yaml", "a.txt: hi\n")]);
+    treegen(dir.path())
+        .args(["spec.yaml", "--out", "out"])
+        .env("TREEGEN_DRY_RUN", "1")
+        .assert()
+        .success();
+    assert!(!dir.path().join("out/a.txt").exists());
+
+    // 命令行上没有关闭 dry-run 的参数，TREEGEN_DRY_RUN=0 即关闭
+    treegen(dir.path())
+        .args(["spec.yaml", "--out", "out"])
+        .env("TREEGEN_DRY_RUN", "0")
+        .assert()
+        .success();
+    assert_eq!(read(dir.path(), "out/a.txt"), "hi");
+}
+
+#[test]
+fn out_from_env_and_cli_precedence() {
+    let dir = tempfile::tempdir().unwrap();
+    write_files(dir.path(), &[("spec.yaml", "a.txt: hi\n")]);
+    treegen(dir.path())
+        .arg("spec.yaml")
+        .env("TREEGEN_OUT", "from-env")
+        .assert()
+        .success();
+    assert!(dir.path().join("from-env/a.txt").is_file());
+
+    treegen(dir.path())
+        .args(["spec.yaml", "--out", "from-cli"])
+        .env("TREEGEN_OUT", "from-env-2")
+        .assert()
+        .success();
+    assert!(dir.path().join("from-cli/a.txt").is_file());
+    assert!(!dir.path().join("from-env-2").exists());
+}
+
+#[test]
+fn vars_from_env_and_cli_precedence() {
+    let dir = tempfile::tempdir().unwrap();
+    write_files(
+        dir.path(),
+        &[("spec.yaml", "\"{{name}}.txt\": \"{{name}}\"\n")],
+    );
+    treegen(dir.path())
+        .args(["spec.yaml", "--out", "a"])
+        .env("TREEGEN_VAR_NAME", "env")
+        .assert()
+        .success();
+    assert_eq!(read(dir.path(), "a/env.txt"), "env");
+
+    treegen(dir.path())
+        .args(["spec.yaml", "--out", "b", "--var", "name=cli"])
+        .env("TREEGEN_VAR_NAME", "env")
+        .assert()
+        .success();
+    assert_eq!(read(dir.path(), "b/cli.txt"), "cli");
+}
+
+#[cfg(unix)]
+#[test]
+fn mode_from_env() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    write_files(dir.path(), &[("spec.yaml", "a.txt: hi\n")]);
+    treegen(dir.path())
+        .args(["spec.yaml", "--out", "out"])
+        .env("TREEGEN_MODE", "0o600")
+        .assert()
+        .success();
+    let mode = std::fs::metadata(dir.path().join("out/a.txt"))
+        .unwrap()
+        .permissions()
+        .mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
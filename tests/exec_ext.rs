@@ -0,0 +1,83 @@
+//! --exec-ext 与 --auto-exec：按扩展名或 shebang 加上可执行位，与 --mode、节点 mode 的优先级
+#![cfg(unix)]
+
+mod common;
+
+use common::{treegen, write_files};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use treegen::{generate, parse_yaml, GenerateOptions};
+
+fn mode(dir: &Path, path: &str) -> u32 {
+    dir.join(path).metadata().unwrap().permissions().mode() & 0o7777
+}
+
+#[test]
+fn exec_bit_precedence() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    let spec = parse_yaml(
+        "build.sh: 'make'\n\
+         tool.PY: 'print()'\n\
+         archive.tar.gz: ''\n\
+         sh: ''\n\
+         run: \"#!/bin/sh\\necho\\n\"\n\
+         notes.txt: 'plain'\n\
+         fixed.sh: { type: file, content: '#!/bin/sh', mode: '0o600' }\n",
+    )
+    .unwrap();
+    let opts = GenerateOptions {
+        mode: 0o640,
+        exec_ext: vec!["sh".into(), "py".into(), "tar.gz".into()],
+        auto_exec: true,
+        ..Default::default()
+    };
+    generate(&spec.root, out, &opts).unwrap();
+    // 扩展名不区分大小写，可以含多段；只有扩展名本身的名称不算
+    assert_eq!(mode(out, "build.sh"), 0o751);
+    assert_eq!(mode(out, "tool.PY"), 0o751);
+    assert_eq!(mode(out, "archive.tar.gz"), 0o751);
+    assert_eq!(mode(out, "sh"), 0o640);
+    // shebang
+    assert_eq!(mode(out, "run"), 0o751);
+    assert_eq!(mode(out, "notes.txt"), 0o640);
+    // 节点的 mode 胜过扩展名与 shebang
+    assert_eq!(mode(out, "fixed.sh"), 0o600);
+}
+
+#[test]
+fn shebang_needs_auto_exec() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    let spec = parse_yaml("run: \"#!/bin/sh\\n\"\n").unwrap();
+    generate(&spec.root, out, &GenerateOptions::default()).unwrap();
+    assert_eq!(mode(out, "run"), 0o644);
+}
+
+#[test]
+fn exec_ext_flag_notes_reason() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "bin:\n  a.sh: ''\n  b.pl: ''\n  c: \"#!/usr/bin/env python\\n\"\n  d.txt: ''\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--verbose", "--mode", "0o600"])
+        .args(["--exec-ext", ".sh,pl", "--auto-exec"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("a.sh (+x: --exec-ext)"), "{}", stdout);
+    assert!(stdout.contains("c (+x: shebang)"), "{}", stdout);
+    assert!(!stdout.contains("d.txt (+x"), "{}", stdout);
+    let out = dir.join("out/bin");
+    assert_eq!(mode(&out, "a.sh"), 0o711);
+    assert_eq!(mode(&out, "b.pl"), 0o711);
+    assert_eq!(mode(&out, "c"), 0o711);
+    assert_eq!(mode(&out, "d.txt"), 0o600);
+}
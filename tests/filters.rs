@@ -0,0 +1,147 @@
+//! 占位符的过滤器：`{{name|snake}}`，名称与内容中都可以使用，按顺序应用
+mod common;
+
+use common::{read, treegen, write_files};
+
+/// 每个过滤器一个文件，内容是过滤后的值
+const SPEC: &str = "\
+upper.txt: '{{name|upper}}'
+lower.txt: '{{name|lower}}'
+snake.txt: '{{name|snake}}'
+kebab.txt: '{{name|kebab}}'
+pascal.txt: '{{name|pascal}}'
+camel.txt: '{{name|camel}}'
+slug.txt: '{{name|slug}}'
+";
+
+/// 以 `name` 生成，返回 (过滤器, 结果)
+fn filtered(name: &str) -> Vec<(String, String)> {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--var"])
+        .arg(format!("name={name}"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    [
+        "upper", "lower", "snake", "kebab", "pascal", "camel", "slug",
+    ]
+    .iter()
+    .map(|f| (f.to_string(), read(dir, &format!("out/{f}.txt"))))
+    .collect()
+}
+
+fn expect(pairs: [(&str, &str); 7]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(f, v)| (f.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn every_filter() {
+    assert_eq!(
+        filtered("My App"),
+        expect([
+            ("upper", "MY APP"),
+            ("lower", "my app"),
+            ("snake", "my_app"),
+            ("kebab", "my-app"),
+            ("pascal", "MyApp"),
+            ("camel", "myApp"),
+            ("slug", "my-app"),
+        ])
+    );
+    assert_eq!(
+        filtered("HTTPServer2Go"),
+        expect([
+            ("upper", "HTTPSERVER2GO"),
+            ("lower", "httpserver2go"),
+            ("snake", "http_server2_go"),
+            ("kebab", "http-server2-go"),
+            ("pascal", "HttpServer2Go"),
+            ("camel", "httpServer2Go"),
+            ("slug", "httpserver2go"),
+        ])
+    );
+}
+
+#[test]
+fn unicode_values() {
+    assert_eq!(
+        filtered("Über Straße"),
+        expect([
+            ("upper", "ÜBER STRASSE"),
+            ("lower", "über straße"),
+            ("snake", "über_straße"),
+            ("kebab", "über-straße"),
+            ("pascal", "ÜberStraße"),
+            ("camel", "überStraße"),
+            ("slug", "über-straße"),
+        ])
+    );
+}
+
+#[test]
+fn already_formatted_values_are_unchanged() {
+    for (filter, value) in [
+        ("upper", "MY_APP"),
+        ("lower", "my app"),
+        ("snake", "my_app_2"),
+        ("kebab", "my-app"),
+        ("pascal", "MyApp"),
+        ("camel", "myApp"),
+        ("slug", "my-app-v2"),
+    ] {
+        let results = filtered(value);
+        let (_, result) = results.iter().find(|(f, _)| f == filter).unwrap();
+        assert_eq!(result, value, "{filter}");
+    }
+}
+
+#[test]
+fn filters_chain_in_names_and_content() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "'{{ name | kebab }}':\n  '{{name|pascal}}.rs': 'mod {{ name | snake | upper }};'\n",
+        )],
+    );
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--var", "name=my app"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/my-app/MyApp.rs"), "mod MY_APP;");
+}
+
+#[test]
+fn unknown_and_empty_filters_are_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    for (content, filter) in [
+        ("'{{name|title}}'", "title"),
+        ("'{{name|}}'", ""),
+        ("'{{ name | upper | }}'", ""),
+    ] {
+        write_files(
+            dir,
+            &[("spec.yaml", &format!("app:\n  a.txt: {content}\n"))],
+        );
+        let output = treegen(dir)
+            .args(["spec.yaml", "--out", "out", "--var", "name=x"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{content}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("Unknown filter '{filter}' in 'app/a.txt'")),
+            "{stderr}"
+        );
+        assert!(!dir.join("out/app/a.txt").exists());
+    }
+}
@@ -0,0 +1,122 @@
+//! --flatten：所有文件放到输出目录的同一层，路径编码进文件名
+mod common;
+
+use common::{read, treegen, write_files};
+use std::path::Path;
+
+const SPEC: &str = "\
+src:
+  main.rs: m
+  util:
+    mod.rs: u
+empty: {}
+nested:
+  inner: {}
+README.md: r
+";
+
+fn entries(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn files_are_flattened_and_empty_directories_noted() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--flatten"])
+        .assert()
+        .success()
+        .stdout(
+            "✅ Generated the file tree in 'out'\n\
+             (--flatten: 3 empty directory(ies) not generated)\n",
+        );
+    assert_eq!(
+        entries(&dir.join("out")),
+        ["README.md", "src__main.rs", "src__util__mod.rs"]
+    );
+    assert_eq!(read(dir, "out/src__util__mod.rs"), "u");
+}
+
+#[test]
+fn flatten_sep_changes_the_separator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    treegen(dir)
+        .args([
+            "spec.yaml",
+            "--out",
+            "out",
+            "--flatten",
+            "--flatten-sep",
+            ".",
+        ])
+        .assert()
+        .success();
+    assert_eq!(
+        entries(&dir.join("out")),
+        ["README.md", "src.main.rs", "src.util.mod.rs"]
+    );
+}
+
+#[test]
+fn colliding_names_are_rejected_before_writing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("spec.yaml", "src:\n  main.rs: m\nsrc__main.rs: x\n")],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--flatten"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("'src/main.rs' and 'src__main.rs' both flatten to 'src__main.rs'"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn invalid_separators_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    for sep in ["", "a/b", "\\"] {
+        let output = treegen(dir)
+            .args([
+                "spec.yaml",
+                "--out",
+                "out",
+                "--flatten",
+                "--flatten-sep",
+                sep,
+            ])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("Invalid flatten separator '{sep}'")),
+            "{stderr}"
+        );
+    }
+    assert!(!dir.join("out").exists());
+
+    // --flatten-sep 需要 --flatten
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--flatten-sep", "."])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+}
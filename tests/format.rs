@@ -0,0 +1,92 @@
+//! 格式选择：FORMAT:PATH > --format > 扩展名 > 内容推断，以及都无法确定时的错误
+
+mod common;
+
+use common::{read, treegen, write_files};
+use treegen::{parse_spec, Detection, Format};
+
+#[test]
+fn override_takes_precedence_over_extension_and_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("spec.tmpl", "src:\n  main.rs: ''\n"),
+            ("spec.json", "src:\n  main.rs: ''\n"),
+        ],
+    );
+    let tmpl = dir.join("spec.tmpl");
+    let parsed = parse_spec(&tmpl, Some(Format::Yaml)).unwrap();
+    assert_eq!(
+        (parsed.format, parsed.detection),
+        (Format::Yaml, Detection::Override)
+    );
+    assert!(parsed.spec.root.find("src/main.rs").is_some());
+    let parsed = parse_spec(&tmpl, None).unwrap();
+    assert_eq!(
+        (parsed.format, parsed.detection),
+        (Format::Yaml, Detection::Sniffed)
+    );
+    let err = parse_spec(&tmpl, Some(Format::Json)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Failed to parse JSON in '{}'", tmpl.display())
+    );
+
+    // 扩展名说是 JSON，指定的格式优先
+    let json = dir.join("spec.json");
+    assert!(parse_spec(&json, None).is_err());
+    let parsed = parse_spec(&json, Some(Format::Yaml)).unwrap();
+    assert_eq!(parsed.detection, Detection::Override);
+}
+
+#[test]
+fn undetectable_input_names_the_extensions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("notes.spec");
+    std::fs::write(&path, "just some prose\n").unwrap();
+    let err = parse_spec(&path, None).unwrap_err();
+    let msg = err.to_string();
+    assert!(
+        msg.starts_with(&format!(
+            "Cannot determine the format of '{}': the extension is not one of ",
+            path.display()
+        )),
+        "{msg}"
+    );
+    assert!(msg.ends_with("and content detection failed"), "{msg}");
+}
+
+#[test]
+fn cli_format_and_per_input_prefix() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("a.tmpl", "a.txt: from yaml\n"),
+            ("b.spec", "{\"b.txt\": \"from json\"}\n"),
+        ],
+    );
+    treegen(dir)
+        .args(["a.tmpl", "--format", "yaml", "--out", "one"])
+        .assert()
+        .success();
+    assert_eq!(read(&dir.join("one"), "a.txt"), "from yaml");
+
+    // 每个输入单独指定格式，合并到同一个输出目录
+    treegen(dir)
+        .args(["yaml:a.tmpl", "json:b.spec", "--out", "both"])
+        .assert()
+        .success();
+    assert_eq!(read(&dir.join("both"), "a.txt"), "from yaml");
+    assert_eq!(read(&dir.join("both"), "b.txt"), "from json");
+
+    // FORMAT:PATH 优先于 --format：a.tmpl 不是合法的 JSON
+    treegen(dir)
+        .args(["yaml:a.tmpl", "--format", "json", "--out", "prefix"])
+        .assert()
+        .success();
+    assert_eq!(read(&dir.join("prefix"), "a.txt"), "from yaml");
+}
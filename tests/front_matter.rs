@@ -0,0 +1,88 @@
+//! Markdown 描述开头的 YAML front matter：out、mode、vars 与 root，命令行参数优先
+
+mod common;
+
+use common::{treegen, write_files};
+
+const SPEC: &str = "---\n\
+out: ./generated\n\
+mode: \"0o600\"\n\
+vars: { name: demo }\n\
+root: \"{{name}}\"\n\
+---\n\
+├── src/\n\
+│   └── main.rs\n\
+└── README.md\n";
+
+#[cfg(unix)]
+fn mode(path: &std::path::Path) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().unwrap().permissions().mode() & 0o7777
+}
+
+#[test]
+fn front_matter_configures_the_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.md", SPEC)]);
+    let output = treegen(dir).arg("app.md").output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(dir.join("generated/demo/src/main.rs").is_file());
+    assert!(dir.join("generated/demo/README.md").is_file());
+    #[cfg(unix)]
+    assert_eq!(mode(&dir.join("generated/demo/README.md")), 0o600);
+}
+
+#[test]
+fn specs_without_front_matter_are_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.md", "app/\n└── README.md\n")]);
+    let output = treegen(dir)
+        .args(["app.md", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(dir.join("out/app/README.md").is_file());
+}
+
+#[test]
+fn cli_flags_override_front_matter() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.md", SPEC)]);
+    let output = treegen(dir)
+        .args([
+            "app.md", "--out", "other", "--mode", "0o644", "--var", "name=x",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!dir.join("generated").exists());
+    assert!(dir.join("other/x/src/main.rs").is_file());
+    #[cfg(unix)]
+    assert_eq!(mode(&dir.join("other/x/README.md")), 0o644);
+}
+
+#[test]
+fn invalid_front_matter_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("unknown.md", "---\noutput: x\n---\nREADME.md\n"),
+            ("open.md", "---\nout: x\nREADME.md\n"),
+        ],
+    );
+    let output = treegen(dir).arg("unknown.md").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Invalid front matter"), "{stderr}");
+    assert!(stderr.contains("unknown field `output`"), "{stderr}");
+
+    let output = treegen(dir).arg("open.md").output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is not closed"), "{stderr}");
+}
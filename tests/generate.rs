@@ -0,0 +1,20 @@
+//! 生成到临时目录的集成测试
+
+use std::fs;
+use treegen::{generate, parse_yaml};
+
+#[test]
+fn parsed_spec_is_generated_with_content() {
+    let out = tempfile::tempdir().unwrap();
+    let spec = parse_yaml("app:\n  src:\n    main.rs: 'fn main() {}'\n  README.md: ''\n").unwrap();
+    let stats = generate(&spec.root, out.path(), &Default::default()).unwrap();
+    assert_eq!((stats.dirs, stats.files), (2, 2));
+    assert_eq!(
+        fs::read_to_string(out.path().join("app/src/main.rs")).unwrap(),
+        "fn main() {}"
+    );
+    assert_eq!(
+        fs::read_to_string(out.path().join("app/README.md")).unwrap(),
+        ""
+    );
+}
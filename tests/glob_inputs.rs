@@ -0,0 +1,102 @@
+//! glob 输入与 --recursive：匹配到的描述文件按路径排序后作为输入
+mod common;
+
+use common::{read, treegen, write_files};
+use std::path::Path;
+
+/// 故意以与排序相反的顺序写入，不依赖文件系统的目录顺序
+fn specs(dir: &Path) {
+    write_files(
+        dir,
+        &[
+            ("specs/z/d.json", r#"{ "d.txt": "d" }"#),
+            ("specs/z/c.toml", "\"c.txt\" = \"c\"\n"),
+            ("specs/b.yaml", "b.txt: b\n"),
+            ("specs/a.yaml", "a.txt: a\n"),
+            ("specs/a/notes.txt", "not a spec\n"),
+        ],
+    );
+}
+
+fn found_specs(stdout: &str) -> Vec<&str> {
+    stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("Found spec: "))
+        .collect()
+}
+
+#[test]
+fn glob_inputs_are_sorted_and_logged() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    specs(dir);
+    let output = treegen(dir)
+        .args(["specs/*.yaml", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        found_specs(&stdout),
+        [
+            "specs/a.yaml (from 'specs/*.yaml')",
+            "specs/b.yaml (from 'specs/*.yaml')"
+        ]
+    );
+    assert_eq!(read(dir, "out/a.txt"), "a");
+    assert_eq!(read(dir, "out/b.txt"), "b");
+    assert!(!dir.join("out/c.txt").exists());
+}
+
+#[test]
+fn recursive_finds_supported_specs_in_sorted_order() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    specs(dir);
+    let output = treegen(dir)
+        .args(["--recursive", "specs", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout).replace('\\', "/");
+    assert_eq!(
+        found_specs(&stdout),
+        [
+            "specs/a.yaml",
+            "specs/b.yaml",
+            "specs/z/c.toml",
+            "specs/z/d.json"
+        ]
+    );
+    for name in ["a", "b", "c", "d"] {
+        assert_eq!(read(dir, &format!("out/{name}.txt")), name);
+    }
+    assert!(!dir.join("out/notes.txt").exists());
+}
+
+#[test]
+fn inputs_that_match_nothing_are_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    specs(dir);
+    for (args, message) in [
+        (
+            ["specs/*.json"].as_slice(),
+            "No spec files match 'specs/*.json'",
+        ),
+        (
+            ["--recursive", "specs/a"].as_slice(),
+            "No spec files found under 'specs/a'",
+        ),
+    ] {
+        let output = treegen(dir)
+            .args(args)
+            .args(["--out", "out"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(message), "{stderr}");
+    }
+    assert!(!dir.join("out").exists());
+}
@@ -0,0 +1,139 @@
+//! 硬链接节点：与目标共享 inode、链接排在目标之后、指向磁盘上已有的文件，以及目标无效时的错误
+
+mod common;
+
+use common::{read, treegen, write_files};
+use std::path::Path;
+use treegen::{generate, parse_yaml, GenerateOptions, Node};
+
+/// 两个路径是否是同一个文件
+#[cfg(unix)]
+fn same_inode(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let (a, b) = (a.metadata().unwrap(), b.metadata().unwrap());
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+#[test]
+fn links_share_the_target_inode() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    // 按名称排序时链接在目标之前，链接的链接也要排在它指向的链接之后
+    let spec = parse_yaml(
+        "alias.bin: { type: hardlink, target: data/base.bin }\n\
+         again.bin: { type: hardlink, target: alias.bin }\n\
+         data:\n  base.bin: big\n",
+    )
+    .unwrap();
+    let stats = generate(&spec.root, out, &GenerateOptions::default()).unwrap();
+    assert_eq!((stats.files, stats.links), (1, 2));
+    assert_eq!(read(out, "alias.bin"), "big");
+    assert_eq!(read(out, "again.bin"), "big");
+    #[cfg(unix)]
+    {
+        assert!(same_inode(
+            &out.join("alias.bin"),
+            &out.join("data/base.bin")
+        ));
+        assert!(same_inode(
+            &out.join("again.bin"),
+            &out.join("data/base.bin")
+        ));
+    }
+
+    // 再次生成时链接不变
+    let stats = generate(&spec.root, out, &GenerateOptions::default()).unwrap();
+    assert_eq!(stats.links, 0);
+}
+
+#[test]
+fn targets_may_already_exist_on_disk() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    write_files(out, &[("existing.txt", "on disk\n")]);
+    let mut root = Node::root().build().unwrap();
+    root.children.push(Node::new_hardlink(
+        "link.txt".to_string(),
+        "existing.txt".into(),
+    ));
+    generate(&root, out, &GenerateOptions::default()).unwrap();
+    assert_eq!(read(out, "link.txt"), "on disk\n");
+    #[cfg(unix)]
+    assert!(same_inode(&out.join("link.txt"), &out.join("existing.txt")));
+}
+
+#[test]
+fn invalid_targets_are_reported_before_writing() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    let spec = parse_yaml("a.txt: ''\nlink: { type: hardlink, target: missing.bin }\n").unwrap();
+    let err = generate(&spec.root, out, &GenerateOptions::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Hardlink target 'missing.bin' of 'link' is neither generated by the spec nor an existing file in '{}'",
+            out.display()
+        )
+    );
+    assert!(!out.join("a.txt").exists());
+
+    let spec = parse_yaml("dir: {}\nlink: { type: hardlink, target: dir }\n").unwrap();
+    let err = generate(&spec.root, out, &GenerateOptions::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Hardlink 'link' points to directory 'dir'; hardlinks must point to files"
+    );
+
+    let err = parse_yaml("link: { type: hardlink, target: ../outside }\n").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("must be a path inside the output root"),
+        "{err}"
+    );
+}
+
+#[test]
+fn dry_run_shows_the_target() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "data:\n  base.bin: big\nalias.bin: { type: hardlink, target: data/base.bin }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--dry-run", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let log =
+        String::from_utf8(output.stderr).unwrap() + &String::from_utf8(output.stdout).unwrap();
+    let link = Path::new("out").join("alias.bin");
+    assert!(
+        log.contains(&format!(
+            "[Dry-Run] Create hardlink: {} -> data/base.bin",
+            link.display()
+        )),
+        "{log}"
+    );
+    assert!(!dir.join("out/alias.bin").exists());
+}
+
+#[test]
+fn cycles_are_reported_with_the_full_chain() {
+    let out = tempfile::tempdir().unwrap();
+    // 0.bin 排在最前，指向循环但不在循环中，只报告循环本身
+    let spec = parse_yaml(
+        "0.bin: { type: hardlink, target: a.bin }\n\
+         a.bin: { type: hardlink, target: sub/b.bin }\n\
+         sub:\n  b.bin: { type: hardlink, target: a.bin }\n",
+    )
+    .unwrap();
+    let err = generate(&spec.root, out.path(), &GenerateOptions::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Hardlinks form a cycle: 'a.bin' -> 'sub/b.bin' -> 'a.bin'"
+    );
+}
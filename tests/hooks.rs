@@ -0,0 +1,86 @@
+//! $hooks：生成成功后在输出目录中依次执行的命令
+#![cfg(unix)]
+
+mod common;
+
+use common::{treegen, write_files};
+
+const SPEC: &str = "a.txt: x\n$hooks:\n  post:\n    - echo one > one.txt\n    - exit 3\n    - echo three > three.txt\n";
+
+fn run(dir: &std::path::Path, args: &[&str]) -> (Option<i32>, String, String) {
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let _ = std::fs::remove_dir_all(dir.join("out"));
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn a_failing_hook_fails_the_run_and_stops_the_rest() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let (code, stdout, stderr) = run(dir, &[]);
+    assert_eq!(code, Some(1));
+    assert!(
+        stderr.contains("Hook 'exit 3' failed with exit status: 3"),
+        "{stderr}"
+    );
+    assert!(
+        stdout.contains("▶ echo one > one.txt\n▶ exit 3\n"),
+        "{stdout}"
+    );
+    // 钩子在输出目录中执行，生成的文件保留
+    assert!(dir.join("out/a.txt").is_file());
+    assert!(dir.join("out/one.txt").is_file());
+    assert!(!dir.join("out/three.txt").exists());
+}
+
+#[test]
+fn keep_going_warns_and_runs_the_rest() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let (code, stdout, stderr) = run(dir, &["--hooks-keep-going"]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(
+        stderr.contains("Warning: hook 'exit 3' failed with exit status: 3"),
+        "{stderr}"
+    );
+    assert!(stdout.contains("▶ echo three > three.txt"), "{stdout}");
+    assert!(dir.join("out/one.txt").is_file());
+    assert!(dir.join("out/three.txt").is_file());
+}
+
+#[test]
+fn no_hooks_skips_them() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let (code, stdout, _) = run(dir, &["--no-hooks"]);
+    assert_eq!(code, Some(0));
+    assert!(!stdout.contains('▶'), "{stdout}");
+    assert!(dir.join("out/a.txt").is_file());
+    assert!(!dir.join("out/one.txt").exists());
+}
+
+#[test]
+fn dry_run_prints_the_commands_without_running_them() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let (code, stdout, _) = run(dir, &["--dry-run"]);
+    assert_eq!(code, Some(0));
+    assert!(
+        stdout.contains(
+            "[Dry-Run] Run hook: echo one > one.txt\n\
+             [Dry-Run] Run hook: exit 3\n\
+             [Dry-Run] Run hook: echo three > three.txt\n"
+        ),
+        "{stdout}"
+    );
+    assert!(!dir.join("out").exists());
+}
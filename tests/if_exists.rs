@@ -0,0 +1,184 @@
+//! 节点元数据 if_exists：已存在的文件按节点自己的策略处理，优先于 --skip-existing
+
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r#"
+Cargo.toml: { type: file, content: "new", if_exists: skip }
+notes.txt: { type: file, content: "+more", if_exists: append }
+config.ini: { type: file, content: "new", if_exists: backup }
+src:
+  generated.rs: { type: file, content: "new", if_exists: overwrite }
+"#;
+
+fn existing(dir: &std::path::Path) {
+    write_files(
+        dir,
+        &[
+            ("app.yaml", SPEC),
+            ("out/Cargo.toml", "old"),
+            ("out/notes.txt", "old"),
+            ("out/config.ini", "old"),
+            ("out/src/generated.rs", "old"),
+        ],
+    );
+}
+
+#[test]
+fn node_policies_override_the_cli() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    existing(dir);
+    let output = treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "out",
+            "--skip-existing",
+            "--summary-json",
+            "summary.json",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/Cargo.toml"), "old");
+    assert_eq!(read(dir, "out/notes.txt"), "old+more");
+    assert_eq!(read(dir, "out/config.ini"), "new");
+    assert_eq!(read(dir, "out/config.ini.bak"), "old");
+    assert_eq!(read(dir, "out/src/generated.rs"), "new");
+
+    let summary: serde_json::Value = serde_json::from_str(&read(dir, "summary.json")).unwrap();
+    assert_eq!(summary["if_exists"]["Cargo.toml"], "skip");
+    assert_eq!(summary["if_exists"]["config.ini"], "backup");
+    assert_eq!(summary["if_exists"]["src/generated.rs"], "overwrite");
+}
+
+#[test]
+fn dry_run_shows_each_decision() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    existing(dir);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for line in [
+        "[Dry-Run] Skip existing file: out/Cargo.toml (if_exists: skip)",
+        "[Dry-Run] Append to file: out/notes.txt (if_exists: append)",
+        "[Dry-Run] Back up and overwrite file: out/config.ini (if_exists: backup)",
+        "[Dry-Run] Create file: out/src/generated.rs (if_exists: overwrite)",
+    ] {
+        assert!(stdout.contains(line), "{line}\n{stdout}");
+    }
+    assert_eq!(read(dir, "out/notes.txt"), "old");
+    assert!(!dir.join("out/config.ini.bak").exists());
+}
+
+#[test]
+fn error_policy_refuses_to_replace_different_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            (
+                "app.yaml",
+                "locked.txt: { type: file, content: 'new', if_exists: error }\n",
+            ),
+            ("out/locked.txt", "old"),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr
+            .contains("'out/locked.txt' already exists with different content (if_exists: error)"),
+        "{stderr}"
+    );
+    assert_eq!(read(dir, "out/locked.txt"), "old");
+}
+
+#[test]
+fn error_conflicts_are_found_before_anything_is_written() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    existing(dir);
+    // 冲突的节点排在会备份、追加与覆盖的节点之后
+    let spec = format!("{SPEC}zz.txt: {{ type: file, content: 'new', if_exists: error }}\n");
+    write_files(dir, &[("app.yaml", &spec), ("out/zz.txt", "old")]);
+    let message = "'out/zz.txt' already exists with different content (if_exists: error)";
+    for args in [&[][..], &["--preflight"], &["--dry-run"]] {
+        let output = treegen(dir)
+            .args(["app.yaml", "--out", "out"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{args:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(message), "{stderr}");
+    }
+    for file in [
+        "Cargo.toml",
+        "notes.txt",
+        "config.ini",
+        "src/generated.rs",
+        "zz.txt",
+    ] {
+        assert_eq!(read(dir, &format!("out/{file}")), "old", "{file}");
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(dir.join("out"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    entries.sort();
+    assert_eq!(
+        entries,
+        ["Cargo.toml", "config.ini", "notes.txt", "src", "zz.txt"]
+    );
+
+    // --keep-going 时其余节点照常生成，冲突的文件记为失败
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--keep-going"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("already exists with different content"),
+        "{stderr}"
+    );
+    assert_eq!(read(dir, "out/notes.txt"), "old+more");
+    assert_eq!(read(dir, "out/zz.txt"), "old");
+}
+
+#[test]
+fn unknown_policies_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "src:\n  a.txt: { type: file, if_exists: replace }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Invalid 'if_exists' value 'replace' in node 'a.txt' (expected one of: overwrite, skip, error, backup, append)"
+        ),
+        "{stderr}"
+    );
+}
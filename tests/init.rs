@@ -0,0 +1,49 @@
+//! treegen init：每种格式的示例都能直接生成
+
+mod common;
+
+use common::{read, treegen};
+
+#[test]
+fn every_example_generates() {
+    let dir = tempfile::tempdir().unwrap();
+    for format in ["yaml", "json5", "md"] {
+        let spec = format!("example.{format}");
+        let out = format!("out-{format}");
+        treegen(dir.path())
+            .args(["init", "--format", format, &spec])
+            .assert()
+            .success();
+        treegen(dir.path())
+            .args([&spec, "--out", &out])
+            .assert()
+            .success();
+        assert!(
+            dir.path()
+                .join(&out)
+                .join("my_project/src/main.rs")
+                .is_file(),
+            "{format}"
+        );
+        assert!(
+            dir.path().join(&out).join("my_project/assets").is_dir(),
+            "{format}"
+        );
+    }
+    assert_eq!(
+        read(dir.path(), "out-yaml/my_project/README.md"),
+        "# my_project\n\nGenerated by treegen.\n"
+    );
+}
+
+#[test]
+fn init_refuses_to_overwrite() {
+    let dir = tempfile::tempdir().unwrap();
+    treegen(dir.path()).arg("init").assert().success();
+    treegen(dir.path()).arg("init").assert().failure();
+    treegen(dir.path())
+        .args(["init", "--force"])
+        .assert()
+        .success();
+    assert!(read(dir.path(), "treegen.yaml").starts_with("# treegen example spec."));
+}
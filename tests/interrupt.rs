@@ -0,0 +1,44 @@
+//! 取消生成（Ctrl-C 时命令行设置的取消标志）与撤销本次新建的路径
+use std::path::PathBuf;
+use treegen::{generate, parse_yaml, remove_created, CancelToken, GenerateOptions, Interrupted};
+
+#[test]
+fn cancelled_generation_stops_before_writing() {
+    let out = tempfile::tempdir().unwrap();
+    let spec = parse_yaml("src:\n  main.rs: 'fn main() {}'\nREADME.md: hi\n").unwrap();
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let opts = GenerateOptions {
+        cancel,
+        ..Default::default()
+    };
+    let err = generate(&spec.root, out.path(), &opts).unwrap_err();
+    let interrupted = err.downcast::<Interrupted>().unwrap();
+    assert_eq!(interrupted.remaining, 3);
+    assert!(interrupted.stats.outcomes.is_empty());
+    assert_eq!(std::fs::read_dir(out.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn removing_created_paths_keeps_existing_ones() {
+    let out = tempfile::tempdir().unwrap();
+    std::fs::create_dir(out.path().join("src")).unwrap();
+    std::fs::write(out.path().join("src/lib.rs"), "// mine\n").unwrap();
+    let spec = parse_yaml(
+        "src:\n  lib.rs: \"// mine\\n\"\n  main.rs: 'fn main() {}'\n  bin: { tool.rs: '' }\n",
+    )
+    .unwrap();
+    let stats = generate(&spec.root, out.path(), &Default::default()).unwrap();
+
+    let removed = remove_created(out.path(), &stats).unwrap();
+    assert_eq!(
+        removed,
+        ["src/bin", "src/main.rs"].map(PathBuf::from).to_vec()
+    );
+    assert_eq!(
+        std::fs::read_to_string(out.path().join("src/lib.rs")).unwrap(),
+        "// mine\n"
+    );
+    assert!(!out.path().join("src/main.rs").exists());
+    assert!(!out.path().join("src/bin").exists());
+}
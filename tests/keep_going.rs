@@ -0,0 +1,77 @@
+//! 生成失败的路径按类型分组报告（--max-name-bytes 放宽检查，让超长名称交给文件系统报错）；--keep-going 时其余路径照常生成，--summary-json 中也列出失败
+#![cfg(unix)]
+
+mod common;
+
+use common::{read, treegen, write_files};
+
+fn spec() -> String {
+    format!(
+        "blocked:\n  x.txt: 'x'\n{}.txt: 'long'\nok.txt: 'ok'\n",
+        "n".repeat(300)
+    )
+}
+
+#[test]
+fn first_failure_is_reported_with_its_kind() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", &spec()), ("out/blocked", "a file")]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--max-name-bytes", "1000"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("1 path(s) could not be generated"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("  - blocked: "), "{stderr}");
+    assert!(!dir.join("out/ok.txt").exists());
+}
+
+#[test]
+fn keep_going_groups_failures_and_generates_the_rest() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", &spec()), ("out/blocked", "a file")]);
+    let output = treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "out",
+            "--keep-going",
+            "--max-name-bytes",
+            "1000",
+            "--summary-json",
+            "summary.json",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("2 path(s) could not be generated"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Name too long (1):"), "{stderr}");
+    assert!(stderr.contains("hint: shorten the names"), "{stderr}");
+    assert!(
+        stderr.contains("Other errors (1):\n  - blocked: "),
+        "{stderr}"
+    );
+    // 失败目录下的路径不再尝试
+    assert!(!stderr.contains("blocked/x.txt"), "{stderr}");
+    assert_eq!(read(dir, "out/ok.txt"), "ok");
+
+    let summary: serde_json::Value = serde_json::from_str(&read(dir, "summary.json")).unwrap();
+    assert_eq!(summary["files"], 1);
+    let kinds: Vec<&str> = summary["failures"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, ["other", "name-too-long"]);
+}
@@ -0,0 +1,80 @@
+//! --lang：结果提示按所选语言显示，不指定时按语言环境变量，机器可读的输出不变
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = "app:\n  README.md: hello\n";
+
+/// 运行 treegen 并返回标准输出
+fn stdout(dir: &std::path::Path, args: &[&str], env: &[(&str, &str)]) -> String {
+    let mut cmd = treegen(dir);
+    cmd.args(["app.yaml", "--out", "out"]).args(args);
+    cmd.envs(env.iter().copied());
+    let output = cmd.output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn success_and_dry_run_in_both_languages() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+
+    let en = stdout(dir, &["--dry-run", "--lang", "en"], &[]);
+    assert!(en.contains("✅ Dry-run complete"), "{en}");
+    let zh = stdout(dir, &["--dry-run", "--lang", "zh"], &[]);
+    assert!(zh.contains("✅ Dry‐Run 完成"), "{zh}");
+    // 逐个路径的日志不翻译
+    assert!(
+        zh.contains("[Dry-Run] Create output directory: out"),
+        "{zh}"
+    );
+
+    let en = stdout(dir, &["--lang", "en"], &[]);
+    assert!(en.contains("✅ Generated the file tree in 'out'"), "{en}");
+    let zh = stdout(dir, &["--lang", "zh"], &[]);
+    assert!(zh.contains("✅ 成功在 'out' 生成文件树！"), "{zh}");
+}
+
+#[test]
+fn language_follows_the_locale() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+
+    let en = stdout(dir, &["--dry-run"], &[]);
+    assert!(en.contains("Dry-run complete"), "{en}");
+    let zh = stdout(dir, &["--dry-run"], &[("LANG", "zh_CN.UTF-8")]);
+    assert!(zh.contains("Dry‐Run 完成"), "{zh}");
+    // LC_ALL 优先于 LANG，--lang 优先于两者
+    let en = stdout(
+        dir,
+        &["--dry-run"],
+        &[("LC_ALL", "en_US.UTF-8"), ("LANG", "zh_CN.UTF-8")],
+    );
+    assert!(en.contains("Dry-run complete"), "{en}");
+    let en = stdout(dir, &["--dry-run", "--lang", "en"], &[("LANG", "zh_CN")]);
+    assert!(en.contains("Dry-run complete"), "{en}");
+}
+
+#[test]
+fn summary_json_does_not_depend_on_the_language() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+
+    let mut summaries = Vec::new();
+    for lang in ["en", "zh"] {
+        let path = format!("{lang}.json");
+        stdout(
+            dir,
+            &["--dry-run", "--lang", lang, "--summary-json", &path],
+            &[],
+        );
+        let mut summary: serde_json::Value = serde_json::from_str(&read(dir, &path)).unwrap();
+        summary.as_object_mut().unwrap().remove("timings");
+        summaries.push(summary);
+    }
+    assert_eq!(summaries[0], summaries[1]);
+}
@@ -0,0 +1,45 @@
+//! 大描述的生成：跳过已创建的上级目录、批量写出 --verbose 输出时结果与逐个处理一致
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{generate, parse_yaml, GenerateOptions};
+
+#[test]
+fn keys_with_separators_still_create_parents() {
+    let out = tempfile::tempdir().unwrap();
+    let spec =
+        parse_yaml("src/bin/tool.rs: 'fn main() {}'\ndocs:\n  guide/intro.md: ''\n").unwrap();
+    generate(&spec.root, out.path(), &GenerateOptions::default()).unwrap();
+    assert!(out.path().join("src/bin/tool.rs").is_file());
+    assert!(out.path().join("docs/guide/intro.md").is_file());
+}
+
+#[test]
+fn verbose_output_of_large_tree_is_complete() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    // 输出远超一次写出的缓冲大小
+    let mut spec = String::new();
+    for d in 0..40 {
+        spec.push_str(&format!("dir{}:\n", d));
+        for f in 0..50 {
+            spec.push_str(&format!("  file{}.txt: '{}'\n", f, f));
+        }
+    }
+    write_files(dir, &[("big.yaml", &spec)]);
+    let output = treegen(dir)
+        .args(["big.yaml", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let created: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("Create file: "))
+        .collect();
+    assert_eq!(created.len(), 2000);
+    assert!(created[0].ends_with("file0.txt"), "{}", created[0]);
+    assert_eq!(stdout.matches("Create directory: ").count(), 41);
+    assert!(dir.join("out/dir39/file49.txt").is_file());
+}
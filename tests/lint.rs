@@ -0,0 +1,107 @@
+//! treegen lint：8 个建议性检查，默认只警告；--deny 的检查项出现时以非零状态退出
+mod common;
+
+use common::{treegen, write_files};
+use serde_json::{json, Value};
+
+/// 每个检查项恰好触发一次的描述
+fn spec() -> Value {
+    let long = "x".repeat(80);
+    // 13 层目录
+    let mut deep = json!({ "leaf.txt": "" });
+    for level in (1..=13).rev() {
+        deep = json!({ format!("d{level}"): deep });
+    }
+    json!({
+        "empty": {},
+        "src": { "Main.rs": "", "main.rs": "" },
+        "deep": deep,
+        "trailing.txt": "ok\nspace \n",
+        "mixed.py": "if x:\n\tpass\n    pass\n",
+        "CON.txt": "",
+        "a.txt": long,
+        "b.txt": long,
+        "used.txt": "{{name}}",
+    })
+}
+
+fn lint(dir: &std::path::Path, args: &[&str]) -> (Option<i32>, String, String) {
+    write_files(dir, &[("spec.json", &spec().to_string())]);
+    let output = treegen(dir)
+        .args(["lint", "spec.json", "--var", "name=x", "--var", "extra=y"])
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+const WARNINGS: &str = "\
+warning[windows-name]: CON.txt: 'CON' is a reserved device name on Windows
+warning[duplicate-content]: b.txt: same content as 'a.txt'
+warning[deep-nesting]: deep/d1/d2/d3/d4/d5/d6/d7/d8/d9/d10/d11/d12: nested deeper than 12 levels
+warning[empty-dir]: empty: directory is empty
+warning[mixed-indent]: mixed.py: indents with tabs (line 2) and spaces (line 3)
+warning[case-conflict]: src/main.rs: differs from 'Main.rs' only by case
+warning[trailing-whitespace]: trailing.txt: trailing whitespace on line 2
+warning[unused-var]: variable 'extra' is not referenced by the spec
+";
+
+#[test]
+fn reports_every_lint_as_a_warning() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (code, stdout, _) = lint(tmp.path(), &[]);
+    assert_eq!(code, Some(0));
+    assert_eq!(stdout, format!("{WARNINGS}⚠️ 8 warning(s)\n"));
+}
+
+#[test]
+fn denied_lints_are_errors() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (code, stdout, stderr) = lint(tmp.path(), &["--deny", "empty-dir", "--deny", "unused-var"]);
+    assert_eq!(code, Some(1));
+    assert!(
+        stdout.contains("error[empty-dir]: empty: directory is empty\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("error[unused-var]: variable 'extra'"),
+        "{stdout}"
+    );
+    // 其余仍是警告
+    assert!(stdout.contains("warning[case-conflict]: "), "{stdout}");
+    assert!(stderr.contains("2 denied lint(s) found"), "{stderr}");
+}
+
+#[test]
+fn denying_an_absent_lint_passes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("clean.yaml", "src:\n  main.rs: 'fn main() {}\\n'\n")],
+    );
+    let output = treegen(dir)
+        .args(["lint", "clean.yaml", "--deny", "empty-dir"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "✅ No problems found\n"
+    );
+}
+
+#[test]
+fn unknown_lint_names_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (code, _, stderr) = lint(tmp.path(), &["--deny", "no-such-lint"]);
+    assert_eq!(code, Some(2));
+    assert!(
+        stderr.contains("possible values: empty-dir, case-conflict"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,61 @@
+//! --list：按创建顺序打印计划生成的相对路径，标准输出中只有路径
+
+mod common;
+
+use common::{treegen, write_files};
+
+const SPEC: &str = "app:
+  src:
+    main.rs: \"fn main() {}\\n\"
+    gen:
+      a.rs: \"\"
+  target:
+    x: \"\"
+  README.md: hi
+";
+
+fn list(dir: &std::path::Path, args: &[&str]) -> (String, String) {
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn lists_paths_in_creation_order_without_logs() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let (stdout, stderr) = list(dir, &["--dry-run", "--list", "--verbose"]);
+    assert_eq!(
+        stdout,
+        "app/\napp/README.md\napp/src/\napp/src/gen/\napp/src/gen/a.rs\napp/src/main.rs\napp/target/\napp/target/x\n"
+    );
+    assert!(stderr.contains("Output directory:"), "{}", stderr);
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn kind_exclude_and_print0() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let (stdout, _) = list(dir, &["--list=dirs", "--exclude", "app/target"]);
+    assert_eq!(stdout, "app/\napp/src/\napp/src/gen/\n");
+    let (stdout, _) = list(dir, &["--list=files", "--exclude", "**/gen", "--print0"]);
+    assert_eq!(stdout, "app/README.md\0app/src/main.rs\0app/target/x\0");
+
+    // Markdown 树中的文件没有内容，--no-touch 时不会创建
+    write_files(dir, &[("tree.md", "app/\n├── src/\n└── main.rs\n")]);
+    let output = treegen(dir)
+        .args(["tree.md", "--list", "--no-touch"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "app/\napp/src/\n");
+}
@@ -0,0 +1,80 @@
+//! 内容中字面的 `{{`：GitHub Actions 的 `${{ }}` 原样保留，`\{{` 转义，`raw: true` 整个内容不替换
+mod common;
+
+use common::{read, treegen, write_files};
+use serde_json::json;
+
+const WORKFLOW: &str = include_str!("fixtures/github-ci.yml");
+const HELM: &str = include_str!("fixtures/helm-deployment.yaml");
+
+fn generate(dir: &std::path::Path, spec: serde_json::Value) -> std::process::Output {
+    write_files(dir, &[("spec.json", &spec.to_string())]);
+    treegen(dir)
+        .args(["spec.json", "--out", "out", "--var", "name=web"])
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn github_actions_expressions_are_kept() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let output = generate(
+        dir,
+        json!({ ".github": { "workflows": { "{{name}}.yml": WORKFLOW } } }),
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/.github/workflows/web.yml"), WORKFLOW);
+}
+
+#[test]
+fn raw_files_are_written_as_is_but_names_are_substituted() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    // Helm 模板中的 {{ end }} 看起来像占位符，不加 raw 时报未定义的变量
+    let output = generate(dir, json!({ "templates": { "deployment.yaml": HELM } }));
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Undefined template variable 'end'"),
+        "{stderr}"
+    );
+
+    let output = generate(
+        dir,
+        json!({ "{{name}}": { "templates": {
+            "deployment.yaml": { "type": "file", "raw": true, "content": HELM }
+        } } }),
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/web/templates/deployment.yaml"), HELM);
+}
+
+#[test]
+fn backslash_escapes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let content = r"runs-on: \{{matrix.os}}
+image: \{{ .Values.image }}
+path: C:\\{{name}}\bin
+dir: C:\dir
+";
+    let output = generate(dir, json!({ "a.txt": content }));
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        read(dir, "out/a.txt"),
+        r"runs-on: {{matrix.os}}
+image: {{ .Values.image }}
+path: C:\web\bin
+dir: C:\dir
+"
+    );
+
+    // raw 时反斜杠同样原样保留
+    let output = generate(
+        dir,
+        json!({ "a.txt": { "type": "file", "raw": true, "content": content } }),
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/a.txt"), content);
+}
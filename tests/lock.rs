@@ -0,0 +1,117 @@
+//! 输出目录的锁：同时往同一目录生成的 treegen 依次进行，被占用时等待 --lock-timeout 后报错，
+//! 同一主机上已退出的进程留下的锁被抢占
+mod common;
+
+use common::{read, treegen, write_files};
+use std::{
+    sync::{Arc, Barrier},
+    thread,
+    time::{Duration, Instant},
+};
+use treegen::{
+    generate,
+    lock::{OutputLock, LOCK_FILE},
+    parse_yaml,
+};
+
+#[test]
+fn concurrent_generations_are_serialized() {
+    let out = tempfile::tempdir().unwrap();
+    let barrier = Arc::new(Barrier::new(2));
+    let runs: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|name| {
+            let out = out.path().to_path_buf();
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                let spec = parse_yaml(&format!("shared.txt: {name}\n{name}.txt: x\n")).unwrap();
+                barrier.wait();
+                let lock = OutputLock::acquire(&out, Duration::from_secs(10)).unwrap();
+                let start = Instant::now();
+                generate(&spec.root, &out, &Default::default()).unwrap();
+                thread::sleep(Duration::from_millis(300));
+                let end = Instant::now();
+                drop(lock);
+                (start, end)
+            })
+        })
+        .collect();
+    let mut spans: Vec<_> = runs.into_iter().map(|run| run.join().unwrap()).collect();
+    spans.sort();
+    assert!(spans[0].1 <= spans[1].0, "{spans:?}");
+    assert!(out.path().join("a.txt").exists() && out.path().join("b.txt").exists());
+    assert!(!out.path().join(LOCK_FILE).exists());
+}
+
+#[test]
+fn locked_output_fails_after_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "a.txt: hi\n"), ("out/old.txt", "")]);
+    let lock = OutputLock::acquire(&dir.join("out"), Duration::ZERO).unwrap();
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--lock-timeout", "0"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!("is locked by PID {} on ", std::process::id())),
+        "{stderr}"
+    );
+    assert!(!dir.join("out/a.txt").exists());
+
+    // dry-run 不加锁
+    treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "out",
+            "--dry-run",
+            "--lock-timeout",
+            "0",
+        ])
+        .assert()
+        .success();
+
+    drop(lock);
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--lock-timeout", "0"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/a.txt"), "hi");
+    assert!(!dir.join("out").join(LOCK_FILE).exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn stale_lock_of_exited_process_is_stolen() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "a.txt: hi\n"), ("out/old.txt", "")]);
+    // 锁仍被持有，但记录的是同一主机上一个已经退出的进程
+    let _lock = OutputLock::acquire(&dir.join("out"), Duration::ZERO).unwrap();
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    exited.wait().unwrap();
+    let path = dir.join("out").join(LOCK_FILE);
+    let record = std::fs::read_to_string(&path).unwrap().replace(
+        &format!("pid: {}\n", std::process::id()),
+        &format!("pid: {}\n", exited.id()),
+    );
+    std::fs::write(&path, record).unwrap();
+
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--lock-timeout", "0"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "Warning: removed a stale lock on 'out' left by PID {} on ",
+            exited.id()
+        )),
+        "{stderr}"
+    );
+    assert_eq!(read(dir, "out/a.txt"), "hi");
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::{treegen, write_files};
+use serde_json::Value;
+
+const SPEC: &str = "src:\n  main.rs: x\ndocs:\n  a.md: y\n";
+
+#[test]
+fn json_log_has_an_event_per_path_inside_top_level_spans() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .args(["--log-level", "info", "--log-format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // 标准输出不变
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("\"level\""), "{}", stdout);
+    let events: Vec<Value> = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let main = events
+        .iter()
+        .find(|e| e["fields"]["path"] == "src/main.rs")
+        .unwrap();
+    assert_eq!(main["level"], "INFO");
+    assert_eq!(main["fields"]["kind"], "file");
+    assert_eq!(main["fields"]["outcome"], "created");
+    assert_eq!(main["span"]["name"], "dir");
+    assert_eq!(main["span"]["path"], "src");
+    assert_eq!(main["spans"][0]["name"], "generate");
+    assert_eq!(events.len(), 4);
+}
+
+#[test]
+fn rust_log_selects_debug_parse_events() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["lint", "spec.yaml"])
+        .env("RUST_LOG", "treegen=debug")
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("parse{path=spec.yaml}"), "{}", stderr);
+    assert!(stderr.contains("parsed"), "{}", stderr);
+
+    // --log-level 优先于 RUST_LOG
+    let output = treegen(dir)
+        .args(["lint", "spec.yaml", "--log-level", "warn"])
+        .env("RUST_LOG", "treegen=debug")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+}
+
+#[test]
+fn no_log_output_without_a_level() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Create file: out/src/main.rs"));
+}
@@ -0,0 +1,93 @@
+//! `exa -T`/`lsd --tree` 的输出（可带图标与 -l 的列）按 Markdown 树解析
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{parse_markdown, Node, NodeType};
+
+/// 树中每个节点的（路径，是否目录）
+fn shape(root: &Node) -> Vec<(String, bool)> {
+    root.iter()
+        .skip(1)
+        .map(|(path, node)| {
+            let path = path.to_string_lossy().replace('\\', "/");
+            (
+                path.trim_end_matches('/').to_string(),
+                node.node_type == NodeType::Dir,
+            )
+        })
+        .collect()
+}
+
+fn expected(docs_is_dir: bool) -> Vec<(String, bool)> {
+    [
+        ("Cargo.toml", false),
+        ("docs", docs_is_dir),
+        ("README.md", false),
+        ("src", true),
+        ("src/bin", true),
+        ("src/bin/tool.rs", false),
+        ("src/lib.rs", false),
+        ("src/main.rs", false),
+    ]
+    .iter()
+    .map(|(path, dir)| (path.to_string(), *dir))
+    .collect()
+}
+
+#[test]
+fn tree_output_is_parsed() {
+    // 没有 -l 时只能从子项判断目录，空目录 docs 被当作文件
+    for fixture in [
+        include_str!("fixtures/exa-tree.txt"),
+        include_str!("fixtures/lsd-tree.txt"),
+    ] {
+        let spec = parse_markdown(fixture).unwrap();
+        assert_eq!(shape(&spec.root), expected(false), "{fixture}");
+    }
+}
+
+#[test]
+fn long_listing_columns_are_stripped() {
+    for fixture in [
+        include_str!("fixtures/exa-long-tree.txt"),
+        include_str!("fixtures/lsd-long-tree.txt"),
+    ] {
+        let spec = parse_markdown(fixture).unwrap();
+        assert_eq!(shape(&spec.root), expected(true), "{fixture}");
+    }
+}
+
+#[test]
+fn named_root_and_standard_trees_are_unchanged() {
+    let spec = parse_markdown("app\n├── a.txt\n└── src\n   └── main.rs\n").unwrap();
+    let app = &spec.root.children[0];
+    assert_eq!(app.node_type, NodeType::Dir);
+    assert_eq!(app.find("src/main.rs").unwrap().node_type, NodeType::File);
+    // 标准树中没有 `/` 的名称仍是文件，其下还有行时报错
+    let err = parse_markdown(".\n├── a\n│   └── b\n└── c\n").unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("'.' at line 1 is a file but line 2"),
+        "{err}"
+    );
+}
+
+#[test]
+fn tree_output_is_detected_and_generated() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[("tree.txt", include_str!("fixtures/lsd-long-tree.txt"))],
+    );
+    let output = treegen(dir)
+        .args(["tree.txt", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Read as exa/lsd tree output"), "{stdout}");
+    assert!(dir.join("out/docs").is_dir());
+    assert!(dir.join("out/src/bin/tool.rs").is_file());
+}
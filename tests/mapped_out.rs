@@ -0,0 +1,89 @@
+//! SPEC=OUTDIR：每个输入生成到自己的输出目录，未映射的输入合并后生成到 --out
+mod common;
+
+use common::{read, treegen, write_files};
+use std::path::Path;
+
+fn specs(dir: &Path) {
+    write_files(
+        dir,
+        &[
+            ("a.yaml", "a.txt: a\n"),
+            ("b.yaml", "b.txt: b\n"),
+            ("c.yaml", "c.txt: c\n"),
+        ],
+    );
+}
+
+#[test]
+fn mapped_inputs_generate_into_their_own_directories() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    specs(dir);
+    treegen(dir)
+        .args(["a.yaml=one", "b.yaml=two", "c.yaml", "--out", "base"])
+        .assert()
+        .success()
+        .stdout(
+            "✅ Generated the file tree in 'one'\n\
+             ✅ Generated the file tree in 'two'\n\
+             ✅ Generated the file tree in 'base'\n",
+        );
+    assert_eq!(read(dir, "one/a.txt"), "a");
+    assert_eq!(read(dir, "two/b.txt"), "b");
+    assert_eq!(read(dir, "base/c.txt"), "c");
+    assert!(!dir.join("one/b.txt").exists());
+    assert!(!dir.join("base/a.txt").exists());
+
+    // 映射到同一目录的输入合并后生成一次
+    treegen(dir)
+        .args(["a.yaml=same", "b.yaml=same"])
+        .assert()
+        .success()
+        .stdout("✅ Generated the file tree in 'same'\n");
+    assert_eq!(read(dir, "same/a.txt"), "a");
+    assert_eq!(read(dir, "same/b.txt"), "b");
+}
+
+#[test]
+fn nested_output_directories_need_allow_nested_out() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    specs(dir);
+    let output = treegen(dir)
+        .args(["a.yaml=base/inner", "b.yaml", "--out", "base"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is inside"), "{stderr}");
+    assert!(
+        stderr.contains("pass --allow-nested-out to allow nested output directories"),
+        "{stderr}"
+    );
+    assert!(!dir.join("base").exists());
+
+    treegen(dir)
+        .args(["a.yaml=base/inner", "b.yaml", "--out", "base"])
+        .arg("--allow-nested-out")
+        .assert()
+        .success();
+    assert_eq!(read(dir, "base/inner/a.txt"), "a");
+    assert_eq!(read(dir, "base/b.txt"), "b");
+}
+
+#[test]
+fn empty_spec_or_outdir_is_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    specs(dir);
+    for arg in ["a.yaml=", "=out"] {
+        let output = treegen(dir).arg(arg).output().unwrap();
+        assert_eq!(output.status.code(), Some(2));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("expected SPEC=OUTDIR, got '{arg}'")),
+            "{stderr}"
+        );
+    }
+}
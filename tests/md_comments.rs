@@ -0,0 +1,55 @@
+//! Markdown 树中的整行注释与只有连接线的续行：跳过它们，不影响后续行的层级
+
+use treegen::{parse_markdown, parse_str, Format, ParseOptions};
+
+const COMMENTED: &str = include_str!("fixtures/commented.md");
+
+fn paths(root: &treegen::Node) -> Vec<String> {
+    root.iter()
+        .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn comments_and_continuations_are_skipped() {
+    let spec = parse_markdown(COMMENTED).unwrap();
+    assert_eq!(
+        paths(&spec.root),
+        [
+            "",
+            "app/",
+            "app/src/",
+            "app/src/main.rs",
+            "app/src/bin/",
+            "app/src/bin/tool.rs",
+            "app/src/lib.rs",
+            // 带连接符的行总是节点
+            "app/# notes.md",
+            "app/README.md",
+        ]
+    );
+}
+
+#[test]
+fn comment_prefixes_are_configurable() {
+    let opts = ParseOptions {
+        comment_prefixes: vec![";".to_string()],
+        ..Default::default()
+    };
+    let parsed = parse_str(
+        "tree.md",
+        "; comment\napp/\n│   ; nested\n└── #keep.txt\n",
+        Some(Format::Markdown),
+        &opts,
+    )
+    .unwrap();
+    assert_eq!(paths(&parsed.spec.root), ["", "app/", "app/#keep.txt"]);
+
+    // 没有前缀时没有注释
+    let opts = ParseOptions {
+        comment_prefixes: Vec::new(),
+        ..Default::default()
+    };
+    let parsed = parse_str("tree.md", "# title\n", Some(Format::Markdown), &opts).unwrap();
+    assert_eq!(paths(&parsed.spec.root), ["", "# title"]);
+}
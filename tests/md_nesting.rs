@@ -0,0 +1,66 @@
+//! Markdown 树中嵌套在文件下的行：默认报错并指出两行，--infer-dirs 时把文件改为目录
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{parse_markdown, parse_str, Format, NodeType, ParseOptions};
+
+const TREE: &str = "app/\n├── src\n│   └── main.rs\n└── README.md\n";
+
+#[test]
+fn nested_lines_under_a_file_are_an_error() {
+    let err = parse_markdown(TREE).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "'src' at line 2 is a file but line 3 is nested beneath it — add a trailing '/' or pass --infer-dirs"
+    );
+    // 显式的 [f] 不会被推断覆盖
+    let opts = ParseOptions {
+        infer_dirs: true,
+        ..Default::default()
+    };
+    let err = parse_str(
+        "tree.md",
+        "app/\n├── src [f]\n│   └── main.rs\n",
+        Some(Format::Markdown),
+        &opts,
+    )
+    .unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "Failed to parse Markdown in 'tree.md': 'src' at line 2 is marked as a file with [f] but line 3 is nested beneath it"
+    );
+}
+
+#[test]
+fn infer_dirs_turns_the_file_into_a_directory() {
+    let opts = ParseOptions {
+        infer_dirs: true,
+        ..Default::default()
+    };
+    let parsed = parse_str("tree.md", TREE, Some(Format::Markdown), &opts).unwrap();
+    let src = parsed.spec.root.find("app/src").unwrap();
+    assert_eq!(src.node_type, NodeType::Dir);
+    assert_eq!(src.children[0].name, "main.rs");
+    assert!(parsed.spec.root.find("app/README.md").is_some());
+    assert_eq!(
+        parsed.notes,
+        ["Line 2: 'src' has nested lines; read as a directory"]
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("tree.md", TREE)]);
+    let output = treegen(dir)
+        .args(["tree.md", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!dir.join("out/app").exists());
+    let output = treegen(dir)
+        .args(["tree.md", "--out", "out", "--infer-dirs"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(dir.join("out/app/src/main.rs").is_file());
+}
@@ -0,0 +1,62 @@
+//! Markdown 树的逐行扫描：宽度不一的连接线、连接符后的空白、不间断空格与制表符缩进，错误中给出列
+use treegen::parse_markdown;
+
+fn paths(md: &str) -> Vec<String> {
+    let spec = parse_markdown(md).unwrap_or_else(|e| panic!("{e:#}"));
+    spec.root
+        .iter()
+        .skip(1)
+        .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+        .collect()
+}
+
+const EXPECTED: [&str; 5] = [
+    "app/",
+    "app/src/",
+    "app/src/main.rs",
+    "app/src/lib.rs",
+    "app/README.md",
+];
+
+#[test]
+fn unusual_indentation_and_connectors() {
+    // 连接线后的空格多少不一，连接符后没有空格或有多个空格
+    // （连接符都在第 3 列时是 exa/lsd 的输出，见 ls_tree）
+    assert_eq!(
+        paths("app/\n├── src/\n│ ├── main.rs\n│     └─lib.rs\n└──README.md\n"),
+        EXPECTED
+    );
+    assert_eq!(
+        paths("app/\n├─── src/\n│   ├──   main.rs\n│   └── lib.rs\n└── README.md\n"),
+        EXPECTED
+    );
+    // GNU tree 的缩进中是不间断空格
+    assert_eq!(
+        paths(
+            "app/\n├── src/\n│\u{a0}\u{a0} ├── main.rs\n│\u{a0}\u{a0} └── lib.rs\n└── README.md\n"
+        ),
+        EXPECTED
+    );
+    // 制表符缩进，每个制表符一级
+    assert_eq!(
+        paths("app/\n\tsrc/\n\t\tmain.rs\n\t\tlib.rs\n\tREADME.md\n"),
+        EXPECTED
+    );
+    // 连接符之后的连接线属于名称
+    assert_eq!(paths("app/\n└── └── x\n"), ["app/", "app/└── x"]);
+}
+
+#[test]
+fn errors_point_at_the_column() {
+    let err = parse_markdown("app/\n│   └── /etc/passwd\n").unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("Line 2, column 9: '/etc/passwd'"),
+        "{err}"
+    );
+    let err = parse_markdown("app/\n\t\tsrc/ [f]\n").unwrap_err();
+    assert!(
+        err.to_string().starts_with("Line 2, column 3: 'src/'"),
+        "{err}"
+    );
+}
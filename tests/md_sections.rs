@@ -0,0 +1,131 @@
+//! 分节的 Markdown 描述：每个二级标题是一个以其 slug 命名的子目录，其下代码块中的树生成在该目录中
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{parse_markdown, parse_str, Format, MdStyle, ParseOptions};
+
+const DOC: &str = "# Architecture
+
+Overview, with a tree that belongs to no section:
+
+```
+ignored/
+```
+
+## Backend API
+
+```text
+src/
+├── main.rs
+└── db.rs
+```
+
+```bash
+cargo run
+```
+
+## Frontend
+
+```
+web/
+└── index.html
+```
+
+## Notes
+
+No tree here.
+
+## Backend API
+
+```
+Cargo.toml
+```
+";
+
+fn paths(root: &treegen::Node) -> Vec<String> {
+    root.iter()
+        .map(|(path, _)| path.to_string_lossy().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn headings_become_directories_and_duplicates_merge() {
+    let spec = parse_markdown(DOC).unwrap();
+    assert_eq!(
+        paths(&spec.root),
+        [
+            "",
+            "backend-api",
+            "backend-api/src/",
+            "backend-api/src/main.rs",
+            "backend-api/src/db.rs",
+            "backend-api/Cargo.toml",
+            "frontend",
+            "frontend/web/",
+            "frontend/web/index.html",
+        ]
+    );
+}
+
+#[test]
+fn section_filter_and_explicit_style() {
+    let opts = ParseOptions {
+        sections: vec!["Frontend".to_string()],
+        ..Default::default()
+    };
+    let parsed = parse_str("arch.md", DOC, Some(Format::Markdown), &opts).unwrap();
+    assert_eq!(
+        paths(&parsed.spec.root),
+        ["", "frontend", "frontend/web/", "frontend/web/index.html"]
+    );
+
+    let opts = ParseOptions {
+        sections: vec!["database".to_string()],
+        ..Default::default()
+    };
+    let err = parse_str("arch.md", DOC, Some(Format::Markdown), &opts).unwrap_err();
+    assert_eq!(
+        format!("{:#}", err),
+        "Failed to parse Markdown in 'arch.md': Section 'database' not found; the spec has backend-api, frontend"
+    );
+
+    // 没有代码块的文档自动检测为一棵树，显式指定时按分节解析
+    let opts = ParseOptions {
+        md_style: MdStyle::Sections,
+        ..Default::default()
+    };
+    let parsed = parse_str("a.md", "## Only\n", Some(Format::Markdown), &opts).unwrap();
+    assert_eq!(paths(&parsed.spec.root), [""]);
+}
+
+#[test]
+fn cli_generates_one_section() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("arch.md", DOC)]);
+    let output = treegen(dir)
+        .args(["arch.md", "--out", "out", "--section", "backend-api"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(dir.join("out/backend-api/src/main.rs").is_file());
+    assert!(dir.join("out/backend-api/Cargo.toml").is_file());
+    assert!(!dir.join("out/frontend").exists());
+    assert!(!dir.join("out/ignored").exists());
+
+    let output = treegen(dir)
+        .args([
+            "arch.md",
+            "--out",
+            "tree",
+            "--md-style",
+            "tree",
+            "--section",
+            "x",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no '## ' headings"));
+}
@@ -0,0 +1,102 @@
+//! treegen merge：合并多个描述文件并写出一个描述，设置一并保留
+
+mod common;
+
+use common::{read, treegen, write_files};
+use treegen::{parse_file, parse_yaml, Format};
+
+const BASE: &str = "$vars: { name: demo, port: { type: int, default: 8080 } }
+$hooks: { post: [\"cargo fmt\"] }
+$rules: [{ match: \"**/*.sh\", mode: \"0o755\" }]
+app:
+  README.md: \"# {{name}}\\n\"
+  run.sh: \"echo `hi`\\n\"
+";
+
+const EXTRAS: &str = "{ app: { LICENSE: 'MIT', 'README.md': 'other' } }\n";
+
+#[test]
+fn merged_spec_round_trips_in_every_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("base.yaml", BASE), ("extras.json5", EXTRAS)]);
+    let expected = parse_yaml(BASE).unwrap();
+    for ext in ["yaml", "json", "toml", "json5"] {
+        let output = format!("combined.{}", ext);
+        let result = treegen(dir)
+            .args(["merge", "base.yaml", "extras.json5", "-o", &output])
+            .output()
+            .unwrap();
+        assert!(result.status.success(), "{:?}", result);
+        let merged = parse_file(&dir.join(&output)).unwrap();
+        let app = merged.root.find("app").unwrap();
+        assert_eq!(app.find("LICENSE").unwrap().content.as_deref(), Some("MIT"));
+        assert_eq!(
+            app.find("README.md").unwrap().content.as_deref(),
+            Some("other"),
+            "{}",
+            ext
+        );
+        assert_eq!(merged.vars, expected.vars, "{}", ext);
+        assert_eq!(merged.hooks, expected.hooks, "{}", ext);
+        assert_eq!(merged.rules, expected.rules, "{}", ext);
+    }
+}
+
+#[test]
+fn format_strategy_and_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("base.yaml", BASE), ("extras.json5", EXTRAS)]);
+
+    // 默认写到标准输出，--to 指定格式
+    let output = treegen(dir)
+        .args(["merge", "base.yaml", "extras.json5", "--to", "json"])
+        .args(["--on-duplicate", "first"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\n  \"$hooks\""), "{}", stdout);
+    let merged = Format::Json.parse(&stdout).unwrap();
+    assert_eq!(
+        merged
+            .root
+            .find("app/README.md")
+            .unwrap()
+            .content
+            .as_deref(),
+        Some("# {{name}}\n")
+    );
+
+    let output = treegen(dir)
+        .args([
+            "merge",
+            "base.yaml",
+            "extras.json5",
+            "--on-duplicate",
+            "error",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("'app/README.md' is defined in both 'base.yaml' and 'extras.json5'"));
+
+    // Markdown 树无法表示钩子
+    let output = treegen(dir)
+        .args(["merge", "base.yaml", "--to", "md"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("'$hooks' cannot be written to Markdown")
+    );
+
+    let output = treegen(dir)
+        .args(["merge", "base.yaml", "extras.json5", "-o", "base.yaml"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(read(dir, "base.yaml"), BASE);
+}
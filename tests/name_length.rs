@@ -0,0 +1,84 @@
+//! 超长的名称与路径在写入之前报错，列出节点路径、长度与上限
+mod common;
+
+use common::{treegen, write_files};
+
+#[test]
+fn long_names_fail_before_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let spec = format!("ok.txt: 'ok'\nsrc:\n  {}.rs: ''\n", "n".repeat(300));
+    write_files(dir, &[("app.yaml", &spec)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("1 path(s) too long"), "{stderr}");
+    assert!(
+        stderr.contains("name is 303 bytes, the limit is "),
+        "{stderr}"
+    );
+    assert!(!dir.join("out/ok.txt").exists());
+    assert!(!dir.join("out/src").exists());
+
+    // dry-run 在预检中报告
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Pre-flight checks failed"), "{stderr}");
+    assert!(stderr.contains("name is 303 bytes"), "{stderr}");
+}
+
+#[test]
+fn max_name_bytes_overrides_the_limit() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "abcdefghij.txt: 'x'\n")]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--max-name-bytes", "8"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("'abcdefghij.txt': name is 14 bytes, the limit is 8"),
+        "{stderr}"
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn full_path_length_includes_the_output_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    // 40 层 200 字节的目录，每段都合法，合起来超过任何平台的路径上限
+    let segment = "d".repeat(200);
+    let mut spec = String::new();
+    for depth in 0..40 {
+        spec.push_str(&format!("{}{}:\n", "  ".repeat(depth), segment));
+    }
+    spec.push_str(&format!("{}leaf.txt: ''\n", "  ".repeat(40)));
+    write_files(dir, &[("app.yaml", &spec)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    // 只列出最浅的超长路径，不重复列出其下的路径
+    assert!(stderr.contains("1 path(s) too long"), "{stderr}");
+    assert!(stderr.contains("full path is "), "{stderr}");
+    assert!(
+        stderr.contains("/out'") || stderr.contains("\\out'"),
+        "{stderr}"
+    );
+}
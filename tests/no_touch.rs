@@ -0,0 +1,67 @@
+//! --no-touch：没有内容的文件节点不生成（也不检查），显式的空内容照常生成
+
+mod common;
+
+use common::{read, treegen, write_files};
+use treegen::{generate, parse_markdown, parse_yaml, GenerateOptions, Outcome};
+
+#[test]
+fn content_less_files_are_skipped() {
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path();
+    let mut spec = parse_markdown("app/\n├── src/\n│   └── main.rs\n└── README.md\n").unwrap();
+    let extra = parse_yaml("app:\n  empty.txt: ''\n  notes.txt: hi\n").unwrap();
+    spec.root.merge(extra.root).unwrap();
+    let opts = GenerateOptions {
+        no_touch: true,
+        ..Default::default()
+    };
+    let stats = generate(&spec.root, out, &opts).unwrap();
+    assert_eq!((stats.dirs, stats.files, stats.skipped), (2, 2, 2));
+    assert!(out.join("app/src").is_dir());
+    assert!(!out.join("app/src/main.rs").exists());
+    assert!(!out.join("app/README.md").exists());
+    assert_eq!(read(out, "app/empty.txt"), "");
+    assert_eq!(read(out, "app/notes.txt"), "hi");
+    assert_eq!(
+        stats.outcomes[std::path::Path::new("app/README.md")],
+        Outcome::Skipped
+    );
+}
+
+#[test]
+fn no_touch_flag_with_check() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            (
+                "tree.md",
+                "docs/\n├── guide.md\n└── api/\n    └── index.md\n",
+            ),
+            ("out/docs/guide.md", "# Guide\n"),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["tree.md", "--out", "out", "--no-touch", "--lang", "zh"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("2 个没有内容的文件已跳过"), "{stdout}");
+    // 已有的文件不被清空，缺失的文件不被创建
+    assert_eq!(read(&dir.join("out"), "docs/guide.md"), "# Guide\n");
+    assert!(dir.join("out/docs/api").is_dir());
+    assert!(!dir.join("out/docs/api/index.md").exists());
+
+    // 没有内容的文件缺失或内容不同都不算不一致
+    treegen(dir)
+        .args(["tree.md", "--out", "out", "--no-touch", "--check"])
+        .assert()
+        .success();
+    treegen(dir)
+        .args(["tree.md", "--out", "out", "--check"])
+        .assert()
+        .code(1);
+}
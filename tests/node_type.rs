@@ -0,0 +1,59 @@
+//! 显式节点类型：Markdown 的 `[f]`/`[d]` 后缀与 YAML 的 `type` 优先于名称的推断
+
+use std::fs;
+use treegen::{generate, parse_markdown, parse_yaml, NodeType};
+
+#[test]
+fn markdown_markers_override_the_name() {
+    let spec = parse_markdown(
+        "app/\n├── README.md [d]\n│   └── index.md\n├── src [f]\n├── docs [d] #[full]\n└── lib/ [d]\n",
+    )
+    .unwrap();
+    let app = &spec.root.children[0];
+    let readme = app.find("README.md").unwrap();
+    assert_eq!(readme.node_type, NodeType::Dir);
+    assert_eq!(readme.children[0].name, "index.md");
+    let src = app.find("src").unwrap();
+    assert_eq!((src.name.as_str(), src.node_type), ("src", NodeType::File));
+    let docs = app.find("docs").unwrap();
+    assert_eq!(docs.node_type, NodeType::Dir);
+    assert!(docs.attrs.tags.contains("full"));
+    assert_eq!(app.find("lib").unwrap().node_type, NodeType::Dir);
+
+    let out = tempfile::tempdir().unwrap();
+    generate(&spec.root, out.path(), &Default::default()).unwrap();
+    assert!(out.path().join("app/README.md/index.md").is_file());
+    assert!(out.path().join("app/src").is_file());
+}
+
+#[test]
+fn markdown_file_marker_conflicts_with_slash() {
+    let err = parse_markdown("app/\n├── a.txt\n└── src/ [f]\n").unwrap_err();
+    let msg = format!("{:#}", err);
+    assert!(msg.contains("Line 3"), "{msg}");
+    assert!(msg.contains("src/"), "{msg}");
+}
+
+#[test]
+fn yaml_type_overrides_the_name() {
+    let spec = parse_yaml(
+        "README.md: { type: dir, children: { index.md: hi } }\nsrc: { type: file, content: '' }\n",
+    )
+    .unwrap();
+    assert_eq!(
+        spec.root.find("README.md").unwrap().node_type,
+        NodeType::Dir
+    );
+    assert_eq!(spec.root.find("src").unwrap().node_type, NodeType::File);
+
+    let out = tempfile::tempdir().unwrap();
+    generate(&spec.root, out.path(), &Default::default()).unwrap();
+    assert_eq!(
+        fs::read_to_string(out.path().join("README.md/index.md")).unwrap(),
+        "hi"
+    );
+    assert!(out.path().join("src").is_file());
+
+    let err = parse_yaml("'src/': { type: file }\n").unwrap_err();
+    assert!(format!("{:#}", err).contains("ends with '/'"), "{err:#}");
+}
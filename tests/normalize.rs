@@ -0,0 +1,81 @@
+//! --normalize：名称的 Unicode 规范化（é 的组合形式 U+00E9 与分解形式 e + U+0301）
+mod common;
+
+use common::{treegen, write_files};
+use std::fs;
+use std::path::Path;
+
+const COMPOSED: &str = "caf\u{e9}.txt";
+const DECOMPOSED: &str = "cafe\u{301}.txt";
+
+fn names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn names_are_composed_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", &format!("{}: 'x'\n", DECOMPOSED))]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(names(&dir.join("out")), [COMPOSED]);
+}
+
+// macOS 的 APFS/HFS+ 会自行处理名称的规范化形式，只在保存原始字节的文件系统上比较
+#[cfg(not(target_os = "macos"))]
+#[test]
+fn nfd_and_none_keep_the_requested_form() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", &format!("{}: 'x'\n", COMPOSED))]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "nfd", "--normalize", "nfd"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(names(&dir.join("nfd")), [DECOMPOSED]);
+
+    let spec = format!("{}: 'a'\n{}: 'b'\n", COMPOSED, DECOMPOSED);
+    write_files(dir, &[("both.yaml", &spec)]);
+    let output = treegen(dir)
+        .args(["both.yaml", "--out", "none", "--normalize", "none"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(names(&dir.join("none")).len(), 2);
+}
+
+#[test]
+fn both_spellings_are_caught_as_duplicates() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("a.yaml", &format!("{}: 'a'\n", COMPOSED)),
+            ("b.md", &format!("└── {}\n", DECOMPOSED)),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["a.yaml", "b.md", "--out", "out", "--on-duplicate", "error"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!(
+            "'{}' is defined in both 'a.yaml' and 'b.md'",
+            COMPOSED
+        )),
+        "{stderr}"
+    );
+}
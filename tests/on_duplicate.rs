@@ -0,0 +1,39 @@
+//! --on-duplicate：多个输入定义同一路径时的处理
+
+mod common;
+
+use common::{read, treegen, write_files};
+
+#[test]
+fn rename_keeps_both_and_reports_on_stderr() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("a.yaml", "config.yaml: a\n"),
+            ("b.yaml", "config.yaml: b\n"),
+        ],
+    );
+    let output = treegen(dir)
+        .args([
+            "a.yaml",
+            "b.yaml",
+            "--out",
+            "out",
+            "--on-duplicate",
+            "rename",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/config.yaml"), "a");
+    assert_eq!(read(dir, "out/config_1.yaml"), "b");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("Renamed"), "{stdout}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Renamed duplicate 'config.yaml' from 'b.yaml' to 'config_1.yaml'"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r#"app:
+  README.md: hello
+  logo.png: { type: copy, from: ./assets/logo.png, optional: true }
+"#;
+
+#[test]
+fn optional_copy_with_missing_source_warns() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--summary-json", "summary.json"])
+        .args(["--lang", "zh"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.matches("Warning: optional").count(), 1, "{stderr}");
+    assert!(stderr.contains("app/logo.png"), "{stderr}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1 个可选路径"), "{stdout}");
+    assert_eq!(read(dir, "out/app/README.md"), "hello");
+    assert!(!dir.join("out/app/logo.png").exists());
+
+    let summary: serde_json::Value = serde_json::from_str(&read(dir, "summary.json")).unwrap();
+    assert_eq!(summary["warnings"].as_array().unwrap().len(), 1);
+    assert!(summary["failures"].as_array().unwrap().is_empty());
+
+    // 预检与 --check 同样不要求可选节点存在
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--dry-run"])
+        .assert()
+        .success();
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--check"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn required_copy_with_missing_source_fails() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", &SPEC.replace(", optional: true", ""))]);
+    treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn optional_is_still_validated() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "a: { type: copy, from: ./x, optional: true, mode: '0o644' }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Field 'mode' does not apply"), "{stderr}");
+}
@@ -0,0 +1,66 @@
+//! --out-mode：只有 treegen 新建的输出目录各级才设置权限
+#![cfg(unix)]
+
+mod common;
+
+use common::{read, treegen, write_files};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+fn mode(path: &Path) -> u32 {
+    path.metadata().unwrap().permissions().mode() & 0o7777
+}
+
+#[test]
+fn only_created_components_get_the_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "a.txt: 'a'\n")]);
+    fs::create_dir(dir.join("srv")).unwrap();
+    fs::set_permissions(dir.join("srv"), fs::Permissions::from_mode(0o755)).unwrap();
+    let output = treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "srv/app/current",
+            "--out-mode",
+            "0o750",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mode(&dir.join("srv")), 0o755);
+    assert_eq!(mode(&dir.join("srv/app")), 0o750);
+    assert_eq!(mode(&dir.join("srv/app/current")), 0o750);
+    assert_eq!(read(dir, "srv/app/current/a.txt"), "a");
+}
+
+#[test]
+fn dry_run_lists_components_to_create() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "a.txt: 'a'\n")]);
+    let output = treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "srv/app",
+            "--out-mode",
+            "750",
+            "--dry-run",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("[Dry-Run] Create output directory: srv (mode 0o750)"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("[Dry-Run] Create output directory: srv/app (mode 0o750)"),
+        "{stdout}"
+    );
+    assert!(!dir.join("srv").exists());
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = "README.md: \"# {{name}}\\n\"\n";
+
+#[test]
+fn out_path_is_substituted_from_var_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("spec.yaml", SPEC), ("vars.yaml", "name: billing\n")],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--var-file", "vars.yaml"])
+        .args(["--out", "services/{{name}}", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "services/billing/README.md"), "# billing\n");
+    assert!(!dir.join("services/{{name}}").exists());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("services/billing"), "{}", stdout);
+    assert!(!stdout.contains("{{name}}"), "{}", stdout);
+}
+
+#[test]
+fn var_overrides_var_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("spec.yaml", SPEC),
+            ("vars.json", r#"{"name": "billing", "port": 8080}"#),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--var-file", "vars.json", "--var", "name=auth"])
+        .args(["--out", "services/{{name|upper}}"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "services/AUTH/README.md"), "# auth\n");
+    assert!(!dir.join("services/BILLING").exists());
+}
+
+#[test]
+fn undefined_out_var_fails_before_creating_anything() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "a.txt: x\n")]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "services/{{service}}"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'service'"), "{}", stderr);
+    assert!(stderr.contains("--out"), "{}", stderr);
+    assert!(!dir.join("services").exists());
+}
+
+#[test]
+fn var_file_must_be_a_mapping() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC), ("vars.yaml", "- billing\n")]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--var-file", "vars.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("vars.yaml"), "{}", stderr);
+    assert!(!dir.join("out").exists());
+}
@@ -0,0 +1,83 @@
+//! 多个输入并行解析：结果保持输入顺序，所有失败的输入一次报告
+
+mod common;
+
+use common::{treegen, write_files};
+use std::path::{Path, PathBuf};
+use treegen::parse_files;
+
+/// 在 `dir` 下写 `count` 个描述文件 `specN.yaml`，`invalid` 中的编号写成无法解析的内容
+fn specs(dir: &Path, count: usize, invalid: &[usize]) -> Vec<PathBuf> {
+    (0..count)
+        .map(|i| {
+            let name = format!("spec{}.yaml", i);
+            let content = if invalid.contains(&i) {
+                "broken: [unclosed\n".to_string()
+            } else {
+                format!("file{}.txt: 'from {}'\n", i, i)
+            };
+            write_files(dir, &[(&name, &content)]);
+            dir.join(name)
+        })
+        .collect()
+}
+
+#[test]
+fn results_keep_the_input_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let inputs = specs(dir.path(), 12, &[]);
+    let merged = parse_files(&inputs).unwrap();
+    let expected: Vec<_> = (0..12).map(|i| format!("file{}.txt", i)).collect();
+    let names: Vec<_> = merged.root.children.iter().map(|c| &c.name).collect();
+    assert_eq!(names, expected.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn every_failing_input_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let inputs = specs(dir.path(), 12, &[2, 7, 11]);
+    let err = parse_files(&inputs).unwrap_err().to_string();
+    assert!(
+        err.starts_with("Failed to parse 3 of 12 input files:\n"),
+        "{err}"
+    );
+    let lines: Vec<_> = err.lines().filter(|l| l.starts_with("  - ")).collect();
+    assert_eq!(lines.len(), 3, "{err}");
+    for (line, i) in lines.iter().zip([2, 7, 11]) {
+        let name = dir.path().join(format!("spec{}.yaml", i));
+        assert!(
+            line.starts_with(&format!("  - Failed to parse YAML in '{}'", name.display())),
+            "{line}"
+        );
+    }
+
+    // 只有一个失败时原样返回它的错误
+    let inputs = specs(dir.path(), 3, &[1]);
+    let err = parse_files(&inputs).unwrap_err();
+    let name = dir.path().join("spec1.yaml");
+    assert_eq!(
+        err.to_string(),
+        format!("Failed to parse YAML in '{}'", name.display())
+    );
+}
+
+#[test]
+fn cli_reports_all_failures_and_writes_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    specs(dir, 4, &[0, 3]);
+    let output = treegen(dir)
+        .args(["spec0.yaml", "spec1.yaml", "spec2.yaml", "spec3.yaml"])
+        .args(["--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Failed to parse 2 of 4 input files"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("spec0.yaml"), "{stderr}");
+    assert!(stderr.contains("spec3.yaml"), "{stderr}");
+    assert!(!dir.join("out").exists());
+}
@@ -0,0 +1,88 @@
+//! 路径列表格式（.paths/.list，`git ls-files`、`find` 的输出）
+
+mod common;
+
+use common::{treegen, write_files};
+use treegen::{parse_paths, Format, NodeType};
+
+#[test]
+fn paths_build_a_tree() {
+    let spec = parse_paths(
+        ".\n./src/main.rs\nsrc/bin/tool.rs\n./src/main.rs\ndocs/\nsrc\nvendor\nvendor/lib.rs\r\n\nREADME.md\n",
+    )
+    .unwrap();
+    let shape: Vec<(String, NodeType)> = spec
+        .root
+        .iter()
+        .skip(1)
+        .map(|(path, node)| (path.to_string_lossy().replace('\\', "/"), node.node_type))
+        .collect();
+    assert_eq!(
+        shape,
+        [
+            ("src".to_string(), NodeType::Dir),
+            ("src/main.rs".to_string(), NodeType::File),
+            // 中间目录自动补齐
+            ("src/bin".to_string(), NodeType::Dir),
+            ("src/bin/tool.rs".to_string(), NodeType::File),
+            ("docs".to_string(), NodeType::Dir),
+            // 先作为文件出现，之后有子项
+            ("vendor".to_string(), NodeType::Dir),
+            ("vendor/lib.rs".to_string(), NodeType::File),
+            ("README.md".to_string(), NodeType::File),
+        ]
+    );
+    assert!(spec.root.find("README.md").unwrap().content.is_none());
+}
+
+#[test]
+fn escaping_paths_are_rejected() {
+    let err = parse_paths("a.txt\nsrc/../../etc/passwd\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Line 2: '..' is not allowed in 'src/../../etc/passwd'"
+    );
+    let err = parse_paths("/etc/passwd\n").unwrap_err();
+    assert!(err.to_string().contains("must be a relative path"), "{err}");
+}
+
+#[test]
+fn paths_round_trip() {
+    let spec = treegen::parse_yaml("src:\n  main.rs: ''\n  util: {}\nREADME.md: hi\n").unwrap();
+    let list = spec.root.to_paths().unwrap();
+    assert_eq!(list, "README.md\nsrc/\nsrc/main.rs\nsrc/util/\n");
+    let back = Format::Paths.parse(&list).unwrap();
+    assert_eq!(back.root.to_paths().unwrap(), list);
+}
+
+#[test]
+fn path_lists_from_files_and_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("repo.list", "src/main.rs\nCargo.toml\n")]);
+    treegen(dir)
+        .args(["repo.list", "--out", "from-file"])
+        .assert()
+        .success();
+    assert!(dir.join("from-file/src/main.rs").is_file());
+
+    treegen(dir)
+        .args(["-", "--format", "paths", "--out", "from-stdin"])
+        .write_stdin("./docs/\n./docs/index.md\n")
+        .assert()
+        .success();
+    assert!(dir.join("from-stdin/docs/index.md").is_file());
+
+    // FORMAT:- 与 -=OUTDIR 同样可用；标准输入只能是唯一的输入
+    treegen(dir)
+        .args(["paths:-=mapped"])
+        .write_stdin("a/b.txt\n")
+        .assert()
+        .success();
+    assert!(dir.join("mapped/a/b.txt").is_file());
+    treegen(dir)
+        .args(["-", "repo.list", "--format", "paths"])
+        .write_stdin("x\n")
+        .assert()
+        .failure();
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::{treegen, write_files};
+use std::fs;
+
+const SPEC: &str = r#"data:
+  zero.bin: { type: file, size: 2KiB }
+  sparse.img: { type: file, size: "1 MiB", fill: sparse }
+  a.bin: { type: file, size: 5000, fill: random }
+  b.bin: { type: file, size: 5000, fill: random }
+"#;
+
+#[test]
+fn placeholder_files_have_the_declared_size() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args([
+            "spec.yaml",
+            "--out",
+            "out",
+            "--seed",
+            "42",
+            "--verify-writes",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let zero = fs::read(dir.join("out/data/zero.bin")).unwrap();
+    assert_eq!(zero.len(), 2048);
+    assert!(zero.iter().all(|byte| *byte == 0));
+    let sparse = fs::read(dir.join("out/data/sparse.img")).unwrap();
+    assert_eq!(sparse.len(), 1 << 20);
+    assert!(sparse.iter().all(|byte| *byte == 0));
+    let a = fs::read(dir.join("out/data/a.bin")).unwrap();
+    let b = fs::read(dir.join("out/data/b.bin")).unwrap();
+    assert_eq!(a.len(), 5000);
+    assert_ne!(a, b);
+
+    // 同一种子重新生成的内容相同
+    fs::remove_dir_all(dir.join("out")).unwrap();
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--seed", "42"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read(dir.join("out/data/a.bin")).unwrap(), a);
+
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--check"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn dry_run_shows_sizes_and_max_bytes_counts_them() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("sparse.img (1.0 MiB, sparse)"), "{stdout}");
+    assert!(!dir.join("out").exists());
+
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--max-bytes", "1MiB"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("more than --max-bytes 1.0 MiB"), "{stderr}");
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn size_and_content_conflict() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("spec.yaml", "a.bin: { type: file, size: 10, content: x }\n")],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("has both 'size' and 'content'"), "{stderr}");
+}
+
+#[test]
+fn sizes_beyond_the_free_space_are_refused_before_writing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "app:\n  a.txt: x\n  huge.bin: { type: file, size: 99999TiB }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Not enough disk space on "), "{stderr}");
+    assert!(!dir.join("out").exists());
+
+    // 稀疏文件不占用空间，不受影响
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "huge.img: { type: file, size: 100GiB, fill: sparse }\n",
+        )],
+    );
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .assert()
+        .success();
+    assert_eq!(
+        fs::metadata(dir.join("out/huge.img")).unwrap().len(),
+        100 << 30
+    );
+}
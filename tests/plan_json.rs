@@ -0,0 +1,99 @@
+mod common;
+
+use common::{treegen, write_files};
+use serde_json::Value;
+
+const SPEC: &str = r##"src:
+  main.rs: "fn main() {}\n"
+  run.sh: "#!/bin/sh\n"
+  keep.txt: { type: file, content: "x", if_exists: skip }
+big.bin: { type: file, size: 1KiB }
+id.txt: "@uuid"
+copy.rs: { type: hardlink, target: src/main.rs }
+"##;
+
+fn plan(dir: &std::path::Path, args: &[&str]) -> Value {
+    let output = treegen(dir)
+        .args(args)
+        .args(["--out", "out", "--dry-run", "--json", "--auto-exec"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn dry_run_json_prints_the_plan() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let plan = plan(dir, &["spec.yaml"]);
+    assert!(!dir.join("out").exists());
+
+    let actions = plan["actions"].as_array().unwrap();
+    let paths: Vec<&str> = actions
+        .iter()
+        .map(|a| a["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "",
+            "big.bin",
+            "id.txt",
+            "src",
+            "src/keep.txt",
+            "src/main.rs",
+            "src/run.sh",
+            "copy.rs"
+        ]
+    );
+    let action = |path: &str| actions.iter().find(|a| a["path"] == path).unwrap();
+    assert_eq!(action("src")["kind"], "dir");
+    assert_eq!(action("src/main.rs")["content_ref"]["type"], "inline");
+    assert_eq!(action("src/main.rs")["content_ref"]["bytes"], 13);
+    assert_eq!(action("src/main.rs")["mode"], "0o644");
+    assert_eq!(action("src/main.rs")["policy"], "overwrite");
+    assert_eq!(action("src/run.sh")["mode"], "0o755");
+    assert_eq!(action("src/keep.txt")["policy"], "skip");
+    assert_eq!(action("big.bin")["content_ref"]["type"], "placeholder");
+    assert_eq!(action("big.bin")["content_ref"]["size"], 1024);
+    assert_eq!(action("id.txt")["content_ref"]["type"], "generator");
+    assert_eq!(action("copy.rs")["kind"], "hardlink");
+    assert_eq!(action("copy.rs")["content_ref"]["target"], "src/main.rs");
+
+    assert_eq!(plan["stats"]["dirs"], 1);
+    assert_eq!(plan["stats"]["files"], 5);
+    assert_eq!(plan["stats"]["links"], 1);
+}
+
+#[test]
+fn no_touch_files_are_planned_as_skips() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("tree.md", "app/\n├── README.md\n└── notes.txt\n")]);
+    write_files(dir, &[("extra.yaml", "app:\n  notes.txt: hi\n")]);
+    let plan = plan(dir, &["tree.md", "extra.yaml", "--no-touch"]);
+    let skipped: Vec<&Value> = plan["actions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|a| a["kind"] == "skip")
+        .collect();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0]["path"], "app/README.md");
+    assert_eq!(plan["stats"]["skipped"], 1);
+}
+
+#[test]
+fn json_requires_dry_run() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!dir.join("out").exists());
+}
@@ -0,0 +1,113 @@
+//! dry-run 与 --preflight 的预检：一次列出所有会让生成失败的问题，且不写入任何东西
+
+mod common;
+
+use common::{treegen, write_files};
+
+const SPEC: &str = "\
+app:
+  logo.png: { type: copy, from: ./missing.png }
+  skeleton: { type: copy, from: ./skeleton }
+  src:
+    main.rs: 'fn main() {}'
+";
+
+fn stderr(output: &std::process::Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn dry_run_lists_every_problem() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", SPEC),
+            ("skeleton/a.txt", ""),
+            ("out/app/src", ""),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--dry-run", "--mode", "0o999"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = stderr(&output);
+    assert!(
+        stderr.contains("Pre-flight checks failed (4 problem(s)):"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("  - Invalid mode '0o999'"), "{stderr}");
+    assert!(
+        stderr.contains("  - Copy source '")
+            && stderr.contains("missing.png' does not exist (for 'app/logo.png')"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("set \"recursive\": true to copy it (for 'app/skeleton')"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("src' exists as a file but the spec has a directory"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn preflight_stops_a_real_run_before_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--preflight"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Pre-flight checks failed (2 problem(s)):"));
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn output_path_must_be_a_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", "a.txt: ''\n"), ("taken", "")]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "taken", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Output path 'taken' is not a directory"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn passing_preflight_generates() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", "src:\n  main.rs: ''\n"),
+            ("out/src/main.rs", "old"),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--preflight", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Pre-flight checks passed"), "{stdout}");
+    // --clean 会先删掉输出目录，已有路径的类型不算问题
+    write_files(dir, &[("other.yaml", "src: ''\n")]);
+    let output = treegen(dir)
+        .args(["other.yaml", "--out", "out", "--dry-run", "--clean"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
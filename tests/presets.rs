@@ -0,0 +1,90 @@
+//! 内置预设（`treegen new @<name>`）：每个预设都能生成，关键文件存在且替换了变量
+
+mod common;
+
+use common::{read, treegen};
+
+/// 用 `treegen new @<preset> --var name=demo` 生成到临时目录
+fn generate(preset: &str) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    treegen(dir.path())
+        .args(["new", &format!("@{}", preset), "--out", "out"])
+        .args(["--var", "name=demo"])
+        .assert()
+        .success();
+    dir
+}
+
+#[test]
+fn rust_bin() {
+    let dir = generate("rust-bin");
+    let out = dir.path().join("out");
+    assert!(read(&out, "Cargo.toml").contains("name = \"demo\""));
+    assert!(read(&out, "src/main.rs").contains("fn main()"));
+    assert_eq!(read(&out, "README.md"), "# demo\n");
+    assert_eq!(read(&out, ".gitignore"), "/target\n");
+}
+
+#[test]
+fn rust_lib() {
+    let dir = generate("rust-lib");
+    let out = dir.path().join("out");
+    assert!(read(&out, "Cargo.toml").contains("name = \"demo\""));
+    assert!(read(&out, "src/lib.rs").contains("pub fn add"));
+    assert!(read(&out, ".gitignore").contains("Cargo.lock"));
+}
+
+#[test]
+fn python_pkg() {
+    let dir = generate("python-pkg");
+    let out = dir.path().join("out");
+    assert!(read(&out, "pyproject.toml").contains("name = \"demo\""));
+    assert_eq!(
+        read(&out, "src/demo/__init__.py"),
+        "__version__ = \"0.1.0\"\n"
+    );
+    assert_eq!(read(&out, "tests/__init__.py"), "");
+}
+
+#[test]
+fn node() {
+    let dir = generate("node");
+    let out = dir.path().join("out");
+    assert!(read(&out, "package.json").contains("\"name\": \"demo\""));
+    assert_eq!(
+        read(&out, "index.js"),
+        "console.log(\"Hello from demo!\");\n"
+    );
+    assert_eq!(read(&out, ".gitignore"), "node_modules/\n");
+}
+
+#[test]
+fn every_preset_is_listed() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = treegen(dir.path())
+        .args(["new", "--list"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let list = String::from_utf8(output.stdout).unwrap();
+    for preset in ["rust-bin", "rust-lib", "python-pkg", "node"] {
+        assert!(list.contains(&format!("@{}", preset)), "{list}");
+    }
+}
+
+#[test]
+fn unknown_presets_list_the_available_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = treegen(dir.path())
+        .args(["new", "@rust", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(
+            "Unknown built-in preset '@rust'. Available presets: @rust-bin, @rust-lib, @python-pkg, @node"
+        ),
+        "{stderr}"
+    );
+}
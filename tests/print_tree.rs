@@ -0,0 +1,63 @@
+//! --print-tree：生成后打印最终的目录树，并标注每个节点的结果
+mod common;
+
+use common::{treegen, write_files};
+
+const SPEC: &str = "\
+app/
+├── src/
+│   └── main.rs
+└── README.md
+";
+
+fn print_tree(dir: &std::path::Path) -> String {
+    let output = treegen(dir)
+        .args(["spec.md", "--out", "out", "--print-tree"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn first_run_marks_everything_created() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.md", SPEC)]);
+    assert_eq!(
+        print_tree(dir),
+        "✅ Generated the file tree in 'out'\n\
+         app/  (created)\n\
+         ├── src/  (created)\n\
+         │   └── main.rs  (created)\n\
+         └── README.md  (created)\n"
+    );
+}
+
+#[test]
+fn second_run_marks_unchanged_and_overwritten_nodes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.md", SPEC)]);
+    print_tree(dir);
+    assert_eq!(
+        print_tree(dir),
+        "✅ Generated the file tree in 'out'\n\
+         app/  (unchanged)\n\
+         ├── src/  (unchanged)\n\
+         │   └── main.rs  (unchanged)\n\
+         └── README.md  (unchanged)\n"
+    );
+
+    // 内容不同的文件被覆盖，其余节点保持 unchanged
+    write_files(dir, &[("out/app/README.md", "edited")]);
+    let stdout = print_tree(dir);
+    assert!(
+        stdout.contains("└── README.md  (overwritten)\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("│   └── main.rs  (unchanged)\n"),
+        "{stdout}"
+    );
+}
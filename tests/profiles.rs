@@ -0,0 +1,140 @@
+//! 节点的 tags 与 --profile：只生成没有标签或带有所选标签的节点，多个 --profile 取并集
+mod common;
+
+use common::{treegen, write_files};
+use std::path::Path;
+
+const SPEC: &str = "\
+app/
+├── src/
+│   └── main.rs
+├── docs/ #[docs, full]
+│   └── guide.md
+├── benches/ #[full]
+│   └── b.rs
+└── CHANGELOG.md #[docs]
+";
+
+/// 生成后输出目录中的所有文件（相对路径，排序）
+fn generated(dir: &Path, profiles: &[&str]) -> Vec<String> {
+    let _ = std::fs::remove_dir_all(dir.join("out"));
+    write_files(dir, &[("spec.md", SPEC)]);
+    let mut cmd = treegen(dir);
+    cmd.args(["spec.md", "--out", "out"]);
+    for profile in profiles {
+        cmd.args(["--profile", profile]);
+    }
+    cmd.assert().success();
+    let mut files = Vec::new();
+    let mut pending = vec![dir.join("out")];
+    while let Some(path) = pending.pop() {
+        for entry in std::fs::read_dir(path).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let rel = path.strip_prefix(dir.join("out")).unwrap();
+                files.push(rel.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn without_a_profile_everything_is_generated() {
+    let tmp = tempfile::tempdir().unwrap();
+    assert_eq!(
+        generated(tmp.path(), &[]),
+        [
+            "app/CHANGELOG.md",
+            "app/benches/b.rs",
+            "app/docs/guide.md",
+            "app/src/main.rs"
+        ]
+    );
+}
+
+#[test]
+fn a_profile_prunes_other_tagged_nodes_with_their_contents() {
+    let tmp = tempfile::tempdir().unwrap();
+    assert_eq!(
+        generated(tmp.path(), &["docs"]),
+        ["app/CHANGELOG.md", "app/docs/guide.md", "app/src/main.rs"]
+    );
+    assert!(!tmp.path().join("out/app/benches").exists());
+}
+
+#[test]
+fn several_profiles_union() {
+    let tmp = tempfile::tempdir().unwrap();
+    assert_eq!(
+        generated(tmp.path(), &["full"]),
+        ["app/benches/b.rs", "app/docs/guide.md", "app/src/main.rs"]
+    );
+    assert_eq!(
+        generated(tmp.path(), &["full", "docs"]),
+        [
+            "app/CHANGELOG.md",
+            "app/benches/b.rs",
+            "app/docs/guide.md",
+            "app/src/main.rs"
+        ]
+    );
+}
+
+#[test]
+fn dry_run_lists_excluded_paths_and_warns_about_unused_profiles() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.md", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.md", "--out", "out", "--dry-run", "--profile", "docs"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let excluded: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("[Dry-Run] Excluded by profile: "))
+        .collect();
+    assert_eq!(excluded, ["app/benches"]);
+    assert!(!dir.join("out").exists());
+
+    let output = treegen(dir)
+        .args(["spec.md", "--out", "out", "--dry-run", "--profile", "nope"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: Profile 'nope' is not used by any node"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn profiles_command_lists_tags_and_counts() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("spec.md", SPEC),
+            (
+                "spec.yaml",
+                "bench.rs: { type: file, content: '', tags: [full, perf] }\n",
+            ),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["profiles", "spec.md", "spec.yaml"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "docs  2 node(s)\nfull  3 node(s)\nperf  1 node(s)\n"
+    );
+}
@@ -0,0 +1,86 @@
+//! 模板变量的询问：只在标准输入是终端且没有 --no-input 时询问，否则采用默认值或报错
+mod common;
+
+use common::{read, treegen, write_files};
+
+const UNDECLARED: &str = "a.txt: \"hi {{who}}\"\n";
+const WITH_DEFAULT: &str = "\
+$vars:
+  who: { default: world, description: Who to greet }
+a.txt: \"hi {{who}}\"
+";
+
+#[test]
+fn no_input_keeps_the_undefined_variable_error() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", UNDECLARED)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--no-input"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Undefined template variable 'who' in 'a.txt' (pass --var who=...)"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out/a.txt").exists());
+}
+
+#[test]
+fn piped_stdin_is_not_prompted() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("spec.yaml", UNDECLARED), ("default.yaml", WITH_DEFAULT)],
+    );
+    // 标准输入不是终端：不询问、不读取管道中的内容，仍然报错
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .write_stdin("bob\n")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("who:"), "{stderr}");
+    assert!(
+        stderr.contains("Undefined template variable 'who'"),
+        "{stderr}"
+    );
+
+    // 有默认值时直接采用，不询问
+    let output = treegen(dir)
+        .args(["default.yaml", "--out", "out"])
+        .write_stdin("bob\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stderr.is_empty(), "{:?}", output);
+    assert_eq!(read(dir, "out/a.txt"), "hi world");
+}
+
+#[test]
+fn no_input_uses_defaults_and_given_values() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", WITH_DEFAULT)]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--no-input"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/a.txt"), "hi world");
+    treegen(dir)
+        .args([
+            "spec.yaml",
+            "--out",
+            "out",
+            "--no-input",
+            "--var",
+            "who=bob",
+        ])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/a.txt"), "hi bob");
+}
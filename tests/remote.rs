@@ -0,0 +1,293 @@
+//! git 仓库中的描述：git+URL//PATH 浅克隆到缓存，--ref 选择分支或标签，取不到时改用缓存
+
+mod common;
+
+use common::{read, treegen, write_files};
+use std::{path::Path, process::Command};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+        .args(args)
+        .env("HOME", dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?}", args);
+}
+
+/// 在 `dir/templates` 建一个仓库：v1 标签与之后 main 上的提交内容不同
+fn templates(dir: &Path) -> String {
+    let repo = dir.join("templates");
+    write_files(
+        &repo,
+        &[
+            ("rust/cli.yaml", "\"{{name}}\":\n  version.txt: v1\n  LICENSE: { type: copy, from: ../assets/LICENSE }\n"),
+            ("assets/LICENSE", "MIT\n"),
+        ],
+    );
+    git(&repo, &["init", "--quiet", "-b", "main"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "v1"]);
+    git(&repo, &["tag", "v1"]);
+    write_files(
+        &repo,
+        &[("rust/cli.yaml", "\"{{name}}\":\n  version.txt: v2\n")],
+    );
+    git(&repo, &["commit", "--quiet", "-am", "v2"]);
+    format!("git+file://{}//rust/cli.yaml", repo.display())
+}
+
+#[test]
+fn fetches_specs_from_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let input = templates(dir);
+
+    let output = treegen(dir)
+        .args([&input, "--out", "out", "--var", "name=foo", "--ref", "v1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/foo/version.txt"), "v1");
+    // 相对的复制来源指向检出的仓库
+    assert_eq!(read(dir, "out/foo/LICENSE"), "MIT\n");
+
+    // 默认分支，缓存在 $XDG_CACHE_HOME/treegen/git 下
+    let output = treegen(dir)
+        .args([&input, "--out", "out", "--var", "name=bar"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/bar/version.txt"), "v2");
+    let cached = std::fs::read_dir(dir.join(".cache/treegen/git"))
+        .unwrap()
+        .count();
+    assert_eq!(cached, 2);
+}
+
+#[test]
+fn falls_back_to_the_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let input = templates(dir);
+
+    let output = treegen(dir)
+        .args([&input, "--offline", "--out", "out", "--cache-dir", "cache"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is not in the cache"), "{}", stderr);
+
+    let output = treegen(dir)
+        .args([
+            &input,
+            "--out",
+            "out",
+            "--var",
+            "name=a",
+            "--cache-dir",
+            "cache",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    // 仓库不可用时使用缓存并给出警告；--offline 时直接使用缓存
+    std::fs::rename(dir.join("templates"), dir.join("gone")).unwrap();
+    let output = treegen(dir)
+        .args([
+            &input,
+            "--out",
+            "out",
+            "--var",
+            "name=b",
+            "--cache-dir",
+            "cache",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/b/version.txt"), "v2");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning: Failed to fetch"), "{}", stderr);
+    assert!(stderr.contains("using the cached copy"), "{}", stderr);
+
+    let output = treegen(dir)
+        .args([&input, "--offline", "--out", "out", "--var", "name=c"])
+        .env("TREEGEN_CACHE_DIR", dir.join("cache"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/c/version.txt"), "v2");
+    assert!(output.stderr.is_empty(), "{:?}", output);
+}
+
+#[test]
+fn missing_repositories_and_paths_are_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let input = templates(dir);
+
+    let missing = input.replace("rust/cli.yaml", "go/cli.yaml");
+    let output = treegen(dir)
+        .args([&missing, "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("'go/cli.yaml' not found in file://"),
+        "{}",
+        stderr
+    );
+
+    let output = treegen(dir)
+        .args(["git+file:///nonexistent/repo//a.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Failed to fetch 'git+file:///nonexistent/repo//a.yaml'"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("git clone failed"), "{}", stderr);
+}
+
+/// 以 `-` 开头的地址或 ref 不能变成 git 的选项（如 --upload-pack 执行任意命令）
+#[cfg(unix)]
+#[test]
+fn urls_and_refs_are_not_git_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let input = templates(dir);
+    let pwned = dir.join("pwned");
+
+    let output = treegen(dir)
+        .arg(format!("git+-utouch {}//a.yaml", pwned.display()))
+        .args(["--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    // 地址按仓库处理，而不是 -u（--upload-pack）
+    assert!(
+        stderr.contains("git clone failed: fatal: repository '-utouch "),
+        "{}",
+        stderr
+    );
+    assert!(!pwned.exists());
+
+    let upload_pack = format!("--ref=-utouch {}", pwned.display());
+    let output = treegen(dir)
+        .args([&input, "--out", "out", &upload_pack])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is not a branch or tag name"), "{}", stderr);
+    assert!(!pwned.exists());
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn remote_hooks_need_opt_in() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let repo = dir.join("hooks");
+    write_files(
+        &repo,
+        &[(
+            "app.yaml",
+            "$hooks:\n  post: ['touch HOOK_RAN']\napp:\n  a.txt: a\n",
+        )],
+    );
+    git(&repo, &["init", "--quiet", "-b", "main"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "hooks"]);
+    let input = format!("git+file://{}//app.yaml", repo.display());
+
+    // 标准输入不是终端：不询问，跳过并警告；--run 给出的命令照常执行
+    let output = treegen(dir)
+        .args([&input, "--out", "out", "--run", "touch RUN_RAN"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!dir.join("out/HOOK_RAN").exists());
+    assert!(dir.join("out/RUN_RAN").exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Skipped 1 $hooks command(s) from 'git+file://"),
+        "{}",
+        stderr
+    );
+
+    let output = treegen(dir)
+        .args([&input, "--out", "out", "--allow-remote-hooks"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(dir.join("out/HOOK_RAN").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn remote_specs_stay_inside_the_checkout() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    let repo = dir.join("escape");
+    write_files(dir, &[("secret.txt", "local secret\n")]);
+    write_files(
+        &repo,
+        &[
+            ("abs.yaml", "a: { type: copy, from: /etc/hostname }\n"),
+            ("up.yaml", "a: { type: copy, from: ../secret.txt }\n"),
+            ("out.yaml", "$out: ../elsewhere\na.txt: a\n"),
+            (
+                "ok/nested.yaml",
+                "a.txt: { type: copy, from: ../inside.txt }\n",
+            ),
+            ("inside.txt", "inside\n"),
+        ],
+    );
+    std::os::unix::fs::symlink(dir.join("secret.txt"), repo.join("link.txt")).unwrap();
+    write_files(
+        &repo,
+        &[("link.yaml", "a: { type: copy, from: link.txt }\n")],
+    );
+    git(&repo, &["init", "--quiet", "-b", "main"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "--quiet", "-m", "escape"]);
+    let url = format!("git+file://{}", repo.display());
+
+    let refused = |path: &str, expected: &str| {
+        let output = treegen(dir)
+            .args([&format!("{}//{}", url, path), "--out", "out"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{}: {:?}", path, output);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(expected), "{}: {}", path, stderr);
+    };
+    refused("../secret.txt", "must name a file inside the repository");
+    refused("/etc/hostname", "must name a file inside the repository");
+    refused(
+        "abs.yaml",
+        "Copy source '/etc/hostname' of 'a' is outside the repository",
+    );
+    refused("up.yaml", "is outside the repository");
+    refused("link.yaml", "is outside the repository");
+    refused("out.yaml", "/../elsewhere' is outside the repository");
+    assert!(!dir.join("out").exists());
+
+    // 仓库中的 .. 没有离开仓库
+    let output = treegen(dir)
+        .args([&format!("{}//ok/nested.yaml", url), "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/a.txt"), "inside\n");
+}
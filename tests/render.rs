@@ -0,0 +1,75 @@
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r##"src:
+  main.rs: "fn main() { if 1 < 2 { println!(\"</script><b>hi</b>\"); } }\n"
+  util: {}
+"docs & notes":
+  guide.md: "# Guide\n"
+"##;
+
+#[test]
+fn html_is_standalone_and_escaped() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["render", "spec.yaml", "--to", "html", "-o", "tree.html"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let page = read(dir, "tree.html");
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("<title>spec.yaml</title>"));
+    // 只有页面自己的两个 <script>
+    assert_eq!(page.matches("</script>").count(), 2);
+    assert!(!page.contains("<b>hi</b>"));
+    assert!(!page.contains("docs & notes"));
+    assert!(page.contains(r#""n":"main.rs""#));
+    assert!(page.contains(r#""n":"docs \u0026 notes""#));
+    assert!(!page.contains("http://") && !page.contains("https://"));
+    // 不生成文件树
+    assert!(!dir.join("src").exists());
+}
+
+#[test]
+fn svg_is_inferred_from_output_extension() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["render", "spec.yaml", "-o", "tree.svg"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let svg = read(dir, "tree.svg");
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains(">src/</text>"));
+    assert!(svg.contains(">main.rs</text>"));
+    assert!(svg.contains(">util/</text>"));
+    assert!(svg.contains(">docs &amp; notes/</text>"));
+    assert_eq!(svg.matches("<rect").count(), 5);
+    assert_eq!(svg.matches("<path").count(), 3);
+    assert!(!dir.join("src").exists());
+}
+
+#[test]
+fn render_writes_to_stdout_and_refuses_to_overwrite_input() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["render", "spec.yaml", "--to", "svg"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("<svg"));
+
+    let output = treegen(dir)
+        .args(["render", "spec.yaml", "-o", "spec.yaml"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(read(dir, "spec.yaml"), SPEC);
+}
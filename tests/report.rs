@@ -0,0 +1,191 @@
+//! --report：调用参数、描述的哈希、变量（--redact-var 隐去）、每个路径的动作与写入内容的哈希；
+//! 中途失败与解析失败时同样写出已记录的部分
+mod common;
+
+use common::{read, treegen, write_files};
+use serde_json::Value;
+use treegen::checksum::sha256_bytes;
+
+const SPEC: &str = "app:\n  a.txt: 'hi {{name}}'\n  b:\n    c.txt: 'x'\n";
+
+fn read_report(dir: &std::path::Path) -> Value {
+    serde_json::from_str(&read(dir, "report.json")).unwrap()
+}
+
+/// 各路径的 (路径, 动作)
+fn actions(report: &Value) -> Vec<(String, String)> {
+    report["runs"][0]["paths"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            let field = |key: &str| p[key].as_str().unwrap().to_string();
+            (field("path"), field("action"))
+        })
+        .collect()
+}
+
+#[test]
+fn records_a_successful_run() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--report", "report.json"])
+        .args([
+            "--var",
+            "name=bob",
+            "--var=token=s3cret",
+            "--redact-var",
+            "token",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let report = read_report(dir);
+    assert_eq!(report["version"], 1);
+    assert_eq!(report["status"], "success");
+    assert!(report.get("error").is_none());
+    let args = report["args"].to_string();
+    assert!(args.contains("\"name=bob\""), "{args}");
+    assert!(args.contains("\"--var=token=<redacted>\""), "{args}");
+    assert!(!args.contains("s3cret"), "{args}");
+    assert_eq!(report["specs"][0]["path"], "app.yaml");
+    assert_eq!(report["specs"][0]["sha256"], sha256_bytes(SPEC.as_bytes()));
+    assert_eq!(report["vars"]["name"], "bob");
+    assert_eq!(report["vars"]["token"], "<redacted>");
+
+    let run = &report["runs"][0];
+    assert_eq!(run["out"], "out");
+    assert_eq!(run["paths"][1]["path"], "app/a.txt");
+    assert_eq!(run["paths"][1]["kind"], "file");
+    assert_eq!(run["paths"][1]["sha256"], sha256_bytes(b"hi bob"));
+    // 目录没有哈希
+    assert!(run["paths"][0].get("sha256").is_none());
+    let phases: Vec<&str> = run["timings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["phase"].as_str().unwrap())
+        .collect();
+    assert!(phases.contains(&"execute: files"), "{phases:?}");
+    assert!(phases.contains(&"hooks"), "{phases:?}");
+    assert!(actions(&report)
+        .iter()
+        .all(|(_, action)| action == "created"));
+
+    // 再次生成：内容相同的文件没有重写
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--report", "report.json"])
+        .args(["--var", "name=bob"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report = read_report(dir);
+    assert!(actions(&report)
+        .iter()
+        .all(|(_, action)| action == "unchanged"));
+    assert_eq!(
+        report["runs"][0]["paths"][1]["sha256"],
+        sha256_bytes(b"hi bob")
+    );
+}
+
+#[test]
+fn written_when_generation_fails_partway() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    // 计划中的目录 app/b 已经是一个文件
+    write_files(dir, &[("app.yaml", SPEC), ("out/app/b", "a file")]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--report", "report.json"])
+        .args(["--var", "name=bob"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let report = read_report(dir);
+    assert_eq!(report["status"], "failed");
+    let error = report["error"].as_str().unwrap();
+    assert!(
+        error.starts_with("1 path(s) could not be generated"),
+        "{error}"
+    );
+    assert_eq!(
+        actions(&report),
+        [
+            ("app".to_string(), "unchanged".to_string()),
+            ("app/a.txt".to_string(), "created".to_string()),
+            ("app/b".to_string(), "failed".to_string()),
+            ("app/b/c.txt".to_string(), "skipped".to_string()),
+        ]
+    );
+    let paths = &report["runs"][0]["paths"];
+    assert!(paths[2]["reason"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to create directory"));
+    assert_eq!(paths[3]["reason"], "'app/b' failed");
+    assert_eq!(paths[1]["sha256"], sha256_bytes(b"hi bob"));
+}
+
+#[test]
+fn digests_of_redacted_content_are_hidden() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let spec = "app:\n  .env: 'TOKEN={{token}}'\n  README.md: '# {{name}}'\n  \
+                env.bak: { type: hardlink, target: app/.env }\n";
+    write_files(dir, &[("app.yaml", spec)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--report", "report.json"])
+        .args(["--var", "name=bob", "--var", "token=abc"])
+        .args(["--redact-var", "token"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/app/.env"), "TOKEN=abc");
+
+    // 短的值可以由哈希穷举出来，用到它的文件（及其硬链接）不给出哈希
+    let report = read_report(dir);
+    let text = report.to_string();
+    assert!(!text.contains(&sha256_bytes(b"TOKEN=abc")), "{text}");
+    let sha256 = |path: &str| {
+        let paths = report["runs"][0]["paths"].as_array().unwrap();
+        let entry = paths.iter().find(|p| p["path"] == path).unwrap();
+        entry["sha256"].clone()
+    };
+    assert_eq!(sha256("app/.env"), "<redacted>");
+    assert_eq!(sha256("app/env.bak"), "<redacted>");
+    assert_eq!(sha256("app/README.md"), sha256_bytes(b"# bob"));
+}
+
+#[test]
+fn written_when_the_spec_does_not_parse() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let spec = "app:\n  a.txt: 1\n";
+    write_files(dir, &[("app.yaml", spec)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--report", "report.json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+
+    let report = read_report(dir);
+    assert_eq!(report["status"], "failed");
+    assert!(report["error"].as_str().unwrap().contains("app/a.txt"));
+    assert_eq!(report["specs"][0]["sha256"], sha256_bytes(spec.as_bytes()));
+    assert_eq!(report["runs"], serde_json::json!([]));
+}
+
+#[test]
+fn redact_var_needs_report() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    treegen(dir)
+        .args(["app.yaml", "--redact-var", "token"])
+        .assert()
+        .failure();
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::{read, treegen, write_files};
+
+#[test]
+fn each_input_goes_under_its_stem() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("blog-engine.yaml", "README.md: \"# blog\\n\"\n"),
+            ("specs/api.json", r#"{"main.rs": "fn main() {}\n"}"#),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["blog-engine.yaml", "specs/api.json", "--root-from-spec"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "blog-engine/README.md"), "# blog\n");
+    assert_eq!(read(dir, "api/main.rs"), "fn main() {}\n");
+    assert!(!dir.join("README.md").exists());
+}
+
+#[test]
+fn roots_go_under_out_and_show_in_dry_run() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("blog-engine.yaml", "a.txt: x\n")]);
+    let output = treegen(dir)
+        .args(["blog-engine.yaml", "--root-from-spec", "--out", "projects"])
+        .arg("--dry-run")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("projects/blog-engine"), "{}", stdout);
+    assert!(!dir.join("projects").exists());
+
+    let output = treegen(dir)
+        .args(["blog-engine.yaml", "--root-from-spec", "--out", "projects"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "projects/blog-engine/a.txt"), "x");
+}
+
+#[test]
+fn conflicts_with_mapped_out_dir() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("blog.yaml", "a.txt: x\n")]);
+    let output = treegen(dir)
+        .args(["blog.yaml=site", "--root-from-spec"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("mutually exclusive"), "{}", stderr);
+    assert!(!dir.join("site").exists());
+    assert!(!dir.join("blog").exists());
+}
@@ -0,0 +1,106 @@
+//! 描述中的 `$rules`：按顺序对路径匹配的文件应用，同一字段以后出现的规则为准
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r##"
+"$rules":
+  - { match: "**/*.rs", prepend: "// A\n", mode: "0o600" }
+  - { match: "src/bin/*.rs", prepend: "// B\n", append: "// end\n" }
+  - { match: "**/*.sh", mode: "0o755" }
+src:
+  lib.rs: "lib\n"
+  bin:
+    main.rs: "main\n"
+run.sh: "#!/bin/sh\n"
+README.md: "r\n"
+"##;
+
+#[test]
+fn later_rules_win_per_field() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/src/lib.rs"), "// A\nlib\n");
+    // 第二条规则的 prepend 取代第一条的，append 来自第二条
+    assert_eq!(read(dir, "out/src/bin/main.rs"), "// B\nmain\n// end\n");
+    assert_eq!(read(dir, "out/run.sh"), "#!/bin/sh\n");
+    assert_eq!(read(dir, "out/README.md"), "r\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn rules_set_the_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .assert()
+        .success();
+    let mode = |path: &str| {
+        std::fs::metadata(dir.join("out").join(path))
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777
+    };
+    assert_eq!(mode("src/lib.rs"), 0o600);
+    // 后面的规则没有设置 mode 时保留前面规则的
+    assert_eq!(mode("src/bin/main.rs"), 0o600);
+    assert_eq!(mode("run.sh"), 0o755);
+}
+
+#[test]
+fn dry_run_lists_the_rules_applied_to_each_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let applied: Vec<&str> = stdout
+        .lines()
+        .filter(|line| line.starts_with("[Dry-Run] Rules applied"))
+        .collect();
+    assert_eq!(
+        applied,
+        [
+            "[Dry-Run] Rules applied to run.sh: #3 (**/*.sh)",
+            "[Dry-Run] Rules applied to src/bin/main.rs: #1 (**/*.rs), #2 (src/bin/*.rs)",
+            "[Dry-Run] Rules applied to src/lib.rs: #1 (**/*.rs)",
+        ]
+    );
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn invalid_globs_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "\"$rules\":\n  - { match: \"src/[\", mode: \"0o600\" }\na.txt: x\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid rule 1 in '$rules'"), "{stderr}");
+    assert!(stderr.contains("Invalid glob 'src/['"), "{stderr}");
+    assert!(!dir.join("out").exists());
+}
@@ -0,0 +1,81 @@
+//! treegen schema 与 --schema-validate：违反之处以 JSON Pointer 报告，校验失败时不生成
+mod common;
+
+use common::{treegen, write_files};
+use serde_json::Value;
+
+#[test]
+fn schema_prints_a_json_schema() {
+    let tmp = tempfile::tempdir().unwrap();
+    let output = treegen(tmp.path()).arg("schema").output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let schema: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+    assert_eq!(schema["title"], "treegen spec");
+    for setting in ["$hooks", "$vars", "$rules", "$out"] {
+        assert!(
+            schema["properties"].get(setting).is_some(),
+            "{setting} missing"
+        );
+    }
+}
+
+#[test]
+fn violations_are_reported_by_pointer() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "bad.yaml",
+            "src:\n  main.rs: 1\n  lib.rs: [a]\nok.txt: hi\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["bad.yaml", "--out", "out", "--schema-validate"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Schema validation failed for 'bad.yaml'"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("(2 violation(s))"), "{stderr}");
+    assert!(
+        stderr.contains(
+            "- /src/main.rs: expected file content (a string) or a directory (a map), got 1"
+        ),
+        "{stderr}"
+    );
+    assert!(stderr.contains("- /src/lib.rs: "), "{stderr}");
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn valid_specs_pass_in_every_structured_format() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            (
+                "a.yaml",
+                "src:\n  main.rs: 'fn main() {}'\n$hooks: { post: [echo hi] }\n",
+            ),
+            (
+                "b.json",
+                r#"{"docs": {"a.md": {"type": "file", "content": "x"}}}"#,
+            ),
+            ("c.toml", "[lib]\n\"x.rs\" = \"\"\n"),
+            ("d.json5", "{ e: { 'f.txt': '' } }"),
+        ],
+    );
+    treegen(dir)
+        .args(["a.yaml", "b.json", "c.toml", "d.json5"])
+        .args(["--out", "out", "--schema-validate", "--no-hooks"])
+        .assert()
+        .success();
+    assert!(dir.join("out/src/main.rs").is_file());
+    assert!(dir.join("out/e/f.txt").is_file());
+}
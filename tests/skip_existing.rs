@@ -0,0 +1,73 @@
+//! 权限只在写入内容时设置：--skip-existing 保留的文件与内容未变化的文件不改权限，除非 --fix-modes
+#![cfg(unix)]
+
+mod common;
+
+use common::{read, treegen, write_files};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+fn mode(path: &Path) -> u32 {
+    path.metadata().unwrap().permissions().mode() & 0o7777
+}
+
+fn chmod(path: &Path, mode: u32) {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+}
+
+#[test]
+fn skip_existing_keeps_content_and_mode() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", "run.sh: 'new'\nnotes.txt: 'hi'\n"),
+            ("out/run.sh", "mine"),
+        ],
+    );
+    chmod(&dir.join("out/run.sh"), 0o700);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--skip-existing", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Skip existing file: "), "{stdout}");
+    assert_eq!(read(dir, "out/run.sh"), "mine");
+    assert_eq!(mode(&dir.join("out/run.sh")), 0o700);
+    assert_eq!(read(dir, "out/notes.txt"), "hi");
+    assert_eq!(mode(&dir.join("out/notes.txt")), 0o644);
+}
+
+#[test]
+fn unchanged_files_keep_custom_modes_unless_fixed() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", "run.sh: 'same'\nold.txt: 'new'\n"),
+            ("out/run.sh", "same"),
+            ("out/old.txt", "old"),
+        ],
+    );
+    chmod(&dir.join("out/run.sh"), 0o755);
+    chmod(&dir.join("out/old.txt"), 0o600);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // 内容没变的不动，重写的按 --mode
+    assert_eq!(mode(&dir.join("out/run.sh")), 0o755);
+    assert_eq!(mode(&dir.join("out/old.txt")), 0o644);
+
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--fix-modes", "--skip-existing"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(mode(&dir.join("out/run.sh")), 0o644);
+}
@@ -0,0 +1,113 @@
+//! 扩展名缺失或无法识别（包括看起来像另一种格式的扩展名）时根据内容推断格式，--verbose 报告推断结果
+mod common;
+
+use common::{read, treegen, write_files};
+
+/// （文件名，内容，推断出的格式）
+const FIXTURES: [(&str, &str, &str); 5] = [
+    ("Treefile", include_str!("fixtures/sniff/Treefile"), "YAML"),
+    (
+        "layout.yaml.bak",
+        include_str!("fixtures/sniff/layout.yaml.bak"),
+        "TOML",
+    ),
+    (
+        "spec.tmpl",
+        include_str!("fixtures/sniff/spec.tmpl"),
+        "JSON5",
+    ),
+    (
+        "tree.txt",
+        include_str!("fixtures/sniff/tree.txt"),
+        "Markdown",
+    ),
+    (
+        "spec.data",
+        include_str!("fixtures/sniff/spec.data"),
+        "JSON",
+    ),
+];
+
+#[test]
+fn formats_are_detected_from_content() {
+    for (name, content, format) in FIXTURES {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        write_files(dir, &[(name, content)]);
+        let output = treegen(dir)
+            .args([name, "--out", "out", "--verbose"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{name}: {:?}", output);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.contains(&format!(
+                "Detected {format} format for '{name}' from its content"
+            )),
+            "{stdout}"
+        );
+        let main = if format == "Markdown" {
+            "out/demo/src/main.rs"
+        } else {
+            "out/src/main.rs"
+        };
+        assert!(dir.join(main).is_file(), "{name}");
+    }
+    // 推断出的格式同样按它的规则解析内容
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let (name, content, _) = FIXTURES[1];
+    write_files(dir, &[(name, content)]);
+    treegen(dir).args([name, "--out", "out"]).assert().success();
+    assert_eq!(read(dir, "out/src/main.rs"), "fn main() {}\n");
+}
+
+#[test]
+fn detection_is_only_reported_with_verbose_or_when_sniffed() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let (name, content, _) = FIXTURES[0];
+    write_files(dir, &[(name, content), ("spec.yaml", content)]);
+
+    let output = treegen(dir).args([name, "--out", "out"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Detected"));
+
+    // 显式指定或按扩展名确定的格式不是推断出来的
+    for input in ["yaml:Treefile", "spec.yaml"] {
+        let output = treegen(dir)
+            .args([input, "--out", "out", "--verbose"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert!(
+            !String::from_utf8_lossy(&output.stdout).contains("Detected"),
+            "{input}"
+        );
+    }
+}
+
+#[test]
+fn undetectable_content_is_an_error() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[("words.conf", include_str!("fixtures/sniff/words.conf"))],
+    );
+    let output = treegen(dir)
+        .args(["words.conf", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Cannot determine the format of 'words.conf'"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("Content does not look like any supported format"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+}
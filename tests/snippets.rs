@@ -0,0 +1,76 @@
+mod common;
+
+use chrono::Datelike;
+use common::{read, treegen, write_files};
+
+#[test]
+fn mit_license_substitutes_year_and_author() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "LICENSE: \"@snippet:mit-license\"\n")]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--var", "author=Jane Doe"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let license = read(dir, "out/LICENSE");
+    let year = chrono::Utc::now().year();
+    assert!(license.starts_with("MIT License\n"), "{license}");
+    assert!(
+        license.contains(&format!("Copyright (c) {} Jane Doe\n", year)),
+        "{license}"
+    );
+    assert!(!license.contains("{{"), "{license}");
+}
+
+#[test]
+fn user_snippets_override_built_ins() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            (".config/treegen/snippets/editorconfig", "root = true # {{name}}\n"),
+            (".config/treegen/snippets/header", "// {{name}}\n"),
+            (
+                "spec.yaml",
+                ".editorconfig: \"@snippet:editorconfig\"\nmain.rs: \"@snippet:header\"\nraw.txt: { type: file, content: \"@snippet:header\", raw: true }\n",
+            ),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--var", "name=demo"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/.editorconfig"), "root = true # demo\n");
+    assert_eq!(read(dir, "out/main.rs"), "// demo\n");
+    assert_eq!(read(dir, "out/raw.txt"), "@snippet:header");
+
+    let output = treegen(dir).args(["snippets", "list"]).output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("mit-license          built-in"), "{stdout}");
+    assert!(stdout.contains("header "), "{stdout}");
+    let editorconfig = stdout
+        .lines()
+        .find(|line| line.starts_with("editorconfig "))
+        .unwrap();
+    assert!(editorconfig.contains(".config"), "{stdout}");
+}
+
+#[test]
+fn unknown_snippet_suggests_close_matches() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "LICENSE: \"@snippet:mit-licence\"\n")]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Unknown snippet 'mit-licence'"), "{stderr}");
+    assert!(stderr.contains("did you mean: mit-license?"), "{stderr}");
+    assert!(!dir.join("out").exists());
+}
@@ -0,0 +1,101 @@
+//! --sort name：每个目录的子节点按名称排序，同一描述的日志与计划完全可复现
+mod common;
+
+use common::{treegen, write_files};
+use serde_json::Value;
+
+/// 两份输入拼接后描述中的顺序不是名称顺序
+const SPECS: [(&str, &str); 2] = [
+    ("b.md", "src/\n├── z.rs\n└── main.rs\nREADME.md\n"),
+    ("a.yaml", "src:\n  lib.rs: ''\nCargo.toml: ''\n"),
+];
+
+fn verbose_log(dir: &std::path::Path) -> Vec<u8> {
+    let _ = std::fs::remove_dir_all(dir.join("out"));
+    let output = treegen(dir)
+        .args([
+            "b.md",
+            "a.yaml",
+            "--out",
+            "out",
+            "--sort",
+            "name",
+            "--verbose",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    output.stdout
+}
+
+#[test]
+fn verbose_logs_are_identical_across_runs() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &SPECS);
+    let first = verbose_log(dir);
+    assert_eq!(first, verbose_log(dir));
+
+    let log = String::from_utf8(first).unwrap();
+    let created: Vec<&str> = log
+        .lines()
+        .filter_map(|line| line.strip_prefix("Create file: out/"))
+        .collect();
+    assert_eq!(
+        created,
+        [
+            "Cargo.toml",
+            "README.md",
+            "src/lib.rs",
+            "src/main.rs",
+            "src/z.rs"
+        ]
+    );
+}
+
+#[test]
+fn dry_run_plan_follows_the_sort_order() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &SPECS);
+    let plan = |sort: &str| -> Vec<String> {
+        let output = treegen(dir)
+            .args(["b.md", "a.yaml", "--out", "out", "--dry-run", "--json"])
+            .args(["--sort", sort, "--seed", "1"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let plan: Value = serde_json::from_slice(&output.stdout).unwrap();
+        plan["actions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|action| action["path"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(
+        plan("name"),
+        [
+            "",
+            "Cargo.toml",
+            "README.md",
+            "src/",
+            "src/lib.rs",
+            "src/main.rs",
+            "src/z.rs"
+        ]
+    );
+    // 默认按描述中的顺序：先 b.md（行序），再 a.yaml
+    assert_eq!(
+        plan("spec"),
+        [
+            "",
+            "src/",
+            "src/z.rs",
+            "src/main.rs",
+            "src/lib.rs",
+            "README.md",
+            "Cargo.toml"
+        ]
+    );
+}
@@ -0,0 +1,70 @@
+//! 描述中的 "$out"：相对描述文件所在目录的默认输出目录，可以引用模板变量；
+//! 优先级 --out > TREEGEN_OUT > $out > 当前目录
+
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = "\"$out\": \"../services/{{name}}\"\nREADME.md: \"# {{name}}\"\n";
+
+#[test]
+fn spec_out_is_relative_to_the_spec_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("specs/app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["specs/app.yaml", "--var", "name=api", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "services/api/README.md"), "# api");
+    assert!(!dir.join("$out").exists());
+    assert!(!dir.join("services/api/$out").exists());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("(from the spec)"), "{stdout}");
+}
+
+#[test]
+fn out_flag_and_env_take_precedence() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("specs/app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["specs/app.yaml", "--var", "name=api", "--verbose"])
+        .env("TREEGEN_OUT", "from-env")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "from-env/README.md"), "# api");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("(from TREEGEN_OUT)"), "{stdout}");
+
+    let output = treegen(dir)
+        .args(["specs/app.yaml", "--var", "name=api", "--out", "cli"])
+        .env("TREEGEN_OUT", "from-env")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "cli/README.md"), "# api");
+    assert!(!dir.join("services").exists());
+}
+
+#[test]
+fn undefined_variables_in_spec_out_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[("app.yaml", "\"$out\": \"{{target}}\"\nREADME.md: ''\n")],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--no-input"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Undefined template variable 'target' in '$out'"),
+        "{stderr}"
+    );
+}
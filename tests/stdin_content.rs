@@ -0,0 +1,122 @@
+//! --stdin-content：标准输入作为树中某个文件的内容
+
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r#"
+"{{name}}":
+  src:
+    main.rs: "mod api;\n"
+    api.rs: "// placeholder\n"
+"#;
+
+#[test]
+fn replaces_declared_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--var", "name=demo"])
+        .args(["--stdin-content", "demo/src/api.rs"])
+        .write_stdin("pub fn hello() -> &'static str { \"{{not a var}}\" }\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        read(dir, "out/demo/src/api.rs"),
+        "pub fn hello() -> &'static str { \"{{not a var}}\" }\n"
+    );
+    assert_eq!(read(dir, "out/demo/src/main.rs"), "mod api;\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Warning: Content of 'demo/src/api.rs' in the spec is replaced by stdin"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn creates_missing_files_with_binary_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let bytes = vec![0x89, b'P', b'N', b'G', 0, 0xff, 0xfe];
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--var", "name=demo"])
+        .args(["--stdin-content", "demo/assets/logo.png"])
+        .write_stdin(bytes.clone())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        std::fs::read(dir.join("out/demo/assets/logo.png")).unwrap(),
+        bytes
+    );
+    assert!(output.stderr.is_empty(), "{:?}", output);
+}
+
+#[test]
+fn dry_run_shows_the_byte_count() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=demo",
+            "--dry-run",
+        ])
+        .args(["--stdin-content", "demo/README.md"])
+        .write_stdin("# demo\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("[Dry-Run] Read 7 bytes from stdin for: demo/README.md"),
+        "{}",
+        stdout
+    );
+    assert!(!dir.join("out/demo").exists());
+}
+
+#[test]
+fn conflicts_with_spec_from_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = treegen(dir.path())
+        .args(["-", "--out", "out", "--stdin-content", "a.txt"])
+        .write_stdin("a.txt: x\n")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--stdin-content cannot be used with input from stdin ('-')"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn rejects_paths_through_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--var", "name=demo"])
+        .args(["--stdin-content", "demo/src/main.rs/extra.rs"])
+        .write_stdin("x")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("'demo/src/main.rs' is not a directory in the spec"),
+        "{}",
+        stderr
+    );
+}
@@ -0,0 +1,116 @@
+//! 结构化的 content：按 format 序列化为 JSON/YAML/TOML，字符串值中的变量先替换
+
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r#"
+"$vars": { name: demo, author: 'Jane "J" Doe' }
+package.json:
+  type: file
+  format: json
+  content:
+    name: "{{name}}"
+    version: 0.1.0
+    private: true
+    author: "{{author}}"
+    scripts: { build: "tsc -p .", test: vitest }
+    files: [dist, "{{name}}.d.ts"]
+config.yaml:
+  type: file
+  format: yaml
+  content: { service: "{{name}}", replicas: 2 }
+Cargo.toml:
+  type: file
+  format: toml
+  content:
+    package: { name: "{{name}}", edition: "2021" }
+    workspace: { members: [] }
+"#;
+
+#[test]
+fn serializes_structured_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let json = read(dir, "out/package.json");
+    let package: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        package,
+        serde_json::json!({
+            "name": "demo",
+            "version": "0.1.0",
+            "private": true,
+            "author": "Jane \"J\" Doe",
+            "scripts": { "build": "tsc -p .", "test": "vitest" },
+            "files": ["dist", "demo.d.ts"],
+        })
+    );
+    // 缩进两格，键保持描述中的顺序
+    assert!(
+        json.starts_with("{\n  \"name\": \"demo\",\n  \"version\""),
+        "{}",
+        json
+    );
+    assert!(json.ends_with("}\n"));
+
+    assert_eq!(read(dir, "out/config.yaml"), "service: demo\nreplicas: 2\n");
+    assert_eq!(
+        read(dir, "out/Cargo.toml"),
+        "[package]\nname = \"demo\"\nedition = \"2021\"\n\n[workspace]\nmembers = []\n"
+    );
+}
+
+#[test]
+fn structured_content_needs_a_format() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "package.json: { type: file, content: { name: x } }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Structured 'content' of node 'package.json' needs 'format'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn toml_rejects_non_table_values() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "list.toml: { type: file, format: toml, content: [1, 2] }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Failed to serialize 'content' of node 'list.toml'"),
+        "{}",
+        stderr
+    );
+}
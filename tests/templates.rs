@@ -0,0 +1,169 @@
+//! 模板库：treegen template list/add/remove 与 treegen new，模板放在 $XDG_DATA_HOME/treegen/templates
+mod common;
+
+use common::{read, treegen, write_files};
+
+#[test]
+fn add_keeps_the_detected_format() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("Treefile", "app:\n  a.txt: a\n")]);
+    let output = treegen(dir)
+        .args(["template", "add", "Treefile"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        read(dir, ".local/share/treegen/templates/Treefile.yaml"),
+        "app:\n  a.txt: a\n"
+    );
+
+    let output = treegen(dir)
+        .args(["new", "Treefile", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/app/a.txt"), "a");
+}
+
+#[test]
+fn list_add_and_remove() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("rust-cli.yaml", "\"{{name}}\":\n  main.rs: ''\n"),
+            ("other.md", "- a.txt\n"),
+        ],
+    );
+    let list = || {
+        let output = treegen(dir).args(["template", "list"]).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert!(list().starts_with("No templates in '"), "{}", list());
+
+    // 模板名默认取文件名
+    treegen(dir)
+        .args(["template", "add", "rust-cli.yaml"])
+        .assert()
+        .success();
+    treegen(dir)
+        .args(["template", "add", "other.md", "--name", "docs"])
+        .assert()
+        .success();
+    let listed = list();
+    let names: Vec<&str> = listed
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap())
+        .collect();
+    assert_eq!(names, ["docs", "rust-cli"]);
+
+    // 同名模板需要 --force
+    let output = treegen(dir)
+        .args(["template", "add", "other.md", "--name", "rust-cli"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Template 'rust-cli' already exists; pass --force"),
+        "{}",
+        stderr
+    );
+    treegen(dir)
+        .args([
+            "template", "add", "other.md", "--name", "rust-cli", "--force",
+        ])
+        .assert()
+        .success();
+    // 换了格式的同名模板只留一份
+    assert!(!dir
+        .join(".local/share/treegen/templates/rust-cli.yaml")
+        .exists());
+    assert!(dir
+        .join(".local/share/treegen/templates/rust-cli.md")
+        .exists());
+
+    treegen(dir)
+        .args(["template", "remove", "docs"])
+        .assert()
+        .success();
+    let output = treegen(dir)
+        .args(["template", "remove", "docs"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unknown template 'docs'. Available templates: rust-cli"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn new_generates_from_a_template() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "rust-cli.yaml",
+            "\"{{name}}\":\n  main.rs: 'fn main() {}'\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["new", "rust-cli", "--out", "out", "--var", "name=demo"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unknown template 'rust-cli': the template directory"),
+        "{}",
+        stderr
+    );
+
+    treegen(dir)
+        .args(["template", "add", "rust-cli.yaml"])
+        .assert()
+        .success();
+    let output = treegen(dir)
+        .args(["new", "rust-cli", "--out", "out", "--var", "name=demo"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/demo/main.rs"), "fn main() {}");
+
+    let output = treegen(dir).args(["new", "--list"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.lines().any(|line| line.starts_with("rust-cli ")),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn names_must_stay_in_the_template_directory() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("t.yaml", "a.txt: a\n")]);
+    for name in ["../../escaped", "a/b", "..", ".", ""] {
+        let output = treegen(dir)
+            .args(["template", "add", "t.yaml", "--name", name])
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{}", name);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("Invalid template name '{}'", name)),
+            "{}",
+            stderr
+        );
+    }
+    assert!(!dir.join(".local/share/escaped.yaml").exists());
+    assert!(!dir.join(".local/share/treegen/templates").exists());
+}
@@ -0,0 +1,77 @@
+//! --timings 打印各阶段的耗时表格，--summary-json 中总是带有同样的数字
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = "src:\n  main.rs: 'fn main() {}'\n  lib.rs: ''\nREADME.md: hi\n";
+
+#[test]
+fn timings_table_lists_every_phase() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(
+        dir,
+        &[("app.yaml", SPEC), ("docs.md", "docs/\n└── guide.md\n")],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "docs.md", "--out", "out", "--timings"])
+        .args(["--run", "echo done"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let phases: Vec<&str> = stdout
+        .lines()
+        .skip_while(|line| !line.starts_with("phase "))
+        .skip(1)
+        .map(|line| line.split("  ").next().unwrap())
+        .collect();
+    assert_eq!(
+        phases,
+        [
+            "parse app.yaml",
+            "parse docs.md",
+            "merge",
+            "validate",
+            "plan",
+            "execute: dirs",
+            "execute: files",
+            "execute: modes",
+            "hooks",
+            "total"
+        ],
+        "{stdout}"
+    );
+
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out2"])
+        .output()
+        .unwrap();
+    assert!(!String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("nodes/s"));
+}
+
+#[test]
+fn summary_json_includes_timings() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("app.yaml", SPEC)]);
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--summary-json", "summary.json"])
+        .assert()
+        .success();
+    let summary: serde_json::Value = serde_json::from_str(&read(dir, "summary.json")).unwrap();
+    let timings = summary["timings"].as_array().unwrap();
+    let phase = |name: &str| {
+        timings
+            .iter()
+            .find(|t| t["phase"] == name)
+            .unwrap_or_else(|| panic!("no {name} in {timings:?}"))
+    };
+    assert_eq!(phase("parse app.yaml")["bytes"], SPEC.len());
+    assert_eq!(phase("execute: dirs")["nodes"], 2);
+    assert_eq!(phase("execute: files")["nodes"], 3);
+    assert_eq!(phase("execute: files")["bytes"], "fn main() {}hi".len());
+    assert!(phase("plan")["seconds"].as_f64().unwrap() >= 0.0);
+}
@@ -0,0 +1,154 @@
+//! Markdown 树的写出：能写出的树原样读回，读回会变样的名称报错
+
+use treegen::Node;
+
+/// 树中每个节点的（路径，类型，标签）；Markdown 中的目录名带结尾的 `/`，比较时去掉
+fn shape(root: &Node) -> Vec<String> {
+    root.iter()
+        .map(|(path, node)| {
+            format!(
+                "{} {:?} {:?}",
+                path.display().to_string().trim_end_matches('/'),
+                node.node_type,
+                node.attrs.tags
+            )
+        })
+        .collect()
+}
+
+fn round_trip(yaml: &str) {
+    let spec = treegen::parse_yaml(yaml).unwrap();
+    let md = spec.root.to_markdown().unwrap();
+    let back = treegen::parse_markdown(&md).unwrap();
+    assert_eq!(shape(&back.root), shape(&spec.root), "{md}");
+}
+
+#[test]
+fn names_that_only_look_special_round_trip() {
+    // 带连接符的子项中，连接线不会被特殊处理
+    round_trip(
+        "app:\n  '# notes.md': ''\n  '#x': ''\n  '│x': ''\n  '└── y': ''\n  '…': {}\n  'a -> b': ''\n  '[d] x': ''\n  x ]: ''\n",
+    );
+    round_trip("'---': {}\nREADME.md: ''\n");
+}
+
+#[test]
+fn lossy_names_are_refused() {
+    let nested = [
+        ("a:b", "':'"),
+        (" lead", "whitespace"),
+        ("trail ", "whitespace"),
+        ("a\nb", "line break"),
+        ("a [f]", "type marker"),
+    ];
+    for (name, reason) in nested {
+        let mut app = Node::new_dir("app/".to_string());
+        app.children.push(Node::new_file(name.to_string(), None));
+        let err = app.to_markdown().unwrap_err().to_string();
+        assert!(err.contains(reason), "{name:?}: {err}");
+    }
+    let top = [
+        ("│x", "connector"),
+        ("├── x", "connector"),
+        ("a └── x", "connector"),
+        ("# x", "comment"),
+        ("//x", "comment"),
+    ];
+    for (name, reason) in top {
+        let mut root = Node::new_dir(String::new());
+        root.children.push(Node::new_file(name.to_string(), None));
+        let err = root.to_markdown().unwrap_err().to_string();
+        assert!(err.contains(reason), "{name:?}: {err}");
+    }
+}
+
+#[test]
+fn tags_round_trip_and_ambiguous_tags_are_refused() {
+    let mut docs = Node::new_dir("docs/".to_string());
+    docs.attrs.tags = ["docs".to_string(), "full".to_string()].into();
+    let mut root = Node::new_dir("app/".to_string());
+    root.children.push(docs);
+    let md = root.to_markdown().unwrap();
+    assert_eq!(md, "app/\n└── docs/ #[docs, full]\n");
+    assert_eq!(
+        shape(&treegen::parse_markdown(&md).unwrap().root)[1..],
+        shape(&root)
+    );
+
+    // 名称本身以标签后缀结尾时读回会变成标签
+    let mut app = Node::new_dir("app/".to_string());
+    app.children
+        .push(Node::new_file("a #[tag]".to_string(), None));
+    let err = app.to_markdown().unwrap_err().to_string();
+    assert!(err.contains("tag suffix"), "{err}");
+
+    let mut file = Node::new_file("a".to_string(), None);
+    file.attrs.tags = ["two words".to_string()].into();
+    let mut root = Node::new_dir(String::new());
+    root.children.push(file);
+    assert!(root.to_markdown().is_err());
+}
+
+/// 随机生成的树（名称由容易被误读的片段拼成）：能写出的都原样读回，再写出的文本不变
+#[test]
+fn random_trees_round_trip() {
+    const PARTS: [&str; 16] = [
+        "a",
+        "src",
+        "main.rs",
+        "#",
+        "# ",
+        "│",
+        "├── ",
+        "└─",
+        "…",
+        "...",
+        "[d]",
+        "[f]",
+        "#[x]",
+        " ",
+        "名字",
+        "-> b",
+    ];
+    fn name(rng: &mut fastrand::Rng, index: usize) -> String {
+        let mut name: String = (0..rng.usize(1..4))
+            .map(|_| PARTS[rng.usize(..PARTS.len())])
+            .collect();
+        // 同级名称不重复
+        name.push_str(&index.to_string());
+        name
+    }
+    fn tree(rng: &mut fastrand::Rng, node: &mut Node, depth: usize) {
+        for index in 0..rng.usize(0..4) {
+            let mut child = if depth < 4 && rng.bool() {
+                let mut dir = Node::new_dir(format!("{}/", name(rng, index)));
+                tree(rng, &mut dir, depth + 1);
+                dir
+            } else {
+                Node::new_file(name(rng, index), None)
+            };
+            if rng.usize(..4) == 0 {
+                child.attrs.tags = ["full".to_string()].into();
+            }
+            node.children.push(child);
+        }
+    }
+
+    let mut rng = fastrand::Rng::with_seed(200);
+    let mut written = 0;
+    for _ in 0..2000 {
+        let mut root = Node::new_dir(String::new());
+        tree(&mut rng, &mut root, 0);
+        let Ok(md) = root.to_markdown() else {
+            continue;
+        };
+        written += 1;
+        let back = treegen::parse_markdown(&md)
+            .unwrap_or_else(|e| panic!("{e:#}\n{md}"))
+            .root;
+        assert_eq!(shape(&back), shape(&root), "{md}");
+        assert_eq!(back.to_markdown().unwrap(), md);
+    }
+    // 大部分随机树都能写出，测试确实覆盖了解析
+    assert!(written > 500, "{written}");
+}
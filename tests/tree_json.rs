@@ -0,0 +1,75 @@
+//! GNU tree 的 JSON 输出（`tree -J`）作为描述：目录、文件与指向树内文件的链接
+
+mod common;
+
+use common::{treegen, write_files};
+use std::path::Path;
+use treegen::{parse_json, sniff, Format, NodeType};
+
+const FIXTURE: &str = include_str!("fixtures/tree-j.json");
+
+#[test]
+fn tree_json_is_converted() {
+    let spec = parse_json(FIXTURE).unwrap();
+    let root = &spec.root;
+    // `.` 的内容直接放在根下
+    assert_eq!(root.find("Cargo.toml").unwrap().node_type, NodeType::File);
+    assert_eq!(root.find("releases/v2").unwrap().node_type, NodeType::Dir);
+    assert_eq!(root.find("locked").unwrap().children.len(), 0);
+    // 相对链接所在目录的目标转为相对根的硬链接，链接的链接也可以
+    let build = root.find("bin/build").unwrap();
+    assert_eq!(build.node_type, NodeType::Hardlink);
+    assert_eq!(build.target.as_deref(), Some(Path::new("scripts/build.sh")));
+    assert_eq!(
+        root.find("src/main.rs").unwrap().target.as_deref(),
+        Some(Path::new("src/lib.rs"))
+    );
+    // 指向树外、目录或不存在的目标的链接与特殊文件被跳过
+    for skipped in ["bin/python", "current", "dangling", "events"] {
+        assert!(root.find(skipped).is_none(), "{skipped}");
+    }
+}
+
+#[test]
+fn named_top_level_directory_is_kept() {
+    let spec = parse_json(
+        r#"[{"type":"directory","name":"/home/me/app","contents":[{"type":"file","name":"a"}]}]"#,
+    )
+    .unwrap();
+    assert_eq!(spec.root.find("app/a").unwrap().node_type, NodeType::File);
+    // 只有顶层对象都带 type 的数组才是 tree -J 输出
+    assert!(parse_json(r#"[{"name":"a"}]"#).is_err());
+    assert_eq!(sniff(FIXTURE).unwrap().0, Format::Json);
+}
+
+#[test]
+fn tree_json_is_generated_with_notes() {
+    let dir = tempfile::tempdir().unwrap();
+    let dir = dir.path();
+    write_files(dir, &[("snapshot", FIXTURE)]);
+    let output = treegen(dir)
+        .args(["snapshot", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Read as tree -J output"), "{stdout}");
+    assert!(
+        stdout.contains("Skip link 'bin/python' -> '/usr/bin/python3': target is outside the tree"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("Skip link 'current' -> 'releases/v2': target is a directory"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Skip fifo 'events'"), "{stdout}");
+    assert!(
+        stdout.contains("Skip error in 'locked': opening dir"),
+        "{stdout}"
+    );
+    let out = dir.join("out");
+    assert!(out.join("bin/build").is_file());
+    assert!(out.join("src/main.rs").is_file());
+    assert!(out.join("releases/v2").is_dir());
+    assert!(!out.join("events").exists());
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use common::{treegen, write_files};
+
+const SKELETON: &[(&str, &str)] = &[
+    ("skeleton/.git/HEAD", "ref: refs/heads/main\n"),
+    (
+        "skeleton/.treegenignore",
+        "# 构建产物与编辑器文件\ntarget/\nnode_modules/\n*.swp\n!keep.swp\n",
+    ),
+    ("skeleton/src/main.rs", "fn main() {}\n"),
+    ("skeleton/src/.main.rs.swp", "swap"),
+    ("skeleton/keep.swp", "kept"),
+    ("skeleton/target/debug/app", "bin"),
+    ("skeleton/web/node_modules/left-pad/index.js", "pad"),
+    ("skeleton/web/index.js", "app"),
+];
+
+#[test]
+fn treegenignore_and_vcs_defaults_are_skipped() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SKELETON);
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "app: { type: copy, from: ./skeleton, recursive: true }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Ignored 4 entries in copy source"),
+        "{stdout}"
+    );
+    let out = dir.join("out/app");
+    assert!(out.join("src/main.rs").exists());
+    assert!(out.join("keep.swp").exists());
+    assert!(out.join("web/index.js").exists());
+    for ignored in [
+        ".git",
+        ".treegenignore",
+        "src/.main.rs.swp",
+        "target",
+        "web/node_modules",
+    ] {
+        assert!(!out.join(ignored).exists(), "{ignored}");
+    }
+}
+
+#[test]
+fn no_default_ignores_keeps_vcs_directories() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SKELETON);
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "app: { type: copy, from: ./skeleton, recursive: true }\n",
+        )],
+    );
+    treegen(dir)
+        .args(["app.yaml", "--out", "out", "--no-default-ignores"])
+        .assert()
+        .success();
+    assert!(dir.join("out/app/.git/HEAD").exists());
+    // .treegenignore 仍然生效
+    assert!(!dir.join("out/app/target").exists());
+}
+
+#[test]
+fn ignores_apply_before_include() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, SKELETON);
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "app: { type: copy, from: ./skeleton, include: [\"**/*.js\"] }\n",
+        )],
+    );
+    treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .assert()
+        .success();
+    assert!(dir.join("out/app/web/index.js").exists());
+    assert!(!dir.join("out/app/web/node_modules").exists());
+    assert!(!dir.join("out/app/src").exists());
+
+    // 只有被忽略的文件匹配 include 时同样算没有匹配
+    write_files(
+        dir,
+        &[(
+            "bin.yaml",
+            "app: { type: copy, from: ./skeleton, include: [\"target/**\"] }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["bin.yaml", "--out", "bin"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("match include target/**"), "{stderr}");
+}
@@ -0,0 +1,78 @@
+//! --trim-trailing-whitespace 与节点的 trim: true：去掉行尾空白、压缩连续空行，复制的文件与标准输入的内容不处理
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = "app:
+  README.md: |
+    # {{name}}   \n\n\n\n\n    text\t
+  notes.txt: \"a  \\r\\n\\r\\n\\r\\n\\r\\nb \\r\\nno newline  \"
+  logo.txt: { type: copy, from: ./assets/logo.txt }
+  keep.md: \"kept  \\n\"
+";
+
+#[test]
+fn trims_every_text_file() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", SPEC),
+            ("assets/logo.txt", "copied  \n\n\n\n\n"),
+        ],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out", "--trim-trailing-whitespace"])
+        .args(["--var", "name=demo  "])
+        .args(["--stdin-content", "app/stdin.txt"])
+        .write_stdin("stdin  \n")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // 替换进来的值同样处理；结尾的换行保持原样
+    assert_eq!(read(dir, "out/app/README.md"), "# demo\n\n\ntext\n");
+    assert_eq!(
+        read(dir, "out/app/notes.txt"),
+        "a\r\n\r\n\r\nb\r\nno newline"
+    );
+    assert_eq!(read(dir, "out/app/keep.md"), "kept\n");
+    assert_eq!(read(dir, "out/app/logo.txt"), "copied  \n\n\n\n\n");
+    assert_eq!(read(dir, "out/app/stdin.txt"), "stdin  \n");
+}
+
+#[test]
+fn trim_per_node() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "app.yaml",
+            "app:\n  a.md: { type: file, trim: true, content: \"a  \\n\" }\n  b.md: \"b  \\n\"\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["app.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(read(dir, "out/app/a.md"), "a\n");
+    assert_eq!(read(dir, "out/app/b.md"), "b  \n");
+
+    // trim 只用于文件
+    write_files(
+        dir,
+        &[("bad.yaml", "app: { type: dir, trim: true, children: {} }\n")],
+    );
+    let output = treegen(dir)
+        .args(["bad.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Field 'trim' does not apply to dir node"),
+        "{stderr}"
+    );
+}
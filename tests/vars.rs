@@ -0,0 +1,153 @@
+//! 描述中的 `$vars`：带类型、默认值与说明的模板变量，以及 `treegen vars`
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = "\
+$vars:
+  name: { description: Project name }
+  port: { type: int, default: 8080 }
+  debug: { type: bool, default: false, description: Debug mode }
+  license: MIT
+a.txt: \"{{name}}:{{port}}:{{debug}}:{{license}}\"
+\"{{dir}}\": {}
+";
+
+fn fails(dir: &std::path::Path, args: &[&str]) -> String {
+    let output = treegen(dir).args(args).output().unwrap();
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn defaults_fill_in_missing_variables() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    treegen(dir)
+        .args([
+            "spec.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=app",
+            "--var",
+            "dir=d",
+        ])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/a.txt"), "app:8080:false:MIT");
+    treegen(dir)
+        .args([
+            "spec.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=app",
+            "--var",
+            "dir=d",
+        ])
+        .args(["--var", "port=3000", "--var", "debug=true"])
+        .assert()
+        .success();
+    assert_eq!(read(dir, "out/a.txt"), "app:3000:true:MIT");
+    // $vars 不出现在生成的树中
+    assert!(!dir.join("out/$vars").exists());
+}
+
+#[test]
+fn values_are_checked_against_declared_types() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("spec.yaml", SPEC),
+            (
+                "bad-default.yaml",
+                "$vars:\n  port: { type: int, default: eighty }\na.txt: \"{{port}}\"\n",
+            ),
+        ],
+    );
+    let base = [
+        "spec.yaml",
+        "--out",
+        "out",
+        "--var",
+        "name=app",
+        "--var",
+        "dir=d",
+    ];
+    let stderr = fails(dir, &[&base[..], &["--var", "port=abc"]].concat());
+    assert!(
+        stderr.contains("Invalid value 'abc' for variable 'port': expected int"),
+        "{stderr}"
+    );
+    let stderr = fails(dir, &[&base[..], &["--var", "debug=yes"]].concat());
+    assert!(
+        stderr.contains("Invalid value 'yes' for variable 'debug': expected bool"),
+        "{stderr}"
+    );
+    let stderr = fails(dir, &["bad-default.yaml", "--out", "out"]);
+    assert!(stderr.contains("Invalid default in '$vars'"), "{stderr}");
+    assert!(
+        stderr.contains("Invalid value 'eighty' for variable 'port': expected int"),
+        "{stderr}"
+    );
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn unknown_vars_are_rejected() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let stderr = fails(
+        dir,
+        &[
+            "spec.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=app",
+            "--var",
+            "nmae=x",
+        ],
+    );
+    assert!(
+        stderr.contains("Unknown variable 'nmae' (the spec declares: debug, license, name, port)"),
+        "{stderr}"
+    );
+    // 引用了但没有声明的变量可以用 --var 给出
+    treegen(dir)
+        .args([
+            "spec.yaml",
+            "--out",
+            "out",
+            "--var",
+            "name=app",
+            "--var",
+            "dir=d",
+        ])
+        .assert()
+        .success();
+    assert!(dir.join("out/d").is_dir());
+}
+
+#[test]
+fn vars_command_lists_declared_and_referenced_variables() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    treegen(dir)
+        .args(["vars", "spec.yaml"])
+        .assert()
+        .success()
+        .stdout(
+            "debug    bool = false  # Debug mode\n\
+             license  string = MIT\n\
+             name     string  # Project name\n\
+             port     int = 8080\n\
+             dir      (referenced but not declared)\n",
+        );
+}
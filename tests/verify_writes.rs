@@ -0,0 +1,74 @@
+mod common;
+
+use common::{read, treegen, write_files};
+
+const SPEC: &str = r#"app:
+  README.md: hello
+  config.toml: "name = \"app\"\n"
+  assets: { type: copy, from: ./assets, recursive: true }
+"#;
+
+#[test]
+fn verify_writes_reports_verified_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[
+            ("app.yaml", SPEC),
+            ("assets/logo.svg", "<svg/>"),
+            ("assets/fonts/a.txt", "font"),
+        ],
+    );
+    let output = treegen(dir)
+        .args([
+            "app.yaml",
+            "--out",
+            "out",
+            "--verify-writes",
+            "--lang",
+            "zh",
+        ])
+        .args(["--summary-json", "summary.json", "--checksums", "sums.txt"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("4 个文件已回读校验"), "{stdout}");
+    assert_eq!(read(dir, "out/app/assets/fonts/a.txt"), "font");
+
+    let summary: serde_json::Value = serde_json::from_str(&read(dir, "summary.json")).unwrap();
+    assert_eq!(summary["verified"], 4);
+
+    // 清单使用回读时的哈希，仍与磁盘上的内容一致
+    let sums = read(dir, "sums.txt");
+    assert_eq!(sums.lines().count(), 4, "{sums}");
+    let output = treegen(dir)
+        .args(["verify", "--checksums", "sums.txt", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+}
+
+#[test]
+fn verify_writes_skips_unchanged_files() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("app.yaml", "app:\n  README.md: hello\n")]);
+    for expected in ["1 个文件", "0 个文件"] {
+        let output = treegen(dir)
+            .args([
+                "app.yaml",
+                "--out",
+                "out",
+                "--verify-writes",
+                "--lang",
+                "zh",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains(expected), "{stdout}");
+    }
+}
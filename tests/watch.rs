@@ -0,0 +1,68 @@
+//! --watch：初次生成后监视输入文件，变化时重新生成；单次生成失败只报告、不退出
+mod common;
+
+use common::{treegen_process, write_files};
+use std::{
+    path::Path,
+    process::Stdio,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 等到 `path` 的内容为 `expected`，最多 10 秒
+fn wait_for(path: &Path, expected: &str) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if std::fs::read_to_string(path).is_ok_and(|content| content == expected) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn regenerates_when_the_spec_changes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", "a.txt: v1\n")]);
+    let mut child = treegen_process(dir)
+        .args(["spec.yaml", "--out", "out", "--watch"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let out = dir.join("out/a.txt");
+
+    let first = wait_for(&out, "v1");
+    // 监视开始前的修改可能被错过，等监视器就绪
+    thread::sleep(Duration::from_millis(500));
+    write_files(dir, &[("spec.yaml", "a.txt: v2\n")]);
+    let second = wait_for(&out, "v2");
+
+    // 写坏的描述只报告错误，修好后继续生成
+    thread::sleep(Duration::from_millis(500));
+    write_files(dir, &[("spec.yaml", "a.txt: [\n")]);
+    thread::sleep(Duration::from_millis(1000));
+    let alive = child.try_wait().unwrap().is_none();
+    write_files(dir, &[("spec.yaml", "a.txt: v3\n")]);
+    let third = wait_for(&out, "v3");
+
+    child.kill().unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(first, "initial generation: {stdout}{stderr}");
+    assert!(second, "regeneration: {stdout}{stderr}");
+    assert!(alive, "exited on a bad spec: {stdout}{stderr}");
+    assert!(third, "regeneration after a fix: {stdout}{stderr}");
+    assert!(
+        stdout.contains("Watching 1 input file(s) for changes"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Change detected, regenerating"), "{stdout}");
+    assert!(
+        stderr.contains("] Error: Failed to parse YAML in "),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,87 @@
+//! 节点的 xattrs：写入扩展属性，`@base64:` 表示二进制值
+#![cfg(unix)]
+
+mod common;
+
+use common::{treegen, write_files};
+use std::path::Path;
+
+/// 临时目录所在的文件系统是否支持 user.* 扩展属性（如某些 tmpfs 不支持）
+fn supported(dir: &Path) -> bool {
+    let probe = dir.join("probe");
+    std::fs::write(&probe, "").unwrap();
+    let ok = xattr::SUPPORTED_PLATFORM && xattr::set(&probe, "user.probe", b"1").is_ok();
+    std::fs::remove_file(probe).unwrap();
+    ok
+}
+
+const SPEC: &str = r#"a.txt: { type: file, content: "", xattrs: { user.mime_type: text/plain, user.bin: "@base64:AAH/" } }
+d: { type: dir, xattrs: { user.k: v } }
+"#;
+
+#[test]
+fn attributes_are_written() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    if !supported(dir) {
+        eprintln!("skipping: no user xattrs on {}", dir.display());
+        return;
+    }
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .assert()
+        .success();
+    let get = |path: &str, name: &str| xattr::get(dir.join(path), name).unwrap();
+    assert_eq!(
+        get("out/a.txt", "user.mime_type"),
+        Some(b"text/plain".to_vec())
+    );
+    // @base64: 的值按二进制写入
+    assert_eq!(get("out/a.txt", "user.bin"), Some(vec![0x00, 0x01, 0xff]));
+    assert_eq!(get("out/d", "user.k"), Some(b"v".to_vec()));
+    assert_eq!(get("out/d", "user.mime_type"), None);
+}
+
+#[test]
+fn dry_run_lists_attributes_without_writing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(dir, &[("spec.yaml", SPEC)]);
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out", "--dry-run", "--verbose"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("[Dry-Run] Set xattr user.bin (3 bytes) on out/a.txt"),
+        "{stdout}"
+    );
+    assert!(!dir.join("out").exists());
+}
+
+#[test]
+fn invalid_base64_values_are_rejected_before_writing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    write_files(
+        dir,
+        &[(
+            "spec.yaml",
+            "a.txt: x\nb.txt: { type: file, content: '', xattrs: { user.bin: '@base64:!!' } }\n",
+        )],
+    );
+    let output = treegen(dir)
+        .args(["spec.yaml", "--out", "out"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Invalid value of xattr 'user.bin' in node 'b.txt'"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Invalid base64 value '!!'"), "{stderr}");
+    assert!(!dir.join("out").exists());
+}